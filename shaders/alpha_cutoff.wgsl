@@ -0,0 +1,12 @@
+// alpha test of masked materials, bound at ALPHA_CUTOFF_BINDING which must be defined before including.
+// renderer writes cutoff given to Material::set_alpha_cutoff, 0 when not masked.
+[[block]]
+struct AlphaCutoff {
+    cutoff: f32;
+};
+[[group(0), binding(ALPHA_CUTOFF_BINDING)]]
+var alpha_cutoff: AlphaCutoff;
+
+fn alpha_discarded(alpha: f32) -> bool {
+    return alpha < alpha_cutoff.cutoff;
+}
@@ -0,0 +1,12 @@
+[[group(0), binding(3)]]
+var right_texture: texture_2d<f32>;
+
+// red from left eye, green and blue from right eye
+[[stage(fragment)]]
+fn fs_main([[builtin(position)]] position: vec4<f32>) -> [[location(0)]] vec4<f32> {
+    let uv = screen_uv(position);
+    let left = textureSample(texture, sampler, uv);
+    let right = textureSample(right_texture, sampler, uv);
+
+    return vec4<f32>(left.r, right.g, right.b, 1.0);
+}
@@ -0,0 +1,93 @@
+// bins point lights into clusters read by clusters.wgsl, one invocation per cluster
+[[block]]
+struct Clusters {
+    view: mat4x4<f32>;
+    inverse_projection: mat4x4<f32>;
+    viewport: vec4<f32>;
+    near: f32;
+    far: f32;
+    light_count: u32;
+};
+
+struct PointLight {
+    position: vec4<f32>;
+    color: vec4<f32>;
+};
+[[block]]
+struct PointLights {
+    lights: array<PointLight>;
+};
+
+struct Cluster {
+    count: u32;
+    lights: array<u32, 63>;
+};
+[[block]]
+struct ClusterLights {
+    clusters: array<Cluster>;
+};
+
+[[group(0), binding(0)]]
+var<uniform> clusters: Clusters;
+[[group(0), binding(1)]]
+var<storage, read> point_lights: PointLights;
+[[group(0), binding(2)]]
+var<storage, read_write> cluster_lights: ClusterLights;
+
+let CLUSTER_COUNT_X: u32 = 16u;
+let CLUSTER_COUNT_Y: u32 = 9u;
+let CLUSTER_COUNT_Z: u32 = 24u;
+let MAX_CLUSTER_LIGHTS: u32 = 63u;
+
+// point on the ray through ndc at view space depth, interpolated so orthographic projections work too
+fn view_position(ndc: vec2<f32>, depth: f32) -> vec3<f32> {
+    let near_clip = clusters.inverse_projection * vec4<f32>(ndc, 0.0, 1.0);
+    let far_clip = clusters.inverse_projection * vec4<f32>(ndc, 1.0, 1.0);
+    let near = near_clip.xyz / near_clip.w;
+    let far = far_clip.xyz / far_clip.w;
+
+    return near + (far - near) * ((-depth - near.z) / (far.z - near.z));
+}
+
+fn slice_depth(slice: u32) -> f32 {
+    return clusters.near * pow(clusters.far / clusters.near, f32(slice) / f32(CLUSTER_COUNT_Z));
+}
+
+[[stage(compute), workgroup_size(4, 4, 4)]]
+fn main([[builtin(global_invocation_id)]] id: vec3<u32>) {
+    if (id.x >= CLUSTER_COUNT_X || id.y >= CLUSTER_COUNT_Y || id.z >= CLUSTER_COUNT_Z) {
+        return;
+    }
+    let index = id.x + (id.y + id.z * CLUSTER_COUNT_Y) * CLUSTER_COUNT_X;
+
+    // tiles go down from top of viewport, ndc y goes up
+    let count = vec2<f32>(f32(CLUSTER_COUNT_X), f32(CLUSTER_COUNT_Y));
+    let tile_min = vec2<f32>(id.xy) / count;
+    let tile_max = vec2<f32>(id.xy + vec2<u32>(1u, 1u)) / count;
+    let ndc_min = vec2<f32>(tile_min.x * 2.0 - 1.0, 1.0 - tile_max.y * 2.0);
+    let ndc_max = vec2<f32>(tile_max.x * 2.0 - 1.0, 1.0 - tile_min.y * 2.0);
+
+    let near = slice_depth(id.z);
+    let far = slice_depth(id.z + 1u);
+    let a = view_position(ndc_min, near);
+    let b = view_position(ndc_max, near);
+    let c = view_position(ndc_min, far);
+    let d = view_position(ndc_max, far);
+    let box_min = min(min(a, b), min(c, d));
+    let box_max = max(max(a, b), max(c, d));
+
+    // lights past the limit are dropped from the cluster
+    var light_count = 0u;
+    for (var i = 0u; i < clusters.light_count; i = i + 1u) {
+        let light = point_lights.lights[i];
+        let center = (clusters.view * vec4<f32>(light.position.xyz, 1.0)).xyz;
+        let offset = center - clamp(center, box_min, box_max);
+
+        if (light_count < MAX_CLUSTER_LIGHTS && dot(offset, offset) <= light.position.w * light.position.w) {
+            cluster_lights.clusters[index].lights[light_count] = i;
+            light_count = light_count + 1u;
+        }
+    }
+
+    cluster_lights.clusters[index].count = light_count;
+}
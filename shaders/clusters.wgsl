@@ -0,0 +1,91 @@
+// point lights binned into view space clusters by renderer, see LightingEnvironment::point_lights.
+// bound at CLUSTERS_BINDING, POINT_LIGHTS_BINDING and CLUSTER_LIGHTS_BINDING which must be defined before including.
+// with DOWNLEVEL, nearest lights are bound as uniform and every cluster has all of them.
+[[block]]
+struct Clusters {
+    view: mat4x4<f32>;
+    inverse_projection: mat4x4<f32>;
+    // x, y, width, height of viewport in framebuffer pixels
+    viewport: vec4<f32>;
+    // view space depth range sliced exponentially
+    near: f32;
+    far: f32;
+    light_count: u32;
+};
+[[group(0), binding(CLUSTERS_BINDING)]]
+var clusters: Clusters;
+
+struct PointLight {
+    // w is radius
+    position: vec4<f32>;
+    color: vec4<f32>;
+};
+#ifdef DOWNLEVEL
+[[block]]
+struct PointLights {
+    lights: array<PointLight, 32>;
+};
+[[group(0), binding(POINT_LIGHTS_BINDING)]]
+var<uniform> point_lights: PointLights;
+#else
+[[block]]
+struct PointLights {
+    lights: array<PointLight>;
+};
+[[group(0), binding(POINT_LIGHTS_BINDING)]]
+var<storage, read> point_lights: PointLights;
+
+struct Cluster {
+    count: u32;
+    lights: array<u32, 63>;
+};
+[[block]]
+struct ClusterLights {
+    clusters: array<Cluster>;
+};
+[[group(0), binding(CLUSTER_LIGHTS_BINDING)]]
+var<storage, read> cluster_lights: ClusterLights;
+#endif
+
+let CLUSTER_COUNT_X: u32 = 16u;
+let CLUSTER_COUNT_Y: u32 = 9u;
+let CLUSTER_COUNT_Z: u32 = 24u;
+
+// cluster of fragment at framebuffer position, with its world position for depth
+fn cluster_index(frag_position: vec4<f32>, world_position: vec3<f32>) -> u32 {
+    let uv = clamp((frag_position.xy - clusters.viewport.xy) / clusters.viewport.zw, vec2<f32>(0.0, 0.0), vec2<f32>(0.999, 0.999));
+    let depth = -(clusters.view * vec4<f32>(world_position, 1.0)).z;
+    let slice = log(max(depth, clusters.near) / clusters.near) / log(clusters.far / clusters.near);
+
+    let x = u32(uv.x * f32(CLUSTER_COUNT_X));
+    let y = u32(uv.y * f32(CLUSTER_COUNT_Y));
+    let z = u32(clamp(slice, 0.0, 0.999) * f32(CLUSTER_COUNT_Z));
+
+    return x + (y + z * CLUSTER_COUNT_Y) * CLUSTER_COUNT_X;
+}
+
+// number of lights reaching cluster
+fn cluster_light_count(cluster: u32) -> u32 {
+#ifdef DOWNLEVEL
+    return clusters.light_count;
+#else
+    return cluster_lights.clusters[cluster].count;
+#endif
+}
+
+// i-th light reaching cluster
+fn cluster_light(cluster: u32, i: u32) -> PointLight {
+#ifdef DOWNLEVEL
+    return point_lights.lights[i];
+#else
+    let light_index = cluster_lights.clusters[cluster].lights[i];
+    return point_lights.lights[light_index];
+#endif
+}
+
+// same falloff as deferred path, zero at radius
+fn point_light_attenuation(light: PointLight, distance: f32) -> f32 {
+    let attenuation = clamp(1.0 - distance / light.position.w, 0.0, 1.0);
+
+    return attenuation * attenuation;
+}
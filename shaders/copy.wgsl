@@ -0,0 +1,4 @@
+[[stage(fragment)]]
+fn fs_main([[builtin(position)]] position: vec4<f32>) -> [[location(0)]] vec4<f32> {
+    return textureSample(texture, sampler, screen_uv(position));
+}
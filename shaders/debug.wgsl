@@ -0,0 +1,29 @@
+struct VertexOutput {
+    [[location(0)]] color: vec3<f32>;
+    [[builtin(position)]] position: vec4<f32>;
+};
+
+[[block]]
+struct Transform {
+    mvp: mat4x4<f32>;
+};
+[[group(0), binding(0)]]
+var transform: Transform;
+
+[[stage(vertex)]]
+fn vs_main(
+    [[location(0)]] position: vec3<f32>,
+    [[location(1)]] color: vec3<f32>,
+) -> VertexOutput {
+    var out: VertexOutput;
+
+    out.position = transform.mvp * vec4<f32>(position, 1.0);
+    out.color = color;
+
+    return out;
+}
+
+[[stage(fragment)]]
+fn fs_main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+    return vec4<f32>(in.color, 1.0);
+}
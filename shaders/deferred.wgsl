@@ -0,0 +1,77 @@
+struct PointLight {
+    // w is radius
+    position: vec4<f32>;
+    color: vec4<f32>;
+};
+
+[[block]]
+struct Deferred {
+    inverse_view_projection: mat4x4<f32>;
+    camera_position: vec4<f32>;
+    // x, y, width, height
+    viewport: vec4<f32>;
+    light_count: u32;
+    lights: array<PointLight, 64>;
+};
+[[group(0), binding(0)]]
+var deferred: Deferred;
+
+[[group(0), binding(3)]]
+var normal_texture: texture_2d<f32>;
+[[group(0), binding(4)]]
+var material_texture: texture_2d<f32>;
+[[group(0), binding(5)]]
+var depth_texture: texture_depth_2d;
+
+#define LIGHTING_BINDING 6
+#include "lighting.wgsl"
+
+// material params: r is specular intensity, g is roughness, b is ambient occlusion
+fn shade(albedo: vec3<f32>, params: vec4<f32>, normal: vec3<f32>, view_dir: vec3<f32>, light_dir: vec3<f32>, light_color: vec3<f32>) -> vec3<f32> {
+    let diffuse = max(dot(normal, light_dir), 0.0);
+    let half_dir = normalize(light_dir + view_dir);
+    let shininess = mix(128.0, 2.0, params.g);
+    let specular = pow(max(dot(normal, half_dir), 0.0), shininess) * params.r;
+
+    return (albedo * diffuse + vec3<f32>(specular)) * light_color;
+}
+
+[[stage(fragment)]]
+fn fs_main([[builtin(position)]] position: vec4<f32>) -> [[location(0)]] vec4<f32> {
+    let coord = vec2<i32>(position.xy);
+    let depth = textureLoad(depth_texture, coord, 0);
+    if (depth >= 1.0) {
+        return vec4<f32>(1.0, 1.0, 1.0, 1.0);
+    }
+
+    let albedo = textureLoad(texture, coord, 0);
+    let normal = normalize(textureLoad(normal_texture, coord, 0).xyz);
+    let params = textureLoad(material_texture, coord, 0);
+
+    let ndc = (position.xy - deferred.viewport.xy) / deferred.viewport.zw * vec2<f32>(2.0, -2.0) + vec2<f32>(-1.0, 1.0);
+    let clip = deferred.inverse_view_projection * vec4<f32>(ndc, depth, 1.0);
+    let world = clip.xyz / clip.w;
+    let view_dir = normalize(deferred.camera_position.xyz - world);
+
+    var color: vec3<f32> = albedo.rgb * lighting.ambient.rgb * params.b;
+    color = color + shade(albedo.rgb, params, normal, view_dir, -lighting.sun_direction.xyz, lighting.sun_color.rgb);
+
+    var i: u32 = 0u;
+    loop {
+        if (i >= deferred.light_count) {
+            break;
+        }
+
+        let light = deferred.lights[i];
+        let to_light = light.position.xyz - world;
+        let distance = length(to_light);
+        let attenuation = clamp(1.0 - distance / light.position.w, 0.0, 1.0);
+        color = color + shade(albedo.rgb, params, normal, view_dir, to_light / distance, light.color.rgb * attenuation * attenuation);
+
+        i = i + 1u;
+    }
+
+    let fog = 1.0 - exp(-lighting.fog.w * length(deferred.camera_position.xyz - world));
+
+    return vec4<f32>(mix(color, lighting.fog.rgb, vec3<f32>(fog)), albedo.a);
+}
@@ -0,0 +1,141 @@
+// prefiltering of equirectangular environment map given as input texture, appended to fullscreen.wgsl
+
+[[block]]
+struct Prefilter {
+    // size of output level in pixels
+    size: vec2<f32>;
+    roughness: f32;
+};
+[[group(0), binding(3)]]
+var prefilter: Prefilter;
+
+let PI: f32 = 3.14159265;
+let SAMPLE_COUNT: u32 = 256u;
+
+// v goes from +y down to -y
+fn equirect_direction(uv: vec2<f32>) -> vec3<f32> {
+    let phi = (uv.x - 0.5) * 2.0 * PI;
+    let theta = uv.y * PI;
+
+    return vec3<f32>(sin(theta) * cos(phi), cos(theta), sin(theta) * sin(phi));
+}
+
+fn equirect_uv(direction: vec3<f32>) -> vec2<f32> {
+    return vec2<f32>(atan2(direction.z, direction.x) / (2.0 * PI) + 0.5, acos(clamp(direction.y, -1.0, 1.0)) / PI);
+}
+
+// low discrepancy points, so every texel uses the same well spread samples
+fn hammersley(i: u32) -> vec2<f32> {
+    // van der corput radical inverse of index
+    var bits = i;
+    var inverse = 0.0;
+    var scale = 0.5;
+    loop {
+        if (bits == 0u) {
+            break;
+        }
+        if ((bits & 1u) == 1u) {
+            inverse = inverse + scale;
+        }
+        bits = bits >> 1u;
+        scale = scale * 0.5;
+    }
+
+    return vec2<f32>(f32(i) / f32(SAMPLE_COUNT), inverse);
+}
+
+fn to_world(local: vec3<f32>, normal: vec3<f32>) -> vec3<f32> {
+    let up = select(vec3<f32>(1.0, 0.0, 0.0), vec3<f32>(0.0, 0.0, 1.0), abs(normal.z) < 0.999);
+    let tangent = normalize(cross(up, normal));
+    let bitangent = cross(normal, tangent);
+
+    return tangent * local.x + bitangent * local.y + normal * local.z;
+}
+
+// half vector around +z distributed by ggx of roughness
+fn sample_ggx(xi: vec2<f32>, roughness: f32) -> vec3<f32> {
+    let a = roughness * roughness;
+    let phi = 2.0 * PI * xi.x;
+    let cos_theta = sqrt((1.0 - xi.y) / (1.0 + (a * a - 1.0) * xi.y));
+    let sin_theta = sqrt(1.0 - cos_theta * cos_theta);
+
+    return vec3<f32>(cos(phi) * sin_theta, sin(phi) * sin_theta, cos_theta);
+}
+
+fn environment(direction: vec3<f32>) -> vec3<f32> {
+    return textureSampleLevel(texture, sampler, equirect_uv(direction), 0.0).rgb;
+}
+
+// cosine weighted average of incoming light over hemisphere around each direction
+[[stage(fragment)]]
+fn fs_irradiance([[builtin(position)]] position: vec4<f32>) -> [[location(0)]] vec4<f32> {
+    let normal = equirect_direction(position.xy / prefilter.size);
+
+    var result = vec3<f32>(0.0, 0.0, 0.0);
+    for (var i = 0u; i < SAMPLE_COUNT; i = i + 1u) {
+        let xi = hammersley(i);
+        let radius = sqrt(xi.y);
+        let phi = 2.0 * PI * xi.x;
+        let local = vec3<f32>(cos(phi) * radius, sin(phi) * radius, sqrt(1.0 - xi.y));
+
+        result = result + environment(to_world(local, normal));
+    }
+
+    return vec4<f32>(result / f32(SAMPLE_COUNT), 1.0);
+}
+
+// reflection seen along each direction by surface of roughness, assuming view direction equals normal
+[[stage(fragment)]]
+fn fs_specular([[builtin(position)]] position: vec4<f32>) -> [[location(0)]] vec4<f32> {
+    let normal = equirect_direction(position.xy / prefilter.size);
+
+    var result = vec3<f32>(0.0, 0.0, 0.0);
+    var total = 0.0;
+    for (var i = 0u; i < SAMPLE_COUNT; i = i + 1u) {
+        let half = to_world(sample_ggx(hammersley(i), prefilter.roughness), normal);
+        let light = reflect(-normal, half);
+
+        let n_dot_l = dot(normal, light);
+        if (n_dot_l > 0.0) {
+            result = result + environment(light) * n_dot_l;
+            total = total + n_dot_l;
+        }
+    }
+
+    return vec4<f32>(result / max(total, 0.0001), 1.0);
+}
+
+fn geometry_schlick(n_dot_x: f32, roughness: f32) -> f32 {
+    let k = roughness * roughness / 2.0;
+
+    return n_dot_x / (n_dot_x * (1.0 - k) + k);
+}
+
+// scale and bias to fresnel reflectance at normal incidence, by n dot v along x and roughness along y
+[[stage(fragment)]]
+fn fs_brdf([[builtin(position)]] position: vec4<f32>) -> [[location(0)]] vec4<f32> {
+    let uv = position.xy / prefilter.size;
+    let n_dot_v = max(uv.x, 0.001);
+    let roughness = uv.y;
+    let view = vec3<f32>(sqrt(1.0 - n_dot_v * n_dot_v), 0.0, n_dot_v);
+
+    var scale = 0.0;
+    var bias = 0.0;
+    for (var i = 0u; i < SAMPLE_COUNT; i = i + 1u) {
+        let half = sample_ggx(hammersley(i), roughness);
+        let light = reflect(-view, half);
+
+        let n_dot_l = max(light.z, 0.0);
+        let n_dot_h = max(half.z, 0.0);
+        let v_dot_h = max(dot(view, half), 0.0);
+        if (n_dot_l > 0.0) {
+            let visibility = geometry_schlick(n_dot_v, roughness) * geometry_schlick(n_dot_l, roughness) * v_dot_h / (n_dot_h * n_dot_v);
+            let fresnel = pow(1.0 - v_dot_h, 5.0);
+
+            scale = scale + (1.0 - fresnel) * visibility;
+            bias = bias + fresnel * visibility;
+        }
+    }
+
+    return vec4<f32>(scale / f32(SAMPLE_COUNT), bias / f32(SAMPLE_COUNT), 0.0, 1.0);
+}
@@ -0,0 +1,26 @@
+struct FullscreenVertexOutput {
+    [[builtin(position)]] position: vec4<f32>;
+};
+
+// single triangle covering the whole viewport
+[[stage(vertex)]]
+fn fullscreen_vs_main([[builtin(vertex_index)]] vertex_index: u32) -> FullscreenVertexOutput {
+    var out: FullscreenVertexOutput;
+
+    let x = f32((vertex_index << 1u) & 2u) * 2.0 - 1.0;
+    let y = f32(vertex_index & 2u) * 2.0 - 1.0;
+    out.position = vec4<f32>(x, y, 0.0, 1.0);
+
+    return out;
+}
+
+[[group(0), binding(1)]]
+var texture: texture_2d<f32>;
+[[group(0), binding(2)]]
+var sampler: sampler;
+
+// input texture may be larger than the viewport, so derive uv from the pixel position.
+fn screen_uv(position: vec4<f32>) -> vec2<f32> {
+    return position.xy / vec2<f32>(textureDimensions(texture));
+}
+
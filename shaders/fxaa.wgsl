@@ -0,0 +1,48 @@
+// fast approximate anti-aliasing, blurs along edges found from luma contrast
+
+let FXAA_REDUCE_MIN: f32 = 0.0078125;
+let FXAA_REDUCE_MUL: f32 = 0.125;
+let FXAA_SPAN_MAX: f32 = 8.0;
+
+fn luma(color: vec3<f32>) -> f32 {
+    return dot(color, vec3<f32>(0.299, 0.587, 0.114));
+}
+
+[[stage(fragment)]]
+fn fs_main([[builtin(position)]] position: vec4<f32>) -> [[location(0)]] vec4<f32> {
+    let texel = vec2<f32>(1.0, 1.0) / vec2<f32>(textureDimensions(texture));
+    let uv = screen_uv(position);
+
+    let center = textureSample(texture, sampler, uv);
+    let luma_nw = luma(textureSample(texture, sampler, uv + vec2<f32>(-1.0, -1.0) * texel).rgb);
+    let luma_ne = luma(textureSample(texture, sampler, uv + vec2<f32>(1.0, -1.0) * texel).rgb);
+    let luma_sw = luma(textureSample(texture, sampler, uv + vec2<f32>(-1.0, 1.0) * texel).rgb);
+    let luma_se = luma(textureSample(texture, sampler, uv + vec2<f32>(1.0, 1.0) * texel).rgb);
+    let luma_m = luma(center.rgb);
+
+    let luma_min = min(luma_m, min(min(luma_nw, luma_ne), min(luma_sw, luma_se)));
+    let luma_max = max(luma_m, max(max(luma_nw, luma_ne), max(luma_sw, luma_se)));
+
+    // perpendicular to luma gradient, which is along the edge
+    var direction = vec2<f32>(-((luma_nw + luma_ne) - (luma_sw + luma_se)), (luma_nw + luma_sw) - (luma_ne + luma_se));
+    let reduce = max((luma_nw + luma_ne + luma_sw + luma_se) * 0.25 * FXAA_REDUCE_MUL, FXAA_REDUCE_MIN);
+    let scale = 1.0 / (min(abs(direction.x), abs(direction.y)) + reduce);
+    direction = clamp(direction * scale, vec2<f32>(-FXAA_SPAN_MAX, -FXAA_SPAN_MAX), vec2<f32>(FXAA_SPAN_MAX, FXAA_SPAN_MAX)) * texel;
+
+    let near = 0.5 * (
+        textureSample(texture, sampler, uv + direction * (1.0 / 3.0 - 0.5)).rgb +
+        textureSample(texture, sampler, uv + direction * (2.0 / 3.0 - 0.5)).rgb
+    );
+    let far = near * 0.5 + 0.25 * (
+        textureSample(texture, sampler, uv + direction * -0.5).rgb +
+        textureSample(texture, sampler, uv + direction * 0.5).rgb
+    );
+
+    // wider span crossed another edge, fall back to closer samples
+    let luma_far = luma(far);
+    if (luma_far < luma_min || luma_far > luma_max) {
+        return vec4<f32>(near, center.a);
+    }
+
+    return vec4<f32>(far, center.a);
+}
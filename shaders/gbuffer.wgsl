@@ -0,0 +1,8 @@
+// targets of deferred render path, written by fragment entry points given to Shader::with_gbuffer_entry
+struct GBufferOutput {
+    [[location(0)]] albedo: vec4<f32>;
+    // world space, not normalized
+    [[location(1)]] normal: vec4<f32>;
+    // r is specular intensity, g is roughness, b is ambient occlusion which is 1 without it
+    [[location(2)]] material: vec4<f32>;
+};
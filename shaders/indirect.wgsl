@@ -0,0 +1,110 @@
+[[block]]
+struct Batch {
+    view_projection: mat4x4<f32>;
+    // frustum planes facing inwards, normalized
+    planes: array<vec4<f32>, 6>;
+    // bounding sphere of mesh
+    sphere: vec4<f32>;
+    index_count: u32;
+    instance_count: u32;
+};
+[[group(0), binding(0)]]
+var<uniform> batch: Batch;
+
+#ifndef DOWNLEVEL
+[[block]]
+struct Instances {
+    transforms: array<mat4x4<f32>>;
+};
+[[group(0), binding(3)]]
+var<storage, read> instances: Instances;
+
+struct DrawCommand {
+    index_count: u32;
+    instance_count: u32;
+    first_index: u32;
+    base_vertex: i32;
+    first_instance: u32;
+};
+
+[[block]]
+struct Commands {
+    commands: array<DrawCommand>;
+};
+[[group(0), binding(4)]]
+var<storage, read_write> commands: Commands;
+
+// one draw per instance, culled ones draw no instances
+[[stage(compute), workgroup_size(64)]]
+fn cull([[builtin(global_invocation_id)]] id: vec3<u32>) {
+    let index = id.x;
+    if (index >= batch.instance_count) {
+        return;
+    }
+
+    let transform = instances.transforms[index];
+    let center = transform * vec4<f32>(batch.sphere.xyz, 1.0);
+    let scale = max(length(transform[0].xyz), max(length(transform[1].xyz), length(transform[2].xyz)));
+    let radius = batch.sphere.w * scale;
+
+    var visible = 1u;
+    for (var i = 0; i < 6; i = i + 1) {
+        let plane = batch.planes[i];
+        if (dot(plane.xyz, center.xyz) + plane.w < -radius) {
+            visible = 0u;
+        }
+    }
+
+    commands.commands[index] = DrawCommand(batch.index_count, visible, 0u, 0, index);
+}
+#endif
+
+struct VertexOutput {
+    [[location(0)]] tex_coord: vec2<f32>;
+    [[builtin(position)]] position: vec4<f32>;
+};
+
+#ifdef DOWNLEVEL
+// transforms are instance attributes, every instance is drawn
+[[stage(vertex)]]
+fn vs_main(
+    [[location(0)]] position: vec4<f32>,
+    [[location(1)]] tex_coord: vec2<f32>,
+    [[location(2)]] transform_0: vec4<f32>,
+    [[location(3)]] transform_1: vec4<f32>,
+    [[location(4)]] transform_2: vec4<f32>,
+    [[location(5)]] transform_3: vec4<f32>,
+) -> VertexOutput {
+    var out: VertexOutput;
+
+    let transform = mat4x4<f32>(transform_0, transform_1, transform_2, transform_3);
+    out.position = batch.view_projection * transform * position;
+    out.tex_coord = tex_coord;
+
+    return out;
+}
+#else
+[[stage(vertex)]]
+fn vs_main(
+    [[location(0)]] position: vec4<f32>,
+    [[location(1)]] tex_coord: vec2<f32>,
+    [[builtin(instance_index)]] instance: u32,
+) -> VertexOutput {
+    var out: VertexOutput;
+
+    out.position = batch.view_projection * instances.transforms[instance] * position;
+    out.tex_coord = tex_coord;
+
+    return out;
+}
+#endif
+
+[[group(0), binding(1)]]
+var texture: texture_2d<f32>;
+[[group(0), binding(2)]]
+var sampler: sampler;
+
+[[stage(fragment)]]
+fn fs_main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+    return textureSample(texture, sampler, in.tex_coord);
+}
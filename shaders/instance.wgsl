@@ -0,0 +1,20 @@
+// transforms of each instance of merged draws, see Shader::with_instanced_entry.
+// matrices are passed by columns, as vertex inputs can't be matrices.
+struct Instance {
+    [[location(8)]] mvp_0: vec4<f32>;
+    [[location(9)]] mvp_1: vec4<f32>;
+    [[location(10)]] mvp_2: vec4<f32>;
+    [[location(11)]] mvp_3: vec4<f32>;
+    [[location(12)]] model_0: vec4<f32>;
+    [[location(13)]] model_1: vec4<f32>;
+    [[location(14)]] model_2: vec4<f32>;
+    [[location(15)]] model_3: vec4<f32>;
+};
+
+fn instance_mvp(instance: Instance) -> mat4x4<f32> {
+    return mat4x4<f32>(instance.mvp_0, instance.mvp_1, instance.mvp_2, instance.mvp_3);
+}
+
+fn instance_model(instance: Instance) -> mat4x4<f32> {
+    return mat4x4<f32>(instance.model_0, instance.model_1, instance.model_2, instance.model_3);
+}
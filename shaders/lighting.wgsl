@@ -0,0 +1,11 @@
+// scene lighting, bound at LIGHTING_BINDING which must be defined before including
+[[block]]
+struct Lighting {
+    ambient: vec4<f32>;
+    sun_direction: vec4<f32>;
+    sun_color: vec4<f32>;
+    fog: vec4<f32>;
+    eye: vec4<f32>;
+};
+[[group(0), binding(LIGHTING_BINDING)]]
+var lighting: Lighting;
@@ -0,0 +1,59 @@
+struct VertexOutput {
+    [[location(0)]] tex_coord: vec2<f32>;
+    [[location(1)]] lightmap_coord: vec2<f32>;
+    [[builtin(position)]] position: vec4<f32>;
+};
+
+[[block]]
+struct Transform {
+    mvp: mat4x4<f32>;
+    model: mat4x4<f32>;
+};
+[[group(0), binding(0)]]
+var transform: Transform;
+
+[[block]]
+struct Lightmapped {
+    color: vec4<f32>;
+    // scales baked light, which is stored in 0..1
+    intensity: f32;
+};
+[[group(0), binding(3)]]
+var lightmapped: Lightmapped;
+
+#define ALPHA_CUTOFF_BINDING 5
+#include "alpha_cutoff.wgsl"
+
+[[stage(vertex)]]
+fn vs_main(
+    [[location(0)]] position: vec4<f32>,
+    [[location(1)]] tex_coord: vec2<f32>,
+    [[location(2)]] lightmap_coord: vec2<f32>,
+) -> VertexOutput {
+    var out: VertexOutput;
+
+    out.position = transform.mvp * position;
+    out.tex_coord = tex_coord;
+    out.lightmap_coord = lightmap_coord;
+
+    return out;
+}
+
+[[group(0), binding(1)]]
+var texture: texture_2d<f32>;
+[[group(0), binding(2)]]
+var sampler: sampler;
+[[group(0), binding(4)]]
+var lightmap: texture_2d<f32>;
+
+[[stage(fragment)]]
+fn fs_main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+    let albedo = textureSample(texture, sampler, in.tex_coord) * lightmapped.color;
+    let light = textureSample(lightmap, sampler, in.lightmap_coord).rgb * lightmapped.intensity;
+
+    if (alpha_discarded(albedo.a)) {
+        discard;
+    }
+
+    return vec4<f32>(albedo.rgb * light, albedo.a);
+}
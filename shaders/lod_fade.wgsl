@@ -0,0 +1,18 @@
+// dithered cross-fade between levels of LodGroup. fade is written after model matrix in Mvp uniform:
+// 1 draws every pixel, fade in 0..1 draws that fraction, and negative fade draws complement of same positive one.
+fn lod_fade_discarded(position: vec4<f32>, fade: f32) -> bool {
+    // 4x4 bayer matrix
+    var pattern: array<f32, 16> = array<f32, 16>(
+        0.0, 8.0, 2.0, 10.0,
+        12.0, 4.0, 14.0, 6.0,
+        3.0, 11.0, 1.0, 9.0,
+        15.0, 7.0, 13.0, 5.0,
+    );
+    let pixel = vec2<u32>(position.xy) % vec2<u32>(4u, 4u);
+    let threshold = (pattern[pixel.y * 4u + pixel.x] + 0.5) / 16.0;
+
+    if (fade >= 0.0) {
+        return threshold >= fade;
+    }
+    return threshold < -fade;
+}
@@ -0,0 +1,62 @@
+struct VertexOutput {
+    [[location(0)]] tex_coord: vec2<f32>;
+    [[location(1)]] world_position: vec3<f32>;
+    [[builtin(position)]] position: vec4<f32>;
+};
+
+[[block]]
+struct Transform {
+    mvp: mat4x4<f32>;
+    model: mat4x4<f32>;
+};
+[[group(0), binding(0)]]
+var transform: Transform;
+
+[[block]]
+struct Mirror {
+    color: vec4<f32>;
+    reflectivity: f32;
+};
+[[group(0), binding(3)]]
+var mirror: Mirror;
+
+// of mirrored camera the reflection was rendered with, written by renderer each frame
+[[block]]
+struct ReflectionView {
+    view_projection: mat4x4<f32>;
+};
+[[group(0), binding(5)]]
+var reflection_view: ReflectionView;
+
+[[stage(vertex)]]
+fn vs_main(
+    [[location(0)]] position: vec4<f32>,
+    [[location(1)]] tex_coord: vec2<f32>,
+) -> VertexOutput {
+    var out: VertexOutput;
+
+    out.position = transform.mvp * position;
+    out.tex_coord = tex_coord;
+    out.world_position = (transform.model * position).xyz;
+
+    return out;
+}
+
+[[group(0), binding(1)]]
+var texture: texture_2d<f32>;
+[[group(0), binding(2)]]
+var sampler: sampler;
+[[group(0), binding(4)]]
+var reflection: texture_2d<f32>;
+
+// reflected scene is where mirrored camera sees this point
+[[stage(fragment)]]
+fn fs_main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+    let clip = reflection_view.view_projection * vec4<f32>(in.world_position, 1.0);
+    let uv = vec2<f32>(clip.x / clip.w * 0.5 + 0.5, 0.5 - clip.y / clip.w * 0.5);
+
+    let base = textureSample(texture, sampler, in.tex_coord) * mirror.color;
+    let reflected = textureSample(reflection, sampler, uv).rgb;
+
+    return vec4<f32>(mix(base.rgb, reflected, vec3<f32>(mirror.reflectivity, mirror.reflectivity, mirror.reflectivity)), base.a);
+}
@@ -0,0 +1,34 @@
+// morph targets of mesh, bound at MORPH_TARGETS_BINDING which must be defined before including.
+// weights of up to 8 targets are written after lod fade in Mvp uniform, see Model::set_morph_weights.
+// with DOWNLEVEL, positions are already morphed on cpu and nothing is bound.
+#ifdef DOWNLEVEL
+fn morph_position(position: vec4<f32>, vertex_index: u32, weights: array<vec4<f32>, 2>) -> vec4<f32> {
+    return position;
+}
+#else
+struct MorphDelta {
+    position: vec4<f32>;
+};
+[[block]]
+struct MorphTargets {
+    vertex_count: u32;
+    target_count: u32;
+    deltas: array<MorphDelta>;
+};
+[[group(0), binding(MORPH_TARGETS_BINDING)]]
+var<storage, read> morph_targets: MorphTargets;
+
+fn morph_position(position: vec4<f32>, vertex_index: u32, weights: array<vec4<f32>, 2>) -> vec4<f32> {
+    var weights = weights;
+    var result = position.xyz;
+
+    for (var i = 0u; i < min(morph_targets.target_count, 8u); i = i + 1u) {
+        let weight = weights[i / 4u][i % 4u];
+        if (weight != 0.0) {
+            result = result + morph_targets.deltas[i * morph_targets.vertex_count + vertex_index].position.xyz * weight;
+        }
+    }
+
+    return vec4<f32>(result, position.w);
+}
+#endif
@@ -0,0 +1,154 @@
+[[block]]
+struct Occlusion {
+    view_projection: mat4x4<f32>;
+    // depth buffer pixels covered by hi-z
+    viewport: vec2<f32>;
+    mip_count: u32;
+    box_count: u32;
+};
+
+struct Box {
+    min: vec4<f32>;
+    max: vec4<f32>;
+};
+
+[[block]]
+struct Boxes {
+    boxes: array<Box>;
+};
+
+[[block]]
+struct Visibility {
+    visible: array<u32>;
+};
+
+[[group(0), binding(0)]]
+var depth: texture_depth_2d;
+[[group(0), binding(1)]]
+var source: texture_2d<f32>;
+[[group(0), binding(2)]]
+var destination: texture_storage_2d<r32float, write>;
+
+[[group(0), binding(3)]]
+var<uniform> occlusion: Occlusion;
+[[group(0), binding(4)]]
+var<storage, read_write> boxes: Boxes;
+[[group(0), binding(5)]]
+var<storage, read_write> visibility: Visibility;
+[[group(0), binding(6)]]
+var hi_z: texture_2d<f32>;
+
+// last texel of odd sized source also covers its remaining row or column
+fn source_range(position: vec2<i32>, source_size: vec2<i32>, destination_size: vec2<i32>) -> vec4<i32> {
+    let start = position * 2;
+    var end = min(start + vec2<i32>(1, 1), source_size - vec2<i32>(1, 1));
+    if (position.x == destination_size.x - 1) {
+        end.x = source_size.x - 1;
+    }
+    if (position.y == destination_size.y - 1) {
+        end.y = source_size.y - 1;
+    }
+
+    return vec4<i32>(start, end);
+}
+
+// first level is farthest depth of each 2x2 pixels of depth buffer
+[[stage(compute), workgroup_size(8, 8)]]
+fn downsample_depth([[builtin(global_invocation_id)]] id: vec3<u32>) {
+    let position = vec2<i32>(id.xy);
+    let destination_size = textureDimensions(destination);
+    if (position.x >= destination_size.x || position.y >= destination_size.y) {
+        return;
+    }
+
+    let range = source_range(position, vec2<i32>(occlusion.viewport), destination_size);
+    var result = 0.0;
+    for (var y = range.y; y <= range.w; y = y + 1) {
+        for (var x = range.x; x <= range.z; x = x + 1) {
+            result = max(result, textureLoad(depth, vec2<i32>(x, y), 0));
+        }
+    }
+
+    textureStore(destination, position, vec4<f32>(result, 0.0, 0.0, 0.0));
+}
+
+[[stage(compute), workgroup_size(8, 8)]]
+fn downsample([[builtin(global_invocation_id)]] id: vec3<u32>) {
+    let position = vec2<i32>(id.xy);
+    let destination_size = textureDimensions(destination);
+    if (position.x >= destination_size.x || position.y >= destination_size.y) {
+        return;
+    }
+
+    let range = source_range(position, textureDimensions(source), destination_size);
+    var result = 0.0;
+    for (var y = range.y; y <= range.w; y = y + 1) {
+        for (var x = range.x; x <= range.z; x = x + 1) {
+            result = max(result, textureLoad(source, vec2<i32>(x, y), 0).r);
+        }
+    }
+
+    textureStore(destination, position, vec4<f32>(result, 0.0, 0.0, 0.0));
+}
+
+// box is visible if its nearest depth is in front of farthest depth of hi-z texels under its screen rect
+fn is_visible(box: Box) -> bool {
+    var rect_min = vec2<f32>(1.0, 1.0);
+    var rect_max = vec2<f32>(-1.0, -1.0);
+    var nearest = 1.0;
+
+    for (var i = 0u; i < 8u; i = i + 1u) {
+        let corner = vec3<f32>(
+            select(box.min.x, box.max.x, (i & 1u) != 0u),
+            select(box.min.y, box.max.y, (i & 2u) != 0u),
+            select(box.min.z, box.max.z, (i & 4u) != 0u),
+        );
+        let clip = occlusion.view_projection * vec4<f32>(corner, 1.0);
+
+        // crosses near plane
+        if (clip.w <= 0.0) {
+            return true;
+        }
+
+        let ndc = clip.xyz / clip.w;
+        rect_min = min(rect_min, ndc.xy);
+        rect_max = max(rect_max, ndc.xy);
+        nearest = min(nearest, ndc.z);
+    }
+
+    // outside of view, left to frustum culling
+    if (any(rect_max < vec2<f32>(-1.0, -1.0)) || any(rect_min > vec2<f32>(1.0, 1.0)) || nearest < 0.0) {
+        return true;
+    }
+
+    // ndc y is up, pixels go down
+    let pixel_min = (vec2<f32>(rect_min.x, -rect_max.y) * 0.5 + 0.5) * occlusion.viewport;
+    let pixel_max = (vec2<f32>(rect_max.x, -rect_min.y) * 0.5 + 0.5) * occlusion.viewport;
+    let pixel_min = clamp(pixel_min, vec2<f32>(0.0, 0.0), occlusion.viewport - 1.0);
+    let pixel_max = clamp(pixel_max, vec2<f32>(0.0, 0.0), occlusion.viewport - 1.0);
+
+    // level where rect spans at most two texels on each axis, first level is half of viewport
+    let extent = max(pixel_max.x - pixel_min.x, pixel_max.y - pixel_min.y) / 2.0;
+    let level = min(i32(ceil(log2(max(extent, 1.0)))), i32(occlusion.mip_count) - 1);
+
+    let scale = exp2(f32(level + 1));
+    let level_max = textureDimensions(hi_z, level) - vec2<i32>(1, 1);
+    let texel_min = min(vec2<i32>(pixel_min / scale), level_max);
+    let texel_max = min(vec2<i32>(pixel_max / scale), level_max);
+
+    let farthest = max(
+        max(textureLoad(hi_z, texel_min, level).r, textureLoad(hi_z, vec2<i32>(texel_max.x, texel_min.y), level).r),
+        max(textureLoad(hi_z, vec2<i32>(texel_min.x, texel_max.y), level).r, textureLoad(hi_z, texel_max, level).r),
+    );
+
+    return nearest <= farthest;
+}
+
+[[stage(compute), workgroup_size(64)]]
+fn test([[builtin(global_invocation_id)]] id: vec3<u32>) {
+    if (id.x >= occlusion.box_count) {
+        return;
+    }
+
+    visibility.visible[id.x] = select(0u, 1u, is_visible(boxes.boxes[id.x]));
+}
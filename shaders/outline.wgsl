@@ -0,0 +1,40 @@
+[[block]]
+struct Transform {
+    mvp: mat4x4<f32>;
+};
+[[group(0), binding(0)]]
+var transform: Transform;
+
+[[block]]
+struct Outline {
+    color: vec4<f32>;
+    // in model units
+    width: f32;
+};
+[[group(0), binding(1)]]
+var outline: Outline;
+
+// inverted hull, back faces pushed out along normals
+[[stage(vertex)]]
+fn vs_main(
+    [[location(0)]] position: vec4<f32>,
+    [[location(1)]] normal: vec3<f32>,
+) -> [[builtin(position)]] vec4<f32> {
+    return transform.mvp * vec4<f32>(position.xyz + normalize(normal) * outline.width, position.w);
+}
+
+// meshes without normals are pushed out from their origin instead, which suits convex ones
+[[stage(vertex)]]
+fn vs_scale(
+    [[location(0)]] position: vec4<f32>,
+) -> [[builtin(position)]] vec4<f32> {
+    let direction = select(vec3<f32>(0.0, 0.0, 0.0), normalize(position.xyz), length(position.xyz) > 0.0);
+
+    return transform.mvp * vec4<f32>(position.xyz + direction * outline.width, position.w);
+}
+
+// depth tested against completed scene, so only the rim around the model's silhouette shows
+[[stage(fragment)]]
+fn fs_main() -> [[location(0)]] vec4<f32> {
+    return outline.color;
+}
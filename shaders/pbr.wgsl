@@ -0,0 +1,199 @@
+struct VertexOutput {
+    [[location(0)]] tex_coord: vec2<f32>;
+    [[location(1)]] normal: vec3<f32>;
+    [[location(2)]] world_position: vec3<f32>;
+    // baked ambient occlusion, 1 without it
+    [[location(3)]] occlusion: f32;
+    [[builtin(position)]] position: vec4<f32>;
+};
+
+[[block]]
+struct Transform {
+    mvp: mat4x4<f32>;
+    model: mat4x4<f32>;
+};
+[[group(0), binding(0)]]
+var transform: Transform;
+
+[[block]]
+struct Pbr {
+    color: vec4<f32>;
+    metallic: f32;
+    roughness: f32;
+};
+[[group(0), binding(3)]]
+var pbr: Pbr;
+
+#define LIGHTING_BINDING 4
+#include "lighting.wgsl"
+#define ALPHA_CUTOFF_BINDING 9
+#include "alpha_cutoff.wgsl"
+#define CLUSTERS_BINDING 10
+#define POINT_LIGHTS_BINDING 11
+#define CLUSTER_LIGHTS_BINDING 12
+#include "clusters.wgsl"
+#include "instance.wgsl"
+#include "gbuffer.wgsl"
+
+[[stage(vertex)]]
+fn vs_main(
+    [[location(0)]] position: vec4<f32>,
+    [[location(1)]] tex_coord: vec2<f32>,
+    [[location(2)]] normal: vec3<f32>,
+) -> VertexOutput {
+    var out: VertexOutput;
+
+    out.position = transform.mvp * position;
+    out.tex_coord = tex_coord;
+    out.normal = (transform.model * vec4<f32>(normal, 0.0)).xyz;
+    out.world_position = (transform.model * position).xyz;
+    out.occlusion = 1.0;
+
+    return out;
+}
+
+// for meshes with Occlusion item, see bake_vertex_ao
+[[stage(vertex)]]
+fn vs_occlusion(
+    [[location(0)]] position: vec4<f32>,
+    [[location(1)]] tex_coord: vec2<f32>,
+    [[location(2)]] normal: vec3<f32>,
+    [[location(3)]] occlusion: f32,
+) -> VertexOutput {
+    var out: VertexOutput;
+
+    out.position = transform.mvp * position;
+    out.tex_coord = tex_coord;
+    out.normal = (transform.model * vec4<f32>(normal, 0.0)).xyz;
+    out.world_position = (transform.model * position).xyz;
+    out.occlusion = occlusion;
+
+    return out;
+}
+
+// transform of each instance is read from instance input instead of Mvp
+[[stage(vertex)]]
+fn vs_instanced(
+    [[location(0)]] position: vec4<f32>,
+    [[location(1)]] tex_coord: vec2<f32>,
+    [[location(2)]] normal: vec3<f32>,
+    instance: Instance,
+) -> VertexOutput {
+    var out: VertexOutput;
+    let model = instance_model(instance);
+
+    out.position = instance_mvp(instance) * position;
+    out.tex_coord = tex_coord;
+    out.normal = (model * vec4<f32>(normal, 0.0)).xyz;
+    out.world_position = (model * position).xyz;
+    out.occlusion = 1.0;
+
+    return out;
+}
+
+[[group(0), binding(1)]]
+var texture: texture_2d<f32>;
+[[group(0), binding(2)]]
+var sampler: sampler;
+// prefiltered from skybox by renderer, see LightingEnvironment::skybox
+[[group(0), binding(5)]]
+var irradiance_map: texture_2d<f32>;
+[[group(0), binding(6)]]
+var specular_map: texture_2d<f32>;
+[[group(0), binding(7)]]
+var brdf_lut: texture_2d<f32>;
+[[group(0), binding(8)]]
+var environment_sampler: sampler;
+
+let PI: f32 = 3.14159265;
+// mip levels of specular map, roughness 1 at last one
+let SPECULAR_LEVELS: f32 = 5.0;
+
+fn equirect_uv(direction: vec3<f32>) -> vec2<f32> {
+    return vec2<f32>(atan2(direction.z, direction.x) / (2.0 * PI) + 0.5, acos(clamp(direction.y, -1.0, 1.0)) / PI);
+}
+
+fn distribution_ggx(n_dot_h: f32, roughness: f32) -> f32 {
+    let a2 = roughness * roughness * roughness * roughness;
+    let d = n_dot_h * n_dot_h * (a2 - 1.0) + 1.0;
+
+    return a2 / (PI * d * d);
+}
+
+fn geometry_smith(n_dot_v: f32, n_dot_l: f32, roughness: f32) -> f32 {
+    let k = (roughness + 1.0) * (roughness + 1.0) / 8.0;
+
+    return n_dot_v / (n_dot_v * (1.0 - k) + k) * n_dot_l / (n_dot_l * (1.0 - k) + k);
+}
+
+fn fresnel_schlick(cos_theta: f32, f0: vec3<f32>) -> vec3<f32> {
+    return f0 + (vec3<f32>(1.0, 1.0, 1.0) - f0) * pow(1.0 - cos_theta, 5.0);
+}
+
+// cook-torrance reflectance of light from direction, scaled by n dot l
+fn direct_light(albedo: vec3<f32>, f0: vec3<f32>, normal: vec3<f32>, view: vec3<f32>, light: vec3<f32>, n_dot_v: f32, roughness: f32) -> vec3<f32> {
+    let half = normalize(view + light);
+    let n_dot_l = max(dot(normal, light), 0.0);
+    let fresnel = fresnel_schlick(max(dot(half, view), 0.0), f0);
+    let specular = fresnel * distribution_ggx(max(dot(normal, half), 0.0), roughness) * geometry_smith(n_dot_v, n_dot_l, roughness) / (4.0 * n_dot_v * max(n_dot_l, 0.001));
+    let diffuse = (vec3<f32>(1.0, 1.0, 1.0) - fresnel) * (1.0 - pbr.metallic) * albedo / PI;
+
+    return (diffuse + specular) * n_dot_l;
+}
+
+[[stage(fragment)]]
+fn fs_main(in: VertexOutput, [[builtin(front_facing)]] front_facing: bool) -> [[location(0)]] vec4<f32> {
+    let albedo = textureSample(texture, sampler, in.tex_coord) * pbr.color;
+    let roughness = clamp(pbr.roughness, 0.04, 1.0);
+
+    // back faces are only drawn by double sided materials, lit as seen from their side
+    let normal = select(-normalize(in.normal), normalize(in.normal), front_facing);
+    let view = normalize(lighting.eye.xyz - in.world_position);
+    let n_dot_v = max(dot(normal, view), 0.001);
+    let f0 = mix(vec3<f32>(0.04, 0.04, 0.04), albedo.rgb, vec3<f32>(pbr.metallic, pbr.metallic, pbr.metallic));
+
+    // sun, then point lights of fragment's cluster
+    var direct = direct_light(albedo.rgb, f0, normal, view, -lighting.sun_direction.xyz, n_dot_v, roughness) * lighting.sun_color.rgb;
+    let cluster = cluster_index(in.position, in.world_position);
+    for (var i = 0u; i < cluster_light_count(cluster); i = i + 1u) {
+        let light = cluster_light(cluster, i);
+        let to_light = light.position.xyz - in.world_position;
+        let distance = max(length(to_light), 0.0001);
+        let radiance = light.color.rgb * point_light_attenuation(light, distance);
+
+        direct = direct + direct_light(albedo.rgb, f0, normal, view, to_light / distance, n_dot_v, roughness) * radiance;
+    }
+
+    // environment, split sum approximation
+    let ambient_fresnel = fresnel_schlick(n_dot_v, f0);
+    let irradiance = textureSample(irradiance_map, environment_sampler, equirect_uv(normal)).rgb;
+    let ambient_diffuse = (vec3<f32>(1.0, 1.0, 1.0) - ambient_fresnel) * (1.0 - pbr.metallic) * albedo.rgb * (irradiance + lighting.ambient.rgb);
+
+    let reflection = reflect(-view, normal);
+    let prefiltered = textureSampleLevel(specular_map, environment_sampler, equirect_uv(reflection), roughness * (SPECULAR_LEVELS - 1.0)).rgb;
+    let brdf = textureSample(brdf_lut, environment_sampler, vec2<f32>(n_dot_v, roughness)).rg;
+    let ambient_specular = prefiltered * (f0 * brdf.x + brdf.y);
+
+    // discarded after sampling, which must be in uniform control flow
+    if (alpha_discarded(albedo.a)) {
+        discard;
+    }
+
+    return vec4<f32>(direct + (ambient_diffuse + ambient_specular) * in.occlusion, albedo.a);
+}
+
+// unlit surface for deferred render path, metals reflect more and have less diffuse
+[[stage(fragment)]]
+fn fs_gbuffer(in: VertexOutput, [[builtin(front_facing)]] front_facing: bool) -> GBufferOutput {
+    let albedo = textureSample(texture, sampler, in.tex_coord) * pbr.color;
+    if (alpha_discarded(albedo.a)) {
+        discard;
+    }
+
+    var out: GBufferOutput;
+    out.albedo = vec4<f32>(albedo.rgb * (1.0 - pbr.metallic * 0.96), albedo.a);
+    out.normal = vec4<f32>(select(-in.normal, in.normal, front_facing), 0.0);
+    out.material = vec4<f32>(mix(0.04, 1.0, pbr.metallic), clamp(pbr.roughness, 0.04, 1.0), in.occlusion, 0.0);
+
+    return out;
+}
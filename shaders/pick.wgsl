@@ -0,0 +1,30 @@
+struct VertexOutput {
+    [[builtin(position)]] position: vec4<f32>;
+    [[location(0), interpolate(flat)]] id: u32;
+};
+
+[[block]]
+struct Transform {
+    mvp: mat4x4<f32>;
+};
+[[group(0), binding(0)]]
+var transform: Transform;
+
+// model id is passed as instance index
+[[stage(vertex)]]
+fn vs_main(
+    [[location(0)]] position: vec4<f32>,
+    [[builtin(instance_index)]] id: u32,
+) -> VertexOutput {
+    var out: VertexOutput;
+
+    out.position = transform.mvp * position;
+    out.id = id;
+
+    return out;
+}
+
+[[stage(fragment)]]
+fn fs_main(in: VertexOutput) -> [[location(0)]] u32 {
+    return in.id;
+}
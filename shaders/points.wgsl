@@ -0,0 +1,64 @@
+struct VertexOutput {
+    [[location(0)]] color: vec4<f32>;
+    // -1..1 across the point
+    [[location(1)]] corner: vec2<f32>;
+    [[builtin(position)]] position: vec4<f32>;
+};
+
+[[block]]
+struct PointCloud {
+    mvp: mat4x4<f32>;
+    // x is 1 if sizes are in world units, otherwise fraction of view height. y is 1 for round points.
+    params: vec4<f32>;
+};
+[[group(0), binding(0)]]
+var cloud: PointCloud;
+
+// each point is an instance of a quad, expanded facing the camera
+[[stage(vertex)]]
+fn vs_main(
+    [[builtin(vertex_index)]] vertex_index: u32,
+    [[location(0)]] position: vec3<f32>,
+    [[location(1)]] size: f32,
+    [[location(2)]] color: vec4<f32>,
+) -> VertexOutput {
+    var corners: array<vec2<f32>, 6> = array<vec2<f32>, 6>(
+        vec2<f32>(-1.0, -1.0),
+        vec2<f32>(1.0, -1.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(-1.0, -1.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(-1.0, 1.0),
+    );
+    let corner = corners[vertex_index];
+
+    let clip = cloud.mvp * vec4<f32>(position, 1.0);
+    // clip space length of a unit along screen axes, which are lengths of mvp rows
+    let scale = vec2<f32>(
+        length(vec3<f32>(cloud.mvp[0].x, cloud.mvp[1].x, cloud.mvp[2].x)),
+        length(vec3<f32>(cloud.mvp[0].y, cloud.mvp[1].y, cloud.mvp[2].y)),
+    );
+
+    var offset: vec2<f32>;
+    if (cloud.params.x > 0.5) {
+        offset = corner * size * 0.5 * scale;
+    } else {
+        offset = corner * size * clip.w * vec2<f32>(scale.x / scale.y, 1.0);
+    }
+
+    var out: VertexOutput;
+    out.position = vec4<f32>(clip.xy + offset, clip.zw);
+    out.color = color;
+    out.corner = corner;
+
+    return out;
+}
+
+[[stage(fragment)]]
+fn fs_main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+    if (cloud.params.y > 0.5 && dot(in.corner, in.corner) > 1.0) {
+        discard;
+    }
+
+    return in.color;
+}
@@ -0,0 +1,46 @@
+// appended to fullscreen.wgsl, `texture` is atlas of faces rendered by ReflectionProbe
+
+[[block]]
+struct Probe {
+    // view projection of each face, placed in 3x2 grid of atlas
+    faces: array<mat4x4<f32>, 6>;
+    position: vec4<f32>;
+    // width, height of equirectangular output
+    output_size: vec2<f32>;
+    face_size: f32;
+};
+[[group(0), binding(3)]]
+var probe: Probe;
+
+let PI: f32 = 3.14159265;
+
+// inverse of equirect_uv of pbr.wgsl
+fn equirect_direction(uv: vec2<f32>) -> vec3<f32> {
+    let phi = (uv.x - 0.5) * 2.0 * PI;
+    let theta = uv.y * PI;
+
+    return vec3<f32>(cos(phi) * sin(theta), cos(theta), sin(phi) * sin(theta));
+}
+
+// faces overlap, so first one containing the direction is used
+[[stage(fragment)]]
+fn fs_equirect([[builtin(position)]] position: vec4<f32>) -> [[location(0)]] vec4<f32> {
+    let direction = equirect_direction(position.xy / probe.output_size);
+    // between near and far plane of cameras
+    let point = vec4<f32>(probe.position.xyz + direction * 2.0, 1.0);
+    let atlas_size = vec2<f32>(textureDimensions(texture));
+
+    for (var i = 0; i < 6; i = i + 1) {
+        let clip = probe.faces[i] * point;
+        let ndc = clip.xy / clip.w;
+        // away from edges of cells, so filtering doesn't bleed neighbors in
+        if (clip.w > 0.0 && abs(ndc.x) <= 0.9 && abs(ndc.y) <= 0.9) {
+            let face_uv = vec2<f32>(ndc.x * 0.5 + 0.5, 0.5 - ndc.y * 0.5);
+            let cell = vec2<f32>(f32(i % 3), f32(i / 3));
+
+            return textureSampleLevel(texture, sampler, (cell + face_uv) * probe.face_size / atlas_size, 0.0);
+        }
+    }
+
+    return vec4<f32>(0.0, 0.0, 0.0, 1.0);
+}
@@ -26,3 +26,18 @@ var sampler: sampler;
 fn fs_main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
     return textureSample(texture, sampler, in.tex_coord);
 }
+
+fn linear_to_srgb(linear: vec3<f32>) -> vec3<f32> {
+    let low = linear * 12.92;
+    let high = 1.055 * pow(linear, vec3<f32>(1.0 / 2.4)) - vec3<f32>(0.055);
+
+    return select(high, low, linear <= vec3<f32>(0.0031308));
+}
+
+// used when output surface doesn't encode srgb by itself
+[[stage(fragment)]]
+fn fs_main_encode_srgb(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+    let color = textureSample(texture, sampler, in.tex_coord);
+
+    return vec4<f32>(linear_to_srgb(color.rgb), color.a);
+}
@@ -0,0 +1,106 @@
+// appended to fullscreen.wgsl
+
+[[block]]
+struct Ssao {
+    projection: mat4x4<f32>;
+    inverse_projection: mat4x4<f32>;
+    // hemisphere around +z, scaled towards center
+    kernel: array<vec4<f32>, 32>;
+    // width, height of viewport
+    viewport: vec2<f32>;
+    radius: f32;
+    intensity: f32;
+    sample_count: u32;
+    blur_radius: i32;
+};
+[[group(0), binding(3)]]
+var ssao: Ssao;
+
+[[group(0), binding(4)]]
+var depth: texture_depth_2d;
+// occlusion result of fs_occlusion, r is ambient visibility
+[[group(0), binding(5)]]
+var occlusion: texture_2d<f32>;
+
+fn view_position(pixel: vec2<i32>) -> vec3<f32> {
+    let clamped = clamp(pixel, vec2<i32>(0, 0), vec2<i32>(ssao.viewport) - vec2<i32>(1, 1));
+    let uv = (vec2<f32>(clamped) + vec2<f32>(0.5, 0.5)) / ssao.viewport;
+    let ndc = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, textureLoad(depth, clamped, 0), 1.0);
+    let position = ssao.inverse_projection * ndc;
+
+    return position.xyz / position.w;
+}
+
+// normal from the neighbors closer in depth on each axis, so edges don't bleed
+fn view_normal(pixel: vec2<i32>, center: vec3<f32>) -> vec3<f32> {
+    let left = view_position(pixel - vec2<i32>(1, 0));
+    let right = view_position(pixel + vec2<i32>(1, 0));
+    let up = view_position(pixel - vec2<i32>(0, 1));
+    let down = view_position(pixel + vec2<i32>(0, 1));
+
+    let dx = select(center - left, right - center, abs(right.z - center.z) < abs(center.z - left.z));
+    let dy = select(center - up, down - center, abs(down.z - center.z) < abs(center.z - up.z));
+
+    return normalize(cross(dy, dx));
+}
+
+// per pixel rotation of kernel, repeating every 4x4 pixels which blur averages out
+fn random_vector(pixel: vec2<i32>) -> vec3<f32> {
+    let index = f32((pixel.x & 3) + (pixel.y & 3) * 4);
+    let angle = index * 2.39996323;
+
+    return vec3<f32>(cos(angle), sin(angle), 0.0);
+}
+
+[[stage(fragment)]]
+fn fs_occlusion([[builtin(position)]] position: vec4<f32>) -> [[location(0)]] vec4<f32> {
+    let pixel = vec2<i32>(position.xy);
+    if (textureLoad(depth, pixel, 0) >= 1.0) {
+        return vec4<f32>(1.0, 1.0, 1.0, 1.0);
+    }
+
+    let center = view_position(pixel);
+    let normal = view_normal(pixel, center);
+    let random = random_vector(pixel);
+    let tangent = normalize(random - normal * dot(random, normal));
+    let bitangent = cross(normal, tangent);
+
+    var occluded = 0.0;
+    for (var i = 0u; i < ssao.sample_count; i = i + 1u) {
+        let offset = ssao.kernel[i].xyz;
+        let sample = center + (tangent * offset.x + bitangent * offset.y + normal * offset.z) * ssao.radius;
+
+        let clip = ssao.projection * vec4<f32>(sample, 1.0);
+        let uv = vec2<f32>(clip.x / clip.w * 0.5 + 0.5, 0.5 - clip.y / clip.w * 0.5);
+        let scene = view_position(vec2<i32>(uv * ssao.viewport));
+
+        // view space looks along -z, so closer surfaces have larger z
+        let range = smoothStep(0.0, 1.0, ssao.radius / abs(center.z - scene.z));
+        occluded = occluded + select(0.0, range, scene.z >= sample.z + 0.025);
+    }
+
+    let visibility = 1.0 - occluded / f32(ssao.sample_count) * ssao.intensity;
+
+    return vec4<f32>(visibility, visibility, visibility, 1.0);
+}
+
+// box blur of occlusion over the kernel rotation pattern, multiplied into the scene
+[[stage(fragment)]]
+fn fs_blur([[builtin(position)]] position: vec4<f32>) -> [[location(0)]] vec4<f32> {
+    let pixel = vec2<i32>(position.xy);
+    let last = vec2<i32>(ssao.viewport) - vec2<i32>(1, 1);
+
+    var total = 0.0;
+    var count = 0.0;
+    for (var y = -ssao.blur_radius; y < ssao.blur_radius; y = y + 1) {
+        for (var x = -ssao.blur_radius; x < ssao.blur_radius; x = x + 1) {
+            let coord = clamp(pixel + vec2<i32>(x, y), vec2<i32>(0, 0), last);
+            total = total + textureLoad(occlusion, coord, 0).r;
+            count = count + 1.0;
+        }
+    }
+
+    let color = textureSample(texture, sampler, screen_uv(position));
+
+    return vec4<f32>(color.rgb * clamp(total / count, 0.0, 1.0), color.a);
+}
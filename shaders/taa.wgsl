@@ -0,0 +1,60 @@
+// temporal anti-aliasing, accumulates jittered frames into reprojected history. appended to fullscreen.wgsl
+
+[[block]]
+struct Taa {
+    // unjittered matrices of current and previous frame
+    inverse_view_projection: mat4x4<f32>;
+    previous_view_projection: mat4x4<f32>;
+    // width, height of viewport
+    viewport: vec2<f32>;
+    // weight of current frame, 1 when there's no history yet
+    blend: f32;
+};
+[[group(0), binding(3)]]
+var taa: Taa;
+
+[[group(0), binding(4)]]
+var depth: texture_depth_2d;
+// uv offset to where each pixel was in previous frame
+[[group(0), binding(5)]]
+var velocity: texture_2d<f32>;
+[[group(0), binding(6)]]
+var history: texture_2d<f32>;
+
+// velocity of camera motion, reprojecting depth of each pixel
+[[stage(fragment)]]
+fn fs_velocity([[builtin(position)]] position: vec4<f32>) -> [[location(0)]] vec4<f32> {
+    let uv = position.xy / taa.viewport;
+    let ndc = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, textureLoad(depth, vec2<i32>(position.xy), 0), 1.0);
+
+    let world = taa.inverse_view_projection * ndc;
+    let previous = taa.previous_view_projection * (world / world.w);
+    let previous_uv = vec2<f32>(previous.x / previous.w * 0.5 + 0.5, 0.5 - previous.y / previous.w * 0.5);
+
+    return vec4<f32>(previous_uv - uv, 0.0, 1.0);
+}
+
+[[stage(fragment)]]
+fn fs_resolve([[builtin(position)]] position: vec4<f32>) -> [[location(0)]] vec4<f32> {
+    let pixel = vec2<i32>(position.xy);
+    let last = vec2<i32>(taa.viewport) - vec2<i32>(1, 1);
+    let current = textureLoad(texture, pixel, 0);
+
+    // history outside of current neighborhood color range is stale, clamp it to reduce ghosting
+    var neighborhood_min = current;
+    var neighborhood_max = current;
+    for (var y = -1; y <= 1; y = y + 1) {
+        for (var x = -1; x <= 1; x = x + 1) {
+            let neighbor = textureLoad(texture, clamp(pixel + vec2<i32>(x, y), vec2<i32>(0, 0), last), 0);
+            neighborhood_min = min(neighborhood_min, neighbor);
+            neighborhood_max = max(neighborhood_max, neighbor);
+        }
+    }
+
+    let history_uv = position.xy / taa.viewport + textureLoad(velocity, pixel, 0).xy;
+    let outside = any(history_uv < vec2<f32>(0.0, 0.0)) || any(history_uv > vec2<f32>(1.0, 1.0));
+    let previous = clamp(textureSample(history, sampler, history_uv), neighborhood_min, neighborhood_max);
+    let blend = select(taa.blend, 1.0, outside);
+
+    return mix(previous, current, vec4<f32>(blend, blend, blend, blend));
+}
@@ -0,0 +1,69 @@
+struct VertexOutput {
+    [[location(0)]] tex_coord: vec2<f32>;
+    [[location(1)]] normal: vec3<f32>;
+    [[builtin(position)]] position: vec4<f32>;
+};
+
+[[block]]
+struct Transform {
+    mvp: mat4x4<f32>;
+    model: mat4x4<f32>;
+};
+[[group(0), binding(0)]]
+var transform: Transform;
+
+[[block]]
+struct Terrain {
+    // repeats of each layer texture across whole terrain
+    layer_scale: vec4<f32>;
+};
+[[group(0), binding(7)]]
+var terrain: Terrain;
+
+#define LIGHTING_BINDING 8
+#include "lighting.wgsl"
+
+[[stage(vertex)]]
+fn vs_main(
+    [[location(0)]] position: vec3<f32>,
+    [[location(1)]] normal: vec3<f32>,
+    [[location(2)]] tex_coord: vec2<f32>,
+) -> VertexOutput {
+    var out: VertexOutput;
+
+    out.position = transform.mvp * vec4<f32>(position, 1.0);
+    out.tex_coord = tex_coord;
+    out.normal = (transform.model * vec4<f32>(normal, 0.0)).xyz;
+
+    return out;
+}
+
+// rgba of splat map weights each layer
+[[group(0), binding(1)]]
+var splat_map: texture_2d<f32>;
+[[group(0), binding(2)]]
+var sampler: sampler;
+[[group(0), binding(3)]]
+var layer0: texture_2d<f32>;
+[[group(0), binding(4)]]
+var layer1: texture_2d<f32>;
+[[group(0), binding(5)]]
+var layer2: texture_2d<f32>;
+[[group(0), binding(6)]]
+var layer3: texture_2d<f32>;
+
+[[stage(fragment)]]
+fn fs_main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+    let weights = textureSample(splat_map, sampler, in.tex_coord);
+    let total = max(weights.r + weights.g + weights.b + weights.a, 0.0001);
+
+    var albedo: vec3<f32> = textureSample(layer0, sampler, in.tex_coord * terrain.layer_scale.x).rgb * weights.r;
+    albedo = albedo + textureSample(layer1, sampler, in.tex_coord * terrain.layer_scale.y).rgb * weights.g;
+    albedo = albedo + textureSample(layer2, sampler, in.tex_coord * terrain.layer_scale.z).rgb * weights.b;
+    albedo = albedo + textureSample(layer3, sampler, in.tex_coord * terrain.layer_scale.w).rgb * weights.a;
+    albedo = albedo / total;
+
+    let diffuse = max(dot(normalize(in.normal), -lighting.sun_direction.xyz), 0.0);
+
+    return vec4<f32>(albedo * (lighting.ambient.rgb + diffuse * lighting.sun_color.rgb), 1.0);
+}
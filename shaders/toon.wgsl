@@ -0,0 +1,152 @@
+struct VertexOutput {
+    [[location(0)]] tex_coord: vec2<f32>;
+    [[location(1)]] normal: vec3<f32>;
+    [[location(2)]] world_position: vec3<f32>;
+    // baked ambient occlusion, 1 without it
+    [[location(3)]] occlusion: f32;
+    [[builtin(position)]] position: vec4<f32>;
+};
+
+[[block]]
+struct Transform {
+    mvp: mat4x4<f32>;
+    model: mat4x4<f32>;
+};
+[[group(0), binding(0)]]
+var transform: Transform;
+
+[[block]]
+struct Toon {
+    color: vec4<f32>;
+    // rgb is outline color, a is outline width in model units
+    outline: vec4<f32>;
+};
+[[group(0), binding(4)]]
+var toon: Toon;
+
+#define LIGHTING_BINDING 5
+#include "lighting.wgsl"
+#define ALPHA_CUTOFF_BINDING 6
+#include "alpha_cutoff.wgsl"
+#define CLUSTERS_BINDING 7
+#define POINT_LIGHTS_BINDING 8
+#define CLUSTER_LIGHTS_BINDING 9
+#include "clusters.wgsl"
+#include "instance.wgsl"
+
+[[stage(vertex)]]
+fn vs_main(
+    [[location(0)]] position: vec4<f32>,
+    [[location(1)]] tex_coord: vec2<f32>,
+    [[location(2)]] normal: vec3<f32>,
+) -> VertexOutput {
+    var out: VertexOutput;
+
+    out.position = transform.mvp * position;
+    out.tex_coord = tex_coord;
+    out.normal = (transform.model * vec4<f32>(normal, 0.0)).xyz;
+    out.world_position = (transform.model * position).xyz;
+    out.occlusion = 1.0;
+
+    return out;
+}
+
+// for meshes with Occlusion item, see bake_vertex_ao
+[[stage(vertex)]]
+fn vs_occlusion(
+    [[location(0)]] position: vec4<f32>,
+    [[location(1)]] tex_coord: vec2<f32>,
+    [[location(2)]] normal: vec3<f32>,
+    [[location(3)]] occlusion: f32,
+) -> VertexOutput {
+    var out: VertexOutput;
+
+    out.position = transform.mvp * position;
+    out.tex_coord = tex_coord;
+    out.normal = (transform.model * vec4<f32>(normal, 0.0)).xyz;
+    out.world_position = (transform.model * position).xyz;
+    out.occlusion = occlusion;
+
+    return out;
+}
+
+// transform of each instance is read from instance input instead of Mvp
+[[stage(vertex)]]
+fn vs_instanced(
+    [[location(0)]] position: vec4<f32>,
+    [[location(1)]] tex_coord: vec2<f32>,
+    [[location(2)]] normal: vec3<f32>,
+    instance: Instance,
+) -> VertexOutput {
+    var out: VertexOutput;
+    let model = instance_model(instance);
+
+    out.position = instance_mvp(instance) * position;
+    out.tex_coord = tex_coord;
+    out.normal = (model * vec4<f32>(normal, 0.0)).xyz;
+    out.world_position = (model * position).xyz;
+    out.occlusion = 1.0;
+
+    return out;
+}
+
+[[group(0), binding(1)]]
+var texture: texture_2d<f32>;
+[[group(0), binding(2)]]
+var sampler: sampler;
+// lookup of half lambert term, left is unlit side
+[[group(0), binding(3)]]
+var ramp: texture_2d<f32>;
+
+[[stage(fragment)]]
+fn fs_main(in: VertexOutput, [[builtin(front_facing)]] front_facing: bool) -> [[location(0)]] vec4<f32> {
+    let albedo = textureSample(texture, sampler, in.tex_coord) * toon.color;
+
+    // back faces are only drawn by double sided materials, lit as seen from their side
+    let normal = select(-normalize(in.normal), normalize(in.normal), front_facing);
+    let half_lambert = dot(normal, -lighting.sun_direction.xyz) * 0.5 + 0.5;
+    // keep away from edges, sampler repeats
+    let ramp_coord = vec2<f32>(clamp(half_lambert, 0.01, 0.99), 0.5);
+    let shade = textureSample(ramp, sampler, ramp_coord).rgb;
+    var light = shade * lighting.sun_color.rgb;
+
+    // point lights of fragment's cluster go through the same ramp
+    let cluster = cluster_index(in.position, in.world_position);
+    for (var i = 0u; i < cluster_light_count(cluster); i = i + 1u) {
+        let point_light = cluster_light(cluster, i);
+        let to_light = point_light.position.xyz - in.world_position;
+        let distance = max(length(to_light), 0.0001);
+        let half_lambert = dot(normal, to_light / distance) * 0.5 + 0.5;
+        let point_shade = textureSampleLevel(ramp, sampler, vec2<f32>(clamp(half_lambert, 0.01, 0.99), 0.5), 0.0).rgb;
+
+        light = light + point_shade * point_light.color.rgb * point_light_attenuation(point_light, distance);
+    }
+
+    if (alpha_discarded(albedo.a)) {
+        discard;
+    }
+
+    return vec4<f32>(albedo.rgb * (lighting.ambient.rgb * in.occlusion + light), albedo.a);
+}
+
+// inverted hull, drawn with front faces culled
+[[stage(vertex)]]
+fn vs_outline(
+    [[location(0)]] position: vec4<f32>,
+    [[location(2)]] normal: vec3<f32>,
+) -> VertexOutput {
+    var out: VertexOutput;
+
+    out.position = transform.mvp * vec4<f32>(position.xyz + normal * toon.outline.a, position.w);
+    out.tex_coord = vec2<f32>(0.0, 0.0);
+    out.normal = normal;
+    out.world_position = vec3<f32>(0.0, 0.0, 0.0);
+    out.occlusion = 1.0;
+
+    return out;
+}
+
+[[stage(fragment)]]
+fn fs_outline(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+    return vec4<f32>(toon.outline.rgb, 1.0);
+}
@@ -0,0 +1,100 @@
+struct VertexOutput {
+    [[location(0)]] tex_coord: vec2<f32>;
+    [[location(1)]] color: vec4<f32>;
+    [[builtin(position)]] position: vec4<f32>;
+};
+
+[[block]]
+struct Transform {
+    mvp: mat4x4<f32>;
+    model: mat4x4<f32>;
+};
+[[group(0), binding(0)]]
+var transform: Transform;
+
+[[block]]
+struct Unlit {
+    color: vec4<f32>;
+};
+[[group(0), binding(3)]]
+var unlit: Unlit;
+
+#define ALPHA_CUTOFF_BINDING 4
+#include "alpha_cutoff.wgsl"
+#include "instance.wgsl"
+
+[[stage(vertex)]]
+fn vs_main(
+    [[location(0)]] position: vec4<f32>,
+    [[location(1)]] tex_coord: vec2<f32>,
+) -> VertexOutput {
+    var out: VertexOutput;
+
+    out.position = transform.mvp * position;
+    out.tex_coord = tex_coord;
+    out.color = unlit.color;
+
+    return out;
+}
+
+// baked ambient occlusion darkens color, see bake_vertex_ao
+[[stage(vertex)]]
+fn vs_occlusion(
+    [[location(0)]] position: vec4<f32>,
+    [[location(1)]] tex_coord: vec2<f32>,
+    [[location(3)]] occlusion: f32,
+) -> VertexOutput {
+    var out: VertexOutput;
+
+    out.position = transform.mvp * position;
+    out.tex_coord = tex_coord;
+    out.color = unlit.color * vec4<f32>(occlusion, occlusion, occlusion, 1.0);
+
+    return out;
+}
+
+// transform of each instance is read from instance input instead of Mvp
+[[stage(vertex)]]
+fn vs_instanced(
+    [[location(0)]] position: vec4<f32>,
+    [[location(1)]] tex_coord: vec2<f32>,
+    instance: Instance,
+) -> VertexOutput {
+    var out: VertexOutput;
+
+    out.position = instance_mvp(instance) * position;
+    out.tex_coord = tex_coord;
+    out.color = unlit.color;
+
+    return out;
+}
+
+[[stage(vertex)]]
+fn vs_vertex_color(
+    [[location(0)]] position: vec4<f32>,
+    [[location(1)]] tex_coord: vec2<f32>,
+    [[location(2)]] color: vec4<f32>,
+) -> VertexOutput {
+    var out: VertexOutput;
+
+    out.position = transform.mvp * position;
+    out.tex_coord = tex_coord;
+    out.color = unlit.color * color;
+
+    return out;
+}
+
+[[group(0), binding(1)]]
+var texture: texture_2d<f32>;
+[[group(0), binding(2)]]
+var sampler: sampler;
+
+[[stage(fragment)]]
+fn fs_main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+    let color = textureSample(texture, sampler, in.tex_coord) * in.color;
+    if (alpha_discarded(color.a)) {
+        discard;
+    }
+
+    return color;
+}
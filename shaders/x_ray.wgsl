@@ -0,0 +1,26 @@
+[[block]]
+struct Transform {
+    mvp: mat4x4<f32>;
+};
+[[group(0), binding(0)]]
+var transform: Transform;
+
+[[block]]
+struct XRay {
+    color: vec4<f32>;
+};
+[[group(0), binding(1)]]
+var x_ray: XRay;
+
+[[stage(vertex)]]
+fn vs_main(
+    [[location(0)]] position: vec4<f32>,
+) -> [[builtin(position)]] vec4<f32> {
+    return transform.mvp * position;
+}
+
+// drawn only where scene depth is in front of the model
+[[stage(fragment)]]
+fn fs_main() -> [[location(0)]] vec4<f32> {
+    return x_ray.color;
+}
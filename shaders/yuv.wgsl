@@ -0,0 +1,41 @@
+// appended to fullscreen.wgsl, `texture` is luma plane
+
+// nv12: interleaved chroma, i420: chroma in separate planes. both half resolution.
+[[group(0), binding(3)]]
+var chroma_u: texture_2d<f32>;
+[[group(0), binding(4)]]
+var chroma_v: texture_2d<f32>;
+
+// output is srgb texture, so decode here and let it encode back on store
+fn srgb_to_linear(color: vec3<f32>) -> vec3<f32> {
+    let low = color / 12.92;
+    let high = pow((color + vec3<f32>(0.055, 0.055, 0.055)) / 1.055, vec3<f32>(2.4, 2.4, 2.4));
+
+    return select(high, low, color <= vec3<f32>(0.04045, 0.04045, 0.04045));
+}
+
+// bt.601 limited range, as most cameras and sd video
+fn yuv_to_rgb(y: f32, u: f32, v: f32) -> vec4<f32> {
+    let luma = (y - 16.0 / 255.0) * 1.164;
+    let cb = u - 0.5;
+    let cr = v - 0.5;
+
+    let rgb = vec3<f32>(luma + 1.596 * cr, luma - 0.392 * cb - 0.813 * cr, luma + 2.017 * cb);
+
+    return vec4<f32>(srgb_to_linear(clamp(rgb, vec3<f32>(0.0, 0.0, 0.0), vec3<f32>(1.0, 1.0, 1.0))), 1.0);
+}
+
+[[stage(fragment)]]
+fn fs_nv12([[builtin(position)]] position: vec4<f32>) -> [[location(0)]] vec4<f32> {
+    let uv = screen_uv(position);
+    let chroma = textureSample(chroma_u, sampler, uv).rg;
+
+    return yuv_to_rgb(textureSample(texture, sampler, uv).r, chroma.r, chroma.g);
+}
+
+[[stage(fragment)]]
+fn fs_i420([[builtin(position)]] position: vec4<f32>) -> [[location(0)]] vec4<f32> {
+    let uv = screen_uv(position);
+
+    return yuv_to_rgb(textureSample(texture, sampler, uv).r, textureSample(chroma_u, sampler, uv).r, textureSample(chroma_v, sampler, uv).r);
+}
@@ -0,0 +1,99 @@
+use alloc::string::String;
+#[cfg(not(target_arch = "wasm32"))]
+use alloc::vec::Vec;
+
+use crate::renderer_options::{Backend, PowerPreference, RendererOptions};
+
+// Gpu and its capabilities, e.g. to lower texture sizes or skip effects on weaker adapters.
+#[derive(Clone, Debug)]
+pub struct AdapterInfo {
+    pub name: String,
+    // none for adapters of no real backend
+    pub backend: Option<Backend>,
+    pub device_type: wgpu::DeviceType,
+    // supported by adapter, renderer enables only ones it uses
+    pub features: wgpu::Features,
+    pub limits: wgpu::Limits,
+    // renderer runs in downlevel mode on it, see Renderer::is_downlevel
+    pub downlevel: bool,
+}
+
+impl AdapterInfo {
+    pub(crate) fn new(adapter: &wgpu::Adapter) -> Self {
+        let info = adapter.get_info();
+
+        Self {
+            name: info.name,
+            backend: Backend::from_wgpu(info.backend),
+            device_type: info.device_type,
+            features: adapter.features(),
+            limits: adapter.limits(),
+            downlevel: is_downlevel(adapter),
+        }
+    }
+}
+
+// adapters without compute or storage buffers in every stage, like WebGL2, GLES and older mobile gpus
+pub(crate) fn is_downlevel(adapter: &wgpu::Adapter) -> bool {
+    let required = wgpu::DownlevelFlags::COMPUTE_SHADERS | wgpu::DownlevelFlags::FRAGMENT_WRITABLE_STORAGE | wgpu::DownlevelFlags::VERTEX_STORAGE;
+
+    !adapter.get_downlevel_properties().flags.contains(required)
+}
+
+// downlevel defaults lowered further to what adapter has, as webgl has no storage buffers at all
+pub(crate) fn downlevel_limits(adapter: &wgpu::Adapter) -> wgpu::Limits {
+    let supported = adapter.limits();
+    let defaults = wgpu::Limits::downlevel_defaults().using_resolution(supported.clone());
+
+    wgpu::Limits {
+        max_storage_buffers_per_shader_stage: defaults
+            .max_storage_buffers_per_shader_stage
+            .min(supported.max_storage_buffers_per_shader_stage),
+        max_storage_textures_per_shader_stage: defaults
+            .max_storage_textures_per_shader_stage
+            .min(supported.max_storage_textures_per_shader_stage),
+        max_dynamic_storage_buffers_per_pipeline_layout: defaults
+            .max_dynamic_storage_buffers_per_pipeline_layout
+            .min(supported.max_dynamic_storage_buffers_per_pipeline_layout),
+        max_storage_buffer_binding_size: defaults.max_storage_buffer_binding_size.min(supported.max_storage_buffer_binding_size),
+        ..defaults
+    }
+}
+
+pub(crate) fn backends(backend: Option<Backend>) -> wgpu::Backends {
+    backend.map(|x| x.wgpu_type()).unwrap_or(wgpu::Backends::PRIMARY)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn enumerate_adapters(backend: Option<Backend>) -> Vec<AdapterInfo> {
+    let instance = wgpu::Instance::new(backends(backend));
+
+    instance.enumerate_adapters(backends(backend)).map(|x| AdapterInfo::new(&x)).collect()
+}
+
+// adapter named in options if there's one, otherwise one wgpu picks by power preference.
+pub(crate) async fn request_adapter(instance: &wgpu::Instance, options: &RendererOptions) -> wgpu::Adapter {
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Some(name) = &options.adapter_name {
+        let name = name.to_lowercase();
+        let named = instance
+            .enumerate_adapters(backends(options.backend))
+            .find(|x| x.get_info().name.to_lowercase().contains(&name));
+        if let Some(adapter) = named {
+            return adapter;
+        }
+    }
+
+    let power_preference = match options.power_preference {
+        PowerPreference::LowPower => wgpu::PowerPreference::LowPower,
+        PowerPreference::HighPerformance => wgpu::PowerPreference::HighPerformance,
+    };
+
+    instance
+        .request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference,
+            compatible_surface: None,
+        })
+        .await
+        .unwrap()
+}
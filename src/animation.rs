@@ -0,0 +1,491 @@
+use alloc::{sync::Arc, vec, vec::Vec};
+
+use nalgebra::{Matrix4, Quaternion, UnitQuaternion, Vector3};
+
+// Translation, rotation and scale of an animated node, composed in that order.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Transform {
+    pub translation: Vector3<f32>,
+    pub rotation: UnitQuaternion<f32>,
+    pub scale: Vector3<f32>,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self {
+            translation: Vector3::zeros(),
+            rotation: UnitQuaternion::identity(),
+            scale: Vector3::new(1.0, 1.0, 1.0),
+        }
+    }
+}
+
+impl Transform {
+    pub fn to_matrix(&self) -> Matrix4<f32> {
+        Matrix4::new_translation(&self.translation) * self.rotation.to_homogeneous() * Matrix4::new_nonuniform_scaling(&self.scale)
+    }
+
+    // rotation is slerped, translation and scale are lerped
+    pub fn interpolate(&self, other: &Transform, t: f32) -> Transform {
+        Transform {
+            translation: self.translation.lerp(&other.translation, t),
+            rotation: self.rotation.slerp(&other.rotation, t),
+            scale: self.scale.lerp(&other.scale, t),
+        }
+    }
+}
+
+// Same modes as glTF animation samplers.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Interpolation {
+    Step,
+    Linear,
+    // each key has in tangent, value and out tangent
+    CubicSpline,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Property {
+    Translation,
+    Rotation,
+    Scale,
+    // morph target weights
+    Weights,
+    // values of AnimationCurve
+    Value,
+}
+
+struct Channel {
+    // index of animated transform or morphed mesh
+    target: usize,
+    property: Property,
+    interpolation: Interpolation,
+    times: Vec<f32>,
+    // components of each value, 4 for rotation quaternion as xyzw
+    width: usize,
+    values: Vec<f32>,
+}
+
+impl Channel {
+    fn new(target: usize, property: Property, interpolation: Interpolation, times: &[f32], width: usize, values: Vec<f32>) -> Self {
+        Self {
+            target,
+            property,
+            interpolation,
+            times: times.to_vec(),
+            width,
+            values,
+        }
+    }
+
+    // writes value at time into result of channel width
+    fn sample(&self, time: f32, result: &mut [f32]) {
+        let stride = if self.interpolation == Interpolation::CubicSpline { 3 } else { 1 };
+        // nth value of key, value itself is in the middle of tangents of cubic splines
+        let value = |key: usize, n: usize| &self.values[(key * stride + n) * self.width..(key * stride + n + 1) * self.width];
+        let middle = stride / 2;
+
+        let next = self.times.iter().position(|&x| x > time).unwrap_or(self.times.len());
+        if next == 0 || next == self.times.len() {
+            result.copy_from_slice(value(next.saturating_sub(1), middle));
+            return;
+        }
+
+        let previous = next - 1;
+        let delta = self.times[next] - self.times[previous];
+        let t = (time - self.times[previous]) / delta;
+
+        match self.interpolation {
+            Interpolation::Step => result.copy_from_slice(value(previous, 0)),
+            Interpolation::Linear if self.property == Property::Rotation => {
+                let a = Self::quaternion(value(previous, 0));
+                let b = Self::quaternion(value(next, 0));
+
+                result.copy_from_slice(a.slerp(&b, t).coords.as_slice());
+            }
+            Interpolation::Linear => {
+                for (i, result) in result.iter_mut().enumerate() {
+                    *result = value(previous, 0)[i] * (1.0 - t) + value(next, 0)[i] * t;
+                }
+            }
+            Interpolation::CubicSpline => {
+                // hermite basis, tangents are scaled by key interval
+                let (t2, t3) = (t * t, t * t * t);
+                for (i, result) in result.iter_mut().enumerate() {
+                    *result = value(previous, 1)[i] * (2.0 * t3 - 3.0 * t2 + 1.0)
+                        + value(previous, 2)[i] * delta * (t3 - 2.0 * t2 + t)
+                        + value(next, 1)[i] * (-2.0 * t3 + 3.0 * t2)
+                        + value(next, 0)[i] * delta * (t3 - t2);
+                }
+
+                if self.property == Property::Rotation {
+                    result.copy_from_slice(Self::quaternion(result).coords.as_slice());
+                }
+            }
+        }
+    }
+
+    fn apply(&self, time: f32, transforms: &mut [Transform]) {
+        let transform = match transforms.get_mut(self.target) {
+            Some(x) if !matches!(self.property, Property::Weights | Property::Value) => x,
+            _ => return,
+        };
+
+        let mut value = [0.0; 4];
+        self.sample(time, &mut value[..self.width]);
+        match self.property {
+            Property::Translation => transform.translation = Vector3::new(value[0], value[1], value[2]),
+            Property::Rotation => transform.rotation = Self::quaternion(&value),
+            Property::Scale => transform.scale = Vector3::new(value[0], value[1], value[2]),
+            Property::Weights | Property::Value => {}
+        }
+    }
+
+    fn quaternion(xyzw: &[f32]) -> UnitQuaternion<f32> {
+        UnitQuaternion::new_normalize(Quaternion::new(xyzw[3], xyzw[0], xyzw[1], xyzw[2]))
+    }
+}
+
+// Keyframed translation, rotation and scale channels of indexed transforms, and morph target weights of indexed meshes,
+// like a glTF animation. Key times are in seconds and must be increasing.
+#[derive(Default)]
+pub struct AnimationClip {
+    channels: Vec<Channel>,
+    duration: f32,
+}
+
+impl AnimationClip {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // for cubic splines, values hold in tangent, value and out tangent of each key.
+    pub fn add_translation(&mut self, target: usize, interpolation: Interpolation, times: &[f32], values: &[Vector3<f32>]) {
+        let values = values.iter().flat_map(|x| x.iter().copied()).collect();
+        self.add_channel(target, Property::Translation, interpolation, times, 3, values);
+    }
+
+    pub fn add_rotation(&mut self, target: usize, interpolation: Interpolation, times: &[f32], values: &[UnitQuaternion<f32>]) {
+        let values = values.iter().flat_map(|x| x.coords.iter().copied()).collect();
+        self.add_channel(target, Property::Rotation, interpolation, times, 4, values);
+    }
+
+    pub fn add_scale(&mut self, target: usize, interpolation: Interpolation, times: &[f32], values: &[Vector3<f32>]) {
+        let values = values.iter().flat_map(|x| x.iter().copied()).collect();
+        self.add_channel(target, Property::Scale, interpolation, times, 3, values);
+    }
+
+    // values hold weight of every morph target for each key, see Mesh::with_morph_targets.
+    pub fn add_weights(&mut self, target: usize, interpolation: Interpolation, times: &[f32], values: &[f32]) {
+        let stride = if interpolation == Interpolation::CubicSpline { 3 } else { 1 };
+        let width = values.len() / (times.len() * stride).max(1);
+        self.add_channel(target, Property::Weights, interpolation, times, width, values.to_vec());
+    }
+
+    // time of last key of any channel
+    pub fn duration(&self) -> f32 {
+        self.duration
+    }
+
+    // writes animated properties at time into transforms indexed by channel targets, others are left as is.
+    pub fn sample(&self, time: f32, transforms: &mut [Transform]) {
+        for channel in &self.channels {
+            channel.apply(time, transforms);
+        }
+    }
+
+    // writes morph target weights of target at time, left as is if clip doesn't animate them.
+    pub fn sample_weights(&self, time: f32, target: usize, weights: &mut [f32]) {
+        for channel in self.channels.iter().filter(|x| x.property == Property::Weights && x.target == target) {
+            let mut value = vec![0.0; channel.width];
+            channel.sample(time, &mut value);
+
+            let count = weights.len().min(value.len());
+            weights[..count].copy_from_slice(&value[..count]);
+        }
+    }
+
+    fn add_channel(&mut self, target: usize, property: Property, interpolation: Interpolation, times: &[f32], width: usize, values: Vec<f32>) {
+        let stride = if interpolation == Interpolation::CubicSpline { 3 } else { 1 };
+        if times.is_empty() || width == 0 || values.len() != times.len() * stride * width {
+            panic!("Animation channel needs {} values per key", stride * width.max(1));
+        }
+
+        self.duration = self.duration.max(*times.last().unwrap());
+        self.channels.push(Channel::new(target, property, interpolation, times, width, values));
+    }
+}
+
+// Keyframed vector of floats, e.g. a material parameter driven with Material::animate.
+pub struct AnimationCurve {
+    channel: Channel,
+    looping: bool,
+}
+
+impl AnimationCurve {
+    // values hold width floats for each key, width being up to 4. for cubic splines, in tangent, value and out tangent of each.
+    pub fn new(interpolation: Interpolation, times: &[f32], values: &[f32]) -> Self {
+        let stride = if interpolation == Interpolation::CubicSpline { 3 } else { 1 };
+        let width = values.len() / (times.len() * stride).max(1);
+        if times.is_empty() || width == 0 || width > 4 || values.len() != times.len() * stride * width {
+            panic!("Animation curve needs 1 to 4 values per key");
+        }
+
+        Self {
+            channel: Channel::new(0, Property::Value, interpolation, times, width, values.to_vec()),
+            looping: true,
+        }
+    }
+
+    // curves loop by default, otherwise they hold their last value.
+    pub fn set_looping(&mut self, looping: bool) {
+        self.looping = looping;
+    }
+
+    pub fn width(&self) -> usize {
+        self.channel.width
+    }
+
+    pub fn duration(&self) -> f32 {
+        *self.channel.times.last().unwrap()
+    }
+
+    // writes value at time into result of curve width
+    pub fn sample(&self, time: f32, result: &mut [f32]) {
+        let duration = self.duration();
+        let time = if self.looping && duration > 0.0 {
+            (time % duration + duration) % duration
+        } else {
+            time
+        };
+
+        self.channel.sample(time, &mut result[..self.channel.width]);
+    }
+}
+
+struct Playback {
+    clip: Arc<AnimationClip>,
+    time: f32,
+}
+
+// Plays a clip at adjustable speed, cross-fading from previous clip when switched with crossfade.
+pub struct AnimationPlayer {
+    current: Option<Playback>,
+    // previous clip and fade duration, fading out while current one fades in
+    previous: Option<(Playback, f32)>,
+    fade_elapsed: f32,
+    speed: f32,
+    looping: bool,
+    paused: bool,
+}
+
+impl Default for AnimationPlayer {
+    fn default() -> Self {
+        Self {
+            current: None,
+            previous: None,
+            fade_elapsed: 0.0,
+            speed: 1.0,
+            looping: true,
+            paused: false,
+        }
+    }
+}
+
+impl AnimationPlayer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // starts clip from beginning, replacing current one at once
+    pub fn play(&mut self, clip: Arc<AnimationClip>) {
+        self.current = Some(Playback { clip, time: 0.0 });
+        self.previous = None;
+        self.paused = false;
+    }
+
+    // starts clip from beginning, blending from current one over duration in seconds. zero or less plays it at once.
+    pub fn crossfade(&mut self, clip: Arc<AnimationClip>, duration: f32) {
+        if duration <= 0.0 || duration.is_nan() {
+            return self.play(clip);
+        }
+
+        self.previous = self.current.take().map(|x| (x, duration));
+        self.fade_elapsed = 0.0;
+        self.current = Some(Playback { clip, time: 0.0 });
+        self.paused = false;
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    // negative speed plays backwards
+    pub fn set_speed(&mut self, speed: f32) {
+        self.speed = speed;
+    }
+
+    // clips loop by default, otherwise they stop at their ends.
+    pub fn set_looping(&mut self, looping: bool) {
+        self.looping = looping;
+    }
+
+    pub fn time(&self) -> f32 {
+        self.current.as_ref().map(|x| x.time).unwrap_or(0.0)
+    }
+
+    // true once a non looping clip reached its end
+    pub fn is_finished(&self) -> bool {
+        match &self.current {
+            Some(x) => !self.looping && (x.time >= x.clip.duration() && self.speed >= 0.0 || x.time <= 0.0 && self.speed < 0.0),
+            None => true,
+        }
+    }
+
+    // advances by frame delta in seconds
+    pub fn advance(&mut self, dt: f32) {
+        if self.paused {
+            return;
+        }
+
+        let delta = dt * self.speed;
+        let looping = self.looping;
+        if let Some(current) = &mut self.current {
+            Self::advance_playback(current, delta, looping);
+        }
+
+        if let Some((previous, duration)) = &mut self.previous {
+            Self::advance_playback(previous, delta, looping);
+
+            self.fade_elapsed += dt;
+            if self.fade_elapsed >= *duration {
+                self.previous = None;
+            }
+        }
+    }
+
+    // writes current pose into transforms indexed by channel targets.
+    pub fn apply(&self, transforms: &mut [Transform]) {
+        let current = match &self.current {
+            Some(x) => x,
+            None => return,
+        };
+
+        match &self.previous {
+            Some((previous, duration)) => {
+                let mut from = transforms.to_vec();
+                previous.clip.sample(previous.time, &mut from);
+                current.clip.sample(current.time, transforms);
+
+                let weight = (self.fade_elapsed / duration).clamp(0.0, 1.0);
+                for (transform, from) in transforms.iter_mut().zip(from.iter()) {
+                    *transform = from.interpolate(transform, weight);
+                }
+            }
+            None => current.clip.sample(current.time, transforms),
+        }
+    }
+
+    // writes current morph target weights of target, blended like transforms while cross-fading.
+    pub fn apply_weights(&self, target: usize, weights: &mut [f32]) {
+        let current = match &self.current {
+            Some(x) => x,
+            None => return,
+        };
+
+        match &self.previous {
+            Some((previous, duration)) => {
+                let mut from = weights.to_vec();
+                previous.clip.sample_weights(previous.time, target, &mut from);
+                current.clip.sample_weights(current.time, target, weights);
+
+                let weight = (self.fade_elapsed / duration).clamp(0.0, 1.0);
+                for (x, from) in weights.iter_mut().zip(from.iter()) {
+                    *x = from * (1.0 - weight) + *x * weight;
+                }
+            }
+            None => current.clip.sample_weights(current.time, target, weights),
+        }
+    }
+
+    fn advance_playback(playback: &mut Playback, delta: f32, looping: bool) {
+        let duration = playback.clip.duration();
+        playback.time += delta;
+
+        if looping && duration > 0.0 {
+            playback.time = (playback.time % duration + duration) % duration;
+        } else {
+            playback.time = playback.time.clamp(0.0, duration);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // keys at 1 and 3 with values 1 and 5, in tangent 100 and out tangent 2 on first, in tangent 2 and out tangent 100 on second
+    fn cubic_curve() -> AnimationCurve {
+        let mut curve = AnimationCurve::new(Interpolation::CubicSpline, &[1.0, 3.0], &[100.0, 1.0, 2.0, 2.0, 5.0, 100.0]);
+        curve.set_looping(false);
+
+        curve
+    }
+
+    fn sample(curve: &AnimationCurve, time: f32) -> f32 {
+        let mut result = [0.0];
+        curve.sample(time, &mut result);
+
+        result[0]
+    }
+
+    #[test]
+    fn test_cubic_spline_at_keys() {
+        let curve = cubic_curve();
+
+        // outside keys hold values, not tangents
+        assert_eq!(sample(&curve, 0.0), 1.0);
+        assert_eq!(sample(&curve, 1.0), 1.0);
+        assert_eq!(sample(&curve, 3.0), 5.0);
+        assert_eq!(sample(&curve, 4.0), 5.0);
+    }
+
+    #[test]
+    fn test_cubic_spline_between_keys() {
+        let curve = cubic_curve();
+
+        // tangents of 2 over interval of 2 make a straight line from 1 to 5
+        assert!((sample(&curve, 2.0) - 3.0).abs() < 1e-5);
+        assert!((sample(&curve, 2.5) - 4.0).abs() < 1e-5);
+        assert!((sample(&curve, 3.0 - 1e-4) - 5.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_step_and_linear() {
+        let mut step = AnimationCurve::new(Interpolation::Step, &[0.0, 1.0, 2.0], &[1.0, 2.0, 3.0]);
+        step.set_looping(false);
+        let mut linear = AnimationCurve::new(Interpolation::Linear, &[0.0, 1.0, 2.0], &[1.0, 2.0, 4.0]);
+        linear.set_looping(false);
+
+        assert_eq!(sample(&step, 0.999), 1.0);
+        assert_eq!(sample(&step, 1.0), 2.0);
+        assert_eq!(sample(&linear, 1.5), 3.0);
+        assert_eq!(sample(&linear, 2.0), 4.0);
+    }
+
+    #[test]
+    fn test_looping_curve() {
+        let curve = AnimationCurve::new(Interpolation::Linear, &[0.0, 2.0], &[0.0, 4.0]);
+
+        assert_eq!(sample(&curve, 3.0), 2.0);
+        assert_eq!(sample(&curve, -1.0), 2.0);
+    }
+}
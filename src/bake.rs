@@ -0,0 +1,152 @@
+use alloc::{vec, vec::Vec};
+
+use nalgebra::{Point3, Vector3};
+
+use crate::{Model, Ray};
+
+// keeps rays from hitting the surface they start on
+const RAY_OFFSET: f32 = 1e-4;
+// texels lightmap coverage is grown by
+const LIGHTMAP_DILATE_PASSES: usize = 2;
+
+// Bakes ambient occlusion for each vertex by casting hemisphere rays against occluders in world space.
+// Returns 1.0 for fully open and 0.0 for fully occluded vertices, to be stored in Occlusion item of VertexItemType::Float1,
+// which darkens unlit materials and ambient light of toon and pbr ones.
+pub fn bake_vertex_ao(positions: &[Point3<f32>], normals: &[Vector3<f32>], occluders: &[&Model], sample_count: usize, max_distance: f32) -> Vec<f32> {
+    let directions = hemisphere_directions(sample_count);
+
+    positions
+        .iter()
+        .zip(normals.iter())
+        .map(|(position, normal)| ambient_occlusion(position, normal, &directions, occluders, max_distance))
+        .collect()
+}
+
+// Bakes ambient occlusion into a lightmap of given size, sampled at texel centers covered by triangles in lightmap coordinates.
+// Returns rgba8 data for Texture::new with TextureFormat::Rgba8Unorm, to be used with Material::lightmapped.
+// Triangles shouldn't overlap in lightmap space, uncovered texels are filled from their neighbors so bilinear filtering doesn't bleed.
+#[allow(clippy::too_many_arguments)]
+pub fn bake_lightmap_ao(
+    positions: &[Point3<f32>],
+    normals: &[Vector3<f32>],
+    lightmap_coords: &[[f32; 2]],
+    indices: &[u16],
+    occluders: &[&Model],
+    size: (u32, u32),
+    sample_count: usize,
+    max_distance: f32,
+) -> Vec<u8> {
+    let directions = hemisphere_directions(sample_count);
+    let (width, height) = (size.0 as usize, size.1 as usize);
+    let mut texels = vec![None; width * height];
+
+    for triangle in indices.chunks_exact(3) {
+        let [a, b, c] = [triangle[0] as usize, triangle[1] as usize, triangle[2] as usize];
+        let uv = [a, b, c].map(|i| (lightmap_coords[i][0] * width as f32, lightmap_coords[i][1] * height as f32));
+
+        let area = (uv[1].0 - uv[0].0) * (uv[2].1 - uv[0].1) - (uv[2].0 - uv[0].0) * (uv[1].1 - uv[0].1);
+        if area.abs() < f32::EPSILON {
+            continue;
+        }
+
+        let min_x = uv.iter().map(|x| x.0).fold(f32::MAX, f32::min).floor().max(0.0) as usize;
+        let max_x = (uv.iter().map(|x| x.0).fold(f32::MIN, f32::max).ceil() as usize).min(width);
+        let min_y = uv.iter().map(|x| x.1).fold(f32::MAX, f32::min).floor().max(0.0) as usize;
+        let max_y = (uv.iter().map(|x| x.1).fold(f32::MIN, f32::max).ceil() as usize).min(height);
+
+        for y in min_y..max_y {
+            for x in min_x..max_x {
+                let (px, py) = (x as f32 + 0.5, y as f32 + 0.5);
+
+                // barycentric weights of texel center
+                let w0 = ((uv[1].0 - px) * (uv[2].1 - py) - (uv[2].0 - px) * (uv[1].1 - py)) / area;
+                let w1 = ((uv[2].0 - px) * (uv[0].1 - py) - (uv[0].0 - px) * (uv[2].1 - py)) / area;
+                let w2 = 1.0 - w0 - w1;
+                if w0 < 0.0 || w1 < 0.0 || w2 < 0.0 {
+                    continue;
+                }
+
+                let position = Point3::from(positions[a].coords * w0 + positions[b].coords * w1 + positions[c].coords * w2);
+                let normal = normals[a] * w0 + normals[b] * w1 + normals[c] * w2;
+
+                texels[y * width + x] = Some(ambient_occlusion(&position, &normal, &directions, occluders, max_distance));
+            }
+        }
+    }
+
+    // dilate covered texels outwards, a few texels is enough for bilinear filtering and mipmaps of small lightmaps
+    for _ in 0..LIGHTMAP_DILATE_PASSES {
+        let previous = texels.clone();
+        for y in 0..height {
+            for x in 0..width {
+                if previous[y * width + x].is_some() {
+                    continue;
+                }
+
+                let neighbors = [(-1, 0), (1, 0), (0, -1), (0, 1)]
+                    .iter()
+                    .map(|(dx, dy)| (x as isize + dx, y as isize + dy))
+                    .filter(|&(nx, ny)| nx >= 0 && ny >= 0 && (nx as usize) < width && (ny as usize) < height)
+                    .filter_map(|(nx, ny)| previous[ny as usize * width + nx as usize])
+                    .collect::<Vec<_>>();
+                if !neighbors.is_empty() {
+                    texels[y * width + x] = Some(neighbors.iter().sum::<f32>() / neighbors.len() as f32);
+                }
+            }
+        }
+    }
+
+    texels
+        .iter()
+        .flat_map(|x| {
+            let value = (x.unwrap_or(1.0).clamp(0.0, 1.0) * 255.0 + 0.5) as u8;
+            [value, value, value, 255]
+        })
+        .collect()
+}
+
+// fraction of hemisphere around normal not hit by occluders within max_distance
+fn ambient_occlusion(position: &Point3<f32>, normal: &Vector3<f32>, directions: &[Vector3<f32>], occluders: &[&Model], max_distance: f32) -> f32 {
+    let normal = normal.normalize();
+    let helper = if normal.x.abs() < 0.9 { Vector3::x() } else { Vector3::y() };
+    let tangent = normal.cross(&helper).normalize();
+    let bitangent = normal.cross(&tangent);
+    let origin = position + normal * RAY_OFFSET;
+
+    // cosine weighted, so rays near the normal count more
+    let mut occluded = 0.0;
+    let mut total = 0.0;
+    for direction in directions {
+        let ray = Ray::new(origin, tangent * direction.x + bitangent * direction.y + normal * direction.z);
+        if occluders
+            .iter()
+            .any(|x| x.intersect(&ray).map(|hit| hit.distance < max_distance).unwrap_or(false))
+        {
+            occluded += direction.z;
+        }
+        total += direction.z;
+    }
+
+    if total > 0.0 {
+        1.0 - occluded / total
+    } else {
+        1.0
+    }
+}
+
+// evenly spread directions around +z using fibonacci spiral, so bakes are deterministic
+pub(crate) fn hemisphere_directions(count: usize) -> Vec<Vector3<f32>> {
+    use core::f32::consts::PI;
+
+    let golden_angle = PI * (3.0 - 5.0f32.sqrt());
+
+    (0..count)
+        .map(|i| {
+            let z = 1.0 - (i as f32 + 0.5) / count as f32;
+            let radius = (1.0 - z * z).sqrt();
+            let angle = golden_angle * i as f32;
+
+            Vector3::new(angle.cos() * radius, angle.sin() * radius, z)
+        })
+        .collect()
+}
@@ -0,0 +1,103 @@
+use nalgebra::{Matrix4, Point3};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Aabb {
+    pub min: Point3<f32>,
+    pub max: Point3<f32>,
+}
+
+impl Aabb {
+    pub fn new(min: Point3<f32>, max: Point3<f32>) -> Self {
+        Self { min, max }
+    }
+
+    // returns None if there are no points
+    pub fn from_points<'a, I: IntoIterator<Item = &'a Point3<f32>>>(points: I) -> Option<Self> {
+        let mut points = points.into_iter();
+        let first = points.next()?;
+
+        let mut result = Self::new(*first, *first);
+        for point in points {
+            result.grow(point);
+        }
+
+        Some(result)
+    }
+
+    pub fn grow(&mut self, point: &Point3<f32>) {
+        self.min = self.min.inf(point);
+        self.max = self.max.sup(point);
+    }
+
+    pub fn center(&self) -> Point3<f32> {
+        nalgebra::center(&self.min, &self.max)
+    }
+
+    pub fn corners(&self) -> [Point3<f32>; 8] {
+        let (min, max) = (self.min, self.max);
+
+        [
+            Point3::new(min.x, min.y, min.z),
+            Point3::new(max.x, min.y, min.z),
+            Point3::new(min.x, max.y, min.z),
+            Point3::new(max.x, max.y, min.z),
+            Point3::new(min.x, min.y, max.z),
+            Point3::new(max.x, min.y, max.z),
+            Point3::new(min.x, max.y, max.z),
+            Point3::new(max.x, max.y, max.z),
+        ]
+    }
+
+    // box enclosing transformed corners
+    pub fn transform(&self, matrix: &Matrix4<f32>) -> Self {
+        let corners = self.corners();
+
+        let first = matrix.transform_point(&corners[0]);
+        let mut result = Self::new(first, first);
+        for corner in &corners[1..] {
+            result.grow(&matrix.transform_point(corner));
+        }
+
+        result
+    }
+
+    pub fn bounding_sphere(&self) -> BoundingSphere {
+        BoundingSphere::new(self.center(), nalgebra::distance(&self.min, &self.max) / 2.0)
+    }
+
+    // conservative, boxes near frustum corners may pass without being visible.
+    pub fn intersects_frustum(&self, view_projection: &Matrix4<f32>) -> bool {
+        let corners = self.corners();
+        let clip = corners.iter().map(|x| view_projection * x.to_homogeneous());
+
+        // outside if every corner is beyond same clip plane
+        let mut outside = [true; 6];
+        for x in clip {
+            let planes = [x.x < -x.w, x.x > x.w, x.y < -x.w, x.y > x.w, x.z < 0.0, x.z > x.w];
+            for (outside, plane) in outside.iter_mut().zip(planes.iter()) {
+                *outside &= *plane;
+            }
+        }
+
+        !outside.iter().any(|x| *x)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BoundingSphere {
+    pub center: Point3<f32>,
+    pub radius: f32,
+}
+
+impl BoundingSphere {
+    pub fn new(center: Point3<f32>, radius: f32) -> Self {
+        Self { center, radius }
+    }
+
+    // radius grows by largest axis scale of matrix
+    pub fn transform(&self, matrix: &Matrix4<f32>) -> Self {
+        let scale = (0..3).map(|i| matrix.fixed_slice::<3, 1>(0, i).norm()).fold(0.0f32, f32::max);
+
+        Self::new(matrix.transform_point(&self.center), self.radius * scale)
+    }
+}
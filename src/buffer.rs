@@ -1,22 +1,47 @@
 use alloc::{boxed::Box, sync::Arc, vec};
-use core::ops::Drop;
+use core::{
+    ops::Drop,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use crate::{event::EventQueue, staging_belt::StagingBelt, RendererEvent};
+
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+// ids of wgpu buffers, which unlike their addresses aren't reused once a buffer is dropped
+pub(crate) fn next_id() -> usize {
+    NEXT_ID.fetch_add(1, Ordering::Relaxed)
+}
 
 pub struct Buffer {
-    queue: Arc<wgpu::Queue>,
+    staging_belt: Arc<StagingBelt>,
+    events: EventQueue,
     pub(crate) buffer: Arc<wgpu::Buffer>,
+    // of buffer, from next_id
+    pub(crate) id: usize,
     pub(crate) offset: usize,
-    size: usize,
+    pub(crate) size: usize,
     free: Box<dyn Fn() + Sync + Send + 'static>,
 }
 
 impl Buffer {
-    pub(crate) fn new<F>(queue: Arc<wgpu::Queue>, buffer: Arc<wgpu::Buffer>, offset: usize, size: usize, free: F) -> Self
+    pub(crate) fn new<F>(
+        staging_belt: Arc<StagingBelt>,
+        events: EventQueue,
+        buffer: Arc<wgpu::Buffer>,
+        id: usize,
+        offset: usize,
+        size: usize,
+        free: F,
+    ) -> Self
     where
         F: Fn() + Sync + Send + 'static,
     {
         Self {
-            queue,
+            staging_belt,
+            events,
             buffer,
+            id,
             offset,
             size,
             free: Box::new(free),
@@ -24,19 +49,25 @@ impl Buffer {
     }
 
     pub fn write(&self, data: &[u8]) {
-        // TODO raise error or warn
-        if data.len() % wgpu::COPY_BUFFER_ALIGNMENT as usize != 0 {
-            let count = data.len() % wgpu::COPY_BUFFER_ALIGNMENT as usize;
-            let mut new_buf = vec![0; data.len() + count];
+        if !data.len().is_multiple_of(wgpu::COPY_BUFFER_ALIGNMENT as usize) {
+            self.events.push(RendererEvent::UnalignedBufferWrite { size: data.len() });
+
+            let alignment = wgpu::COPY_BUFFER_ALIGNMENT as usize;
+            let mut new_buf = vec![0; data.len().div_ceil(alignment) * alignment];
             new_buf[..data.len()].copy_from_slice(data);
 
-            self.queue.write_buffer(&self.buffer, self.offset as u64, &new_buf)
+            self.staging_belt.write_buffer(&self.buffer, self.offset as u64, &new_buf)
         } else {
-            self.queue.write_buffer(&self.buffer, self.offset as u64, data)
+            self.staging_belt.write_buffer(&self.buffer, self.offset as u64, data)
         }
     }
 
-    pub(crate) fn binding_resource(&self) -> wgpu::BindingResource {
+    // writes part of buffer, offset and data length must be multiples of 4
+    pub(crate) fn write_at(&self, offset: usize, data: &[u8]) {
+        self.staging_belt.write_buffer(&self.buffer, (self.offset + offset) as u64, data)
+    }
+
+    pub(crate) fn binding_resource(&self) -> wgpu::BindingResource<'_> {
         wgpu::BindingResource::Buffer(wgpu::BufferBinding {
             buffer: &self.buffer,
             offset: self.offset as wgpu::BufferAddress,
@@ -44,7 +75,7 @@ impl Buffer {
         })
     }
 
-    pub(crate) fn as_slice(&self) -> wgpu::BufferSlice {
+    pub(crate) fn as_slice(&self) -> wgpu::BufferSlice<'_> {
         self.buffer.slice(self.offset as u64..self.offset as u64 + self.size as u64)
     }
 }
@@ -2,22 +2,30 @@ use alloc::{collections::BTreeMap, sync::Arc, vec::Vec};
 
 use spinning_top::Spinlock;
 
-use crate::buffer::Buffer;
+use crate::{
+    buffer::{self, Buffer},
+    deletion_queue::DeletionQueue,
+    event::EventQueue,
+    memory::{Allocation, MemoryTracker},
+    staging_belt::StagingBelt,
+};
 
 const BUFFER_SIZE: usize = 10485760;
 
 struct BufferPoolItem {
     buffer: Arc<wgpu::Buffer>,
+    id: usize,
     allocated: usize,
     allocations: BTreeMap<usize, usize>,
+    _allocation: Allocation,
 }
 
 impl BufferPoolItem {
-    pub fn new(device: &wgpu::Device, usage: wgpu::BufferUsages) -> Self {
+    pub fn new(device: &wgpu::Device, label: &str, usage: wgpu::BufferUsages, allocation: Allocation) -> Self {
         let buffer = Arc::new(device.create_buffer(&wgpu::BufferDescriptor {
             size: BUFFER_SIZE as u64,
             usage,
-            label: None,
+            label: Some(label),
             mapped_at_creation: false,
         }));
 
@@ -26,12 +34,14 @@ impl BufferPoolItem {
 
         Self {
             buffer,
+            id: buffer::next_id(),
             allocated: 0,
             allocations,
+            _allocation: allocation,
         }
     }
 
-    pub fn alloc(&mut self, size: usize) -> Option<(Arc<wgpu::Buffer>, usize)> {
+    pub fn alloc(&mut self, size: usize) -> Option<(Arc<wgpu::Buffer>, usize, usize)> {
         let alignment = wgpu::BIND_BUFFER_ALIGNMENT as usize;
         let rounded_size = Self::round_up(size, alignment);
 
@@ -40,12 +50,14 @@ impl BufferPoolItem {
         self.allocated += rounded_size;
         self.allocations.insert(offset, rounded_size);
 
-        Some((self.buffer.clone(), offset))
+        Some((self.buffer.clone(), self.id, offset))
     }
 
-    pub fn free(&mut self, offset: usize, size: usize) {
-        self.allocated -= size;
-        self.allocations.remove(&offset);
+    // size is the rounded one recorded by alloc, so allocated gets back to zero once all are freed
+    pub fn free(&mut self, offset: usize) {
+        if let Some(size) = self.allocations.remove(&offset) {
+            self.allocated -= size;
+        }
     }
 
     // simple allocator. may fragment a lot.
@@ -77,7 +89,10 @@ impl BufferPoolItem {
 
 pub struct BufferPool {
     device: Arc<wgpu::Device>,
-    queue: Arc<wgpu::Queue>,
+    staging_belt: Arc<StagingBelt>,
+    events: EventQueue,
+    memory: MemoryTracker,
+    deletion_queue: Arc<DeletionQueue>,
 
     // WebGL requires separate index buffer (https://www.khronos.org/registry/webgl/specs/latest/2.0/#5.1)
     buffers: Spinlock<Vec<Arc<Spinlock<BufferPoolItem>>>>,
@@ -85,10 +100,19 @@ pub struct BufferPool {
 }
 
 impl BufferPool {
-    pub fn new(device: Arc<wgpu::Device>, queue: Arc<wgpu::Queue>) -> Self {
+    pub(crate) fn new(
+        device: Arc<wgpu::Device>,
+        staging_belt: Arc<StagingBelt>,
+        events: EventQueue,
+        memory: MemoryTracker,
+        deletion_queue: Arc<DeletionQueue>,
+    ) -> Self {
         Self {
             device,
-            queue,
+            staging_belt,
+            events,
+            memory,
+            deletion_queue,
             index_buffers: Spinlock::new(Vec::new()),
             buffers: Spinlock::new(Vec::new()),
         }
@@ -112,24 +136,57 @@ impl BufferPool {
                 return x;
             }
         }
-        buffers.push(Arc::new(Spinlock::new(BufferPoolItem::new(&self.device, Self::convert_usage(is_index)))));
+        let label = if is_index { "index buffer pool" } else { "buffer pool" };
+        let allocation = self.memory.track(label, BUFFER_SIZE);
+        buffers.push(Arc::new(Spinlock::new(BufferPoolItem::new(
+            &self.device,
+            label,
+            Self::convert_usage(is_index),
+            allocation,
+        ))));
         self.try_alloc(buffers.last().unwrap(), size).unwrap()
     }
 
+    // drops chunks without buffers, which are allocated again on demand.
+    // new chunks get new ids, so bind groups cached for dropped ones aren't handed out for them.
+    pub(crate) fn trim(&self) {
+        for buffers in [&self.buffers, &self.index_buffers] {
+            buffers.lock().retain(|x| x.lock().allocated != 0);
+        }
+    }
+
+    // bytes of pool buffers handed out, including alignment
+    pub(crate) fn used(&self) -> usize {
+        let used = |buffers: &Spinlock<Vec<Arc<Spinlock<BufferPoolItem>>>>| buffers.lock().iter().map(|x| x.lock().allocated).sum::<usize>();
+
+        used(&self.buffers) + used(&self.index_buffers)
+    }
+
     fn try_alloc(&self, buffers: &Arc<Spinlock<BufferPoolItem>>, size: usize) -> Option<Buffer> {
-        let (buffer, offset) = buffers.lock().alloc(size)?;
+        let (buffer, id, offset) = buffers.lock().alloc(size)?;
 
+        // range is reused only after frames reading it are done
         let buffer_item = buffers.clone();
-        Some(Buffer::new(self.queue.clone(), buffer, offset, size, move || {
-            buffer_item.lock().free(offset, size)
-        }))
+        let deletion_queue = self.deletion_queue.clone();
+        Some(Buffer::new(
+            self.staging_belt.clone(),
+            self.events.clone(),
+            buffer,
+            id,
+            offset,
+            size,
+            move || {
+                let buffer_item = buffer_item.clone();
+                deletion_queue.defer(move || buffer_item.lock().free(offset))
+            },
+        ))
     }
 
     fn convert_usage(is_index: bool) -> wgpu::BufferUsages {
         if is_index {
             wgpu::BufferUsages::INDEX | wgpu::BufferUsages::COPY_DST
         } else {
-            wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST
+            wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST
         }
     }
 }
@@ -0,0 +1,246 @@
+use alloc::sync::Arc;
+
+use zerocopy::AsBytes;
+
+use crate::{Buffer, CullMode, Material, PlanarReflection, Renderer, Shader, ShaderBinding, ShaderBindingType, ShaderStage, Texture};
+
+// Ready made materials for stylized rendering, which don't need lighting setup.
+// Unlit, toon, pbr and lightmapped ones can be masked with Material::set_alpha_cutoff.
+impl Material {
+    // texture multiplied by color. with vertex_color, mesh must have Color item too.
+    // unlit, toon and pbr ones are darkened by Occlusion item of mesh if it has one, see bake_vertex_ao.
+    pub fn unlit(renderer: &Renderer, texture: Arc<Texture>, color: [f32; 4], vertex_color: bool) -> Self {
+        let (vs_entry, inputs): (_, &[_]) = if vertex_color {
+            ("vs_vertex_color", &[("Position", 0), ("TexCoord", 1), ("Color", 2)])
+        } else {
+            ("vs_main", &[("Position", 0), ("TexCoord", 1), ("Occlusion", 3)])
+        };
+
+        let shader = Shader::new(
+            renderer,
+            include_str!("../shaders/unlit.wgsl"),
+            vs_entry,
+            "fs_main",
+            &[
+                ("Mvp", ShaderBinding::new(ShaderStage::Vertex, 0, ShaderBindingType::UniformBuffer)),
+                ("Texture", ShaderBinding::new(ShaderStage::Fragment, 1, ShaderBindingType::Texture2D)),
+                ("Sampler", ShaderBinding::new(ShaderStage::Fragment, 2, ShaderBindingType::Sampler)),
+                ("Unlit", ShaderBinding::new(ShaderStage::Vertex, 3, ShaderBindingType::UniformBuffer)),
+                (
+                    "AlphaCutoff",
+                    ShaderBinding::new(ShaderStage::Fragment, 4, ShaderBindingType::UniformBuffer),
+                ),
+            ],
+            inputs,
+        );
+        // occlusion can be baked into Color item instead with vertex_color
+        let shader = if vertex_color {
+            shader
+        } else {
+            shader.with_occlusion_entry("vs_occlusion").with_instanced_entry("vs_instanced")
+        };
+
+        let unlit_buf = Arc::new(renderer.buffer_pool.alloc(color.as_bytes().len()));
+        unlit_buf.write(color.as_bytes());
+
+        Self::new(renderer, &[("Texture", texture)], &[("Unlit", unlit_buf)], Arc::new(shader))
+    }
+
+    // sun and point lighting looked up from ramp texture by half lambert term. mesh must have Normal item.
+    pub fn toon(renderer: &Renderer, texture: Arc<Texture>, ramp: Arc<Texture>, color: [f32; 4]) -> Self {
+        let shader = Shader::new(
+            renderer,
+            include_str!("../shaders/toon.wgsl"),
+            "vs_main",
+            "fs_main",
+            &[
+                ("Mvp", ShaderBinding::new(ShaderStage::Vertex, 0, ShaderBindingType::UniformBuffer)),
+                ("Texture", ShaderBinding::new(ShaderStage::Fragment, 1, ShaderBindingType::Texture2D)),
+                ("Sampler", ShaderBinding::new(ShaderStage::Fragment, 2, ShaderBindingType::Sampler)),
+                ("Ramp", ShaderBinding::new(ShaderStage::Fragment, 3, ShaderBindingType::Texture2D)),
+                (
+                    "Toon",
+                    ShaderBinding::new(ShaderStage::VertexFragment, 4, ShaderBindingType::UniformBuffer),
+                ),
+                ("Lighting", ShaderBinding::new(ShaderStage::Fragment, 5, ShaderBindingType::UniformBuffer)),
+                (
+                    "AlphaCutoff",
+                    ShaderBinding::new(ShaderStage::Fragment, 6, ShaderBindingType::UniformBuffer),
+                ),
+                ("Clusters", ShaderBinding::new(ShaderStage::Fragment, 7, ShaderBindingType::UniformBuffer)),
+                (
+                    "PointLights",
+                    ShaderBinding::new(ShaderStage::Fragment, 8, ShaderBindingType::ReadOnlyStorageBuffer),
+                ),
+                (
+                    "ClusterLights",
+                    ShaderBinding::new(ShaderStage::Fragment, 9, ShaderBindingType::ReadOnlyStorageBuffer),
+                ),
+            ],
+            &[("Position", 0), ("TexCoord", 1), ("Normal", 2), ("Occlusion", 3)],
+        )
+        .with_occlusion_entry("vs_occlusion")
+        .with_instanced_entry("vs_instanced");
+
+        let toon_buf = Self::toon_uniform(renderer, color, [0.0; 4]);
+
+        Self::new(renderer, &[("Texture", texture), ("Ramp", ramp)], &[("Toon", toon_buf)], Arc::new(shader))
+    }
+
+    // back faces pushed out along normals by width. draw with another model of same mesh as toon one.
+    pub fn toon_outline(renderer: &Renderer, color: [f32; 3], width: f32) -> Self {
+        let shader = Shader::new(
+            renderer,
+            include_str!("../shaders/toon.wgsl"),
+            "vs_outline",
+            "fs_outline",
+            &[
+                ("Mvp", ShaderBinding::new(ShaderStage::Vertex, 0, ShaderBindingType::UniformBuffer)),
+                (
+                    "Toon",
+                    ShaderBinding::new(ShaderStage::VertexFragment, 4, ShaderBindingType::UniformBuffer),
+                ),
+            ],
+            &[("Position", 0), ("Normal", 2)],
+        );
+
+        let toon_buf = Self::toon_uniform(renderer, [1.0; 4], [color[0], color[1], color[2], width]);
+
+        let mut material = Self::new(renderer, &[], &[("Toon", toon_buf)], Arc::new(shader));
+        material.render_state.cull_mode = CullMode::Front;
+
+        material
+    }
+
+    // metallic roughness shading of sun, point lights and image based lighting prefiltered from LightingEnvironment::skybox.
+    // mesh must have Normal item.
+    pub fn pbr(renderer: &Renderer, texture: Arc<Texture>, color: [f32; 4], metallic: f32, roughness: f32) -> Self {
+        let shader = Shader::new(
+            renderer,
+            include_str!("../shaders/pbr.wgsl"),
+            "vs_main",
+            "fs_main",
+            &[
+                ("Mvp", ShaderBinding::new(ShaderStage::Vertex, 0, ShaderBindingType::UniformBuffer)),
+                ("Texture", ShaderBinding::new(ShaderStage::Fragment, 1, ShaderBindingType::Texture2D)),
+                ("Sampler", ShaderBinding::new(ShaderStage::Fragment, 2, ShaderBindingType::Sampler)),
+                ("Pbr", ShaderBinding::new(ShaderStage::Fragment, 3, ShaderBindingType::UniformBuffer)),
+                ("Lighting", ShaderBinding::new(ShaderStage::Fragment, 4, ShaderBindingType::UniformBuffer)),
+                (
+                    "IrradianceMap",
+                    ShaderBinding::new(ShaderStage::Fragment, 5, ShaderBindingType::Texture2D),
+                ),
+                ("SpecularMap", ShaderBinding::new(ShaderStage::Fragment, 6, ShaderBindingType::Texture2D)),
+                ("BrdfLut", ShaderBinding::new(ShaderStage::Fragment, 7, ShaderBindingType::Texture2D)),
+                (
+                    "EnvironmentSampler",
+                    ShaderBinding::new(ShaderStage::Fragment, 8, ShaderBindingType::Sampler),
+                ),
+                (
+                    "AlphaCutoff",
+                    ShaderBinding::new(ShaderStage::Fragment, 9, ShaderBindingType::UniformBuffer),
+                ),
+                (
+                    "Clusters",
+                    ShaderBinding::new(ShaderStage::Fragment, 10, ShaderBindingType::UniformBuffer),
+                ),
+                (
+                    "PointLights",
+                    ShaderBinding::new(ShaderStage::Fragment, 11, ShaderBindingType::ReadOnlyStorageBuffer),
+                ),
+                (
+                    "ClusterLights",
+                    ShaderBinding::new(ShaderStage::Fragment, 12, ShaderBindingType::ReadOnlyStorageBuffer),
+                ),
+            ],
+            &[("Position", 0), ("TexCoord", 1), ("Normal", 2), ("Occlusion", 3)],
+        )
+        .with_gbuffer_entry("fs_gbuffer")
+        .with_occlusion_entry("vs_occlusion")
+        .with_instanced_entry("vs_instanced");
+
+        let data = [color[0], color[1], color[2], color[3], metallic, roughness, 0.0, 0.0];
+        let pbr_buf = Arc::new(renderer.buffer_pool.alloc(data.as_bytes().len()));
+        pbr_buf.write(data.as_bytes());
+
+        Self::new(renderer, &[("Texture", texture)], &[("Pbr", pbr_buf)], Arc::new(shader))
+    }
+
+    // texture multiplied by color and light baked into lightmap, e.g. by bake_lightmap_ao. for static geometry,
+    // mesh must have LightmapCoord item as in LightmapVertex.
+    pub fn lightmapped(renderer: &Renderer, texture: Arc<Texture>, lightmap: Arc<Texture>, color: [f32; 4], intensity: f32) -> Self {
+        let shader = Shader::new(
+            renderer,
+            include_str!("../shaders/lightmap.wgsl"),
+            "vs_main",
+            "fs_main",
+            &[
+                ("Mvp", ShaderBinding::new(ShaderStage::Vertex, 0, ShaderBindingType::UniformBuffer)),
+                ("Texture", ShaderBinding::new(ShaderStage::Fragment, 1, ShaderBindingType::Texture2D)),
+                ("Sampler", ShaderBinding::new(ShaderStage::Fragment, 2, ShaderBindingType::Sampler)),
+                (
+                    "Lightmapped",
+                    ShaderBinding::new(ShaderStage::Fragment, 3, ShaderBindingType::UniformBuffer),
+                ),
+                ("Lightmap", ShaderBinding::new(ShaderStage::Fragment, 4, ShaderBindingType::Texture2D)),
+                (
+                    "AlphaCutoff",
+                    ShaderBinding::new(ShaderStage::Fragment, 5, ShaderBindingType::UniformBuffer),
+                ),
+            ],
+            &[("Position", 0), ("TexCoord", 1), ("LightmapCoord", 2)],
+        );
+
+        let data = [color[0], color[1], color[2], color[3], intensity, 0.0, 0.0, 0.0];
+        let lightmapped_buf = Arc::new(renderer.buffer_pool.alloc(data.as_bytes().len()));
+        lightmapped_buf.write(data.as_bytes());
+
+        Self::new(
+            renderer,
+            &[("Texture", texture), ("Lightmap", lightmap)],
+            &[("Lightmapped", lightmapped_buf)],
+            Arc::new(shader),
+        )
+    }
+
+    // texture multiplied by color, blended towards reflection by reflectivity. for meshes on the reflection's plane.
+    pub fn mirror(renderer: &Renderer, reflection: &PlanarReflection, texture: Arc<Texture>, color: [f32; 4], reflectivity: f32) -> Self {
+        let shader = Shader::new(
+            renderer,
+            include_str!("../shaders/mirror.wgsl"),
+            "vs_main",
+            "fs_main",
+            &[
+                ("Mvp", ShaderBinding::new(ShaderStage::Vertex, 0, ShaderBindingType::UniformBuffer)),
+                ("Texture", ShaderBinding::new(ShaderStage::Fragment, 1, ShaderBindingType::Texture2D)),
+                ("Sampler", ShaderBinding::new(ShaderStage::Fragment, 2, ShaderBindingType::Sampler)),
+                ("Mirror", ShaderBinding::new(ShaderStage::Fragment, 3, ShaderBindingType::UniformBuffer)),
+                ("Reflection", ShaderBinding::new(ShaderStage::Fragment, 4, ShaderBindingType::Texture2D)),
+                (
+                    "ReflectionView",
+                    ShaderBinding::new(ShaderStage::Fragment, 5, ShaderBindingType::UniformBuffer),
+                ),
+            ],
+            &[("Position", 0), ("TexCoord", 1)],
+        );
+
+        let data = [color[0], color[1], color[2], color[3], reflectivity, 0.0, 0.0, 0.0];
+        let mirror_buf = Arc::new(renderer.buffer_pool.alloc(data.as_bytes().len()));
+        mirror_buf.write(data.as_bytes());
+
+        Self::new(
+            renderer,
+            &[("Texture", texture), ("Reflection", reflection.texture())],
+            &[("Mirror", mirror_buf), ("ReflectionView", reflection.view_buffer())],
+            Arc::new(shader),
+        )
+    }
+
+    fn toon_uniform(renderer: &Renderer, color: [f32; 4], outline: [f32; 4]) -> Arc<Buffer> {
+        let data = [color, outline];
+        let buffer = Arc::new(renderer.buffer_pool.alloc(data.as_bytes().len()));
+        buffer.write(data.as_bytes());
+
+        buffer
+    }
+}
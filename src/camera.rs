@@ -1,16 +1,279 @@
-use nalgebra::{Matrix4, Point3};
+use nalgebra::{Matrix4, Point3, Vector3};
 
+use crate::{Color, Model, Ray, RenderLayers};
+
+// Anything providing view and projection, so camera rigs and 2d cameras can be driven the same way.
+// scene is drawn from one with Renderer::render_viewpoint.
+pub trait Viewpoint {
+    fn view_matrix(&self) -> Matrix4<f32>;
+    fn projection_matrix(&self, viewport_size: (u32, u32)) -> Matrix4<f32>;
+
+    // advances smoothing or animation by dt seconds
+    fn update(&mut self, _dt: f32) {}
+
+    // origin of view space, for lighting and sorting transparent renderables
+    fn eye(&self) -> Point3<f32> {
+        let inverse = self.view_matrix().try_inverse().unwrap_or_else(Matrix4::identity);
+
+        inverse.transform_point(&Point3::origin())
+    }
+
+    // only renderables on one of these layers are drawn
+    fn layers(&self) -> RenderLayers {
+        RenderLayers::default()
+    }
+
+    fn clear(&self) -> ClearConfig {
+        ClearConfig::default()
+    }
+}
+
+// How targets are cleared before a camera draws. None keeps what's drawn before, e.g. for overlay cameras.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct ClearConfig {
+    pub color: Option<Color>,
+    pub depth: Option<f32>,
+}
+
+impl Default for ClearConfig {
+    fn default() -> Self {
+        Self {
+            color: Some(Color::WHITE),
+            depth: Some(1.0),
+        }
+    }
+}
+
+#[derive(Clone)]
 pub struct Camera {
     eye: Point3<f32>,
     target: Point3<f32>,
+    layers: RenderLayers,
+    clear: ClearConfig,
+    // vertical, in degrees
+    fov: f32,
+    // distances of clip planes along view direction
+    near: f32,
+    far: f32,
 }
 
 impl Camera {
     pub fn new(eye: Point3<f32>, target: Point3<f32>) -> Self {
-        Camera { eye, target }
+        Camera {
+            eye,
+            target,
+            layers: RenderLayers::default(),
+            clear: ClearConfig::default(),
+            fov: 45.0,
+            near: 1.0,
+            far: 10.0,
+        }
+    }
+
+    pub fn eye(&self) -> Point3<f32> {
+        self.eye
+    }
+
+    pub fn target(&self) -> Point3<f32> {
+        self.target
+    }
+
+    // only renderables on one of these layers are drawn
+    pub fn set_layers(&mut self, layers: RenderLayers) {
+        self.layers = layers;
+    }
+
+    pub fn layers(&self) -> RenderLayers {
+        self.layers
+    }
+
+    pub fn set_clear(&mut self, clear: ClearConfig) {
+        self.clear = clear;
+    }
+
+    pub fn clear(&self) -> ClearConfig {
+        self.clear
+    }
+
+    // vertical field of view in degrees, 45 by default
+    pub fn set_fov(&mut self, fov: f32) {
+        self.fov = fov;
+    }
+
+    pub fn fov(&self) -> f32 {
+        self.fov
+    }
+
+    // distances of near and far clip planes from eye, 1 and 10 by default. set by frame to fit the model.
+    pub fn set_clip_planes(&mut self, near: f32, far: f32) {
+        self.near = near;
+        self.far = far;
+    }
+
+    pub fn clip_planes(&self) -> (f32, f32) {
+        (self.near, self.far)
     }
 
     pub fn view(&self) -> Matrix4<f32> {
-        nalgebra::Matrix4::look_at_rh(&self.eye, &self.target, &nalgebra::Vector3::y_axis())
+        nalgebra::Matrix4::look_at_rh(&self.eye, &self.target, &self.up())
+    }
+
+    // up vector of view. looking straight down has -z as up on screen, and straight up +z
+    fn up(&self) -> Vector3<f32> {
+        let forward = self.target - self.eye;
+
+        if forward.cross(&Vector3::y()).norm_squared() > f32::EPSILON * forward.norm_squared() {
+            Vector3::y()
+        } else {
+            Vector3::z() * forward.y.signum()
+        }
+    }
+
+    pub fn projection(&self, aspect_ratio: f32) -> Matrix4<f32> {
+        use core::f32::consts::PI;
+
+        nalgebra::Matrix4::new_perspective(aspect_ratio, self.fov * PI / 180.0, self.near, self.far)
+    }
+
+    // ray from eye through given pixel of viewport, for picking and gameplay queries.
+    pub fn screen_ray(&self, x: f32, y: f32, viewport_size: (u32, u32)) -> Ray {
+        let aspect_ratio = viewport_size.0 as f32 / viewport_size.1 as f32;
+        let inverse = (self.projection(aspect_ratio) * self.view()).try_inverse().unwrap();
+
+        let ndc_x = x / viewport_size.0 as f32 * 2.0 - 1.0;
+        let ndc_y = 1.0 - y / viewport_size.1 as f32 * 2.0;
+        let near = inverse.transform_point(&Point3::new(ndc_x, ndc_y, -1.0));
+        let far = inverse.transform_point(&Point3::new(ndc_x, ndc_y, 1.0));
+
+        Ray::new(near, (far - near).normalize())
+    }
+
+    // moves camera along view direction so model's bounding sphere fits in view, and clip planes around it
+    pub fn frame(&mut self, model: &Model) {
+        use core::f32::consts::PI;
+
+        if let Some(sphere) = model.bounding_sphere() {
+            let direction = (self.eye - self.target).normalize();
+            let distance = sphere.radius / (self.fov * PI / 180.0 / 2.0).sin();
+
+            self.target = sphere.center;
+            self.eye = sphere.center + direction * distance;
+            // near plane is kept away from eye, depth precision drops as it gets closer
+            self.near = (distance - sphere.radius).max(distance * 0.01);
+            self.far = distance + sphere.radius;
+        }
+    }
+
+    // t of 0 is self and 1 is other
+    pub fn lerp(&self, other: &Camera, t: f32) -> Self {
+        Self {
+            eye: self.eye + (other.eye - self.eye) * t,
+            target: self.target + (other.target - self.target) * t,
+            layers: self.layers,
+            clear: self.clear,
+            fov: self.fov + (other.fov - self.fov) * t,
+            near: self.near + (other.near - self.near) * t,
+            far: self.far + (other.far - self.far) * t,
+        }
+    }
+
+    // eye and target reflected by plane through point, for planar reflections. up stays y, so image isn't mirrored.
+    pub(crate) fn mirrored(&self, point: &Point3<f32>, normal: &Vector3<f32>) -> Self {
+        let reflect = |x: &Point3<f32>| x - normal * (2.0 * (x - point).dot(normal));
+
+        Self {
+            eye: reflect(&self.eye),
+            target: reflect(&self.target),
+            ..self.clone()
+        }
+    }
+
+    // looking from eye along direction with given settings of self, e.g. for faces of reflection probes
+    pub(crate) fn looking(&self, eye: Point3<f32>, direction: Vector3<f32>, fov: f32) -> Self {
+        Self {
+            eye,
+            target: eye + direction,
+            fov,
+            ..self.clone()
+        }
+    }
+
+    // moves camera sideways keeping view direction, e.g. for each eye of stereo rendering.
+    // right is that of view, so eyes don't swap when looking straight up or down.
+    pub fn offset(&self, distance: f32) -> Self {
+        let right = (self.target - self.eye).cross(&self.up()).normalize() * distance;
+
+        Self {
+            eye: self.eye + right,
+            target: self.target + right,
+            layers: self.layers,
+            clear: self.clear,
+            fov: self.fov,
+            near: self.near,
+            far: self.far,
+        }
+    }
+}
+
+impl Viewpoint for Camera {
+    fn view_matrix(&self) -> Matrix4<f32> {
+        self.view()
+    }
+
+    fn projection_matrix(&self, viewport_size: (u32, u32)) -> Matrix4<f32> {
+        self.projection(viewport_size.0 as f32 / viewport_size.1 as f32)
+    }
+
+    fn eye(&self) -> Point3<f32> {
+        self.eye
+    }
+
+    fn layers(&self) -> RenderLayers {
+        self.layers
+    }
+
+    fn clear(&self) -> ClearConfig {
+        self.clear
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // right eye is offset along x axis of view
+    fn assert_eyes_match_view(camera: &Camera) {
+        let view = camera.view();
+        let right = Vector3::new(view[(0, 0)], view[(0, 1)], view[(0, 2)]);
+        let separation = camera.offset(0.5).eye() - camera.offset(-0.5).eye();
+
+        assert!((separation - right).norm() < 1e-5, "{:?} {:?}", separation, right);
+    }
+
+    #[test]
+    fn test_offset_along_view_right() {
+        assert_eyes_match_view(&Camera::new(Point3::new(0.0, 0.0, 5.0), Point3::origin()));
+        assert_eyes_match_view(&Camera::new(Point3::new(3.0, 1.0, -2.0), Point3::new(1.0, 0.0, 1.0)));
+    }
+
+    #[test]
+    fn test_offset_looking_along_y() {
+        // straight down and straight up
+        assert_eyes_match_view(&Camera::new(Point3::new(0.0, 5.0, 0.0), Point3::origin()));
+        assert_eyes_match_view(&Camera::new(Point3::new(0.0, -5.0, 0.0), Point3::origin()));
+    }
+
+    // stereo eyes of any viewpoint are offset as cameras are
+    #[test]
+    fn test_viewpoint_eye_matches_offset() {
+        let camera = Camera::new(Point3::new(3.0, 1.0, -2.0), Point3::new(1.0, 0.0, 1.0));
+        let eye = crate::stereo::Eye {
+            viewpoint: &camera,
+            offset: 0.5,
+        };
+        let offset = camera.offset(0.5);
+
+        assert!((eye.view_matrix() - offset.view()).norm() < 1e-5);
+        assert!((Viewpoint::eye(&eye) - offset.eye()).norm() < 1e-5);
     }
 }
@@ -0,0 +1,100 @@
+use nalgebra::{Matrix4, Point2, Point3, Vector3};
+
+use crate::Viewpoint;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Camera2DUnits {
+    // one unit is one pixel at zoom 1, y grows downward like window coordinates
+    Pixels,
+    // given number of world units fit in view height at zoom 1, y grows upward
+    World { view_height: f32 },
+}
+
+// Orthographic camera for 2d content, position is the world point at the center of the viewport.
+pub struct Camera2D {
+    pub position: Point2<f32>,
+    pub zoom: f32,
+    units: Camera2DUnits,
+}
+
+impl Camera2D {
+    pub fn new(units: Camera2DUnits) -> Self {
+        Self {
+            position: Point2::origin(),
+            zoom: 1.0,
+            units,
+        }
+    }
+
+    // pixel space camera with world origin at top left of the viewport
+    pub fn pixels(viewport_size: (u32, u32)) -> Self {
+        Self {
+            position: Point2::new(viewport_size.0 as f32 / 2.0, viewport_size.1 as f32 / 2.0),
+            zoom: 1.0,
+            units: Camera2DUnits::Pixels,
+        }
+    }
+
+    pub fn units(&self) -> Camera2DUnits {
+        self.units
+    }
+
+    pub fn view(&self) -> Matrix4<f32> {
+        Matrix4::new_translation(&Vector3::new(-self.position.x, -self.position.y, 0.0))
+    }
+
+    // content is visible for z in -1..1
+    pub fn projection(&self, viewport_size: (u32, u32)) -> Matrix4<f32> {
+        let (half_width, half_height) = self.half_extent(viewport_size);
+
+        match self.units {
+            Camera2DUnits::Pixels => Matrix4::new_orthographic(-half_width, half_width, half_height, -half_height, -1.0, 1.0),
+            Camera2DUnits::World { .. } => Matrix4::new_orthographic(-half_width, half_width, -half_height, half_height, -1.0, 1.0),
+        }
+    }
+
+    // x and y are in pixels from top left of the viewport
+    pub fn screen_to_world(&self, x: f32, y: f32, viewport_size: (u32, u32)) -> Point2<f32> {
+        let inverse = (self.projection(viewport_size) * self.view()).try_inverse().unwrap();
+
+        let ndc_x = x / viewport_size.0 as f32 * 2.0 - 1.0;
+        let ndc_y = 1.0 - y / viewport_size.1 as f32 * 2.0;
+        let world = inverse.transform_point(&Point3::new(ndc_x, ndc_y, 0.0));
+
+        Point2::new(world.x, world.y)
+    }
+
+    pub fn world_to_screen(&self, point: Point2<f32>, viewport_size: (u32, u32)) -> (f32, f32) {
+        let ndc = (self.projection(viewport_size) * self.view()).transform_point(&Point3::new(point.x, point.y, 0.0));
+
+        ((ndc.x + 1.0) / 2.0 * viewport_size.0 as f32, (1.0 - ndc.y) / 2.0 * viewport_size.1 as f32)
+    }
+
+    // zooms by factor keeping world point under given pixel in place, e.g. for mouse wheel zoom
+    pub fn zoom_at(&mut self, factor: f32, x: f32, y: f32, viewport_size: (u32, u32)) {
+        let before = self.screen_to_world(x, y, viewport_size);
+        self.zoom *= factor;
+        let after = self.screen_to_world(x, y, viewport_size);
+
+        self.position += before - after;
+    }
+
+    fn half_extent(&self, viewport_size: (u32, u32)) -> (f32, f32) {
+        let (width, height) = (viewport_size.0 as f32, viewport_size.1 as f32);
+
+        match self.units {
+            Camera2DUnits::Pixels => (width / 2.0 / self.zoom, height / 2.0 / self.zoom),
+            Camera2DUnits::World { view_height } => (view_height * width / height / 2.0 / self.zoom, view_height / 2.0 / self.zoom),
+        }
+    }
+}
+
+impl Viewpoint for Camera2D {
+    fn view_matrix(&self) -> Matrix4<f32> {
+        self.view()
+    }
+
+    fn projection_matrix(&self, viewport_size: (u32, u32)) -> Matrix4<f32> {
+        self.projection(viewport_size)
+    }
+}
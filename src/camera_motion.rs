@@ -0,0 +1,185 @@
+use nalgebra::{Matrix4, Point3, Vector3};
+
+use crate::{Camera, ClearConfig, RenderLayers, Viewpoint};
+
+// Camera which follows a goal camera with exponential damping, e.g. to smooth out jittery input.
+pub struct SmoothCamera {
+    pub goal: Camera,
+    // seconds to close about two thirds of the distance to goal, zero snaps to goal
+    pub smoothing: f32,
+    camera: Camera,
+}
+
+impl SmoothCamera {
+    pub fn new(camera: Camera, smoothing: f32) -> Self {
+        Self {
+            goal: camera.clone(),
+            smoothing,
+            camera,
+        }
+    }
+
+    pub fn camera(&self) -> &Camera {
+        &self.camera
+    }
+}
+
+impl Viewpoint for SmoothCamera {
+    fn view_matrix(&self) -> Matrix4<f32> {
+        self.camera.view_matrix()
+    }
+
+    fn projection_matrix(&self, viewport_size: (u32, u32)) -> Matrix4<f32> {
+        self.camera.projection_matrix(viewport_size)
+    }
+
+    fn eye(&self) -> Point3<f32> {
+        self.camera.eye()
+    }
+
+    fn layers(&self) -> RenderLayers {
+        self.camera.layers()
+    }
+
+    fn clear(&self) -> ClearConfig {
+        self.camera.clear()
+    }
+
+    fn update(&mut self, dt: f32) {
+        self.camera = self.camera.lerp(&self.goal, damping(dt, self.smoothing));
+    }
+}
+
+// Chase camera placed relative to a target transform, e.g. a model's transform copied each frame.
+pub struct FollowCamera {
+    pub target: Matrix4<f32>,
+    // eye position in target's local space
+    pub offset: Vector3<f32>,
+    // point looked at in target's local space
+    pub look_at: Point3<f32>,
+    // seconds to close about two thirds of the distance to goal, zero sticks to target
+    pub lag: f32,
+    camera: Camera,
+}
+
+impl FollowCamera {
+    pub fn new(target: Matrix4<f32>, offset: Vector3<f32>, lag: f32) -> Self {
+        let mut result = Self {
+            target,
+            offset,
+            look_at: Point3::origin(),
+            lag,
+            camera: Camera::new(Point3::origin(), Point3::origin()),
+        };
+        result.camera = result.goal();
+
+        result
+    }
+
+    pub fn camera(&self) -> &Camera {
+        &self.camera
+    }
+
+    fn goal(&self) -> Camera {
+        let eye = self.target.transform_point(&Point3::from(self.offset));
+        let look_at = self.target.transform_point(&self.look_at);
+
+        Camera::new(eye, look_at)
+    }
+}
+
+impl Viewpoint for FollowCamera {
+    fn view_matrix(&self) -> Matrix4<f32> {
+        self.camera.view_matrix()
+    }
+
+    fn projection_matrix(&self, viewport_size: (u32, u32)) -> Matrix4<f32> {
+        self.camera.projection_matrix(viewport_size)
+    }
+
+    fn eye(&self) -> Point3<f32> {
+        self.camera.eye()
+    }
+
+    fn layers(&self) -> RenderLayers {
+        self.camera.layers()
+    }
+
+    fn clear(&self) -> ClearConfig {
+        self.camera.clear()
+    }
+
+    fn update(&mut self, dt: f32) {
+        self.camera = self.camera.lerp(&self.goal(), damping(dt, self.lag));
+    }
+}
+
+// Moves from one camera to another over duration seconds with ease in and out, for cutscene style moves.
+pub struct CameraTransition {
+    from: Camera,
+    to: Camera,
+    duration: f32,
+    elapsed: f32,
+}
+
+impl CameraTransition {
+    pub fn new(from: Camera, to: Camera, duration: f32) -> Self {
+        Self {
+            from,
+            to,
+            duration,
+            elapsed: 0.0,
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.elapsed >= self.duration
+    }
+
+    // camera at current time, e.g. to assign to scene camera each frame
+    pub fn camera(&self) -> Camera {
+        let t = if self.duration > 0.0 {
+            (self.elapsed / self.duration).min(1.0)
+        } else {
+            1.0
+        };
+
+        // smoothstep
+        self.from.lerp(&self.to, t * t * (3.0 - 2.0 * t))
+    }
+}
+
+impl Viewpoint for CameraTransition {
+    fn view_matrix(&self) -> Matrix4<f32> {
+        self.camera().view_matrix()
+    }
+
+    fn projection_matrix(&self, viewport_size: (u32, u32)) -> Matrix4<f32> {
+        self.camera().projection_matrix(viewport_size)
+    }
+
+    fn eye(&self) -> Point3<f32> {
+        self.camera().eye()
+    }
+
+    fn layers(&self) -> RenderLayers {
+        self.camera().layers()
+    }
+
+    fn clear(&self) -> ClearConfig {
+        self.camera().clear()
+    }
+
+    fn update(&mut self, dt: f32) {
+        self.elapsed += dt;
+    }
+}
+
+// fraction of remaining distance to move in dt for exponential damping, frame rate independent
+fn damping(dt: f32, smoothing: f32) -> f32 {
+    if smoothing > 0.0 {
+        1.0 - (-dt / smoothing).exp()
+    } else {
+        1.0
+    }
+}
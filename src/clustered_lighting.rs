@@ -0,0 +1,215 @@
+use alloc::{sync::Arc, vec, vec::Vec};
+use core::{convert::TryInto, mem::size_of};
+
+use hashbrown::HashMap;
+use nalgebra::{Matrix4, Point3};
+use zerocopy::AsBytes;
+
+use crate::{
+    buffer,
+    buffer_pool::BufferPool,
+    conventions,
+    event::EventQueue,
+    memory::{Allocation, MemoryTracker},
+    staging_belt::StagingBelt,
+    Buffer, ComputeContext, ComputeKernel, LightingEnvironment, PointLight, ShaderBinding, ShaderBindingType, ShaderStage, Viewpoint,
+};
+
+// froxels of each view, sliced exponentially in depth. must match clusters.wgsl and cluster_lights.wgsl.
+const CLUSTER_COUNT: (u32, u32, u32) = (16, 9, 24);
+const WORKGROUP_SIZE: u32 = 4;
+// lights past this are ignored, and each cluster keeps at most 63 of them
+const MAX_LIGHTS: usize = 1024;
+// light count and light indices
+const CLUSTER_SIZE: usize = 64 * 4;
+// nearest lights bound as uniform in downlevel mode, must match clusters.wgsl
+const DOWNLEVEL_MAX_LIGHTS: usize = 32;
+
+#[repr(C)]
+#[derive(AsBytes)]
+struct ClusterUniform {
+    view: [f32; 16],
+    inverse_projection: [f32; 16],
+    viewport: [f32; 4],
+    near: f32,
+    far: f32,
+    light_count: u32,
+    _padding: u32,
+}
+
+#[repr(C)]
+#[derive(AsBytes, Clone, Copy)]
+struct PointLightUniform {
+    position: [f32; 4],
+    color: [f32; 4],
+}
+
+struct Binning {
+    clusters_buf: Arc<Buffer>,
+    kernel: ComputeKernel,
+    _allocation: Allocation,
+}
+
+// Bins point lights into view space clusters on gpu before each view is drawn,
+// so forward lit shaders only loop over lights reaching their fragment.
+// In downlevel mode there's no binning, nearest lights are bound as uniform and light every fragment.
+pub(crate) struct ClusteredLights {
+    uniform_buf: Arc<Buffer>,
+    lights_buf: Arc<Buffer>,
+    binning: Option<Binning>,
+}
+
+impl ClusteredLights {
+    pub(crate) fn new(
+        device: &wgpu::Device,
+        staging_belt: &Arc<StagingBelt>,
+        events: &EventQueue,
+        buffer_pool: &BufferPool,
+        memory: &MemoryTracker,
+        downlevel: bool,
+    ) -> Self {
+        let uniform_buf = Arc::new(buffer_pool.alloc(size_of::<ClusterUniform>()));
+        let max_lights = if downlevel { DOWNLEVEL_MAX_LIGHTS } else { MAX_LIGHTS };
+        let lights_buf = Arc::new(buffer_pool.alloc(max_lights * size_of::<PointLightUniform>()));
+        if downlevel {
+            return Self {
+                uniform_buf,
+                lights_buf,
+                binning: None,
+            };
+        }
+
+        // written by compute while uniform is read, so it can't share a pool buffer
+        let clusters_size = (CLUSTER_COUNT.0 * CLUSTER_COUNT.1 * CLUSTER_COUNT.2) as usize * CLUSTER_SIZE;
+        let clusters = device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: clusters_size as u64,
+            usage: wgpu::BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        });
+        let clusters_buf = Arc::new(Buffer::new(
+            staging_belt.clone(),
+            events.clone(),
+            Arc::new(clusters),
+            buffer::next_id(),
+            0,
+            clusters_size,
+            || {},
+        ));
+
+        let kernel = ComputeKernel::with_device(
+            device,
+            include_str!("../shaders/cluster_lights.wgsl"),
+            "main",
+            &[
+                ("Clusters", ShaderBinding::new(ShaderStage::Compute, 0, ShaderBindingType::UniformBuffer)),
+                (
+                    "PointLights",
+                    ShaderBinding::new(ShaderStage::Compute, 1, ShaderBindingType::ReadOnlyStorageBuffer),
+                ),
+                (
+                    "ClusterLights",
+                    ShaderBinding::new(ShaderStage::Compute, 2, ShaderBindingType::StorageBuffer),
+                ),
+            ],
+            &[],
+            &[
+                ("Clusters", uniform_buf.clone()),
+                ("PointLights", lights_buf.clone()),
+                ("ClusterLights", clusters_buf.clone()),
+            ],
+        );
+
+        Self {
+            uniform_buf,
+            lights_buf,
+            binning: Some(Binning {
+                clusters_buf,
+                kernel,
+                _allocation: memory.track("clustered lights", clusters_size),
+            }),
+        }
+    }
+
+    // buffers bound by name to materials declaring them, see Material::new
+    pub(crate) fn buffers(&self) -> Vec<(&'static str, Arc<Buffer>)> {
+        let mut result = vec![("Clusters", self.uniform_buf.clone()), ("PointLights", self.lights_buf.clone())];
+        if let Some(binning) = &self.binning {
+            result.push(("ClusterLights", binning.clusters_buf.clone()));
+        }
+
+        result
+    }
+
+    // shaders including clusters.wgsl declare bindings as with binning, downlevel has lights as uniform and no clusters.
+    pub(crate) fn lower_bindings(bindings: &mut HashMap<&'static str, ShaderBinding>) {
+        if let Some(binding) = bindings.get_mut("PointLights") {
+            binding.binding_type = ShaderBindingType::UniformBuffer;
+        }
+        bindings.remove("ClusterLights");
+    }
+
+    pub(crate) fn prepare(&self, lighting: &LightingEnvironment, viewpoint: &dyn Viewpoint, viewport: (f32, f32, f32, f32)) {
+        let projection = conventions::correct_projection(viewpoint.projection_matrix((viewport.2 as u32, viewport.3 as u32)));
+        let inverse_projection = projection.try_inverse().unwrap_or_else(Matrix4::identity);
+        // view space depth range of projection, which is looked along -z
+        let near = -inverse_projection.transform_point(&Point3::new(0.0, 0.0, 0.0)).z;
+        let far = -inverse_projection.transform_point(&Point3::new(0.0, 0.0, 1.0)).z;
+
+        let mut point_lights = lighting.point_lights.iter().collect::<Vec<_>>();
+        let max_lights = if self.binning.is_some() {
+            MAX_LIGHTS
+        } else {
+            // lights whose radius comes closest to eye go first
+            let eye = viewpoint.eye();
+            point_lights.sort_by(|a, b| {
+                let distance = |light: &PointLight| (light.position - eye).norm() - light.radius;
+                distance(a).partial_cmp(&distance(b)).unwrap_or(core::cmp::Ordering::Equal)
+            });
+
+            DOWNLEVEL_MAX_LIGHTS
+        };
+
+        let lights = point_lights
+            .into_iter()
+            .take(max_lights)
+            .map(|light| PointLightUniform {
+                position: [light.position.x, light.position.y, light.position.z, light.radius],
+                color: [
+                    light.color[0] * light.intensity,
+                    light.color[1] * light.intensity,
+                    light.color[2] * light.intensity,
+                    1.0,
+                ],
+            })
+            .collect::<Vec<_>>();
+        if !lights.is_empty() {
+            self.lights_buf.write(lights.as_bytes());
+        }
+
+        let uniform = ClusterUniform {
+            view: viewpoint.view_matrix().as_slice().try_into().unwrap(),
+            inverse_projection: inverse_projection.as_slice().try_into().unwrap(),
+            viewport: [viewport.0, viewport.1, viewport.2, viewport.3],
+            near,
+            far,
+            light_count: lights.len() as u32,
+            _padding: 0,
+        };
+        self.uniform_buf.write(uniform.as_bytes());
+    }
+
+    pub(crate) fn dispatch(&self, context: &mut ComputeContext) {
+        let binning = match &self.binning {
+            Some(x) => x,
+            None => return,
+        };
+
+        binning.kernel.dispatch(
+            context,
+            CLUSTER_COUNT.0.div_ceil(WORKGROUP_SIZE),
+            CLUSTER_COUNT.1.div_ceil(WORKGROUP_SIZE),
+            CLUSTER_COUNT.2.div_ceil(WORKGROUP_SIZE),
+        );
+    }
+}
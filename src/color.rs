@@ -0,0 +1,79 @@
+// Linear RGBA color, the space shaders light and blend in.
+// Colors authored in sRGB, like ones from color pickers or image editors, should be converted with from_srgb.
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct Color {
+    pub r: f32,
+    pub g: f32,
+    pub b: f32,
+    pub a: f32,
+}
+
+impl Color {
+    pub const WHITE: Color = Color::new(1.0, 1.0, 1.0, 1.0);
+    pub const BLACK: Color = Color::new(0.0, 0.0, 0.0, 1.0);
+    pub const TRANSPARENT: Color = Color::new(0.0, 0.0, 0.0, 0.0);
+
+    pub const fn new(r: f32, g: f32, b: f32, a: f32) -> Self {
+        Self { r, g, b, a }
+    }
+
+    // alpha is linear in either space
+    pub fn from_srgb(r: f32, g: f32, b: f32, a: f32) -> Self {
+        Self::new(srgb_to_linear(r), srgb_to_linear(g), srgb_to_linear(b), a)
+    }
+
+    pub fn from_srgb8(r: u8, g: u8, b: u8, a: u8) -> Self {
+        Self::from_srgb(r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0, a as f32 / 255.0)
+    }
+
+    pub fn to_srgb(&self) -> [f32; 4] {
+        [linear_to_srgb(self.r), linear_to_srgb(self.g), linear_to_srgb(self.b), self.a]
+    }
+
+    pub fn to_srgb8(&self) -> [u8; 4] {
+        self.to_srgb().map(|x| (x.clamp(0.0, 1.0) * 255.0).round() as u8)
+    }
+
+    pub fn to_array(&self) -> [f32; 4] {
+        [self.r, self.g, self.b, self.a]
+    }
+
+    pub(crate) fn wgpu_type(&self) -> wgpu::Color {
+        wgpu::Color {
+            r: self.r as f64,
+            g: self.g as f64,
+            b: self.b as f64,
+            a: self.a as f64,
+        }
+    }
+}
+
+// arrays are taken as linear, like material colors
+impl From<[f32; 4]> for Color {
+    fn from(x: [f32; 4]) -> Self {
+        Self::new(x[0], x[1], x[2], x[3])
+    }
+}
+
+impl From<Color> for [f32; 4] {
+    fn from(x: Color) -> Self {
+        x.to_array()
+    }
+}
+
+// same curves as the hardware conversion of srgb texture formats
+fn srgb_to_linear(x: f32) -> f32 {
+    if x <= 0.04045 {
+        x / 12.92
+    } else {
+        ((x + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(x: f32) -> f32 {
+    if x <= 0.0031308 {
+        x * 12.92
+    } else {
+        1.055 * x.powf(1.0 / 2.4) - 0.055
+    }
+}
@@ -0,0 +1,240 @@
+use alloc::{boxed::Box, sync::Arc, vec::Vec};
+use core::{
+    future::Future,
+    pin::Pin,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use futures::FutureExt;
+
+use hashbrown::HashMap;
+
+use crate::{Buffer, Renderer, RendererEvent, ShaderBinding, ShaderBindingType, Texture};
+
+pub struct ComputeContext<'a> {
+    pub(crate) command_encoder: &'a mut wgpu::CommandEncoder,
+}
+
+// GPU work which spans multiple frames, like baking or prefiltering.
+// Each step records some dispatches, scheduler runs steps until per-frame budget is spent.
+pub trait ComputeJob: Sync + Send {
+    // returns true when job is finished
+    fn step(&mut self, context: &mut ComputeContext) -> bool;
+
+    // expected gpu time of next step in milliseconds, see RendererOptions::compute_estimate_budget_ms
+    fn estimated_step_ms(&self) -> f32 {
+        1.0
+    }
+}
+
+#[derive(Clone)]
+pub struct ComputeJobHandle {
+    finished: Arc<AtomicBool>,
+    skipped: bool,
+}
+
+impl ComputeJobHandle {
+    // becomes true once gpu finished work of last step of the job
+    pub fn is_finished(&self) -> bool {
+        self.finished.load(Ordering::Acquire)
+    }
+
+    // true if job was never run as renderer is in downlevel mode, see Renderer::is_downlevel
+    pub fn is_skipped(&self) -> bool {
+        self.skipped
+    }
+}
+
+type WorkDone = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+pub(crate) struct ComputeScheduler {
+    jobs: Vec<(Box<dyn ComputeJob>, Arc<AtomicBool>)>,
+    // jobs whose last step was recorded but not submitted yet
+    recorded: Vec<Arc<AtomicBool>>,
+    // submissions of last steps, jobs are finished once gpu is done with them
+    submitted: Vec<(WorkDone, Vec<Arc<AtomicBool>>)>,
+}
+
+impl ComputeScheduler {
+    pub(crate) fn new() -> Self {
+        Self {
+            jobs: Vec::new(),
+            recorded: Vec::new(),
+            submitted: Vec::new(),
+        }
+    }
+
+    pub(crate) fn add(&mut self, job: Box<dyn ComputeJob>) -> ComputeJobHandle {
+        let finished = Arc::new(AtomicBool::new(false));
+        self.jobs.push((job, finished.clone()));
+
+        ComputeJobHandle { finished, skipped: false }
+    }
+
+    pub(crate) fn skipped() -> ComputeJobHandle {
+        ComputeJobHandle {
+            finished: Arc::new(AtomicBool::new(false)),
+            skipped: true,
+        }
+    }
+
+    // jobs run in insertion order. at least one step runs each frame so jobs always make progress.
+    pub(crate) fn run(&mut self, context: &mut ComputeContext, budget_ms: f32) {
+        let mut spent_ms = 0.0;
+        let mut ran = false;
+
+        while let Some((job, finished)) = self.jobs.first_mut() {
+            let cost = job.estimated_step_ms();
+            if ran && spent_ms + cost > budget_ms {
+                break;
+            }
+
+            if job.step(context) {
+                self.recorded.push(finished.clone());
+                self.jobs.remove(0);
+            }
+
+            spent_ms += cost;
+            ran = true;
+        }
+    }
+
+    // must be called after the commands recorded by run are submitted.
+    pub(crate) fn submitted(&mut self, queue: &wgpu::Queue) {
+        if !self.recorded.is_empty() {
+            let done = Box::pin(queue.on_submitted_work_done());
+            self.submitted.push((done, core::mem::take(&mut self.recorded)));
+        }
+    }
+
+    // marks jobs finished whose last step is done on gpu
+    pub(crate) fn poll(&mut self, device: &wgpu::Device) {
+        if self.submitted.is_empty() {
+            return;
+        }
+
+        device.poll(wgpu::Maintain::Poll);
+        self.submitted.retain_mut(|(done, handles)| {
+            if done.as_mut().now_or_never().is_none() {
+                return true;
+            }
+            for handle in handles.iter() {
+                handle.store(true, Ordering::Release);
+            }
+
+            false
+        });
+    }
+}
+
+// Compute shader with its bindings, dispatched from compute jobs.
+pub struct ComputeKernel {
+    // none in downlevel mode, where dispatches are skipped
+    pipeline: Option<(wgpu::ComputePipeline, wgpu::BindGroup)>,
+}
+
+impl ComputeKernel {
+    pub fn new(
+        renderer: &Renderer,
+        source: &str,
+        entry: &'static str,
+        bindings: &[(&'static str, ShaderBinding)],
+        textures: &[(&'static str, Arc<Texture>)],
+        buffers: &[(&'static str, Arc<Buffer>)],
+    ) -> Self {
+        if renderer.downlevel {
+            renderer.events.push(RendererEvent::ComputeSkipped);
+
+            return Self { pipeline: None };
+        }
+
+        Self::with_device(&renderer.device, source, entry, bindings, textures, buffers)
+    }
+
+    pub(crate) fn with_device(
+        device: &wgpu::Device,
+        source: &str,
+        entry: &'static str,
+        bindings: &[(&'static str, ShaderBinding)],
+        textures: &[(&'static str, Arc<Texture>)],
+        buffers: &[(&'static str, Arc<Buffer>)],
+    ) -> Self {
+        let module = device.create_shader_module(&wgpu::ShaderModuleDescriptor {
+            label: None,
+            source: wgpu::ShaderSource::Wgsl(source.into()),
+        });
+
+        let entries = bindings.iter().map(|(_, x)| x.wgpu_entry()).collect::<Vec<_>>();
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &entries,
+            label: None,
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            push_constant_ranges: &[],
+            bind_group_layouts: &[&bind_group_layout],
+        });
+
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: None,
+            layout: Some(&pipeline_layout),
+            module: &module,
+            entry_point: entry,
+        });
+
+        let textures = textures.iter().cloned().collect::<HashMap<_, _>>();
+        let buffers = buffers.iter().cloned().collect::<HashMap<_, _>>();
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let entries = bindings
+            .iter()
+            .map(|(binding_name, binding)| {
+                let resource = match binding.binding_type {
+                    ShaderBindingType::UniformBuffer | ShaderBindingType::StorageBuffer | ShaderBindingType::ReadOnlyStorageBuffer => {
+                        match buffers.get(binding_name) {
+                            Some(x) => x.binding_resource(),
+                            None => panic!("No such buffer named {}", binding_name),
+                        }
+                    }
+                    ShaderBindingType::Texture2D
+                    | ShaderBindingType::DepthTexture2D
+                    | ShaderBindingType::Texture2DArray
+                    | ShaderBindingType::Texture3D => match textures.get(binding_name) {
+                        Some(x) => wgpu::BindingResource::TextureView(&x.texture_view),
+                        None => panic!("No such texture named {}", binding_name),
+                    },
+                    ShaderBindingType::Sampler => wgpu::BindingResource::Sampler(&sampler),
+                    ShaderBindingType::PushConstant(_) => panic!("Push constants are only supported for Mvp"),
+                };
+
+                wgpu::BindGroupEntry {
+                    binding: binding.binding,
+                    resource,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &bind_group_layout,
+            entries: &entries,
+            label: None,
+        });
+
+        Self {
+            pipeline: Some((pipeline, bind_group)),
+        }
+    }
+
+    pub fn dispatch(&self, context: &mut ComputeContext, x: u32, y: u32, z: u32) {
+        if let Some((pipeline, bind_group)) = &self.pipeline {
+            let mut compute_pass = context.command_encoder.begin_compute_pass(&wgpu::ComputePassDescriptor { label: None });
+            compute_pass.set_pipeline(pipeline);
+            compute_pass.set_bind_group(0, bind_group, &[]);
+            compute_pass.dispatch(x, y, z);
+        }
+    }
+}
@@ -1,4 +1,9 @@
 use crate::TextureFormat;
 
+// holds linear color, encoded to srgb when presented
 pub const INTERNAL_COLOR_ATTACHMENT_FORMAT: TextureFormat = TextureFormat::Rgba8Unorm;
 pub const INTERNAL_DEPTH_ATTACHMENT_FORMAT: TextureFormat = TextureFormat::Depth32;
+// mvp and model transform
+pub const MAX_PUSH_CONSTANT_SIZE: u32 = 128;
+// weights fit in two vec4 after lod fade in Mvp uniform
+pub const MAX_MORPH_TARGETS: usize = 8;
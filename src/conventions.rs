@@ -0,0 +1,31 @@
+use nalgebra::Matrix4;
+
+// wgpu exposes the same conventions on every backend: y up NDC, [0, 1] NDC depth and top left texture origin.
+// Helpers here convert content made for other conventions so it behaves identically everywhere.
+
+// nalgebra's projections use [-1, 1] NDC z range, so convert it to [0, 1].
+pub(crate) fn correct_projection(projection: Matrix4<f32>) -> Matrix4<f32> {
+    #[rustfmt::skip]
+    let correction = Matrix4::<f32>::new(
+        1.0, 0.0, 0.0, 0.0,
+        0.0, 1.0, 0.0, 0.0,
+        0.0, 0.0, 0.5, 0.5,
+        0.0, 0.0, 0.0, 1.0,
+    );
+
+    correction * projection
+}
+
+// whether writes to the format are encoded to srgb by hardware.
+pub(crate) fn is_srgb(format: wgpu::TextureFormat) -> bool {
+    format.describe().srgb
+}
+
+// converts rows between bottom left origin (GL style) and top left origin in place.
+pub fn flip_rows(data: &mut [u8], bytes_per_row: usize) {
+    let rows = data.len() / bytes_per_row;
+    for i in 0..rows / 2 {
+        let (top, bottom) = data.split_at_mut((rows - i - 1) * bytes_per_row);
+        top[i * bytes_per_row..(i + 1) * bytes_per_row].swap_with_slice(&mut bottom[..bytes_per_row]);
+    }
+}
@@ -0,0 +1,203 @@
+use alloc::vec::Vec;
+use core::mem::size_of;
+
+use nalgebra::{Matrix4, Point3, Vector3};
+use spinning_top::{Spinlock, SpinlockGuard};
+use zerocopy::AsBytes;
+
+use crate::{
+    buffer_pool::BufferPool, constants::INTERNAL_COLOR_ATTACHMENT_FORMAT, Aabb, Buffer, Shader, ShaderBinding, ShaderBindingType, ShaderStage,
+};
+
+const SPHERE_SEGMENTS: usize = 24;
+
+#[repr(C)]
+#[derive(AsBytes, Clone, Copy)]
+pub(crate) struct DebugVertex {
+    position: [f32; 3],
+    color: [f32; 3],
+}
+
+// Lines queued by Scene::debug_* calls, drawn and cleared by next render.
+#[derive(Default)]
+pub(crate) struct DebugLines {
+    vertices: Spinlock<Vec<DebugVertex>>,
+}
+
+impl DebugLines {
+    pub(crate) fn line(&mut self, start: Point3<f32>, end: Point3<f32>, color: [f32; 3]) {
+        let vertices = self.vertices.get_mut();
+        vertices.push(DebugVertex {
+            position: start.coords.into(),
+            color,
+        });
+        vertices.push(DebugVertex {
+            position: end.coords.into(),
+            color,
+        });
+    }
+
+    pub(crate) fn aabb(&mut self, aabb: &Aabb, color: [f32; 3]) {
+        let corners = aabb.corners();
+
+        // corner index bits are x, y, z, so edges connect indices differing by one bit
+        for i in 0..8 {
+            for bit in &[1, 2, 4] {
+                if i & bit == 0 {
+                    self.line(corners[i], corners[i | bit], color);
+                }
+            }
+        }
+    }
+
+    pub(crate) fn sphere(&mut self, center: Point3<f32>, radius: f32, color: [f32; 3]) {
+        use core::f32::consts::PI;
+
+        // one circle on each axis plane
+        let axes = [(Vector3::x(), Vector3::y()), (Vector3::y(), Vector3::z()), (Vector3::z(), Vector3::x())];
+        for (u, v) in &axes {
+            let point = |i: usize| {
+                let angle = i as f32 / SPHERE_SEGMENTS as f32 * 2.0 * PI;
+                center + (u * angle.cos() + v * angle.sin()) * radius
+            };
+
+            for i in 0..SPHERE_SEGMENTS {
+                self.line(point(i), point(i + 1), color);
+            }
+        }
+    }
+
+    // x, y and z axes of transform in red, green and blue
+    pub(crate) fn axes(&mut self, transform: &Matrix4<f32>, size: f32) {
+        let origin = transform.transform_point(&Point3::origin());
+
+        self.line(origin, transform.transform_point(&Point3::new(size, 0.0, 0.0)), [1.0, 0.0, 0.0]);
+        self.line(origin, transform.transform_point(&Point3::new(0.0, size, 0.0)), [0.0, 1.0, 0.0]);
+        self.line(origin, transform.transform_point(&Point3::new(0.0, 0.0, size)), [0.0, 0.0, 1.0]);
+    }
+
+    pub(crate) fn vertices(&self) -> SpinlockGuard<'_, Vec<DebugVertex>> {
+        self.vertices.lock()
+    }
+
+    pub(crate) fn clear(&self) {
+        self.vertices.lock().clear();
+    }
+}
+
+pub(crate) struct DebugRenderer {
+    pipeline: wgpu::RenderPipeline,
+    bind_group: wgpu::BindGroup,
+    mvp_buf: Buffer,
+}
+
+impl DebugRenderer {
+    pub(crate) fn new(device: &wgpu::Device, buffer_pool: &BufferPool) -> Self {
+        let shader = Shader::with_device(
+            device,
+            include_str!("../shaders/debug.wgsl"),
+            "vs_main",
+            "fs_main",
+            &[("Mvp", ShaderBinding::new(ShaderStage::Vertex, 0, ShaderBindingType::UniformBuffer))],
+            &[("Position", 0), ("Color", 1)],
+        );
+
+        let bindings = shader.wgpu_bindings().collect::<Vec<_>>();
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &bindings,
+            label: None,
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            push_constant_ranges: &[],
+            bind_group_layouts: &[&bind_group_layout],
+        });
+
+        let mvp_buf = buffer_pool.alloc(size_of::<[f32; 16]>());
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: mvp_buf.binding_resource(),
+            }],
+            label: None,
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader.module,
+                entry_point: shader.vs_entry,
+                buffers: &[wgpu::VertexBufferLayout {
+                    array_stride: size_of::<DebugVertex>() as wgpu::BufferAddress,
+                    step_mode: wgpu::VertexStepMode::Vertex,
+                    attributes: &wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x3],
+                }],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: shader.fragment_module(),
+                entry_point: shader.fs_entry,
+                targets: &[INTERNAL_COLOR_ATTACHMENT_FORMAT.wgpu_type().into()],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::LineList,
+                ..Default::default()
+            },
+            // tested against scene depth, but doesn't occlude anything
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: wgpu::TextureFormat::Depth32Float,
+                depth_write_enabled: false,
+                depth_compare: wgpu::CompareFunction::LessEqual,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            label: None,
+            multisample: wgpu::MultisampleState::default(),
+        });
+
+        Self {
+            pipeline,
+            bind_group,
+            mvp_buf,
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn render(
+        &self,
+        command_encoder: &mut wgpu::CommandEncoder,
+        vertex_buf: &Buffer,
+        vertex_count: usize,
+        view_projection: &Matrix4<f32>,
+        color_attachment: &wgpu::TextureView,
+        depth_attachment: &wgpu::TextureView,
+        viewport: (f32, f32, f32, f32),
+    ) {
+        self.mvp_buf.write(view_projection.as_slice().as_bytes());
+
+        let mut render_pass = command_encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            color_attachments: &[wgpu::RenderPassColorAttachment {
+                view: color_attachment,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: true,
+                },
+            }],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: depth_attachment,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: true,
+                }),
+                stencil_ops: None,
+            }),
+            label: Some("debug lines"),
+        });
+        render_pass.set_viewport(viewport.0, viewport.1, viewport.2, viewport.3, 0.0, 1.0);
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        render_pass.set_vertex_buffer(0, vertex_buf.as_slice());
+        render_pass.draw(0..vertex_count as u32, 0..1);
+    }
+}
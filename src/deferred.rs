@@ -0,0 +1,151 @@
+use alloc::sync::Arc;
+use core::{convert::TryInto, mem::size_of};
+
+use nalgebra::Matrix4;
+use zerocopy::AsBytes;
+
+use crate::{
+    buffer_pool::BufferPool, memory::MemoryTracker, shader_preprocessor, Buffer, FullscreenPass, LightingEnvironment, PostProcessContext,
+    ShaderBinding, ShaderBindingType, ShaderStage, Texture, TextureFormat, Viewpoint,
+};
+
+const MAX_POINT_LIGHTS: usize = 64;
+
+#[repr(C)]
+#[derive(AsBytes, Clone, Copy, Default)]
+struct PointLightUniform {
+    position: [f32; 4],
+    color: [f32; 4],
+}
+
+#[repr(C)]
+#[derive(AsBytes)]
+struct DeferredUniform {
+    inverse_view_projection: [f32; 16],
+    camera_position: [f32; 4],
+    viewport: [f32; 4],
+    light_count: u32,
+    _padding: [u32; 3],
+    lights: [PointLightUniform; MAX_POINT_LIGHTS],
+}
+
+pub(crate) struct DeferredPath {
+    albedo: Arc<Texture>,
+    normal: Arc<Texture>,
+    material: Arc<Texture>,
+    pub(crate) depth: Arc<Texture>,
+
+    uniform_buf: Arc<Buffer>,
+    resolve: FullscreenPass,
+}
+
+impl DeferredPath {
+    pub(crate) fn formats() -> [wgpu::TextureFormat; 3] {
+        [
+            TextureFormat::Rgba8Unorm.wgpu_type(),
+            TextureFormat::Rgba16Float.wgpu_type(),
+            TextureFormat::Rgba8Unorm.wgpu_type(),
+        ]
+    }
+
+    pub(crate) fn new(
+        device: &wgpu::Device,
+        buffer_pool: &BufferPool,
+        memory: &MemoryTracker,
+        texture_size: (u32, u32),
+        lighting_buf: Arc<Buffer>,
+    ) -> Self {
+        let (width, height) = texture_size;
+        let create_texture = |format| Arc::new(Texture::with_tracker(device, memory, "gbuffer", width, height, format));
+        let albedo = create_texture(TextureFormat::Rgba8Unorm);
+        let normal = create_texture(TextureFormat::Rgba16Float);
+        let material = create_texture(TextureFormat::Rgba8Unorm);
+        let depth = create_texture(TextureFormat::Depth32);
+
+        let uniform_buf = Arc::new(buffer_pool.alloc(size_of::<DeferredUniform>()));
+
+        let resolve = FullscreenPass::with_device(
+            device,
+            &shader_preprocessor::process_builtin(include_str!("../shaders/deferred.wgsl")),
+            "fs_main",
+            &[
+                ("Deferred", ShaderBinding::new(ShaderStage::Fragment, 0, ShaderBindingType::UniformBuffer)),
+                ("Normal", ShaderBinding::new(ShaderStage::Fragment, 3, ShaderBindingType::Texture2D)),
+                (
+                    "MaterialParams",
+                    ShaderBinding::new(ShaderStage::Fragment, 4, ShaderBindingType::Texture2D),
+                ),
+                ("Depth", ShaderBinding::new(ShaderStage::Fragment, 5, ShaderBindingType::DepthTexture2D)),
+                ("Lighting", ShaderBinding::new(ShaderStage::Fragment, 6, ShaderBindingType::UniformBuffer)),
+            ],
+            &[("Normal", normal.clone()), ("MaterialParams", material.clone()), ("Depth", depth.clone())],
+            &[("Deferred", uniform_buf.clone()), ("Lighting", lighting_buf)],
+        );
+
+        Self {
+            albedo,
+            normal,
+            material,
+            depth,
+            uniform_buf,
+            resolve,
+        }
+    }
+
+    pub(crate) fn color_attachments(&self) -> [&wgpu::TextureView; 3] {
+        [&self.albedo.texture_view, &self.normal.texture_view, &self.material.texture_view]
+    }
+
+    pub(crate) fn prepare(
+        &self,
+        view_projection: &Matrix4<f32>,
+        viewpoint: &dyn Viewpoint,
+        viewport: (f32, f32, f32, f32),
+        lighting: &LightingEnvironment,
+    ) {
+        let inverse_view_projection = view_projection.try_inverse().unwrap_or_else(Matrix4::identity);
+        let eye = viewpoint.eye();
+
+        let mut lights = [PointLightUniform::default(); MAX_POINT_LIGHTS];
+        for (uniform, light) in lights.iter_mut().zip(lighting.point_lights.iter()) {
+            uniform.position = [light.position.x, light.position.y, light.position.z, light.radius];
+            uniform.color = [
+                light.color[0] * light.intensity,
+                light.color[1] * light.intensity,
+                light.color[2] * light.intensity,
+                1.0,
+            ];
+        }
+
+        let uniform = DeferredUniform {
+            inverse_view_projection: inverse_view_projection.as_slice().try_into().unwrap(),
+            camera_position: [eye.x, eye.y, eye.z, 1.0],
+            viewport: [viewport.0, viewport.1, viewport.2, viewport.3],
+            light_count: lighting.point_lights.len().min(MAX_POINT_LIGHTS) as u32,
+            _padding: [0; 3],
+            lights,
+        };
+
+        self.uniform_buf.write(uniform.as_bytes());
+    }
+
+    pub(crate) fn resolve(
+        &self,
+        device: &wgpu::Device,
+        command_encoder: &mut wgpu::CommandEncoder,
+        output: &wgpu::TextureView,
+        viewport: (f32, f32, f32, f32),
+    ) {
+        let mut context = PostProcessContext {
+            device,
+            command_encoder,
+            input: &self.albedo,
+            output,
+            viewport_size: (viewport.2 as u32, viewport.3 as u32),
+            depth: None,
+            projection: Matrix4::identity(),
+        };
+
+        self.resolve.draw_viewport(&mut context, viewport);
+    }
+}
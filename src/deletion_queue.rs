@@ -0,0 +1,73 @@
+use alloc::{boxed::Box, vec::Vec};
+use core::{future::Future, pin::Pin};
+
+use futures::FutureExt;
+use spinning_top::Spinlock;
+
+type Resource = Box<dyn Send + Sync>;
+type WorkDone = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+// Runs cleanup when dropped, for work which must wait for gpu like returning buffer ranges to the pool.
+struct Deferred(Option<Box<dyn FnOnce() + Send + Sync>>);
+
+impl Drop for Deferred {
+    fn drop(&mut self) {
+        if let Some(cleanup) = self.0.take() {
+            cleanup()
+        }
+    }
+}
+
+// Keeps released resources alive until gpu finished submissions which were recorded before release.
+#[derive(Default)]
+pub(crate) struct DeletionQueue {
+    // released since last submission
+    pending: Spinlock<Vec<Resource>>,
+    in_flight: Spinlock<Vec<(WorkDone, Vec<Resource>)>>,
+}
+
+impl DeletionQueue {
+    pub(crate) fn push(&self, resource: Resource) {
+        self.pending.lock().push(resource);
+    }
+
+    // runs cleanup once submissions recorded before this call are done.
+    pub(crate) fn defer<F: FnOnce() + Send + Sync + 'static>(&self, cleanup: F) {
+        self.push(Box::new(Deferred(Some(Box::new(cleanup)))));
+    }
+
+    // must be called after each submission, ties pending resources to it.
+    pub(crate) fn submitted(&self, queue: &wgpu::Queue) {
+        let resources = core::mem::take(&mut *self.pending.lock());
+        if resources.is_empty() {
+            return;
+        }
+
+        self.in_flight.lock().push((Box::pin(queue.on_submitted_work_done()), resources));
+    }
+
+    // drops resources of completed submissions without waiting for the rest.
+    pub(crate) fn collect(&self, device: &wgpu::Device) {
+        let mut in_flight = self.in_flight.lock();
+        if in_flight.is_empty() {
+            return;
+        }
+
+        device.poll(wgpu::Maintain::Poll);
+        in_flight.retain_mut(|(work_done, _)| work_done.as_mut().now_or_never().is_none());
+    }
+
+    // waits for gpu and drops everything, including resources released while dropping others.
+    pub(crate) fn flush(&self, device: &wgpu::Device) {
+        device.poll(wgpu::Maintain::Wait);
+
+        loop {
+            // taken out first, as dropping a resource may release more
+            let in_flight = core::mem::take(&mut *self.in_flight.lock());
+            let pending = core::mem::take(&mut *self.pending.lock());
+            if in_flight.is_empty() && pending.is_empty() {
+                break;
+            }
+        }
+    }
+}
@@ -0,0 +1,151 @@
+use alloc::{sync::Arc, vec, vec::Vec};
+
+use nalgebra::Matrix4;
+
+use crate::{FullscreenPass, PostProcessContext, Renderer, ShaderBinding, ShaderBindingType, ShaderStage, Texture, TextureFormat};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DynamicTextureFormat {
+    // one plane of srgb rgba8 texels
+    Rgba8,
+    // luma plane, then half resolution plane of interleaved u, v
+    Nv12,
+    // luma plane, then half resolution u and v planes
+    I420,
+}
+
+impl DynamicTextureFormat {
+    // format and size of each plane of a frame
+    fn planes(&self, width: u32, height: u32) -> Vec<(TextureFormat, u32, u32)> {
+        let chroma = (width.div_ceil(2), height.div_ceil(2));
+
+        match self {
+            DynamicTextureFormat::Rgba8 => vec![(TextureFormat::Rgba8UnormSrgb, width, height)],
+            DynamicTextureFormat::Nv12 => vec![(TextureFormat::R8Unorm, width, height), (TextureFormat::Rg8Unorm, chroma.0, chroma.1)],
+            DynamicTextureFormat::I420 => vec![
+                (TextureFormat::R8Unorm, width, height),
+                (TextureFormat::R8Unorm, chroma.0, chroma.1),
+                (TextureFormat::R8Unorm, chroma.0, chroma.1),
+            ],
+        }
+    }
+}
+
+// Texture replaced by cpu every frame, like video playback or webcam feeds mapped onto meshes.
+// Frames are uploaded to one of two sets of staging planes in turn and then copied or converted to rgb,
+// so the texture bound to materials stays the same while upload of next frame doesn't touch planes being read.
+pub struct DynamicTexture {
+    texture: Arc<Texture>,
+    format: DynamicTextureFormat,
+    size: (u32, u32),
+    staging: [Vec<Texture>; 2],
+    current: usize,
+    // yuv to rgb conversion, none for rgba frames
+    conversion: Option<FullscreenPass>,
+}
+
+impl DynamicTexture {
+    pub fn new(renderer: &Renderer, width: u32, height: u32, format: DynamicTextureFormat) -> Self {
+        let create_planes = || {
+            format
+                .planes(width, height)
+                .into_iter()
+                .map(|(plane_format, plane_width, plane_height)| {
+                    Texture::with_tracker(
+                        &renderer.device,
+                        &renderer.memory,
+                        "dynamic textures",
+                        plane_width,
+                        plane_height,
+                        plane_format,
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let create_pass = |fs_entry, bindings: &[(&'static str, ShaderBinding)]| {
+            FullscreenPass::with_format(
+                &renderer.device,
+                include_str!("../shaders/yuv.wgsl"),
+                fs_entry,
+                bindings,
+                &[],
+                &[],
+                TextureFormat::Rgba8UnormSrgb,
+            )
+        };
+        let chroma_u = ("ChromaU", ShaderBinding::new(ShaderStage::Fragment, 3, ShaderBindingType::Texture2D));
+        let chroma_v = ("ChromaV", ShaderBinding::new(ShaderStage::Fragment, 4, ShaderBindingType::Texture2D));
+        let conversion = match format {
+            DynamicTextureFormat::Rgba8 => None,
+            DynamicTextureFormat::Nv12 => Some(create_pass("fs_nv12", &[chroma_u])),
+            DynamicTextureFormat::I420 => Some(create_pass("fs_i420", &[chroma_u, chroma_v])),
+        };
+
+        Self {
+            texture: Arc::new(Texture::with_tracker(
+                &renderer.device,
+                &renderer.memory,
+                "dynamic textures",
+                width,
+                height,
+                TextureFormat::Rgba8UnormSrgb,
+            )),
+            format,
+            size: (width, height),
+            staging: [create_planes(), create_planes()],
+            current: 0,
+            conversion,
+        }
+    }
+
+    // rgb result, sampled as srgb texture
+    pub fn texture(&self) -> Arc<Texture> {
+        self.texture.clone()
+    }
+
+    pub fn format(&self) -> DynamicTextureFormat {
+        self.format
+    }
+
+    // replaces contents with a frame, one tightly packed slice per plane of format
+    pub fn update(&mut self, renderer: &Renderer, planes: &[&[u8]]) {
+        let staging = &self.staging[self.current];
+        assert_eq!(planes.len(), staging.len(), "{:?} frames have {} planes", self.format, staging.len());
+
+        let (width, height) = self.size;
+        for ((_, plane_width, plane_height), (plane, data)) in self.format.planes(width, height).into_iter().zip(staging.iter().zip(planes)) {
+            plane.write_region(renderer, 0, 0, plane_width, plane_height, data);
+        }
+
+        let mut command_encoder = renderer.device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        if let Some(conversion) = &self.conversion {
+            let mut context = PostProcessContext {
+                device: &renderer.device,
+                command_encoder: &mut command_encoder,
+                input: &staging[0],
+                output: &self.texture.texture_view,
+                viewport_size: (width, height),
+                depth: None,
+                projection: Matrix4::identity(),
+            };
+            let chroma = ["ChromaU", "ChromaV"].iter().copied().zip(&staging[1..]).collect::<Vec<_>>();
+            let viewport = (0.0, 0.0, width as f32, height as f32);
+
+            conversion.draw_with_textures(&mut context, viewport, &chroma);
+        } else {
+            command_encoder.copy_texture_to_texture(
+                staging[0].texture.as_image_copy(),
+                self.texture.texture.as_image_copy(),
+                wgpu::Extent3d {
+                    width,
+                    height,
+                    depth_or_array_layers: 1,
+                },
+            );
+        }
+        renderer.staging_belt.submit(Some(command_encoder.finish()));
+
+        self.current = 1 - self.current;
+    }
+}
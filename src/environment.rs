@@ -0,0 +1,193 @@
+use alloc::{sync::Arc, vec::Vec};
+
+use nalgebra::Matrix4;
+use zerocopy::AsBytes;
+
+use crate::{
+    buffer_pool::BufferPool, constants::INTERNAL_COLOR_ATTACHMENT_FORMAT, memory::MemoryTracker, staging_belt::StagingBelt, Buffer, FullscreenPass,
+    PostProcessContext, ShaderBinding, ShaderBindingType, ShaderStage, Texture,
+};
+
+const IRRADIANCE_SIZE: (u32, u32) = (32, 16);
+const SPECULAR_SIZE: (u32, u32) = (128, 64);
+// roughness goes from 0 at first level to 1 at last one
+const SPECULAR_LEVELS: u32 = 5;
+const BRDF_LUT_SIZE: u32 = 64;
+
+#[repr(C)]
+#[derive(AsBytes)]
+struct PrefilterUniform {
+    size: [f32; 2],
+    roughness: f32,
+    _padding: f32,
+}
+
+// Image based lighting prefiltered from equirectangular skybox of LightingEnvironment.
+// Textures keep their size, so materials bound to them stay valid when skybox changes.
+pub(crate) struct EnvironmentMaps {
+    irradiance: Arc<Texture>,
+    specular: Arc<Texture>,
+    brdf_lut: Arc<Texture>,
+    specular_levels: Vec<wgpu::TextureView>,
+    irradiance_pass: FullscreenPass,
+    specular_pass: FullscreenPass,
+    uniform_buf: Arc<Buffer>,
+    // skybox maps are currently prefiltered from
+    source: Option<Arc<Texture>>,
+}
+
+impl EnvironmentMaps {
+    pub(crate) fn new(device: &wgpu::Device, staging_belt: &StagingBelt, buffer_pool: &BufferPool, memory: &MemoryTracker) -> Self {
+        let irradiance = Arc::new(Self::create_texture(device, memory, IRRADIANCE_SIZE, 1));
+        let specular = Arc::new(Self::create_texture(device, memory, SPECULAR_SIZE, SPECULAR_LEVELS));
+        let brdf_lut = Arc::new(Self::create_texture(device, memory, (BRDF_LUT_SIZE, BRDF_LUT_SIZE), 1));
+
+        let specular_levels = (0..SPECULAR_LEVELS)
+            .map(|level| {
+                specular.texture.create_view(&wgpu::TextureViewDescriptor {
+                    base_mip_level: level,
+                    mip_level_count: core::num::NonZeroU32::new(1),
+                    ..Default::default()
+                })
+            })
+            .collect();
+
+        let uniform_buf = Arc::new(buffer_pool.alloc(core::mem::size_of::<PrefilterUniform>()));
+        let create_pass = |fs_entry| {
+            FullscreenPass::with_device(
+                device,
+                include_str!("../shaders/environment.wgsl"),
+                fs_entry,
+                &[(
+                    "Prefilter",
+                    ShaderBinding::new(ShaderStage::Fragment, 3, ShaderBindingType::UniformBuffer),
+                )],
+                &[],
+                &[("Prefilter", uniform_buf.clone())],
+            )
+        };
+
+        let result = Self {
+            irradiance,
+            specular,
+            brdf_lut,
+            specular_levels,
+            irradiance_pass: create_pass("fs_irradiance"),
+            specular_pass: create_pass("fs_specular"),
+            uniform_buf: uniform_buf.clone(),
+            source: None,
+        };
+
+        // lut doesn't depend on environment, input is just to fill the binding
+        let brdf_pass = create_pass("fs_brdf");
+        let brdf_size = (BRDF_LUT_SIZE, BRDF_LUT_SIZE);
+        result.prefilter(
+            device,
+            staging_belt,
+            &brdf_pass,
+            &result.irradiance,
+            &result.brdf_lut.texture_view,
+            brdf_size,
+            0.0,
+        );
+
+        result
+    }
+
+    // textures bound by name to materials declaring them, see Material::new
+    pub(crate) fn textures(&self) -> [(&'static str, Arc<Texture>); 3] {
+        [
+            ("IrradianceMap", self.irradiance.clone()),
+            ("SpecularMap", self.specular.clone()),
+            ("BrdfLut", self.brdf_lut.clone()),
+        ]
+    }
+
+    // prefilters skybox if it changed since last call, or if its content changed as told by changed.
+    // maps are left black without any.
+    pub(crate) fn update(&mut self, device: &wgpu::Device, staging_belt: &StagingBelt, skybox: Option<&Arc<Texture>>, changed: bool) {
+        let skybox = match skybox {
+            Some(x) if changed || self.source.as_ref().map(|source| !Arc::ptr_eq(source, x)).unwrap_or(true) => x.clone(),
+            _ => return,
+        };
+
+        self.prefilter(
+            device,
+            staging_belt,
+            &self.irradiance_pass,
+            &skybox,
+            &self.irradiance.texture_view,
+            IRRADIANCE_SIZE,
+            0.0,
+        );
+        for (level, view) in self.specular_levels.iter().enumerate() {
+            let size = (SPECULAR_SIZE.0 >> level, SPECULAR_SIZE.1 >> level);
+            let roughness = level as f32 / (SPECULAR_LEVELS - 1) as f32;
+
+            self.prefilter(device, staging_belt, &self.specular_pass, &skybox, view, size, roughness);
+        }
+
+        self.source = Some(skybox);
+    }
+
+    // each pass is submitted on its own, as the uniform is rewritten for the next one
+    #[allow(clippy::too_many_arguments)]
+    fn prefilter(
+        &self,
+        device: &wgpu::Device,
+        staging_belt: &StagingBelt,
+        pass: &FullscreenPass,
+        input: &Texture,
+        output: &wgpu::TextureView,
+        size: (u32, u32),
+        roughness: f32,
+    ) {
+        let uniform = PrefilterUniform {
+            size: [size.0 as f32, size.1 as f32],
+            roughness,
+            _padding: 0.0,
+        };
+        self.uniform_buf.write(uniform.as_bytes());
+
+        let mut command_encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        let mut context = PostProcessContext {
+            device,
+            command_encoder: &mut command_encoder,
+            input,
+            output,
+            viewport_size: size,
+            depth: None,
+            projection: Matrix4::identity(),
+        };
+        pass.draw(&mut context);
+
+        staging_belt.submit(Some(command_encoder.finish()));
+    }
+
+    fn create_texture(device: &wgpu::Device, memory: &MemoryTracker, size: (u32, u32), mip_level_count: u32) -> Texture {
+        let extent = wgpu::Extent3d {
+            width: size.0,
+            height: size.1,
+            depth_or_array_layers: 1,
+        };
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            size: extent,
+            mip_level_count,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: INTERNAL_COLOR_ATTACHMENT_FORMAT.wgpu_type(),
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::RENDER_ATTACHMENT,
+            label: None,
+        });
+        let texture_view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        Texture {
+            texture: Arc::new(texture),
+            texture_view,
+            format: INTERNAL_COLOR_ATTACHMENT_FORMAT,
+            extent,
+            allocation: Some(memory.track_texture("environment", INTERNAL_COLOR_ATTACHMENT_FORMAT.wgpu_type(), extent, mip_level_count)),
+            deletion_queue: None,
+        }
+    }
+}
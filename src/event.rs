@@ -0,0 +1,39 @@
+use alloc::{sync::Arc, vec::Vec};
+
+use spinning_top::Spinlock;
+
+// Recoverable problems the renderer worked around, so applications can surface them.
+#[derive(Clone, Debug, PartialEq)]
+pub enum RendererEvent {
+    // surface was outdated or lost and configured again
+    SurfaceReconfigured { width: u32, height: u32 },
+    // frame couldn't be acquired in time and was requested again
+    FrameTimeout,
+    // surface frame couldn't be acquired even after that, or gpu is out of memory. nothing was rendered.
+    FrameSkipped,
+    // written data was padded up to copy alignment
+    UnalignedBufferWrite { size: usize },
+    // intermediate targets grew to fit a surface, textures from add_custom_pass should be fetched again
+    TargetsReallocated { width: u32, height: u32 },
+    // gpu memory tracked by Renderer::memory_report went over budget set with Renderer::set_memory_budget
+    MemoryBudgetExceeded { allocated: usize, budget: usize },
+    // compute kernel or job was created in downlevel mode, where compute isn't available. it does nothing.
+    ComputeSkipped,
+    // morph targets were given to a mesh without float triangle positions in downlevel mode, it isn't morphed.
+    MorphTargetsSkipped,
+}
+
+#[derive(Clone, Default)]
+pub(crate) struct EventQueue {
+    events: Arc<Spinlock<Vec<RendererEvent>>>,
+}
+
+impl EventQueue {
+    pub(crate) fn push(&self, event: RendererEvent) {
+        self.events.lock().push(event);
+    }
+
+    pub(crate) fn drain(&self) -> Vec<RendererEvent> {
+        core::mem::take(&mut *self.events.lock())
+    }
+}
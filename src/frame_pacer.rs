@@ -0,0 +1,63 @@
+use alloc::{boxed::Box, collections::VecDeque};
+use core::{future::Future, pin::Pin};
+
+use futures::FutureExt;
+use spinning_top::Spinlock;
+
+type WorkDone = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+// Limits frames gpu is behind cpu, trading throughput for input latency.
+// Without it, cpu runs ahead as far as wgpu and the surface let it.
+pub(crate) struct FramePacer {
+    max_frames_in_flight: usize,
+    // oldest first
+    frames: Spinlock<VecDeque<WorkDone>>,
+}
+
+impl FramePacer {
+    pub(crate) fn new(max_frames_in_flight: u32) -> Self {
+        Self {
+            max_frames_in_flight: max_frames_in_flight.max(1) as usize,
+            frames: Spinlock::new(VecDeque::new()),
+        }
+    }
+
+    // must be called after last submission of each frame.
+    pub(crate) fn submitted(&self, queue: &wgpu::Queue) {
+        self.frames.lock().push_back(Box::pin(queue.on_submitted_work_done()));
+    }
+
+    // frames submitted but not finished on gpu
+    pub(crate) fn in_flight(&self, device: &wgpu::Device) -> usize {
+        let mut frames = self.frames.lock();
+        if !frames.is_empty() {
+            device.poll(wgpu::Maintain::Poll);
+            Self::retire(&mut frames);
+        }
+
+        frames.len()
+    }
+
+    pub(crate) fn can_submit(&self, device: &wgpu::Device) -> bool {
+        self.in_flight(device) < self.max_frames_in_flight
+    }
+
+    // blocks until another frame can be submitted without going over the limit.
+    // wgpu can only wait for all submitted work, so then the gpu is drained rather than just the oldest frame.
+    pub(crate) fn wait(&self, device: &wgpu::Device) {
+        if !self.can_submit(device) {
+            device.poll(wgpu::Maintain::Wait);
+            Self::retire(&mut self.frames.lock());
+        }
+    }
+
+    // frames finish in submission order
+    fn retire(frames: &mut VecDeque<WorkDone>) {
+        while let Some(frame) = frames.front_mut() {
+            if frame.as_mut().now_or_never().is_none() {
+                break;
+            }
+            frames.pop_front();
+        }
+    }
+}
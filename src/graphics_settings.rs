@@ -0,0 +1,160 @@
+use alloc::{format, string::String};
+
+use crate::{renderer_options::config_entries, RendererOptions};
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum WindowMode {
+    Windowed,
+    // exclusive fullscreen, changes display mode
+    Fullscreen,
+    // window covering whole display without decorations
+    BorderlessFullscreen,
+}
+
+// Where settings are kept between runs, e.g. a file on desktop or local storage on web.
+pub trait SettingsStorage {
+    // none when nothing is saved yet
+    fn load(&self) -> Option<String>;
+    fn save(&mut self, config: &str);
+}
+
+// User facing settings persisted across runs. renderer doesn't create windows, so app applies window ones to its own
+// and creates renderer with options and window size of loaded settings.
+#[derive(Clone)]
+pub struct GraphicsSettings {
+    pub options: RendererOptions,
+    pub window_mode: WindowMode,
+    // inner size in physical pixels, of windowed mode
+    pub window_width: u32,
+    pub window_height: u32,
+}
+
+impl Default for GraphicsSettings {
+    fn default() -> Self {
+        Self {
+            options: RendererOptions::default(),
+            window_mode: WindowMode::Windowed,
+            window_width: 1280,
+            window_height: 720,
+        }
+    }
+}
+
+impl GraphicsSettings {
+    // defaults when storage has nothing saved, for startup.
+    pub fn load(storage: &dyn SettingsStorage) -> Self {
+        storage.load().map(|x| Self::from_config(&x)).unwrap_or_default()
+    }
+
+    pub fn save(&self, storage: &mut dyn SettingsStorage) {
+        storage.save(&self.to_config());
+    }
+
+    // applies change and saves settings if it changed any.
+    pub fn update<F>(&mut self, storage: &mut dyn SettingsStorage, change: F)
+    where
+        F: FnOnce(&mut Self),
+    {
+        let before = self.to_config();
+        change(self);
+
+        let after = self.to_config();
+        if after != before {
+            storage.save(&after);
+        }
+    }
+
+    // lines of RendererOptions::to_config followed by window ones.
+    pub fn to_config(&self) -> String {
+        let window_mode = match self.window_mode {
+            WindowMode::Windowed => "windowed",
+            WindowMode::Fullscreen => "fullscreen",
+            WindowMode::BorderlessFullscreen => "borderless_fullscreen",
+        };
+
+        format!(
+            "{}window_mode={}\nwindow_width={}\nwindow_height={}\n",
+            self.options.to_config(),
+            window_mode,
+            self.window_width,
+            self.window_height
+        )
+    }
+
+    // missing, unknown or malformed entries keep their defaults, as in RendererOptions::from_config.
+    pub fn from_config(config: &str) -> Self {
+        let mut result = Self::default();
+
+        for (key, value) in config_entries(config) {
+            if result.options.apply_config_entry(key, &value) {
+                continue;
+            }
+
+            match key {
+                "window_mode" => match value.as_str() {
+                    "windowed" => result.window_mode = WindowMode::Windowed,
+                    "fullscreen" => result.window_mode = WindowMode::Fullscreen,
+                    "borderless_fullscreen" => result.window_mode = WindowMode::BorderlessFullscreen,
+                    _ => {}
+                },
+                "window_width" => {
+                    if let Ok(x) = value.parse() {
+                        result.window_width = x;
+                    }
+                }
+                "window_height" => {
+                    if let Ok(x) = value.parse() {
+                        result.window_height = x;
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct MemoryStorage {
+        config: Option<String>,
+        saves: usize,
+    }
+
+    impl SettingsStorage for MemoryStorage {
+        fn load(&self) -> Option<String> {
+            self.config.clone()
+        }
+
+        fn save(&mut self, config: &str) {
+            self.config = Some(config.into());
+            self.saves += 1;
+        }
+    }
+
+    #[test]
+    fn test_settings_round_trip() {
+        let mut storage = MemoryStorage::default();
+        let mut settings = GraphicsSettings::load(&storage);
+        assert!(settings.window_mode == WindowMode::Windowed);
+
+        settings.update(&mut storage, |x| {
+            x.window_mode = WindowMode::BorderlessFullscreen;
+            x.window_width = 1920;
+            x.options.adapter_name = Some("gpu".into());
+        });
+        // unchanged settings aren't saved again
+        settings.update(&mut storage, |x| x.window_width = 1920);
+        assert_eq!(storage.saves, 1);
+
+        let result = GraphicsSettings::load(&storage);
+        assert!(result.window_mode == WindowMode::BorderlessFullscreen);
+        assert_eq!(result.window_width, 1920);
+        assert_eq!(result.window_height, 720);
+        assert_eq!(result.options.adapter_name.as_deref(), Some("gpu"));
+    }
+}
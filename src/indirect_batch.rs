@@ -0,0 +1,437 @@
+use alloc::{sync::Arc, vec, vec::Vec};
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use hashbrown::HashMap;
+use nalgebra::{Matrix4, Vector4};
+use zerocopy::AsBytes;
+
+use crate::{
+    constants::INTERNAL_COLOR_ATTACHMENT_FORMAT, memory::Allocation, staging_belt::StagingBelt, uniform_arena::UniformArena, ComputeContext,
+    MaterialPass, Mesh, RenderContext, RenderLayers, RenderPath, Renderable, Renderer, ShaderPreprocessor, Texture,
+};
+
+// index count, instance count, first index, base vertex and first instance
+const DRAW_COMMAND_SIZE: u64 = 20;
+
+struct Culling {
+    pipeline: wgpu::ComputePipeline,
+    bind_group: wgpu::BindGroup,
+    command_buf: wgpu::Buffer,
+}
+
+// Many instances of a mesh culled on gpu, which writes a draw command for each instance.
+// Commands are issued with a single multi draw where device supports it, so cpu cost doesn't grow with instance count.
+// Instances are unlit and textured. on deferred render path they are drawn after lighting is resolved.
+// In downlevel mode every instance is drawn with a single instanced draw, without culling.
+pub struct IndirectBatch {
+    mesh: Arc<Mesh>,
+    _texture: Arc<Texture>,
+    render_pipeline: wgpu::RenderPipeline,
+    render_bind_group: wgpu::BindGroup,
+    // none in downlevel mode
+    culling: Option<Culling>,
+    instance_buf: Arc<wgpu::Buffer>,
+    _allocation: Allocation,
+    instance_count: u32,
+    staging_belt: Arc<StagingBelt>,
+    arena: Arc<UniformArena>,
+    multi_draw: bool,
+    layers: RenderLayers,
+    visible: bool,
+    forward: bool,
+    // slot in uniform arena written by last prepare
+    uniform_offset: AtomicU32,
+}
+
+impl IndirectBatch {
+    // mesh must have Position and TexCoord, like meshes created with_simple_vertex.
+    pub fn new(renderer: &Renderer, mesh: Arc<Mesh>, texture: Arc<Texture>, transforms: &[Matrix4<f32>]) -> Self {
+        let device = &*renderer.device;
+        let downlevel = renderer.downlevel;
+
+        let mut preprocessor = ShaderPreprocessor::new();
+        if downlevel {
+            preprocessor.define("DOWNLEVEL", "true");
+        }
+        let module = device.create_shader_module(&wgpu::ShaderModuleDescriptor {
+            label: None,
+            source: wgpu::ShaderSource::Wgsl(preprocessor.process(include_str!("../shaders/indirect.wgsl")).into()),
+        });
+
+        let batch_entry = |visibility| wgpu::BindGroupLayoutEntry {
+            binding: 0,
+            visibility,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: true,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let instances_entry = |visibility| wgpu::BindGroupLayoutEntry {
+            binding: 3,
+            visibility,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only: true },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+
+        let mut render_entries = vec![
+            batch_entry(wgpu::ShaderStages::VERTEX),
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 2,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Sampler {
+                    comparison: false,
+                    filtering: true,
+                },
+                count: None,
+            },
+        ];
+        // transforms are vertex attributes in downlevel mode
+        if !downlevel {
+            render_entries.push(instances_entry(wgpu::ShaderStages::VERTEX));
+        }
+        let render_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &render_entries,
+            label: None,
+        });
+
+        // storage buffers can't be empty
+        let instance_count = transforms.len() as u32;
+        let instance_size = (transforms.len().max(1) * 64) as u64;
+        let command_size = if downlevel {
+            0
+        } else {
+            transforms.len().max(1) as u64 * DRAW_COMMAND_SIZE
+        };
+        let instance_buf = device.create_buffer(&wgpu::BufferDescriptor {
+            size: instance_size,
+            usage: if downlevel {
+                wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST
+            } else {
+                wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST
+            },
+            label: None,
+            mapped_at_creation: false,
+        });
+
+        let sampler = renderer.pipeline_cache.sampler();
+        let mut render_bind_entries = vec![
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: renderer.uniform_arena.binding_resource(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::TextureView(&texture.texture_view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: wgpu::BindingResource::Sampler(&sampler),
+            },
+        ];
+        if !downlevel {
+            render_bind_entries.push(wgpu::BindGroupEntry {
+                binding: 3,
+                resource: instance_buf.as_entire_binding(),
+            });
+        }
+        let render_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &render_layout,
+            entries: &render_bind_entries,
+            label: None,
+        });
+
+        let render_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            push_constant_ranges: &[],
+            bind_group_layouts: &[&render_layout],
+        });
+        let inputs = [("Position", 0), ("TexCoord", 1)].iter().cloned().collect::<HashMap<_, _>>();
+        let attributes = mesh.vertex_formats.iter().map(|x| x.wgpu_attributes(&inputs)).collect::<Vec<_>>();
+        let mut vertex_buffers = attributes
+            .iter()
+            .zip(mesh.strides.iter())
+            .map(|(attributes, stride)| wgpu::VertexBufferLayout {
+                array_stride: *stride as wgpu::BufferAddress,
+                step_mode: wgpu::VertexStepMode::Vertex,
+                attributes,
+            })
+            .collect::<Vec<_>>();
+        // columns of transform, after mesh buffers
+        let instance_attributes = wgpu::vertex_attr_array![2 => Float32x4, 3 => Float32x4, 4 => Float32x4, 5 => Float32x4];
+        if downlevel {
+            vertex_buffers.push(wgpu::VertexBufferLayout {
+                array_stride: 64,
+                step_mode: wgpu::VertexStepMode::Instance,
+                attributes: &instance_attributes,
+            });
+        }
+        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            layout: Some(&render_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &module,
+                entry_point: "vs_main",
+                buffers: &vertex_buffers,
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &module,
+                entry_point: "fs_main",
+                targets: &[INTERNAL_COLOR_ATTACHMENT_FORMAT.wgpu_type().into()],
+            }),
+            primitive: wgpu::PrimitiveState {
+                cull_mode: Some(wgpu::Face::Back),
+                ..Default::default()
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: wgpu::TextureFormat::Depth32Float,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::LessEqual,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            label: None,
+            multisample: wgpu::MultisampleState::default(),
+        });
+
+        let culling = if downlevel {
+            None
+        } else {
+            Some(Self::create_culling(
+                device,
+                renderer,
+                &module,
+                &instance_buf,
+                command_size,
+                batch_entry,
+                instances_entry,
+            ))
+        };
+
+        let result = Self {
+            mesh,
+            _texture: texture,
+            render_pipeline,
+            render_bind_group,
+            culling,
+            instance_buf: Arc::new(instance_buf),
+            _allocation: renderer.memory.track("indirect batches", (instance_size + command_size) as usize),
+            instance_count,
+            staging_belt: renderer.staging_belt.clone(),
+            arena: renderer.uniform_arena.clone(),
+            multi_draw: device.features().contains(wgpu::Features::MULTI_DRAW_INDIRECT),
+            layers: RenderLayers::default(),
+            visible: true,
+            forward: renderer.options.render_path == RenderPath::Deferred,
+            uniform_offset: AtomicU32::new(0),
+        };
+        result.set_transforms(transforms);
+
+        result
+    }
+
+    // updates transforms of first instances, count of instances is fixed at creation.
+    pub fn set_transforms(&self, transforms: &[Matrix4<f32>]) {
+        let count = transforms.len().min(self.instance_count as usize);
+        if count == 0 {
+            return;
+        }
+
+        let data = transforms[..count].iter().flat_map(|x| x.as_slice().iter().copied()).collect::<Vec<_>>();
+        self.staging_belt.write_buffer(&self.instance_buf, 0, data.as_bytes());
+    }
+
+    pub fn instance_count(&self) -> u32 {
+        self.instance_count
+    }
+
+    pub fn set_layers(&mut self, layers: RenderLayers) {
+        self.layers = layers;
+    }
+
+    pub fn set_visible(&mut self, visible: bool) {
+        self.visible = visible;
+    }
+
+    fn create_culling(
+        device: &wgpu::Device,
+        renderer: &Renderer,
+        module: &wgpu::ShaderModule,
+        instance_buf: &wgpu::Buffer,
+        command_size: u64,
+        batch_entry: impl Fn(wgpu::ShaderStages) -> wgpu::BindGroupLayoutEntry,
+        instances_entry: impl Fn(wgpu::ShaderStages) -> wgpu::BindGroupLayoutEntry,
+    ) -> Culling {
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                batch_entry(wgpu::ShaderStages::COMPUTE),
+                instances_entry(wgpu::ShaderStages::COMPUTE),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 4,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: false },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+            label: None,
+        });
+
+        let command_buf = device.create_buffer(&wgpu::BufferDescriptor {
+            size: command_size,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::INDIRECT,
+            label: None,
+            mapped_at_creation: false,
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: renderer.uniform_arena.binding_resource(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: instance_buf.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: command_buf.as_entire_binding(),
+                },
+            ],
+            label: None,
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            push_constant_ranges: &[],
+            bind_group_layouts: &[&layout],
+        });
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: None,
+            layout: Some(&pipeline_layout),
+            module,
+            entry_point: "cull",
+        });
+
+        Culling {
+            pipeline,
+            bind_group,
+            command_buf,
+        }
+    }
+
+    // planes of view frustum as ax + by + cz + d >= 0 inside, from rows of view projection
+    fn frustum_planes(view_projection: &Matrix4<f32>) -> [Vector4<f32>; 6] {
+        let row = |i| view_projection.row(i).transpose();
+        let (x, y, z, w) = (row(0), row(1), row(2), row(3));
+
+        // depth range is 0..1, so near plane is z itself
+        let mut planes = [w + x, w - x, w + y, w - y, z, w - z];
+        for plane in &mut planes {
+            *plane /= plane.xyz().norm();
+        }
+
+        planes
+    }
+}
+
+impl Renderable for IndirectBatch {
+    fn render<'a>(&'a self, render_context: &mut RenderContext<'a>) {
+        if render_context.pass != MaterialPass::Main || self.instance_count == 0 {
+            return;
+        }
+
+        render_context.set_pipeline(&self.render_pipeline);
+        render_context.set_bind_group(&self.render_bind_group, &[self.uniform_offset.load(Ordering::Relaxed)]);
+        render_context.set_mesh(&self.mesh);
+
+        let culling = match &self.culling {
+            Some(x) => x,
+            None => {
+                render_context.set_vertex_buffer(self.mesh.vertex_buffers.len() as u32, self.instance_buf.slice(..));
+                render_context
+                    .render_pass
+                    .draw_indexed(0..self.mesh.index_count as u32, 0, 0..self.instance_count);
+
+                return;
+            }
+        };
+
+        if self.multi_draw {
+            render_context
+                .render_pass
+                .multi_draw_indexed_indirect(&culling.command_buf, 0, self.instance_count);
+        } else {
+            for i in 0..self.instance_count as u64 {
+                render_context
+                    .render_pass
+                    .draw_indexed_indirect(&culling.command_buf, i * DRAW_COMMAND_SIZE);
+            }
+        }
+    }
+
+    fn prepare(&self, view_projection: &Matrix4<f32>) {
+        let sphere = self.mesh.bounding_sphere();
+
+        let mut data = [0.0f32; 46];
+        data[..16].copy_from_slice(view_projection.as_slice());
+        for (i, plane) in Self::frustum_planes(view_projection).iter().enumerate() {
+            data[16 + i * 4..20 + i * 4].copy_from_slice(plane.as_slice());
+        }
+        // meshes without bounds are never culled
+        match sphere {
+            Some(x) => {
+                data[40..43].copy_from_slice(x.center.coords.as_slice());
+                data[43] = x.radius;
+            }
+            None => data[43] = f32::INFINITY,
+        }
+        data[44] = f32::from_bits(self.mesh.index_count as u32);
+        data[45] = f32::from_bits(self.instance_count);
+        self.uniform_offset.store(self.arena.push(data.as_bytes()), Ordering::Relaxed);
+    }
+
+    fn dispatch(&self, context: &mut ComputeContext) {
+        let culling = match &self.culling {
+            Some(x) if self.instance_count > 0 => x,
+            _ => return,
+        };
+
+        let mut compute_pass = context.command_encoder.begin_compute_pass(&wgpu::ComputePassDescriptor { label: None });
+        compute_pass.set_pipeline(&culling.pipeline);
+        compute_pass.set_bind_group(0, &culling.bind_group, &[self.uniform_offset.load(Ordering::Relaxed)]);
+        compute_pass.dispatch(self.instance_count.div_ceil(64), 1, 1);
+    }
+
+    // drawn in forward pass on deferred render path, it can't write g-buffer
+    fn is_transparent(&self) -> bool {
+        self.forward
+    }
+
+    fn layers(&self) -> RenderLayers {
+        self.layers
+    }
+
+    fn is_visible(&self) -> bool {
+        self.visible
+    }
+}
@@ -0,0 +1,130 @@
+use alloc::{sync::Arc, vec::Vec};
+
+use spinning_top::Spinlock;
+use zerocopy::AsBytes;
+
+use crate::{
+    memory::{Allocation, MemoryTracker},
+    staging_belt::StagingBelt,
+    RenderContext, Renderable,
+};
+
+// mvp and model matrices, as in Renderable::instance_transforms
+const INSTANCE_SIZE: usize = 128;
+// 16384 instances per view
+const BUFFER_SIZE: usize = 2097152;
+
+// after vertex attributes of mesh, see shaders/instance.wgsl
+const INSTANCE_ATTRIBUTES: [wgpu::VertexAttribute; 8] = wgpu::vertex_attr_array![
+    8 => Float32x4,
+    9 => Float32x4,
+    10 => Float32x4,
+    11 => Float32x4,
+    12 => Float32x4,
+    13 => Float32x4,
+    14 => Float32x4,
+    15 => Float32x4,
+];
+
+// layout of instance buffer in pipelines of instanced entry points
+pub(crate) fn vertex_buffer_layout() -> wgpu::VertexBufferLayout<'static> {
+    wgpu::VertexBufferLayout {
+        array_stride: INSTANCE_SIZE as wgpu::BufferAddress,
+        step_mode: wgpu::VertexStepMode::Instance,
+        attributes: &INSTANCE_ATTRIBUTES,
+    }
+}
+
+// Single draw of a renderable, or an instanced one of a run of renderables with same sort key.
+#[derive(Clone, Copy)]
+pub(crate) struct Draw<'a> {
+    renderable: &'a dyn Renderable,
+    // transforms of merged renderables and their count
+    instances: Option<(wgpu::BufferSlice<'a>, u32)>,
+}
+
+impl<'a> Draw<'a> {
+    pub(crate) fn singles(renderables: &[&'a dyn Renderable]) -> Vec<Self> {
+        renderables.iter().map(|&renderable| Self { renderable, instances: None }).collect()
+    }
+
+    pub(crate) fn render(&self, render_context: &mut RenderContext<'a>) {
+        let renderable = self.renderable;
+        render_context.debug_group(renderable.label(), |x| match self.instances {
+            Some((instances, count)) => renderable.render_instanced(x, instances, count),
+            None => renderable.render(x),
+        });
+    }
+}
+
+// Per-view transforms of merged opaque draws, staged while merging and uploaded with a single write like UniformArena.
+pub(crate) struct InstanceBuffer {
+    staging_belt: Arc<StagingBelt>,
+    buffer: Arc<wgpu::Buffer>,
+    staging: Spinlock<Vec<u8>>,
+    _allocation: Allocation,
+}
+
+impl InstanceBuffer {
+    pub(crate) fn new(device: &wgpu::Device, staging_belt: Arc<StagingBelt>, memory: &MemoryTracker) -> Self {
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            size: BUFFER_SIZE as u64,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            label: Some("instance buffer"),
+            mapped_at_creation: false,
+        });
+
+        Self {
+            staging_belt,
+            buffer: Arc::new(buffer),
+            staging: Spinlock::new(Vec::new()),
+            _allocation: memory.track("instance buffer", BUFFER_SIZE),
+        }
+    }
+
+    // merges runs of prepared renderables sorted by sort key into instanced draws, where they give instance transforms.
+    // rest are drawn alone, as are runs not fitting in buffer.
+    pub(crate) fn merge<'a>(&'a self, renderables: &[&'a dyn Renderable]) -> Vec<Draw<'a>> {
+        let mut staging = self.staging.lock();
+        let mut result = Vec::with_capacity(renderables.len());
+
+        let mut rest = renderables;
+        while let Some(&renderable) = rest.first() {
+            let key = renderable.sort_key();
+            let transforms = rest
+                .iter()
+                .take_while(|x| x.sort_key() == key)
+                .map_while(|x| x.instance_transforms())
+                .collect::<Vec<_>>();
+
+            let start = staging.len();
+            let end = start + transforms.len() * INSTANCE_SIZE;
+            if transforms.len() > 1 && end <= BUFFER_SIZE {
+                for transform in &transforms {
+                    staging.extend_from_slice(transform.as_bytes());
+                }
+
+                let instances = self.buffer.slice(start as u64..end as u64);
+                result.push(Draw {
+                    renderable,
+                    instances: Some((instances, transforms.len() as u32)),
+                });
+                rest = &rest[transforms.len()..];
+            } else {
+                result.push(Draw { renderable, instances: None });
+                rest = &rest[1..];
+            }
+        }
+
+        result
+    }
+
+    // uploads staged data, it's visible to commands submitted after this.
+    pub(crate) fn flush(&self) {
+        let mut staging = self.staging.lock();
+        if !staging.is_empty() {
+            self.staging_belt.write_buffer(&self.buffer, 0, &staging);
+        }
+        staging.clear();
+    }
+}
@@ -1,32 +1,124 @@
 #![no_std]
 extern crate alloc;
 
+mod adapter;
+mod animation;
+mod bake;
+mod bounds;
 mod buffer;
 mod buffer_pool;
+mod builtin_material;
 mod camera;
+mod camera_2d;
+mod camera_motion;
+mod clustered_lighting;
+mod color;
+mod compute;
 mod constants;
+mod conventions;
+mod debug_draw;
+mod deferred;
+mod deletion_queue;
+mod dynamic_texture;
+mod environment;
+mod event;
+mod frame_pacer;
+mod graphics_settings;
+mod indirect_batch;
+mod instancing;
+mod lighting;
+mod lod;
 mod material;
+mod memory;
 mod mesh;
 mod model;
+mod model_pass;
+mod occlusion;
+mod orientation_gizmo;
+mod overlay;
+mod picking;
+mod pipeline_cache;
+mod point_cloud;
+mod post_process;
+mod raycast;
+mod recorder;
+mod reflection;
 mod render_context;
+mod render_layers;
+mod render_state;
+mod render_stats;
 mod render_target;
 mod renderable;
 mod renderer;
+mod renderer_options;
 mod scene;
 mod shader;
+mod shader_preprocessor;
+mod shader_variants;
+mod ssao;
+mod staging_belt;
+mod stereo;
+mod taa;
+mod target_pool;
+mod task_runner;
+mod terrain;
+#[cfg(feature = "testing")]
+mod testing;
 mod texture;
+mod texture_atlas;
+mod texture_streamer;
+mod time;
+mod uniform_arena;
 mod vertex_format;
 
+pub use adapter::AdapterInfo;
+pub use animation::{AnimationClip, AnimationCurve, AnimationPlayer, Interpolation, Transform};
+pub use bake::{bake_lightmap_ao, bake_vertex_ao};
+pub use bounds::{Aabb, BoundingSphere};
 pub use buffer::Buffer;
-pub use camera::Camera;
-pub use material::Material;
-pub use mesh::{Mesh, SimpleVertex};
+pub use camera::{Camera, ClearConfig, Viewpoint};
+pub use camera_2d::{Camera2D, Camera2DUnits};
+pub use camera_motion::{CameraTransition, FollowCamera, SmoothCamera};
+pub use color::Color;
+pub use compute::{ComputeContext, ComputeJob, ComputeJobHandle, ComputeKernel};
+pub use conventions::flip_rows;
+pub use dynamic_texture::{DynamicTexture, DynamicTextureFormat};
+pub use event::RendererEvent;
+pub use graphics_settings::{GraphicsSettings, SettingsStorage, WindowMode};
+pub use indirect_batch::IndirectBatch;
+pub use lighting::{LightingEnvironment, PointLight};
+pub use lod::LodGroup;
+pub use material::{BlendMode, Material, MaterialPass};
+pub use memory::{MemoryCategory, MemoryReport};
+pub use mesh::{LightmapVertex, Mesh, SimpleVertex};
 pub use model::Model;
+pub use orientation_gizmo::OrientationGizmo;
+pub use overlay::Overlay;
+pub use point_cloud::{CloudPoint, PointCloud};
+pub use post_process::{FullscreenPass, PostProcess, PostProcessContext};
+pub use raycast::{Ray, RayHit};
+pub use recorder::{FrameReceiver, RecordedFrame};
+pub use reflection::{PlanarReflection, ReflectionProbe};
 pub use render_context::RenderContext;
+pub use render_layers::RenderLayers;
+pub use render_state::{CullMode, DepthCompare, PrimitiveTopology, RenderState};
+pub use render_stats::RenderStats;
 pub use render_target::{RenderTarget, WindowRenderTarget};
 pub use renderable::Renderable;
-pub use renderer::Renderer;
-pub use scene::Scene;
+pub use renderer::{Renderer, SurfaceId};
+pub use renderer_options::{AntiAliasing, Backend, PowerPreference, RenderPath, RendererOptions};
+pub use scene::{CameraView, ModelHandle, ModelId, Scene};
 pub use shader::{Shader, ShaderBinding, ShaderBindingType, ShaderStage};
+pub use shader_preprocessor::ShaderPreprocessor;
+pub use shader_variants::ShaderVariants;
+pub use ssao::{Ssao, SsaoQuality};
+pub use stereo::StereoMode;
+pub use task_runner::{Task, TaskRunner};
+pub use terrain::{Terrain, TerrainSplat};
+#[cfg(feature = "testing")]
+pub use testing::{compare_images, render_image, ImageDiff};
 pub use texture::{CompressedTextureFormat, Texture, TextureFormat};
+pub use texture_atlas::{AtlasRegion, TextureAtlas};
+pub use texture_streamer::TextureStreamer;
+pub use time::{FixedTimestep, Time};
 pub use vertex_format::{VertexFormat, VertexFormatItem, VertexItemType};
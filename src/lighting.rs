@@ -0,0 +1,111 @@
+use alloc::{sync::Arc, vec::Vec};
+
+use nalgebra::{Point3, Vector3};
+use zerocopy::AsBytes;
+
+use crate::Texture;
+
+#[derive(Clone)]
+pub struct PointLight {
+    pub position: Point3<f32>,
+    pub color: [f32; 3],
+    pub intensity: f32,
+    // light fades out to zero at this distance
+    pub radius: f32,
+}
+
+// Scene wide lighting settings. Switching presets is a single assignment to Scene::lighting.
+#[derive(Clone)]
+pub struct LightingEnvironment {
+    pub ambient_color: [f32; 3],
+    pub ambient_intensity: f32,
+
+    // direction the sunlight travels to
+    pub sun_direction: Vector3<f32>,
+    pub sun_color: [f32; 3],
+    pub sun_intensity: f32,
+
+    // exponential fog, disabled if density is zero
+    pub fog_color: [f32; 3],
+    pub fog_density: f32,
+
+    // deferred render path resolves first 64 of them. pbr and toon materials drawn forward shade first 1024,
+    // binned into view space clusters of up to 63 lights each.
+    pub point_lights: Vec<PointLight>,
+
+    // equirectangular, prefiltered into image based lighting of pbr materials when it's changed
+    pub skybox: Option<Arc<Texture>>,
+}
+
+#[repr(C)]
+#[derive(AsBytes)]
+pub(crate) struct LightingUniform {
+    ambient: [f32; 4],
+    sun_direction: [f32; 4],
+    sun_color: [f32; 4],
+    fog: [f32; 4],
+    // camera position of the view being rendered
+    eye: [f32; 4],
+}
+
+impl LightingEnvironment {
+    pub fn day() -> Self {
+        Self {
+            ambient_color: [0.6, 0.7, 0.9],
+            ambient_intensity: 0.3,
+            sun_direction: Vector3::new(-0.3, -1.0, -0.4),
+            sun_color: [1.0, 0.95, 0.85],
+            sun_intensity: 1.0,
+            fog_color: [0.7, 0.8, 0.9],
+            fog_density: 0.0,
+            point_lights: Vec::new(),
+            skybox: None,
+        }
+    }
+
+    pub fn night() -> Self {
+        Self {
+            ambient_color: [0.1, 0.12, 0.25],
+            ambient_intensity: 0.2,
+            sun_direction: Vector3::new(0.2, -1.0, 0.3),
+            sun_color: [0.6, 0.7, 1.0],
+            sun_intensity: 0.15,
+            fog_color: [0.02, 0.03, 0.06],
+            fog_density: 0.05,
+            ..Self::day()
+        }
+    }
+
+    pub fn studio() -> Self {
+        Self {
+            ambient_color: [1.0, 1.0, 1.0],
+            ambient_intensity: 0.5,
+            sun_direction: Vector3::new(-1.0, -1.0, -1.0),
+            sun_color: [1.0, 1.0, 1.0],
+            sun_intensity: 0.8,
+            fog_color: [1.0, 1.0, 1.0],
+            fog_density: 0.0,
+            ..Self::day()
+        }
+    }
+
+    pub(crate) fn uniform(&self, eye: &Point3<f32>) -> LightingUniform {
+        let direction = self.sun_direction.normalize();
+        let ambient = self.ambient_color.map(|x| x * self.ambient_intensity);
+        let sun = self.sun_color.map(|x| x * self.sun_intensity);
+
+        LightingUniform {
+            ambient: [ambient[0], ambient[1], ambient[2], 1.0],
+            sun_direction: [direction.x, direction.y, direction.z, 0.0],
+            sun_color: [sun[0], sun[1], sun[2], 1.0],
+            fog: [self.fog_color[0], self.fog_color[1], self.fog_color[2], self.fog_density],
+            eye: [eye.x, eye.y, eye.z, 1.0],
+        }
+    }
+}
+
+impl Default for LightingEnvironment {
+    fn default() -> Self {
+        Self::day()
+    }
+}
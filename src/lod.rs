@@ -0,0 +1,160 @@
+use alloc::{boxed::Box, vec::Vec};
+use core::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+
+use nalgebra::{Matrix4, Point3};
+
+use crate::{RenderContext, Renderable};
+
+// Swaps whole groups of renderables by view distance of center, or by screen size of bounding sphere.
+// Levels are ordered from most detailed, beyond last level nothing is drawn.
+pub struct LodGroup {
+    center: Point3<f32>,
+    // bounding sphere radius if levels are selected by screen size
+    screen_radius: Option<f32>,
+    // max distance, or inverse of min screen size, and renderables of each level
+    levels: Vec<(f32, Vec<Box<dyn Renderable>>)>,
+    // fraction of threshold before it where levels are cross-faded
+    fade_band: f32,
+    current: AtomicUsize,
+    // bits of progress of fade to next level, zero if not fading
+    fade: AtomicU32,
+}
+
+impl LodGroup {
+    pub fn new(center: Point3<f32>) -> Self {
+        Self {
+            center,
+            screen_radius: None,
+            levels: Vec::new(),
+            fade_band: 0.0,
+            current: AtomicUsize::new(0),
+            fade: AtomicU32::new(0),
+        }
+    }
+
+    // thresholds of levels are min fraction of view height covered by sphere of radius around center.
+    pub fn with_screen_size(center: Point3<f32>, radius: f32) -> Self {
+        Self {
+            screen_radius: Some(radius),
+            ..Self::new(center)
+        }
+    }
+
+    // threshold is max distance, or min screen size if created with_screen_size.
+    pub fn add_level(&mut self, threshold: f32, renderables: Vec<Box<dyn Renderable>>) {
+        let threshold = match self.screen_radius {
+            Some(_) => 1.0 / threshold,
+            None => threshold,
+        };
+
+        self.levels.push((threshold, renderables));
+        self.levels.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(core::cmp::Ordering::Equal));
+    }
+
+    // levels are dithered into next one over given fraction of threshold, zero disables it.
+    // materials must discard pixels as in shaders/lod_fade.wgsl, or both levels are drawn overlapped.
+    pub fn set_cross_fade(&mut self, band: f32) {
+        self.fade_band = band.clamp(0.0, 1.0);
+    }
+
+    fn level(&self, index: usize) -> &[Box<dyn Renderable>] {
+        self.levels.get(index).map(|x| &x.1[..]).unwrap_or(&[])
+    }
+
+    fn current_level(&self) -> &[Box<dyn Renderable>] {
+        self.level(self.current.load(Ordering::Relaxed))
+    }
+
+    // level by distance or inverse of screen size, whichever levels use
+    fn metric(&self, view_projection: &Matrix4<f32>) -> f32 {
+        // clip space w is view depth with perspective projection
+        let distance = (view_projection * self.center.to_homogeneous()).w;
+
+        match self.screen_radius {
+            Some(radius) => {
+                // clip space length of a unit along screen y is length of second row
+                let scale = view_projection.fixed_slice::<1, 3>(1, 0).norm();
+                distance / (radius * scale)
+            }
+            None => distance,
+        }
+    }
+}
+
+impl Renderable for LodGroup {
+    fn render<'a>(&'a self, render_context: &mut RenderContext<'a>) {
+        for renderable in self.current_level() {
+            renderable.render(render_context);
+        }
+
+        if self.fade.load(Ordering::Relaxed) != 0 {
+            for renderable in self.level(self.current.load(Ordering::Relaxed) + 1) {
+                renderable.render(render_context);
+            }
+        }
+    }
+
+    fn render_pick<'a>(&'a self, render_context: &mut RenderContext<'a>, id: u32) {
+        for renderable in self.current_level() {
+            renderable.render_pick(render_context, id);
+        }
+    }
+
+    // fading levels discard some of their fragments, so they're shaded without pre-pass
+    fn render_depth<'a>(&'a self, render_context: &mut RenderContext<'a>) {
+        if self.fade.load(Ordering::Relaxed) != 0 {
+            return;
+        }
+
+        for renderable in self.current_level() {
+            renderable.render_depth(render_context);
+        }
+    }
+
+    fn render_x_ray<'a>(&'a self, render_context: &mut RenderContext<'a>) {
+        for renderable in self.current_level() {
+            renderable.render_x_ray(render_context);
+        }
+    }
+
+    fn render_outline<'a>(&'a self, render_context: &mut RenderContext<'a>) {
+        for renderable in self.current_level() {
+            renderable.render_outline(render_context);
+        }
+    }
+
+    fn animate(&self, time: f32) {
+        for renderable in self.levels.iter().flat_map(|x| x.1.iter()) {
+            renderable.animate(time);
+        }
+    }
+
+    fn prepare(&self, view_projection: &Matrix4<f32>) {
+        let metric = self.metric(view_projection);
+        let level = self.levels.iter().position(|x| metric <= x.0).unwrap_or(self.levels.len());
+        self.current.store(level, Ordering::Relaxed);
+
+        let fade_start = self.levels.get(level).map(|x| x.0 * (1.0 - self.fade_band));
+        let fade = match fade_start {
+            Some(start) if self.fade_band > 0.0 && metric > start => (metric - start) / (self.levels[level].0 - start),
+            _ => 0.0,
+        };
+        self.fade.store(fade.to_bits(), Ordering::Relaxed);
+
+        // patterns of both levels are complementary, so every pixel is drawn once
+        for renderable in self.current_level() {
+            renderable.set_lod_fade(if fade > 0.0 { -fade } else { 1.0 });
+            renderable.prepare(view_projection);
+        }
+        if fade > 0.0 {
+            for renderable in self.level(level + 1) {
+                renderable.set_lod_fade(fade);
+                renderable.prepare(view_projection);
+            }
+        }
+    }
+
+    fn position(&self) -> Point3<f32> {
+        self.center
+    }
+}
@@ -1,16 +1,81 @@
-use alloc::{sync::Arc, vec::Vec};
+use alloc::{borrow::ToOwned, string::String, sync::Arc, vec, vec::Vec};
 
 use hashbrown::HashMap;
+use zerocopy::AsBytes;
 
-use crate::{buffer::Buffer, Renderer, Shader, ShaderBindingType, Texture};
+use crate::{
+    buffer::Buffer,
+    pipeline_cache::{PipelineCache, PipelineLayout, ResourceKey},
+    uniform_arena::UniformArena,
+    AnimationCurve, CullMode, RenderState, Renderer, Shader, ShaderBindingType, Texture,
+};
 
+// uniform buffers are bound with at least 16 bytes
+const ALPHA_CUTOFF_SIZE: usize = 16;
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub enum MaterialPass {
+    Main,
+    // rendered into a texture returned by Renderer::add_custom_pass
+    Custom(&'static str),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum BlendMode {
+    Opaque,
+    AlphaBlend,
+    Additive,
+    Premultiplied,
+}
+
+impl BlendMode {
+    pub(crate) fn wgpu_type(&self) -> Option<wgpu::BlendState> {
+        match self {
+            BlendMode::Opaque => None,
+            BlendMode::AlphaBlend => Some(wgpu::BlendState {
+                color: wgpu::BlendComponent {
+                    operation: wgpu::BlendOperation::Add,
+                    src_factor: wgpu::BlendFactor::SrcAlpha,
+                    dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+                },
+                alpha: wgpu::BlendComponent::REPLACE,
+            }),
+            BlendMode::Additive => Some(wgpu::BlendState {
+                color: wgpu::BlendComponent {
+                    operation: wgpu::BlendOperation::Add,
+                    src_factor: wgpu::BlendFactor::SrcAlpha,
+                    dst_factor: wgpu::BlendFactor::One,
+                },
+                alpha: wgpu::BlendComponent::REPLACE,
+            }),
+            BlendMode::Premultiplied => Some(wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING),
+        }
+    }
+}
+
+// clones share bind group and uniforms with original, so their models can be drawn together, see Model::new.
+#[derive(Clone)]
 pub struct Material {
     pub(crate) shader: Arc<Shader>,
-    pub(crate) pipeline_layout: wgpu::PipelineLayout,
-    pub(crate) bind_group: wgpu::BindGroup,
+    pub(crate) layout: Arc<PipelineLayout>,
+    pub(crate) bind_group: Arc<wgpu::BindGroup>,
+    pub(crate) passes: Vec<MaterialPass>,
+    pub(crate) pass_shaders: HashMap<&'static str, Arc<Shader>>,
+    pub(crate) blend_mode: BlendMode,
+    pub(crate) render_state: RenderState,
+    pub(crate) x_ray_color: Option<[f32; 4]>,
+    alpha_cutoff: Option<f32>,
+    name: Option<String>,
+    // mvp and model transform of each draw, bound with dynamic offset
+    pub(crate) mvp_arena: Option<Arc<UniformArena>>,
+    // mvp is set with push constants instead if shader declared it so and device supports it.
+    // range is as declared, mvp and model transform are cut to it.
+    pub(crate) mvp_push_constant: Option<wgpu::PushConstantRange>,
+    // uniform, byte offset in it and curve written there each frame
+    animations: Vec<(Arc<Buffer>, usize, Arc<AnimationCurve>)>,
 
     _textures: HashMap<&'static str, Arc<Texture>>,
-    _uniforms: HashMap<&'static str, Arc<Buffer>>,
+    uniforms: HashMap<&'static str, Arc<Buffer>>,
 }
 
 impl Material {
@@ -20,92 +85,305 @@ impl Material {
         uniforms: &[(&'static str, Arc<Buffer>)],
         shader: Arc<Shader>,
     ) -> Self {
-        Self::with_device(&*renderer.device, Some(&renderer.mvp_buf), textures, uniforms, shader)
+        // environment maps are bound to shaders declaring them unless given
+        let mut textures = textures.to_vec();
+        for (name, texture) in renderer.environment.textures() {
+            if shader.bindings.contains_key(name) && !textures.iter().any(|x| x.0 == name) {
+                textures.push((name, texture));
+            }
+        }
+        // so are point light clusters, and alpha cutoff which isn't masked until set
+        let mut uniforms = uniforms.to_vec();
+        for (name, buffer) in renderer.clustered_lights.buffers() {
+            if shader.bindings.contains_key(name) && !uniforms.iter().any(|x| x.0 == name) {
+                uniforms.push((name, buffer));
+            }
+        }
+        if shader.bindings.contains_key("AlphaCutoff") && !uniforms.iter().any(|x| x.0 == "AlphaCutoff") {
+            let cutoff_buf = renderer.buffer_pool.alloc(ALPHA_CUTOFF_SIZE);
+            cutoff_buf.write(&[0; ALPHA_CUTOFF_SIZE]);
+
+            uniforms.push(("AlphaCutoff", Arc::new(cutoff_buf)));
+        }
+
+        Self::create(
+            &renderer.device,
+            Some(&renderer.pipeline_cache),
+            Mvp::Arena(&renderer.uniform_arena),
+            Some(&renderer.lighting_buf),
+            &textures,
+            &uniforms,
+            shader,
+        )
     }
 
     pub fn with_device(
         device: &wgpu::Device,
         mvp_buf: Option<&Buffer>,
+        lighting_buf: Option<&Buffer>,
+        textures: &[(&'static str, Arc<Texture>)],
+        uniforms: &[(&'static str, Arc<Buffer>)],
+        shader: Arc<Shader>,
+    ) -> Self {
+        let mvp = match mvp_buf {
+            Some(x) => Mvp::Buffer(x),
+            None => Mvp::None,
+        };
+
+        Self::create(device, None, mvp, lighting_buf, textures, uniforms, shader)
+    }
+
+    // layout and bind group are shared with other materials of same bindings if cache is given
+    fn create(
+        device: &wgpu::Device,
+        cache: Option<&PipelineCache>,
+        mvp: Mvp,
+        lighting_buf: Option<&Buffer>,
         textures: &[(&'static str, Arc<Texture>)],
         uniforms: &[(&'static str, Arc<Buffer>)],
         shader: Arc<Shader>,
     ) -> Self {
-        let bindings = shader.wgpu_bindings().collect::<Vec<_>>();
+        let push_constant_range = shader.push_constant_range();
+        let mut bindings = shader.wgpu_bindings().collect::<Vec<_>>();
+        if let (Mvp::Arena(_), Some(binding)) = (&mvp, shader.bindings.get("Mvp")) {
+            if let Some(entry) = bindings.iter_mut().find(|x| x.binding == binding.binding) {
+                if let wgpu::BindingType::Buffer { has_dynamic_offset, .. } = &mut entry.ty {
+                    *has_dynamic_offset = true;
+                }
+            }
+        }
         let textures = textures.iter().cloned().collect::<HashMap<_, _>>();
         let uniforms = uniforms.iter().cloned().collect::<HashMap<_, _>>();
 
         // TODO split bind groups by stage..
-        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-            entries: &bindings,
-            label: None,
-        });
-        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-            label: None,
-            push_constant_ranges: &[],
-            bind_group_layouts: &[&bind_group_layout],
-        });
-
-        // TODO wip
-        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
-            address_mode_u: wgpu::AddressMode::Repeat,
-            address_mode_v: wgpu::AddressMode::Repeat,
-            address_mode_w: wgpu::AddressMode::Repeat,
-            mag_filter: wgpu::FilterMode::Nearest,
-            min_filter: wgpu::FilterMode::Linear,
-            mipmap_filter: wgpu::FilterMode::Nearest,
-            lod_min_clamp: -100.0,
-            lod_max_clamp: 100.0,
-            label: None,
-            anisotropy_clamp: None,
-            compare: None,
-            border_color: None,
-        });
-
-        let entries = shader
+        let layout = match cache {
+            Some(x) => x.layout(device, &bindings, push_constant_range.as_slice()),
+            None => Arc::new(PipelineCache::create_layout(device, &bindings, push_constant_range.as_slice())),
+        };
+
+        let sampler = match cache {
+            Some(x) => x.sampler(),
+            None => Arc::new(PipelineCache::create_sampler(device)),
+        };
+        let environment_sampler = match cache {
+            Some(x) => x.environment_sampler(),
+            None => Arc::new(PipelineCache::create_environment_sampler(device)),
+        };
+
+        let resources = shader
             .bindings
             .iter()
-            .map(|(binding_name, binding)| {
+            .filter_map(|(binding_name, binding)| {
                 let resource = match binding.binding_type {
-                    ShaderBindingType::UniformBuffer => {
+                    ShaderBindingType::UniformBuffer | ShaderBindingType::StorageBuffer | ShaderBindingType::ReadOnlyStorageBuffer => {
                         if *binding_name == "Mvp" {
-                            mvp_buf.unwrap().binding_resource()
+                            match &mvp {
+                                Mvp::Buffer(x) => Resource::Buffer(x),
+                                Mvp::Arena(x) => Resource::Arena(x),
+                                Mvp::None => panic!("No mvp buffer"),
+                            }
+                        } else if *binding_name == "Lighting" {
+                            Resource::Buffer(lighting_buf.unwrap())
                         } else {
                             let buffer = uniforms.get(binding_name);
                             match buffer {
-                                Some(x) => x.binding_resource(),
+                                Some(x) => Resource::Buffer(x),
                                 None => panic!("No such buffer named {}", binding_name),
                             }
                         }
                     }
-                    ShaderBindingType::Texture2D => {
+                    ShaderBindingType::Texture2D
+                    | ShaderBindingType::DepthTexture2D
+                    | ShaderBindingType::Texture2DArray
+                    | ShaderBindingType::Texture3D => {
                         let texture = textures.get(binding_name);
                         match texture {
-                            Some(x) => wgpu::BindingResource::TextureView(&x.texture_view),
+                            Some(x) => Resource::Texture(x),
                             None => panic!("No such texture named {}", binding_name),
                         }
                     }
-                    ShaderBindingType::Sampler => wgpu::BindingResource::Sampler(&sampler),
+                    ShaderBindingType::Sampler if *binding_name == "EnvironmentSampler" => Resource::Sampler(&environment_sampler),
+                    ShaderBindingType::Sampler => Resource::Sampler(&sampler),
+                    ShaderBindingType::PushConstant(_) if *binding_name == "Mvp" => return None,
+                    ShaderBindingType::PushConstant(_) => panic!("Push constants are only supported for Mvp"),
                 };
 
-                wgpu::BindGroupEntry {
-                    binding: binding.binding,
-                    resource,
-                }
+                Some((binding.binding, resource))
             })
             .collect::<Vec<_>>();
 
-        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            layout: &bind_group_layout,
-            entries: &entries,
-            label: None,
-        });
+        let create_bind_group = || {
+            let entries = resources
+                .iter()
+                .map(|(binding, resource)| wgpu::BindGroupEntry {
+                    binding: *binding,
+                    resource: match resource {
+                        Resource::Buffer(x) => x.binding_resource(),
+                        Resource::Arena(x) => x.binding_resource(),
+                        Resource::Texture(x) => wgpu::BindingResource::TextureView(&x.texture_view),
+                        Resource::Sampler(x) => wgpu::BindingResource::Sampler(x),
+                    },
+                })
+                .collect::<Vec<_>>();
+
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                layout: &layout.bind_group_layout,
+                entries: &entries,
+                label: None,
+            })
+        };
+
+        let bind_group = match cache {
+            Some(cache) => {
+                let mut sorted = resources.iter().collect::<Vec<_>>();
+                sorted.sort_by_key(|x| x.0);
+
+                let keys = sorted.iter().map(|(_, x)| x.key()).collect();
+                let weak_textures = sorted
+                    .iter()
+                    .filter_map(|(_, x)| match x {
+                        Resource::Texture(x) => Some(Arc::downgrade(x)),
+                        _ => None,
+                    })
+                    .collect();
+
+                cache.bind_group(&layout, keys, weak_textures, create_bind_group)
+            }
+            None => Arc::new(create_bind_group()),
+        };
 
         Self {
             shader,
-            pipeline_layout,
+            layout,
             bind_group,
+            passes: vec![MaterialPass::Main],
+            pass_shaders: HashMap::new(),
+            blend_mode: BlendMode::Opaque,
+            render_state: RenderState::default(),
+            x_ray_color: None,
+            alpha_cutoff: None,
+            name: None,
+            mvp_arena: match mvp {
+                Mvp::Arena(x) => Some(x.clone()),
+                _ => None,
+            },
+            mvp_push_constant: push_constant_range,
+            animations: Vec::new(),
             _textures: textures,
-            _uniforms: uniforms,
+            uniforms,
+        }
+    }
+
+    // non opaque materials are drawn after opaque ones, sorted back to front.
+    // must be set before creating Model with this material.
+    pub fn set_blend_mode(&mut self, blend_mode: BlendMode) {
+        self.blend_mode = blend_mode;
+    }
+
+    // must be set before creating Model with this material.
+    pub fn set_render_state(&mut self, render_state: RenderState) {
+        self.render_state = render_state;
+    }
+
+    pub fn render_state(&self) -> &RenderState {
+        &self.render_state
+    }
+
+    // back faces are drawn too, e.g. for cloth, leaves and thin geometry. built-in lit shaders flip their normals.
+    // shorthand of render state's cull mode, must be set before creating Model with this material.
+    pub fn set_double_sided(&mut self, double_sided: bool) {
+        self.render_state.cull_mode = if double_sided { CullMode::None } else { CullMode::Back };
+    }
+
+    pub fn is_double_sided(&self) -> bool {
+        self.render_state.cull_mode == CullMode::None
+    }
+
+    // must be set before creating Model with this material.
+    pub fn set_passes(&mut self, passes: &[MaterialPass]) {
+        self.passes = passes.to_vec();
+    }
+
+    // occluded parts of the model are drawn in flat color over the scene, e.g. selected units behind walls.
+    // must be set before creating Model with this material.
+    pub fn set_x_ray(&mut self, color: Option<[f32; 4]>) {
+        self.x_ray_color = color;
+    }
+
+    // drives floats at byte offset of a uniform given on creation with curve sampled at Scene::time, e.g. for uv scrolling,
+    // flashing or dissolve effects. uniforms shared with other materials are animated for them too.
+    pub fn animate(&mut self, uniform: &'static str, offset: usize, curve: Arc<AnimationCurve>) {
+        let buffer = match self.uniforms.get(uniform) {
+            Some(x) => x.clone(),
+            None => panic!("No such buffer named {}", uniform),
+        };
+        if !offset.is_multiple_of(4) || offset + curve.width() * 4 > buffer.size {
+            panic!("Animated value doesn't fit in buffer named {}", uniform);
+        }
+
+        self.animations.push((buffer, offset, curve));
+    }
+
+    pub(crate) fn apply_animations(&self, time: f32) {
+        for (buffer, offset, curve) in &self.animations {
+            let mut value = [0.0f32; 4];
+            curve.sample(time, &mut value);
+
+            buffer.write_at(*offset, value[..curve.width()].as_bytes());
+        }
+    }
+
+    // shown in graphics debuggers like RenderDoc for models of the material, unless model is named.
+    pub fn set_name(&mut self, name: &str) {
+        self.name = Some(name.to_owned());
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    // pixels with alpha below cutoff are discarded, so opaque materials can have holes like foliage or fences
+    // without transparency sorting. shader must declare AlphaCutoff uniform as in shaders/alpha_cutoff.wgsl.
+    pub fn set_alpha_cutoff(&mut self, cutoff: Option<f32>) {
+        let buffer = match self.uniforms.get("AlphaCutoff") {
+            Some(x) => x,
+            None => panic!("Shader doesn't declare AlphaCutoff"),
+        };
+        buffer.write(cutoff.unwrap_or(0.0).as_bytes());
+
+        self.alpha_cutoff = cutoff;
+    }
+
+    pub fn alpha_cutoff(&self) -> Option<f32> {
+        self.alpha_cutoff
+    }
+
+    // replaces shader used in a custom pass. bindings should be a subset of the main shader's.
+    pub fn set_pass_shader(&mut self, pass: &'static str, shader: Arc<Shader>) {
+        self.pass_shaders.insert(pass, shader);
+    }
+}
+
+enum Mvp<'a> {
+    Buffer(&'a Buffer),
+    Arena(&'a Arc<UniformArena>),
+    None,
+}
+
+enum Resource<'a> {
+    Buffer(&'a Buffer),
+    Arena(&'a UniformArena),
+    Texture(&'a Arc<Texture>),
+    Sampler(&'a wgpu::Sampler),
+}
+
+impl Resource<'_> {
+    fn key(&self) -> ResourceKey {
+        match self {
+            Resource::Buffer(x) => ResourceKey::Buffer(x.id, x.offset, x.size),
+            Resource::Arena(x) => ResourceKey::Arena(*x as *const UniformArena as usize),
+            Resource::Texture(x) => ResourceKey::Texture(Arc::as_ptr(x) as usize),
+            Resource::Sampler(x) => ResourceKey::Sampler(*x as *const wgpu::Sampler as usize),
         }
     }
 }
@@ -0,0 +1,169 @@
+use alloc::{collections::BTreeMap, sync::Arc, vec::Vec};
+
+use spinning_top::Spinlock;
+
+use crate::event::{EventQueue, RendererEvent};
+
+// Gpu memory held by one kind of allocation.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct MemoryCategory {
+    pub label: &'static str,
+    pub bytes: usize,
+    pub count: usize,
+}
+
+// Snapshot of gpu memory allocated by the renderer, see Renderer::memory_report.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct MemoryReport {
+    // sorted by label
+    pub categories: Vec<MemoryCategory>,
+    // bytes of buffer pool chunks handed out as buffers, a growing value hints at leaked buffers
+    pub buffer_pool_used: usize,
+}
+
+impl MemoryReport {
+    pub fn total_bytes(&self) -> usize {
+        self.categories.iter().map(|x| x.bytes).sum()
+    }
+
+    pub fn category(&self, label: &str) -> Option<&MemoryCategory> {
+        self.categories.iter().find(|x| x.label == label)
+    }
+}
+
+#[derive(Default)]
+struct State {
+    // bytes and count by label
+    categories: BTreeMap<&'static str, (usize, usize)>,
+    total: usize,
+    budget: Option<usize>,
+}
+
+// Counts bytes of buffers and textures alive, shared by everything allocating them like EventQueue.
+// sizes are computed from descriptors, drivers may pad or compress them.
+#[derive(Clone)]
+pub(crate) struct MemoryTracker {
+    state: Arc<Spinlock<State>>,
+    events: EventQueue,
+}
+
+impl MemoryTracker {
+    pub(crate) fn new(events: EventQueue) -> Self {
+        Self {
+            state: Default::default(),
+            events,
+        }
+    }
+
+    // counted until returned allocation is dropped, so keep it next to the wgpu object.
+    pub(crate) fn track(&self, label: &'static str, bytes: usize) -> Allocation {
+        self.add(label, bytes);
+
+        Allocation {
+            tracker: self.clone(),
+            label,
+            bytes,
+        }
+    }
+
+    pub(crate) fn track_texture(&self, label: &'static str, format: wgpu::TextureFormat, extent: wgpu::Extent3d, mip_level_count: u32) -> Allocation {
+        self.track(label, texture_bytes(format, extent, mip_level_count))
+    }
+
+    // raises RendererEvent::MemoryBudgetExceeded when an allocation goes over budget
+    pub(crate) fn set_budget(&self, budget: Option<usize>) {
+        self.state.lock().budget = budget;
+    }
+
+    pub(crate) fn report(&self, buffer_pool_used: usize) -> MemoryReport {
+        let categories = self
+            .state
+            .lock()
+            .categories
+            .iter()
+            .map(|(label, (bytes, count))| MemoryCategory {
+                label,
+                bytes: *bytes,
+                count: *count,
+            })
+            .collect();
+
+        MemoryReport {
+            categories,
+            buffer_pool_used,
+        }
+    }
+
+    fn add(&self, label: &'static str, bytes: usize) {
+        let mut state = self.state.lock();
+
+        let category = state.categories.entry(label).or_insert((0, 0));
+        category.0 += bytes;
+        category.1 += 1;
+
+        // reported once per crossing, not on every allocation over budget
+        let previous = state.total;
+        state.total += bytes;
+        if let Some(budget) = state.budget {
+            if previous <= budget && state.total > budget {
+                self.events.push(RendererEvent::MemoryBudgetExceeded {
+                    allocated: state.total,
+                    budget,
+                });
+            }
+        }
+    }
+
+    fn remove(&self, label: &'static str, bytes: usize) {
+        let mut state = self.state.lock();
+
+        state.total -= bytes;
+        let category = state.categories.get_mut(label).unwrap();
+        category.0 -= bytes;
+        category.1 -= 1;
+        if category.1 == 0 {
+            state.categories.remove(label);
+        }
+    }
+}
+
+pub(crate) struct Allocation {
+    tracker: MemoryTracker,
+    label: &'static str,
+    bytes: usize,
+}
+
+impl Allocation {
+    // moves bytes to another category, e.g. when a texture is labeled by its owner
+    pub(crate) fn relabel(&mut self, label: &'static str) {
+        if label == self.label {
+            return;
+        }
+
+        self.tracker.remove(self.label, self.bytes);
+        self.tracker.add(label, self.bytes);
+        self.label = label;
+    }
+}
+
+impl Drop for Allocation {
+    fn drop(&mut self) {
+        self.tracker.remove(self.label, self.bytes);
+    }
+}
+
+// whole blocks of each mip level, array layers and volume slices alike
+fn texture_bytes(format: wgpu::TextureFormat, extent: wgpu::Extent3d, mip_level_count: u32) -> usize {
+    let info = format.describe();
+    let (block_width, block_height) = (info.block_dimensions.0 as u32, info.block_dimensions.1 as u32);
+
+    (0..mip_level_count)
+        .map(|level| {
+            let width = (extent.width >> level).max(1);
+            let height = (extent.height >> level).max(1);
+
+            width.div_ceil(block_width) as usize * height.div_ceil(block_height) as usize * info.block_size as usize
+        })
+        .sum::<usize>()
+        * extent.depth_or_array_layers as usize
+}
@@ -1,9 +1,13 @@
-use alloc::{vec, vec::Vec};
+use alloc::{borrow::ToOwned, string::String, sync::Arc, vec, vec::Vec};
 use core::mem::size_of;
 
+use nalgebra::{Point3, Vector3};
 use zerocopy::AsBytes;
 
-use crate::{buffer::Buffer, buffer_pool::BufferPool, Renderer, VertexFormat, VertexFormatItem, VertexItemType};
+use crate::{
+    buffer::Buffer, buffer_pool::BufferPool, constants::MAX_MORPH_TARGETS, raycast::MeshShape, Aabb, BoundingSphere, PrimitiveTopology, Ray, RayHit,
+    Renderer, RendererEvent, VertexFormat, VertexFormatItem, VertexItemType,
+};
 
 #[repr(C)]
 #[derive(AsBytes)]
@@ -18,12 +22,66 @@ impl SimpleVertex {
     }
 }
 
+// Vertex with second uv set, unique across the mesh, for Material::lightmapped.
+#[repr(C)]
+#[derive(AsBytes)]
+pub struct LightmapVertex {
+    pub pos: [f32; 4],
+    pub tex_coord: [f32; 2],
+    pub lightmap_coord: [f32; 2],
+}
+
+impl LightmapVertex {
+    pub fn new(pos: [f32; 4], tex_coord: [f32; 2], lightmap_coord: [f32; 2]) -> Self {
+        Self {
+            pos,
+            tex_coord,
+            lightmap_coord,
+        }
+    }
+}
+
+// positions blended on cpu in downlevel mode, as vertex stage can't read morph targets from storage buffer there
+struct CpuMorph {
+    base: Vec<Point3<f32>>,
+    // offsets of every vertex of first target, then second and so on
+    offsets: Vec<[f32; 3]>,
+    // vertex buffer holding only positions, and their component count
+    buffer_index: usize,
+    components: usize,
+}
+
+impl CpuMorph {
+    fn blend(&self, weights: &[f32]) -> Vec<f32> {
+        let mut result = Vec::with_capacity(self.base.len() * self.components);
+        for (i, base) in self.base.iter().enumerate() {
+            let mut position = *base;
+            for (weight, offsets) in weights.iter().zip(self.offsets.chunks(self.base.len())) {
+                if *weight != 0.0 {
+                    position += Vector3::from(offsets[i]) * *weight;
+                }
+            }
+
+            let components = [position.x, position.y, position.z, 1.0];
+            result.extend_from_slice(&components[..self.components]);
+        }
+
+        result
+    }
+}
+
 pub struct Mesh {
     pub(crate) vertex_buffers: Vec<Buffer>,
     pub(crate) strides: Vec<usize>,
     pub(crate) index_buffer: Buffer,
     pub(crate) index_count: usize,
     pub(crate) vertex_formats: Vec<VertexFormat>,
+    pub(crate) topology: PrimitiveTopology,
+    shape: Option<MeshShape>,
+    aabb: Option<Aabb>,
+    morph_targets: Option<Arc<Buffer>>,
+    cpu_morph: Option<CpuMorph>,
+    name: Option<String>,
 }
 
 impl Mesh {
@@ -31,6 +89,18 @@ impl Mesh {
         Self::with_buffer_pool(&renderer.buffer_pool, vertex_data, strides, indices, vertex_formats)
     }
 
+    // lines and points, or triangle strips. only triangles can be hit by rays.
+    pub fn with_topology(
+        renderer: &Renderer,
+        topology: PrimitiveTopology,
+        vertex_data: &[&[u8]],
+        strides: &[usize],
+        indices: &[u16],
+        vertex_formats: Vec<VertexFormat>,
+    ) -> Self {
+        Self::create(&renderer.buffer_pool, topology, vertex_data, strides, indices, vertex_formats)
+    }
+
     pub fn with_simple_vertex(renderer: &Renderer, vertices: &[SimpleVertex], indices: &[u16]) -> Self {
         let vertex_data = vertices.as_bytes();
         let strides = vec![size_of::<SimpleVertex>()];
@@ -47,12 +117,47 @@ impl Mesh {
         )
     }
 
+    pub fn with_lightmap_vertex(renderer: &Renderer, vertices: &[LightmapVertex], indices: &[u16]) -> Self {
+        let vertex_data = vertices.as_bytes();
+        let strides = vec![size_of::<LightmapVertex>()];
+
+        Self::with_buffer_pool(
+            &renderer.buffer_pool,
+            &[vertex_data],
+            &strides,
+            indices,
+            vec![VertexFormat::new(vec![
+                VertexFormatItem::new("Position", VertexItemType::Float4, 0),
+                VertexFormatItem::new("TexCoord", VertexItemType::Float2, size_of::<f32>() * 4),
+                VertexFormatItem::new("LightmapCoord", VertexItemType::Float2, size_of::<f32>() * 6),
+            ])],
+        )
+    }
+
     pub(crate) fn with_buffer_pool(
         buffer_pool: &BufferPool,
         vertex_data: &[&[u8]],
         strides: &[usize],
         indices: &[u16],
         vertex_formats: Vec<VertexFormat>,
+    ) -> Self {
+        Self::create(
+            buffer_pool,
+            PrimitiveTopology::TriangleList,
+            vertex_data,
+            strides,
+            indices,
+            vertex_formats,
+        )
+    }
+
+    fn create(
+        buffer_pool: &BufferPool,
+        topology: PrimitiveTopology,
+        vertex_data: &[&[u8]],
+        strides: &[usize],
+        indices: &[u16],
+        vertex_formats: Vec<VertexFormat>,
     ) -> Self {
         let mut vertex_buffers = Vec::with_capacity(vertex_data.len());
         for vertex_datum in vertex_data {
@@ -66,12 +171,154 @@ impl Mesh {
         let index_buffer = buffer_pool.alloc_index(index_data.len());
         index_buffer.write(index_data);
 
+        let positions = Self::read_positions(vertex_data, strides, &vertex_formats);
+        let aabb = positions.as_ref().and_then(Aabb::from_points);
+        let shape = positions.and_then(|x| match topology {
+            PrimitiveTopology::TriangleList => Some(MeshShape::new(x, indices)),
+            PrimitiveTopology::TriangleStrip => Some(MeshShape::new(x, &Self::strip_to_list(indices))),
+            _ => None,
+        });
+
         Self {
             vertex_buffers,
             strides: Vec::from(strides),
             index_buffer,
             index_count: indices.len(),
             vertex_formats,
+            topology,
+            shape,
+            aabb,
+            morph_targets: None,
+            cpu_morph: None,
+            name: None,
+        }
+    }
+
+    // shown in graphics debuggers like RenderDoc for models of the mesh, unless model or material is named.
+    pub fn with_name(mut self, name: &str) -> Self {
+        self.name = Some(name.to_owned());
+
+        self
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    // position offsets of each vertex for each target, blended in vertex shader by weights set with Model::set_morph_weights.
+    // bounds and ray casts use positions without morphing.
+    // in downlevel mode they're blended on cpu into a vertex buffer of positions instead, for float triangle meshes only.
+    pub fn with_morph_targets(mut self, renderer: &Renderer, targets: &[&[[f32; 3]]]) -> Self {
+        let vertex_count = targets.first().map(|x| x.len()).unwrap_or(0);
+        if targets.len() > MAX_MORPH_TARGETS || targets.iter().any(|x| x.len() != vertex_count) {
+            panic!("Morph targets need an offset for every vertex, up to {} targets", MAX_MORPH_TARGETS);
+        }
+
+        if renderer.downlevel {
+            match self.shape.as_ref().map(|x| x.positions().to_vec()) {
+                Some(base) if base.len() == vertex_count => self.with_cpu_morph(renderer, base, targets),
+                _ => renderer.events.push(RendererEvent::MorphTargetsSkipped),
+            }
+
+            return self;
+        }
+
+        // header of vertex and target count, then vec4 offsets
+        let mut data = vec![0.0f32; 4];
+        data[0] = f32::from_bits(vertex_count as u32);
+        data[1] = f32::from_bits(targets.len() as u32);
+        for offset in targets.iter().flat_map(|x| x.iter()) {
+            data.extend_from_slice(&[offset[0], offset[1], offset[2], 0.0]);
+        }
+
+        let buffer = renderer.buffer_pool.alloc(data.as_bytes().len());
+        buffer.write(data.as_bytes());
+        self.morph_targets = Some(Arc::new(buffer));
+
+        self
+    }
+
+    fn with_cpu_morph(&mut self, renderer: &Renderer, base: Vec<Point3<f32>>, targets: &[&[[f32; 3]]]) {
+        let (index, (_, components)) = match self.vertex_formats.iter().enumerate().find_map(|(i, x)| Some((i, x.position()?))) {
+            Some(x) => x,
+            None => return,
+        };
+        let format = self.vertex_formats[index].split_position().unwrap();
+
+        let morph = CpuMorph {
+            offsets: targets.iter().flat_map(|x| x.iter().copied()).collect(),
+            buffer_index: self.vertex_buffers.len(),
+            components,
+            base,
+        };
+        let data = morph.blend(&[]);
+        let buffer = renderer.buffer_pool.alloc(data.as_bytes().len());
+        buffer.write(data.as_bytes());
+
+        self.vertex_buffers.push(buffer);
+        self.strides.push(components * size_of::<f32>());
+        self.vertex_formats.push(format);
+        self.cpu_morph = Some(morph);
+    }
+
+    // writes positions blended by weights, if they're morphed on cpu
+    pub(crate) fn morph(&self, weights: &[f32]) {
+        if let Some(morph) = &self.cpu_morph {
+            self.vertex_buffers[morph.buffer_index].write(morph.blend(weights).as_bytes());
         }
     }
+
+    // on gpu or cpu, weights of each model apply
+    pub(crate) fn has_morph_targets(&self) -> bool {
+        self.morph_targets.is_some() || self.cpu_morph.is_some()
+    }
+
+    // bound to materials as MorphTargets, see shaders/morph.wgsl. none in downlevel mode.
+    pub fn morph_targets(&self) -> Option<Arc<Buffer>> {
+        self.morph_targets.clone()
+    }
+
+    pub fn topology(&self) -> PrimitiveTopology {
+        self.topology
+    }
+
+    // bounds in mesh space. meshes without float positions have none.
+    pub fn aabb(&self) -> Option<Aabb> {
+        self.aabb
+    }
+
+    pub fn bounding_sphere(&self) -> Option<BoundingSphere> {
+        self.aabb.map(|x| x.bounding_sphere())
+    }
+
+    // closest hit in mesh space. meshes without float positions can't be hit.
+    pub fn intersect(&self, ray: &Ray) -> Option<RayHit> {
+        self.shape.as_ref()?.intersect(ray)
+    }
+
+    // every other triangle of strip is flipped to keep winding
+    fn strip_to_list(indices: &[u16]) -> Vec<u16> {
+        indices
+            .windows(3)
+            .enumerate()
+            .flat_map(|(i, x)| if i % 2 == 0 { [x[0], x[1], x[2]] } else { [x[1], x[0], x[2]] })
+            .collect()
+    }
+
+    fn read_positions(vertex_data: &[&[u8]], strides: &[usize], vertex_formats: &[VertexFormat]) -> Option<Vec<Point3<f32>>> {
+        let (index, (offset, components)) = vertex_formats.iter().enumerate().find_map(|(i, x)| Some((i, x.position()?)))?;
+        let data = vertex_data[index];
+        let stride = strides[index];
+
+        let read = |offset: usize| f32::from_le_bytes([data[offset], data[offset + 1], data[offset + 2], data[offset + 3]]);
+        let positions = (0..data.len() / stride)
+            .map(|i| {
+                let base = i * stride + offset;
+                let z = if components > 2 { read(base + 8) } else { 0.0 };
+                Point3::new(read(base), read(base + 4), z)
+            })
+            .collect();
+
+        Some(positions)
+    }
 }
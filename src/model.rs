@@ -1,23 +1,143 @@
-use alloc::vec::Vec;
-use core::ops::Range;
+use alloc::{borrow::ToOwned, string::String, sync::Arc, vec, vec::Vec};
+use core::{
+    ops::Range,
+    sync::atomic::{AtomicU32, Ordering},
+};
 
-use crate::{constants::INTERNAL_COLOR_ATTACHMENT_FORMAT, Material, Mesh, RenderContext, Renderable, Renderer};
+use hashbrown::HashMap;
+use nalgebra::{Matrix4, Point3};
+use spinning_top::Spinlock;
+use zerocopy::AsBytes;
+
+use crate::{
+    constants::{INTERNAL_COLOR_ATTACHMENT_FORMAT, MAX_MORPH_TARGETS},
+    deferred::DeferredPath,
+    instancing,
+    model_pass::ModelPass,
+    picking,
+    pipeline_cache::{PipelineCache, PipelineKey},
+    Aabb, BlendMode, BoundingSphere, Buffer, DepthCompare, Material, MaterialPass, Mesh, Ray, RayHit, RenderContext, RenderLayers, RenderPath,
+    Renderable, Renderer, Shader,
+};
 
 pub struct Model {
-    mesh: Mesh,
+    // shared by models drawn with same mesh, so they can be merged into instanced draws
+    mesh: Arc<Mesh>,
     material: Material,
-    pipeline: wgpu::RenderPipeline,
+    pipeline: Arc<wgpu::RenderPipeline>,
+    pass_pipelines: HashMap<&'static str, Arc<wgpu::RenderPipeline>>,
+    // vertex only variant of pipeline for depth pre-pass, if material's depth is final without shading
+    depth_pipeline: Option<Arc<wgpu::RenderPipeline>>,
+    // variant of pipeline with shader's instanced entry point, see Shader::with_instanced_entry
+    instanced_pipeline: Option<Arc<wgpu::RenderPipeline>>,
+    transform: Matrix4<f32>,
+    layers: RenderLayers,
+    visible: bool,
+    picking: Option<ModelPass>,
+    x_ray: Option<(ModelPass, Buffer)>,
+    // inverted hull and its color and width, drawn while enabled
+    outline: Option<(ModelPass, Buffer)>,
+    outline_enabled: bool,
+    // slot in uniform arena written by last prepare
+    mvp_offset: AtomicU32,
+    // mvp and model transform written by last prepare, for push constants and instanced draws
+    transforms: Spinlock<[f32; 32]>,
+    // bits of lod fade written after model transform
+    lod_fade: AtomicU32,
+    morph_weights: Spinlock<[f32; MAX_MORPH_TARGETS]>,
+    name: Option<String>,
+    // drawn after lighting is resolved on deferred render path, as shader has no g-buffer entry point
+    forward: bool,
 }
 
 impl Model {
-    pub fn new(renderer: &Renderer, mesh: Mesh, material: Material) -> Self {
-        Self::with_surface_and_depth_format(
-            &*renderer.device,
-            mesh,
+    // mesh can be shared with other models as Arc<Mesh>. consecutive opaque draws of same mesh and material,
+    // e.g. of cloned material, are merged into instanced draws if shader has instanced entry point.
+    pub fn new(renderer: &Renderer, mesh: impl Into<Arc<Mesh>>, material: Material) -> Self {
+        // transparent models and ones without g-buffer entry point are drawn forward after lighting is resolved
+        let deferred = renderer.options.render_path == RenderPath::Deferred;
+        let gbuffer_entry = match material.shader.gbuffer_entry {
+            Some(x) if deferred && material.blend_mode == BlendMode::Opaque => Some(x),
+            _ => None,
+        };
+        let (color_formats, fs_entry) = match gbuffer_entry {
+            Some(x) => (DeferredPath::formats().to_vec(), x),
+            None => (vec![INTERNAL_COLOR_ATTACHMENT_FORMAT.wgpu_type()], material.shader.fs_entry),
+        };
+
+        let mut model = Self::create(
+            &renderer.device,
+            Some(&renderer.pipeline_cache),
+            mesh.into(),
             material,
-            INTERNAL_COLOR_ATTACHMENT_FORMAT.wgpu_type(),
+            &color_formats,
+            fs_entry,
             Some(wgpu::TextureFormat::Depth32Float),
-        )
+        );
+        model.forward = deferred && gbuffer_entry.is_none();
+        if let Some(arena) = &model.material.mvp_arena {
+            model.picking = Some(ModelPass::new(
+                &renderer.device,
+                &renderer.pick_shader,
+                &model.mesh,
+                arena,
+                &[],
+                picking::PICK_FORMAT.into(),
+                model.material.render_state.primitive_state(model.mesh.topology),
+                wgpu::CompareFunction::LessEqual,
+                true,
+            ));
+
+            if let Some(color) = model.material.x_ray_color {
+                let color_buf = renderer.buffer_pool.alloc(color.as_bytes().len());
+                color_buf.write(color.as_bytes());
+
+                let target = wgpu::ColorTargetState {
+                    format: INTERNAL_COLOR_ATTACHMENT_FORMAT.wgpu_type(),
+                    blend: BlendMode::AlphaBlend.wgpu_type(),
+                    write_mask: wgpu::ColorWrites::ALL,
+                };
+                let pass = ModelPass::new(
+                    &renderer.device,
+                    &renderer.x_ray_shader,
+                    &model.mesh,
+                    arena,
+                    &[&color_buf],
+                    target,
+                    model.material.render_state.primitive_state(model.mesh.topology),
+                    wgpu::CompareFunction::Greater,
+                    false,
+                );
+                model.x_ray = Some((pass, color_buf));
+            }
+
+            let has_normal = model.mesh.vertex_formats.iter().any(|x| x.has_item("Normal"));
+            let shader = &renderer.outline_shaders[if has_normal { 0 } else { 1 }];
+            let outline_buf = renderer.buffer_pool.alloc(core::mem::size_of::<[f32; 8]>());
+
+            let target = wgpu::ColorTargetState {
+                format: INTERNAL_COLOR_ATTACHMENT_FORMAT.wgpu_type(),
+                blend: BlendMode::AlphaBlend.wgpu_type(),
+                write_mask: wgpu::ColorWrites::ALL,
+            };
+            let mut primitive = model.material.render_state.primitive_state(model.mesh.topology);
+            primitive.cull_mode = Some(wgpu::Face::Front);
+
+            let pass = ModelPass::new(
+                &renderer.device,
+                shader,
+                &model.mesh,
+                arena,
+                &[&outline_buf],
+                target,
+                primitive,
+                wgpu::CompareFunction::LessEqual,
+                false,
+            );
+            model.outline = Some((pass, outline_buf));
+        }
+
+        model
     }
 
     pub(crate) fn with_surface_and_depth_format(
@@ -27,13 +147,222 @@ impl Model {
         surface_format: wgpu::TextureFormat,
         depth_format: Option<wgpu::TextureFormat>,
     ) -> Self {
-        let attributes = mesh
-            .vertex_formats
+        Self::with_formats(device, mesh, material, &[surface_format], depth_format)
+    }
+
+    pub(crate) fn with_formats(
+        device: &wgpu::Device,
+        mesh: Mesh,
+        material: Material,
+        color_formats: &[wgpu::TextureFormat],
+        depth_format: Option<wgpu::TextureFormat>,
+    ) -> Self {
+        let fs_entry = material.shader.fs_entry;
+
+        Self::create(device, None, Arc::new(mesh), material, color_formats, fs_entry, depth_format)
+    }
+
+    // pipelines are shared with other models of same state if cache is given
+    fn create(
+        device: &wgpu::Device,
+        cache: Option<&PipelineCache>,
+        mesh: Arc<Mesh>,
+        material: Material,
+        color_formats: &[wgpu::TextureFormat],
+        fs_entry: &'static str,
+        depth_format: Option<wgpu::TextureFormat>,
+    ) -> Self {
+        let pipeline = Self::create_pipeline(
+            device,
+            cache,
+            &mesh,
+            &material,
+            &material.shader,
+            color_formats,
+            fs_entry,
+            depth_format,
+            true,
+            false,
+        );
+
+        // custom passes draw into a single color target, testing against main pass depth
+        let pass_pipelines = material
+            .passes
             .iter()
-            .map(|x| x.wgpu_attributes(&material.shader.inputs))
-            .collect::<Vec<_>>();
+            .filter_map(|pass| match pass {
+                MaterialPass::Main => None,
+                MaterialPass::Custom(name) => {
+                    let shader = material.pass_shaders.get(name).unwrap_or(&material.shader);
+                    let pipeline = Self::create_pipeline(
+                        device,
+                        cache,
+                        &mesh,
+                        &material,
+                        shader,
+                        &[INTERNAL_COLOR_ATTACHMENT_FORMAT.wgpu_type()],
+                        shader.fs_entry,
+                        depth_format,
+                        false,
+                        false,
+                    );
+
+                    Some((*name, pipeline))
+                }
+            })
+            .collect();
+
+        // main pass has to pass depth test against what pre-pass wrote
+        let depth_pipeline = if depth_format.is_some()
+            && material.passes.contains(&MaterialPass::Main)
+            && material.blend_mode == BlendMode::Opaque
+            && material.alpha_cutoff().is_none()
+            && material.render_state.depth_compare == DepthCompare::LessEqual
+        {
+            Some(Self::create_pipeline(
+                device,
+                cache,
+                &mesh,
+                &material,
+                &material.shader,
+                &[],
+                "",
+                depth_format,
+                true,
+                false,
+            ))
+        } else {
+            None
+        };
+
+        // models with per model vertex data besides transform are drawn alone
+        let instanced_pipeline = if material.shader.instanced_entry.is_some()
+            && material.passes.contains(&MaterialPass::Main)
+            && material.blend_mode == BlendMode::Opaque
+            && !mesh.has_morph_targets()
+            && material.shader.mesh_vs_entry(&mesh.vertex_formats) == material.shader.vs_entry
+        {
+            Some(Self::create_pipeline(
+                device,
+                cache,
+                &mesh,
+                &material,
+                &material.shader,
+                color_formats,
+                fs_entry,
+                depth_format,
+                true,
+                true,
+            ))
+        } else {
+            None
+        };
+
+        Self {
+            mesh,
+            material,
+            pipeline,
+            pass_pipelines,
+            depth_pipeline,
+            instanced_pipeline,
+            transform: Matrix4::identity(),
+            layers: RenderLayers::default(),
+            visible: true,
+            picking: None,
+            x_ray: None,
+            outline: None,
+            outline_enabled: false,
+            mvp_offset: AtomicU32::new(0),
+            transforms: Spinlock::new([0.0; 32]),
+            lod_fade: AtomicU32::new(1.0f32.to_bits()),
+            morph_weights: Spinlock::new([0.0; MAX_MORPH_TARGETS]),
+            name: None,
+            forward: false,
+        }
+    }
+
+    // its draws are grouped under name in graphics debuggers like RenderDoc
+    pub fn set_name(&mut self, name: &str) {
+        self.name = Some(name.to_owned());
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    pub fn set_transform(&mut self, transform: Matrix4<f32>) {
+        self.transform = transform;
+    }
+
+    // weights of mesh morph targets, written to Mvp uniform after lod fade. see shaders/morph.wgsl.
+    pub fn set_morph_weights(&self, weights: &[f32]) {
+        let mut morph_weights = self.morph_weights.lock();
+        let count = weights.len().min(MAX_MORPH_TARGETS);
+
+        morph_weights.fill(0.0);
+        morph_weights[..count].copy_from_slice(&weights[..count]);
+        self.mesh.morph(&*morph_weights);
+    }
+
+    pub fn transform(&self) -> &Matrix4<f32> {
+        &self.transform
+    }
+
+    pub fn set_layers(&mut self, layers: RenderLayers) {
+        self.layers = layers;
+    }
 
-        let vertex_buffers = attributes
+    // highlights model with outline of color and width in model units, e.g. for hover or selection.
+    // meshes with hard edges should have smoothed normals, or the outline breaks at corners.
+    pub fn set_outline(&mut self, outline: Option<([f32; 4], f32)>) {
+        if let (Some((_, buffer)), Some((color, width))) = (&self.outline, outline) {
+            let data = [color[0], color[1], color[2], color[3], width, 0.0, 0.0, 0.0];
+            buffer.write(data.as_bytes());
+        }
+
+        self.outline_enabled = outline.is_some();
+    }
+
+    pub fn set_visible(&mut self, visible: bool) {
+        self.visible = visible;
+    }
+
+    // world space bounds of mesh with transform applied
+    pub fn aabb(&self) -> Option<Aabb> {
+        self.mesh.aabb().map(|x| x.transform(&self.transform))
+    }
+
+    pub fn bounding_sphere(&self) -> Option<BoundingSphere> {
+        self.mesh.bounding_sphere().map(|x| x.transform(&self.transform))
+    }
+
+    // closest hit of world space ray, distance is in world units.
+    pub fn intersect(&self, ray: &Ray) -> Option<RayHit> {
+        let inverse = self.transform.try_inverse()?;
+
+        self.mesh.intersect(&ray.transform(&inverse))
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn create_pipeline(
+        device: &wgpu::Device,
+        cache: Option<&PipelineCache>,
+        mesh: &Mesh,
+        material: &Material,
+        shader: &Arc<Shader>,
+        color_formats: &[wgpu::TextureFormat],
+        fs_entry: &'static str,
+        depth_format: Option<wgpu::TextureFormat>,
+        depth_write: bool,
+        instanced: bool,
+    ) -> Arc<wgpu::RenderPipeline> {
+        let attributes = mesh.vertex_formats.iter().map(|x| x.wgpu_attributes(&shader.inputs)).collect::<Vec<_>>();
+        let vs_entry = match shader.instanced_entry {
+            Some(x) if instanced => x,
+            _ => shader.mesh_vs_entry(&mesh.vertex_formats),
+        };
+
+        // instance transforms follow mesh buffers
+        let mut vertex_buffers = attributes
             .iter()
             .zip(mesh.strides.iter())
             .map(|(attributes, stride)| wgpu::VertexBufferLayout {
@@ -42,57 +371,115 @@ impl Model {
                 attributes,
             })
             .collect::<Vec<_>>();
+        if instanced {
+            vertex_buffers.push(instancing::vertex_buffer_layout());
+        }
 
-        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            layout: Some(&material.pipeline_layout),
-            vertex: wgpu::VertexState {
-                module: &material.shader.module,
-                entry_point: material.shader.vs_entry,
-                buffers: &vertex_buffers,
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: &material.shader.module,
-                entry_point: material.shader.fs_entry,
-                targets: &[wgpu::ColorTargetState {
-                    format: surface_format,
-                    blend: Some(wgpu::BlendState {
-                        color: wgpu::BlendComponent {
-                            operation: wgpu::BlendOperation::Add,
-                            src_factor: wgpu::BlendFactor::SrcAlpha,
-                            dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
-                        },
-                        alpha: wgpu::BlendComponent::REPLACE,
-                    }),
-                    write_mask: wgpu::ColorWrites::ALL,
-                }],
-            }),
-            primitive: wgpu::PrimitiveState {
-                cull_mode: Some(wgpu::Face::Back),
-                ..Default::default()
-            },
-            depth_stencil: depth_format.map(|x| wgpu::DepthStencilState {
-                format: x,
-                depth_write_enabled: true,
-                depth_compare: wgpu::CompareFunction::LessEqual,
-                stencil: wgpu::StencilState::default(),
-                bias: wgpu::DepthBiasState::default(),
-            }),
-            label: None,
-            multisample: wgpu::MultisampleState::default(),
-        });
+        // no color targets makes vertex only pipeline, for depth pre-pass.
+        // g-buffer targets are written without blending
+        let blend = if color_formats.len() == 1 {
+            material.blend_mode.wgpu_type()
+        } else {
+            None
+        };
+        let targets = color_formats
+            .iter()
+            .map(|&format| wgpu::ColorTargetState {
+                format,
+                blend,
+                write_mask: wgpu::ColorWrites::ALL,
+            })
+            .collect::<Vec<_>>();
+        let primitive = material.render_state.primitive_state(mesh.topology);
+        let depth_compare = material.render_state.depth_compare.wgpu_type();
+        let depth_bias = material.render_state.depth_bias_state();
+        let depth_write_enabled = depth_write && material.blend_mode == BlendMode::Opaque;
+
+        let create = || {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                layout: Some(&material.layout.pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader.module,
+                    entry_point: vs_entry,
+                    buffers: &vertex_buffers,
+                },
+                fragment: if targets.is_empty() {
+                    None
+                } else {
+                    Some(wgpu::FragmentState {
+                        module: shader.fragment_module(),
+                        entry_point: fs_entry,
+                        targets: &targets,
+                    })
+                },
+                primitive,
+                depth_stencil: depth_format.map(|x| wgpu::DepthStencilState {
+                    format: x,
+                    depth_write_enabled,
+                    depth_compare,
+                    stencil: wgpu::StencilState::default(),
+                    bias: depth_bias,
+                }),
+                label: None,
+                multisample: wgpu::MultisampleState::default(),
+            })
+        };
+
+        match cache {
+            Some(cache) => {
+                let key = PipelineKey {
+                    layout: Arc::as_ptr(&material.layout) as usize,
+                    shader: Arc::as_ptr(shader) as usize,
+                    vs_entry,
+                    fs_entry: if targets.is_empty() { "" } else { fs_entry },
+                    vertex_buffers: vertex_buffers.iter().map(|x| (x.array_stride, x.attributes.to_vec())).collect(),
+                    targets: targets.clone(),
+                    primitive,
+                    depth: depth_format.map(|x| (x, depth_write_enabled, depth_compare)),
+                    depth_bias: (depth_bias.constant, depth_bias.slope_scale.to_bits()),
+                };
 
-        Self { mesh, material, pipeline }
+                cache.pipeline(key, shader, create)
+            }
+            None => Arc::new(create()),
+        }
     }
 
-    pub fn render_ranges<'a>(&'a self, render_context: &mut RenderContext<'a>, ranges: &[Range<u32>]) {
-        render_context.render_pass.set_pipeline(&self.pipeline);
-        render_context.render_pass.set_bind_group(0, &self.material.bind_group, &[]);
-        render_context
-            .render_pass
-            .set_index_buffer(self.mesh.index_buffer.as_slice(), wgpu::IndexFormat::Uint16);
-        for (i, vertex_buffer) in self.mesh.vertex_buffers.iter().enumerate() {
-            render_context.render_pass.set_vertex_buffer(i as u32, vertex_buffer.as_slice());
+    // offsets of material bind group, where mvp is bound unless it's a push constant
+    fn dynamic_offsets(&self) -> Vec<u32> {
+        match (&self.material.mvp_arena, &self.material.mvp_push_constant) {
+            (Some(_), None) => self.arena_offsets(),
+            _ => Vec::new(),
+        }
+    }
+
+    // offsets of picking, x-ray and outline bind groups, which always bind mvp from arena
+    fn arena_offsets(&self) -> Vec<u32> {
+        vec![self.mvp_offset.load(Ordering::Relaxed)]
+    }
+
+    fn bind<'a>(&'a self, render_context: &mut RenderContext<'a>, pipeline: &'a wgpu::RenderPipeline) {
+        render_context.set_pipeline(pipeline);
+        render_context.set_bind_group(&self.material.bind_group, &self.dynamic_offsets());
+        if let Some(range) = &self.material.mvp_push_constant {
+            let data = *self.transforms.lock();
+            let size = (range.range.end as usize).min(data.as_bytes().len());
+            render_context.render_pass.set_push_constants(range.stages, 0, &data.as_bytes()[..size]);
         }
+        render_context.set_mesh(&self.mesh);
+    }
+
+    pub fn render_ranges<'a>(&'a self, render_context: &mut RenderContext<'a>, ranges: &[Range<u32>]) {
+        let pipeline = match &render_context.pass {
+            MaterialPass::Main if self.material.passes.contains(&MaterialPass::Main) => &self.pipeline,
+            MaterialPass::Main => return,
+            MaterialPass::Custom(name) => match self.pass_pipelines.get(name) {
+                Some(x) => x,
+                None => return,
+            },
+        };
+
+        self.bind(render_context, pipeline);
 
         let mut last_start = ranges[0].start;
         let mut last_end = ranges[0].start;
@@ -108,7 +495,124 @@ impl Model {
 }
 
 impl Renderable for Model {
+    fn label(&self) -> Option<&str> {
+        self.name().or_else(|| self.mesh.name()).or_else(|| self.material.name())
+    }
+
     fn render<'a>(&'a self, render_context: &mut RenderContext<'a>) {
-        self.render_ranges(render_context, &[0..self.mesh.index_count as u32]);
+        self.render_ranges(render_context, core::slice::from_ref(&(0..self.mesh.index_count as u32)));
+    }
+
+    fn render_depth<'a>(&'a self, render_context: &mut RenderContext<'a>) {
+        if let Some(pipeline) = &self.depth_pipeline {
+            self.bind(render_context, pipeline);
+            render_context.render_pass.draw_indexed(0..self.mesh.index_count as u32, 0, 0..1);
+        }
+    }
+
+    fn render_pick<'a>(&'a self, render_context: &mut RenderContext<'a>, id: u32) {
+        if let Some(picking) = &self.picking {
+            render_context.set_pipeline(&picking.pipeline);
+            render_context.set_bind_group(&picking.bind_group, &self.arena_offsets());
+            render_context.set_mesh(&self.mesh);
+            render_context.render_pass.draw_indexed(0..self.mesh.index_count as u32, 0, id..id + 1);
+        }
+    }
+
+    fn render_x_ray<'a>(&'a self, render_context: &mut RenderContext<'a>) {
+        if let Some((x_ray, _)) = &self.x_ray {
+            render_context.set_pipeline(&x_ray.pipeline);
+            render_context.set_bind_group(&x_ray.bind_group, &self.arena_offsets());
+            render_context.set_mesh(&self.mesh);
+            render_context.render_pass.draw_indexed(0..self.mesh.index_count as u32, 0, 0..1);
+        }
+    }
+
+    fn render_outline<'a>(&'a self, render_context: &mut RenderContext<'a>) {
+        if let (Some((outline, _)), true) = (&self.outline, self.outline_enabled) {
+            render_context.set_pipeline(&outline.pipeline);
+            render_context.set_bind_group(&outline.bind_group, &self.arena_offsets());
+            render_context.set_mesh(&self.mesh);
+            render_context.render_pass.draw_indexed(0..self.mesh.index_count as u32, 0, 0..1);
+        }
+    }
+
+    fn animate(&self, time: f32) {
+        self.material.apply_animations(time);
+    }
+
+    fn prepare(&self, view_projection: &Matrix4<f32>) {
+        if self.material.mvp_arena.is_none() && self.material.mvp_push_constant.is_none() && self.instanced_pipeline.is_none() {
+            return;
+        }
+
+        let mvp = view_projection * self.transform;
+
+        let mut data = [0.0f32; 32];
+        data[..16].copy_from_slice(mvp.as_slice());
+        data[16..].copy_from_slice(self.transform.as_slice());
+
+        *self.transforms.lock() = data;
+        // picking and x-ray still read mvp from arena
+        if let Some(arena) = &self.material.mvp_arena {
+            // weights start at next 16 byte boundary
+            let mut slot = [0.0f32; 36 + MAX_MORPH_TARGETS];
+            slot[..32].copy_from_slice(&data);
+            slot[32] = f32::from_bits(self.lod_fade.load(Ordering::Relaxed));
+            slot[36..].copy_from_slice(&*self.morph_weights.lock());
+            self.mvp_offset.store(arena.push(slot.as_bytes()), Ordering::Relaxed);
+        }
+    }
+
+    fn set_lod_fade(&self, fade: f32) {
+        self.lod_fade.store(fade.to_bits(), Ordering::Relaxed);
+    }
+
+    fn sort_key(&self) -> (usize, usize, usize) {
+        (
+            Arc::as_ptr(&self.pipeline) as usize,
+            Arc::as_ptr(&self.material.bind_group) as usize,
+            Arc::as_ptr(&self.mesh) as usize,
+        )
+    }
+
+    // models fading between lod levels read fade from their own Mvp slot
+    fn instance_transforms(&self) -> Option<[f32; 32]> {
+        if self.instanced_pipeline.is_none() || self.lod_fade.load(Ordering::Relaxed) != 1.0f32.to_bits() {
+            return None;
+        }
+
+        Some(*self.transforms.lock())
+    }
+
+    fn render_instanced<'a>(&'a self, render_context: &mut RenderContext<'a>, instances: wgpu::BufferSlice<'a>, count: u32) {
+        if let Some(pipeline) = &self.instanced_pipeline {
+            self.bind(render_context, pipeline);
+            render_context
+                .render_pass
+                .set_vertex_buffer(self.mesh.vertex_buffers.len() as u32, instances);
+            render_context.render_pass.draw_indexed(0..self.mesh.index_count as u32, 0, 0..count);
+        }
+    }
+
+    // forward models on deferred render path are drawn with transparent ones, they can't write g-buffer
+    fn is_transparent(&self) -> bool {
+        self.material.blend_mode != BlendMode::Opaque || self.forward
+    }
+
+    fn position(&self) -> Point3<f32> {
+        self.transform.transform_point(&Point3::origin())
+    }
+
+    fn aabb(&self) -> Option<Aabb> {
+        Model::aabb(self)
+    }
+
+    fn layers(&self) -> RenderLayers {
+        self.layers
+    }
+
+    fn is_visible(&self) -> bool {
+        self.visible
     }
 }
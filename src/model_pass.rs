@@ -0,0 +1,90 @@
+use alloc::vec::Vec;
+
+use crate::{uniform_arena::UniformArena, Buffer, Mesh, Shader};
+
+// Draws a model's mesh with a renderer owned shader instead of its material, like picking or x-ray.
+// Binding 0 is model's slot in uniform arena, following bindings are uniform buffers in binding order.
+pub(crate) struct ModelPass {
+    pub(crate) pipeline: wgpu::RenderPipeline,
+    pub(crate) bind_group: wgpu::BindGroup,
+}
+
+impl ModelPass {
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn new(
+        device: &wgpu::Device,
+        shader: &Shader,
+        mesh: &Mesh,
+        arena: &UniformArena,
+        buffers: &[&Buffer],
+        target: wgpu::ColorTargetState,
+        primitive: wgpu::PrimitiveState,
+        depth_compare: wgpu::CompareFunction,
+        depth_write: bool,
+    ) -> Self {
+        let mut bindings = shader.wgpu_bindings().collect::<Vec<_>>();
+        for entry in bindings.iter_mut().filter(|x| x.binding == 0) {
+            if let wgpu::BindingType::Buffer { has_dynamic_offset, .. } = &mut entry.ty {
+                *has_dynamic_offset = true;
+            }
+        }
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &bindings,
+            label: None,
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            push_constant_ranges: &[],
+            bind_group_layouts: &[&bind_group_layout],
+        });
+
+        let entries = Some(arena.binding_resource())
+            .into_iter()
+            .chain(buffers.iter().map(|x| x.binding_resource()))
+            .enumerate()
+            .map(|(i, resource)| wgpu::BindGroupEntry { binding: i as u32, resource })
+            .collect::<Vec<_>>();
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &bind_group_layout,
+            entries: &entries,
+            label: None,
+        });
+
+        let attributes = mesh.vertex_formats.iter().map(|x| x.wgpu_attributes(&shader.inputs)).collect::<Vec<_>>();
+        let vertex_buffers = attributes
+            .iter()
+            .zip(mesh.strides.iter())
+            .map(|(attributes, stride)| wgpu::VertexBufferLayout {
+                array_stride: *stride as wgpu::BufferAddress,
+                step_mode: wgpu::VertexStepMode::Vertex,
+                attributes,
+            })
+            .collect::<Vec<_>>();
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader.module,
+                entry_point: shader.vs_entry,
+                buffers: &vertex_buffers,
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: shader.fragment_module(),
+                entry_point: shader.fs_entry,
+                targets: &[target],
+            }),
+            primitive,
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: wgpu::TextureFormat::Depth32Float,
+                depth_write_enabled: depth_write,
+                depth_compare,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            label: None,
+            multisample: wgpu::MultisampleState::default(),
+        });
+
+        Self { pipeline, bind_group }
+    }
+}
@@ -0,0 +1,419 @@
+use alloc::{boxed::Box, sync::Arc, vec::Vec};
+use core::{future::Future, pin::Pin};
+
+use futures::FutureExt;
+use hashbrown::HashSet;
+use nalgebra::Matrix4;
+use spinning_top::Spinlock;
+use zerocopy::AsBytes;
+
+use crate::{
+    memory::{Allocation, MemoryTracker},
+    staging_belt::StagingBelt,
+    Renderable,
+};
+
+type MapFuture = Pin<Box<dyn Future<Output = Result<(), wgpu::BufferAsyncError>> + Send>>;
+
+const HI_Z_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::R32Float;
+// min and max corners as vec4
+const BOX_SIZE: u64 = 32;
+
+struct HiZ {
+    viewport: (u32, u32),
+    mip_count: u32,
+    texture_view: wgpu::TextureView,
+    levels: Vec<Level>,
+    _allocation: Allocation,
+}
+
+struct Level {
+    size: (u32, u32),
+    view: wgpu::TextureView,
+    // reads previous level, none for first level which is read from depth
+    bind_group: Option<wgpu::BindGroup>,
+}
+
+struct Buffers {
+    capacity: u64,
+    boxes: Arc<wgpu::Buffer>,
+    visibility: wgpu::Buffer,
+    readback: Arc<wgpu::Buffer>,
+    _allocation: Allocation,
+}
+
+#[derive(Default)]
+struct State {
+    hi_z: Option<HiZ>,
+    buffers: Option<Buffers>,
+    // handle ids of renderables tested by last submitted test, and its pending readback
+    pending: Option<(Vec<u64>, MapFuture)>,
+    // submitted but not yet mapped
+    submitted: Option<Vec<u64>>,
+    occluded: HashSet<u64>,
+}
+
+// Builds hierarchical depth of each frame and tests bounding boxes against it on gpu.
+// Results are read back without stalling, so renderables are culled a frame or two after they become occluded,
+// and may pop in as late after they're revealed.
+pub(crate) struct OcclusionCuller {
+    uniform_buf: Arc<wgpu::Buffer>,
+    depth_layout: wgpu::BindGroupLayout,
+    level_layout: wgpu::BindGroupLayout,
+    test_layout: wgpu::BindGroupLayout,
+    depth_pipeline: wgpu::ComputePipeline,
+    level_pipeline: wgpu::ComputePipeline,
+    test_pipeline: wgpu::ComputePipeline,
+    memory: MemoryTracker,
+    state: Spinlock<State>,
+}
+
+impl OcclusionCuller {
+    pub(crate) fn new(device: &wgpu::Device, memory: &MemoryTracker) -> Self {
+        let module = device.create_shader_module(&wgpu::ShaderModuleDescriptor {
+            label: None,
+            source: wgpu::ShaderSource::Wgsl(include_str!("../shaders/occlusion.wgsl").into()),
+        });
+
+        let uniform_entry = Self::layout_entry(
+            3,
+            wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+        );
+        let destination_entry = Self::layout_entry(
+            2,
+            wgpu::BindingType::StorageTexture {
+                access: wgpu::StorageTextureAccess::WriteOnly,
+                format: HI_Z_FORMAT,
+                view_dimension: wgpu::TextureViewDimension::D2,
+            },
+        );
+        let hi_z_entry = |binding| {
+            Self::layout_entry(
+                binding,
+                wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+            )
+        };
+        let storage_entry = |binding| {
+            Self::layout_entry(
+                binding,
+                wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Storage { read_only: false },
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+            )
+        };
+
+        let depth_entry = Self::layout_entry(
+            0,
+            wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Depth,
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            },
+        );
+        let depth_layout = Self::create_layout(device, &[depth_entry, destination_entry, uniform_entry]);
+        let level_layout = Self::create_layout(device, &[hi_z_entry(1), destination_entry]);
+        let test_layout = Self::create_layout(device, &[uniform_entry, storage_entry(4), storage_entry(5), hi_z_entry(6)]);
+
+        let uniform_buf = device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: 80,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        Self {
+            uniform_buf: Arc::new(uniform_buf),
+            depth_pipeline: Self::create_pipeline(device, &module, &depth_layout, "downsample_depth"),
+            level_pipeline: Self::create_pipeline(device, &module, &level_layout, "downsample"),
+            test_pipeline: Self::create_pipeline(device, &module, &test_layout, "test"),
+            depth_layout,
+            level_layout,
+            test_layout,
+            memory: memory.clone(),
+            state: Spinlock::new(State::default()),
+        }
+    }
+
+    // culled by results of last completed test. id is of model's handle, which identifies it across frames.
+    pub(crate) fn is_occluded(&self, id: u64) -> bool {
+        self.state.lock().occluded.contains(&id)
+    }
+
+    // picks up results which finished reading back.
+    pub(crate) fn poll(&self, device: &wgpu::Device) {
+        let mut state = self.state.lock();
+
+        let result = match &mut state.pending {
+            Some((_, map)) => {
+                device.poll(wgpu::Maintain::Poll);
+                match map.as_mut().now_or_never() {
+                    Some(x) => x,
+                    None => return,
+                }
+            }
+            None => return,
+        };
+        let (keys, _) = state.pending.take().unwrap();
+        let readback = state.buffers.as_ref().unwrap().readback.clone();
+        if result.is_err() {
+            return;
+        }
+
+        let occluded = {
+            let mapped = readback.slice(..keys.len() as u64 * 4).get_mapped_range();
+            keys.iter()
+                .zip(mapped.chunks(4))
+                .filter(|(_, visible)| visible.iter().all(|&x| x == 0))
+                .map(|(key, _)| *key)
+                .collect()
+        };
+        readback.unmap();
+
+        state.occluded = occluded;
+    }
+
+    // records hi-z build from depth of rendered viewport and test of renderables with bounds.
+    // skipped while previous results are still reading back.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn test(
+        &self,
+        device: &wgpu::Device,
+        staging_belt: &StagingBelt,
+        command_encoder: &mut wgpu::CommandEncoder,
+        depth: &wgpu::TextureView,
+        viewport: (u32, u32),
+        view_projection: &Matrix4<f32>,
+        renderables: &[(u64, &dyn Renderable)],
+    ) {
+        let mut state = self.state.lock();
+        if state.pending.is_some() || state.submitted.is_some() {
+            return;
+        }
+
+        let (keys, boxes): (Vec<_>, Vec<_>) = renderables
+            .iter()
+            .filter_map(|(id, x)| {
+                let aabb = x.aabb()?;
+                Some((*id, [aabb.min.x, aabb.min.y, aabb.min.z, 0.0, aabb.max.x, aabb.max.y, aabb.max.z, 0.0]))
+            })
+            .unzip();
+        if keys.is_empty() {
+            state.occluded.clear();
+            return;
+        }
+
+        if state.hi_z.as_ref().map(|x| x.viewport) != Some(viewport) {
+            state.hi_z = Some(self.create_hi_z(device, viewport));
+        }
+        if state.buffers.as_ref().map(|x| x.capacity).unwrap_or(0) < keys.len() as u64 {
+            state.buffers = Some(self.create_buffers(device, (keys.len() as u64).next_power_of_two()));
+        }
+        let hi_z = state.hi_z.as_ref().unwrap();
+        let buffers = state.buffers.as_ref().unwrap();
+
+        let mut uniform = [0.0f32; 20];
+        uniform[..16].copy_from_slice(view_projection.as_slice());
+        uniform[16] = viewport.0 as f32;
+        uniform[17] = viewport.1 as f32;
+        uniform[18] = f32::from_bits(hi_z.mip_count);
+        uniform[19] = f32::from_bits(keys.len() as u32);
+        staging_belt.write_buffer(&self.uniform_buf, 0, uniform.as_bytes());
+        staging_belt.write_buffer(&buffers.boxes, 0, boxes.as_bytes());
+
+        let depth_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &self.depth_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(depth),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(&hi_z.levels[0].view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: self.uniform_buf.as_entire_binding(),
+                },
+            ],
+            label: None,
+        });
+        let test_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &self.test_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: self.uniform_buf.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: buffers.boxes.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 5,
+                    resource: buffers.visibility.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 6,
+                    resource: wgpu::BindingResource::TextureView(&hi_z.texture_view),
+                },
+            ],
+            label: None,
+        });
+
+        // separate passes, so each level is written before next one reads it
+        for level in &hi_z.levels {
+            let mut compute_pass = command_encoder.begin_compute_pass(&wgpu::ComputePassDescriptor { label: None });
+            match &level.bind_group {
+                Some(bind_group) => {
+                    compute_pass.set_pipeline(&self.level_pipeline);
+                    compute_pass.set_bind_group(0, bind_group, &[]);
+                }
+                None => {
+                    compute_pass.set_pipeline(&self.depth_pipeline);
+                    compute_pass.set_bind_group(0, &depth_bind_group, &[]);
+                }
+            }
+            compute_pass.dispatch(level.size.0.div_ceil(8), level.size.1.div_ceil(8), 1);
+        }
+        {
+            let mut compute_pass = command_encoder.begin_compute_pass(&wgpu::ComputePassDescriptor { label: None });
+            compute_pass.set_pipeline(&self.test_pipeline);
+            compute_pass.set_bind_group(0, &test_bind_group, &[]);
+            compute_pass.dispatch((keys.len() as u32).div_ceil(64), 1, 1);
+        }
+        command_encoder.copy_buffer_to_buffer(&buffers.visibility, 0, &buffers.readback, 0, keys.len() as u64 * 4);
+
+        state.submitted = Some(keys);
+    }
+
+    // must be called after command buffer containing the test is submitted.
+    pub(crate) fn map(&self) {
+        let mut state = self.state.lock();
+        let keys = match state.submitted.take() {
+            Some(x) => x,
+            None => return,
+        };
+
+        let readback = state.buffers.as_ref().unwrap().readback.clone();
+        let size = keys.len() as u64 * 4;
+        let map = async move { readback.slice(..size).map_async(wgpu::MapMode::Read).await };
+        state.pending = Some((keys, Box::pin(map)));
+    }
+
+    fn layout_entry(binding: u32, ty: wgpu::BindingType) -> wgpu::BindGroupLayoutEntry {
+        wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty,
+            count: None,
+        }
+    }
+
+    fn create_layout(device: &wgpu::Device, entries: &[wgpu::BindGroupLayoutEntry]) -> wgpu::BindGroupLayout {
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor { entries, label: None })
+    }
+
+    fn create_pipeline(device: &wgpu::Device, module: &wgpu::ShaderModule, layout: &wgpu::BindGroupLayout, entry: &str) -> wgpu::ComputePipeline {
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            push_constant_ranges: &[],
+            bind_group_layouts: &[layout],
+        });
+
+        device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: None,
+            layout: Some(&pipeline_layout),
+            module,
+            entry_point: entry,
+        })
+    }
+
+    fn create_buffers(&self, device: &wgpu::Device, capacity: u64) -> Buffers {
+        let create = |size, usage| {
+            device.create_buffer(&wgpu::BufferDescriptor {
+                label: None,
+                size,
+                usage,
+                mapped_at_creation: false,
+            })
+        };
+
+        Buffers {
+            capacity,
+            boxes: Arc::new(create(capacity * BOX_SIZE, wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST)),
+            visibility: create(capacity * 4, wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC),
+            readback: Arc::new(create(capacity * 4, wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST)),
+            _allocation: self.memory.track("occlusion", (capacity * (BOX_SIZE + 8)) as usize),
+        }
+    }
+
+    fn create_hi_z(&self, device: &wgpu::Device, viewport: (u32, u32)) -> HiZ {
+        // first level is half of viewport, last one is a single texel on longer side
+        let size = ((viewport.0 / 2).max(1), (viewport.1 / 2).max(1));
+        let mip_count = 32 - size.0.max(size.1).leading_zeros();
+
+        let extent = wgpu::Extent3d {
+            width: size.0,
+            height: size.1,
+            depth_or_array_layers: 1,
+        };
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: None,
+            size: extent,
+            mip_level_count: mip_count,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: HI_Z_FORMAT,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::STORAGE_BINDING,
+        });
+        let mut levels = Vec::<Level>::new();
+        for level in 0..mip_count {
+            let view = texture.create_view(&wgpu::TextureViewDescriptor {
+                base_mip_level: level,
+                mip_level_count: core::num::NonZeroU32::new(1),
+                ..Default::default()
+            });
+            let bind_group = levels.last().map(|previous| {
+                device.create_bind_group(&wgpu::BindGroupDescriptor {
+                    layout: &self.level_layout,
+                    entries: &[
+                        wgpu::BindGroupEntry {
+                            binding: 1,
+                            resource: wgpu::BindingResource::TextureView(&previous.view),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 2,
+                            resource: wgpu::BindingResource::TextureView(&view),
+                        },
+                    ],
+                    label: None,
+                })
+            });
+
+            levels.push(Level {
+                size: ((size.0 >> level).max(1), (size.1 >> level).max(1)),
+                view,
+                bind_group,
+            });
+        }
+
+        HiZ {
+            viewport,
+            mip_count,
+            texture_view: texture.create_view(&wgpu::TextureViewDescriptor::default()),
+            levels,
+            _allocation: self.memory.track_texture("occlusion", HI_Z_FORMAT, extent, mip_count),
+        }
+    }
+}
@@ -0,0 +1,107 @@
+use alloc::{sync::Arc, vec::Vec};
+
+use nalgebra::{Point3, Vector3};
+
+use crate::{Camera, ClearConfig, CullMode, Material, Mesh, Model, ModelHandle, RenderLayers, Renderer, Scene, SimpleVertex, Texture, TextureFormat};
+
+// distance of gizmo camera from cube center, cube of unit size fits in its fov
+const CAMERA_DISTANCE: f32 = 3.5;
+const CAMERA_FOV: f32 = 30.0;
+
+// Navigation cube drawn in a corner view of its own, turning along with the scene camera.
+// Faces are red, green and blue for x, y and z axes, darker on negative sides. clicking one gives an axis view to snap to.
+pub struct OrientationGizmo {
+    layer: u32,
+    // index in Scene::views, so views should only be added after it
+    view: usize,
+    // outward direction of each face
+    faces: Vec<(ModelHandle<Model>, Vector3<f32>)>,
+}
+
+impl OrientationGizmo {
+    // cube faces are added to scene on given layer, which other cameras shouldn't draw. viewport is as in Scene::add_view.
+    pub fn new(renderer: &Renderer, scene: &mut Scene, layer: u32, viewport: (f32, f32, f32, f32)) -> Self {
+        let texture = Arc::new(Texture::with_texels(renderer, 1, 1, &[255, 255, 255, 255], TextureFormat::Rgba8Unorm));
+
+        let axes = [Vector3::x(), Vector3::y(), Vector3::z()];
+        let colors = [[0.9, 0.2, 0.2], [0.2, 0.8, 0.2], [0.2, 0.4, 0.9]];
+        let faces = axes
+            .iter()
+            .zip(colors.iter())
+            .flat_map(|(axis, color)| [(*axis, *color, 1.0), (-axis, *color, 0.5)])
+            .map(|(normal, color, shade)| {
+                let mut material = Material::unlit(
+                    renderer,
+                    texture.clone(),
+                    [color[0] * shade, color[1] * shade, color[2] * shade, 1.0],
+                    false,
+                );
+                material.render_state.cull_mode = CullMode::None;
+
+                let mut model = Model::new(renderer, Self::face_mesh(renderer, &normal), material);
+                model.set_layers(RenderLayers::layer(layer));
+
+                (scene.add(model), normal)
+            })
+            .collect();
+
+        let mut camera = Camera::new(Point3::new(0.0, 0.0, CAMERA_DISTANCE), Point3::origin());
+        camera.set_fov(CAMERA_FOV);
+        camera.set_clip_planes(0.1, CAMERA_DISTANCE * 2.0);
+        camera.set_layers(RenderLayers::layer(layer));
+        camera.set_clear(ClearConfig {
+            color: None,
+            depth: Some(1.0),
+        });
+
+        let view = scene.views.len();
+        scene.add_view(camera, viewport, i32::MAX);
+
+        Self { layer, view, faces }
+    }
+
+    // turns gizmo camera to match scene camera, call each frame before rendering.
+    // also keeps gizmo layer out of scene camera and other views.
+    pub fn update(&self, scene: &mut Scene) {
+        // camera with eye on its target has no orientation, gizmo keeps last one
+        let direction = (scene.camera.eye() - scene.camera.target()).try_normalize(f32::EPSILON);
+
+        scene.camera.set_layers(scene.camera.layers().without(self.layer));
+        for (i, view) in scene.views.iter_mut().enumerate() {
+            if i != self.view {
+                view.camera.set_layers(view.camera.layers().without(self.layer));
+            } else if let Some(direction) = direction {
+                view.camera = view.camera.looking(Point3::from(direction * CAMERA_DISTANCE), -direction, CAMERA_FOV);
+            }
+        }
+    }
+
+    // scene camera looking at its target along axis of the face under window pixel, at same distance.
+    // none if pixel isn't on a face. snap to it directly or through CameraTransition.
+    pub async fn pick(&self, renderer: &Renderer, scene: &Scene, x: u32, y: u32) -> Option<Camera> {
+        let id = renderer.pick_view(scene, self.view, x, y).await?;
+        let normal = self.faces.iter().find(|x| x.0.id() == id)?.1;
+
+        let camera = &scene.camera;
+        let target = camera.target();
+        let eye = target + normal * (camera.eye() - target).norm();
+
+        Some(camera.looking(eye, target - eye, camera.fov()))
+    }
+
+    // unit square half a unit out along normal
+    fn face_mesh(renderer: &Renderer, normal: &Vector3<f32>) -> Mesh {
+        let u = if normal.x != 0.0 { Vector3::y() } else { Vector3::x() };
+        let v = normal.cross(&u);
+
+        let vertices = [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)]
+            .iter()
+            .map(|&(a, b)| {
+                let position = (normal + u * a + v * b) * 0.5;
+                SimpleVertex::new([position.x, position.y, position.z, 1.0], [0.0, 0.0])
+            })
+            .collect::<Vec<_>>();
+
+        Mesh::with_simple_vertex(renderer, &vertices, &[0, 1, 2, 0, 2, 3])
+    }
+}
@@ -0,0 +1,132 @@
+use alloc::{sync::Arc, vec};
+use core::mem::size_of;
+
+use zerocopy::AsBytes;
+
+use crate::{
+    buffer_pool::BufferPool, BlendMode, Buffer, Material, Mesh, Model, RenderContext, Renderable, Renderer, Shader, ShaderBinding, ShaderBindingType,
+    ShaderStage, Texture, VertexFormat, VertexFormatItem, VertexItemType,
+};
+
+// Textured quad composited over the final image, e.g. a software cursor.
+// Position and size are in logical pixels and scaled by the renderer's scale factor.
+pub struct Overlay {
+    pub visible: bool,
+    position: (f32, f32),
+    size: (f32, f32),
+    hotspot: (f32, f32),
+
+    rect_buf: Arc<Buffer>,
+    model: Model,
+}
+
+impl Overlay {
+    pub fn new(renderer: &Renderer, texture: Arc<Texture>, width: f32, height: f32) -> Self {
+        Self::with_device(
+            &renderer.device,
+            &renderer.buffer_pool,
+            texture,
+            width,
+            height,
+            renderer.main_target().output_format(),
+        )
+    }
+
+    pub(crate) fn with_device(
+        device: &wgpu::Device,
+        buffer_pool: &BufferPool,
+        texture: Arc<Texture>,
+        width: f32,
+        height: f32,
+        surface_format: wgpu::TextureFormat,
+    ) -> Self {
+        #[rustfmt::skip]
+        let quad = [
+            0.0f32, 0.0,
+            0.0,    1.0,
+            1.0,    1.0,
+            0.0,    0.0,
+            1.0,    1.0,
+            1.0,    0.0,
+        ];
+
+        let mesh = Mesh::with_buffer_pool(
+            buffer_pool,
+            &[quad.as_bytes()],
+            &[size_of::<f32>() * 2],
+            &[0u16, 1, 2, 3, 4, 5],
+            vec![VertexFormat::new(vec![VertexFormatItem::new("TexCoord", VertexItemType::Float2, 0)])],
+        );
+
+        let shader = Shader::with_device(
+            device,
+            include_str!("../shaders/overlay.wgsl"),
+            "vs_main",
+            "fs_main",
+            &[
+                ("Rect", ShaderBinding::new(ShaderStage::Vertex, 0, ShaderBindingType::UniformBuffer)),
+                ("Texture", ShaderBinding::new(ShaderStage::Fragment, 1, ShaderBindingType::Texture2D)),
+                ("Sampler", ShaderBinding::new(ShaderStage::Fragment, 2, ShaderBindingType::Sampler)),
+            ],
+            &[("TexCoord", 0)],
+        );
+
+        let rect_buf = Arc::new(buffer_pool.alloc(size_of::<[f32; 4]>()));
+        let mut material = Material::with_device(
+            device,
+            None,
+            None,
+            &[("Texture", texture)],
+            &[("Rect", rect_buf.clone())],
+            Arc::new(shader),
+        );
+        material.set_blend_mode(BlendMode::AlphaBlend);
+
+        Self {
+            visible: true,
+            position: (0.0, 0.0),
+            size: (width, height),
+            hotspot: (0.0, 0.0),
+            rect_buf,
+            model: Model::with_surface_and_depth_format(device, mesh, material, surface_format, None),
+        }
+    }
+
+    pub fn set_position(&mut self, x: f32, y: f32) {
+        self.position = (x, y);
+    }
+
+    pub fn set_size(&mut self, width: f32, height: f32) {
+        self.size = (width, height);
+    }
+
+    // Offset inside the overlay image which is placed at the position, like a cursor's tip.
+    pub fn set_hotspot(&mut self, x: f32, y: f32) {
+        self.hotspot = (x, y);
+    }
+
+    pub(crate) fn prepare(&self, target_size: (u32, u32), scale_factor: f32) {
+        // snap to physical pixels so the image isn't resampled
+        let left = ((self.position.0 - self.hotspot.0) * scale_factor).round();
+        let top = ((self.position.1 - self.hotspot.1) * scale_factor).round();
+        let right = left + (self.size.0 * scale_factor).round();
+        let bottom = top + (self.size.1 * scale_factor).round();
+
+        let width = target_size.0 as f32;
+        let height = target_size.1 as f32;
+        let bounds = [
+            left / width * 2.0 - 1.0,
+            1.0 - top / height * 2.0,
+            right / width * 2.0 - 1.0,
+            1.0 - bottom / height * 2.0,
+        ];
+
+        self.rect_buf.write(bounds.as_bytes());
+    }
+}
+
+impl Renderable for Overlay {
+    fn render<'a>(&'a self, render_context: &mut RenderContext<'a>) {
+        self.model.render(render_context);
+    }
+}
@@ -0,0 +1,21 @@
+use nalgebra::Matrix4;
+
+pub(crate) const PICK_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::R32Uint;
+
+// maps a single pixel of the viewport to whole clip space, so picking renders into 1x1 target.
+pub(crate) fn pick_matrix(x: u32, y: u32, viewport_size: (u32, u32)) -> Matrix4<f32> {
+    let width = viewport_size.0 as f32;
+    let height = viewport_size.1 as f32;
+    let center_x = (x as f32 + 0.5) / width * 2.0 - 1.0;
+    let center_y = 1.0 - (y as f32 + 0.5) / height * 2.0;
+
+    #[rustfmt::skip]
+    let result = Matrix4::new(
+        width, 0.0,    0.0, -center_x * width,
+        0.0,   height, 0.0, -center_y * height,
+        0.0,   0.0,    1.0, 0.0,
+        0.0,   0.0,    0.0, 1.0,
+    );
+
+    result
+}
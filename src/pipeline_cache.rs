@@ -0,0 +1,193 @@
+use alloc::{
+    sync::{Arc, Weak},
+    vec::Vec,
+};
+
+use hashbrown::HashMap;
+use spinning_top::Spinlock;
+
+use crate::{Shader, Texture};
+
+// sorted bind group entries and push constant ranges
+type LayoutKey = (Vec<wgpu::BindGroupLayoutEntry>, Vec<wgpu::PushConstantRange>);
+
+pub(crate) struct PipelineLayout {
+    pub(crate) bind_group_layout: wgpu::BindGroupLayout,
+    pub(crate) pipeline_layout: wgpu::PipelineLayout,
+}
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub(crate) struct PipelineKey {
+    // addresses of cached layout and shader. layouts live as long as the cache, and weak reference
+    // to shader keeps its allocation so address isn't reused while entry is cached.
+    pub(crate) layout: usize,
+    pub(crate) shader: usize,
+    pub(crate) vs_entry: &'static str,
+    pub(crate) fs_entry: &'static str,
+    pub(crate) vertex_buffers: Vec<(wgpu::BufferAddress, Vec<wgpu::VertexAttribute>)>,
+    pub(crate) targets: Vec<wgpu::ColorTargetState>,
+    pub(crate) primitive: wgpu::PrimitiveState,
+    pub(crate) depth: Option<(wgpu::TextureFormat, bool, wgpu::CompareFunction)>,
+    // constant and bits of slope scale, as floats can't be hashed
+    pub(crate) depth_bias: (i32, u32),
+}
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub(crate) enum ResourceKey {
+    // buffer id, offset and size. ids aren't reused, unlike addresses of pool chunks dropped by trim.
+    Buffer(usize, usize, usize),
+    // renderer's uniform arena, lives as long as the cache
+    Arena(usize),
+    Texture(usize),
+    // sampler address, samplers live as long as the cache
+    Sampler(usize),
+}
+
+// Entries whose shader or textures are gone can't be hit again, they're dropped by trim.
+struct CachedPipeline {
+    shader: Weak<Shader>,
+    pipeline: Arc<wgpu::RenderPipeline>,
+}
+
+struct CachedBindGroup {
+    // keeps addresses of textures in key from being reused
+    textures: Vec<Weak<Texture>>,
+    bind_group: Arc<wgpu::BindGroup>,
+}
+
+// Shares layouts, pipelines and bind groups between models and materials created with same state.
+pub(crate) struct PipelineCache {
+    sampler: Arc<wgpu::Sampler>,
+    environment_sampler: Arc<wgpu::Sampler>,
+    layouts: Spinlock<HashMap<LayoutKey, Arc<PipelineLayout>>>,
+    pipelines: Spinlock<HashMap<PipelineKey, CachedPipeline>>,
+    bind_groups: Spinlock<HashMap<(usize, Vec<ResourceKey>), CachedBindGroup>>,
+}
+
+impl PipelineCache {
+    pub(crate) fn new(device: &wgpu::Device) -> Self {
+        Self {
+            sampler: Arc::new(Self::create_sampler(device)),
+            environment_sampler: Arc::new(Self::create_environment_sampler(device)),
+            layouts: Spinlock::new(HashMap::new()),
+            pipelines: Spinlock::new(HashMap::new()),
+            bind_groups: Spinlock::new(HashMap::new()),
+        }
+    }
+
+    // sampler used by materials
+    pub(crate) fn create_sampler(device: &wgpu::Device) -> wgpu::Sampler {
+        device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::Repeat,
+            address_mode_v: wgpu::AddressMode::Repeat,
+            address_mode_w: wgpu::AddressMode::Repeat,
+            mag_filter: wgpu::FilterMode::Nearest,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            lod_min_clamp: -100.0,
+            lod_max_clamp: 100.0,
+            label: None,
+            anisotropy_clamp: None,
+            compare: None,
+            border_color: None,
+        })
+    }
+
+    pub(crate) fn sampler(&self) -> Arc<wgpu::Sampler> {
+        self.sampler.clone()
+    }
+
+    // filtered sampler of prefiltered environment maps, wrapping around horizontally
+    pub(crate) fn create_environment_sampler(device: &wgpu::Device) -> wgpu::Sampler {
+        device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::Repeat,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        })
+    }
+
+    pub(crate) fn environment_sampler(&self) -> Arc<wgpu::Sampler> {
+        self.environment_sampler.clone()
+    }
+
+    pub(crate) fn layout(
+        &self,
+        device: &wgpu::Device,
+        entries: &[wgpu::BindGroupLayoutEntry],
+        push_constant_ranges: &[wgpu::PushConstantRange],
+    ) -> Arc<PipelineLayout> {
+        // binding order in shader is from hash map, so sort to make equal sets share key
+        let mut key = entries.to_vec();
+        key.sort_by_key(|x| x.binding);
+
+        self.layouts
+            .lock()
+            .entry((key, push_constant_ranges.to_vec()))
+            .or_insert_with(|| Arc::new(Self::create_layout(device, entries, push_constant_ranges)))
+            .clone()
+    }
+
+    pub(crate) fn create_layout(
+        device: &wgpu::Device,
+        entries: &[wgpu::BindGroupLayoutEntry],
+        push_constant_ranges: &[wgpu::PushConstantRange],
+    ) -> PipelineLayout {
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor { entries, label: None });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            push_constant_ranges,
+            bind_group_layouts: &[&bind_group_layout],
+        });
+
+        PipelineLayout {
+            bind_group_layout,
+            pipeline_layout,
+        }
+    }
+
+    pub(crate) fn pipeline<F>(&self, key: PipelineKey, shader: &Arc<Shader>, create: F) -> Arc<wgpu::RenderPipeline>
+    where
+        F: FnOnce() -> wgpu::RenderPipeline,
+    {
+        self.pipelines
+            .lock()
+            .entry(key)
+            .or_insert_with(|| CachedPipeline {
+                shader: Arc::downgrade(shader),
+                pipeline: Arc::new(create()),
+            })
+            .pipeline
+            .clone()
+    }
+
+    pub(crate) fn bind_group<F>(
+        &self,
+        layout: &Arc<PipelineLayout>,
+        resources: Vec<ResourceKey>,
+        textures: Vec<Weak<Texture>>,
+        create: F,
+    ) -> Arc<wgpu::BindGroup>
+    where
+        F: FnOnce() -> wgpu::BindGroup,
+    {
+        self.bind_groups
+            .lock()
+            .entry((Arc::as_ptr(layout) as usize, resources))
+            .or_insert_with(|| CachedBindGroup {
+                textures,
+                bind_group: Arc::new(create()),
+            })
+            .bind_group
+            .clone()
+    }
+
+    // drops pipelines of dropped shaders and bind groups of dropped textures
+    pub(crate) fn trim(&self) {
+        self.pipelines.lock().retain(|_, x| x.shader.strong_count() > 0);
+        self.bind_groups.lock().retain(|_, x| x.textures.iter().all(|x| x.strong_count() > 0));
+    }
+}
@@ -0,0 +1,212 @@
+use alloc::{sync::Arc, vec::Vec};
+use core::{
+    mem::size_of,
+    sync::atomic::{AtomicU32, Ordering},
+};
+
+use nalgebra::{Matrix4, Point3};
+use zerocopy::AsBytes;
+
+use crate::{
+    constants::INTERNAL_COLOR_ATTACHMENT_FORMAT, memory::Allocation, uniform_arena::UniformArena, MaterialPass, RenderContext, RenderLayers,
+    RenderPath, Renderable, Renderer, Shader, ShaderBinding, ShaderBindingType, ShaderStage,
+};
+
+#[repr(C)]
+#[derive(AsBytes, Clone, Copy)]
+pub struct CloudPoint {
+    pub position: [f32; 3],
+    // diameter, see PointCloud::set_size_attenuation
+    pub size: f32,
+    // rgba
+    pub color: [u8; 4],
+}
+
+impl CloudPoint {
+    pub fn new(position: [f32; 3], size: f32, color: [u8; 4]) -> Self {
+        Self { position, size, color }
+    }
+}
+
+// Points drawn as camera facing quads instanced from a single buffer, for scans with millions of points.
+// Points are unlit. on deferred render path they are drawn after lighting is resolved.
+pub struct PointCloud {
+    pipeline: wgpu::RenderPipeline,
+    bind_group: wgpu::BindGroup,
+    point_buf: wgpu::Buffer,
+    _allocation: Allocation,
+    point_count: u32,
+    arena: Arc<UniformArena>,
+    transform: Matrix4<f32>,
+    layers: RenderLayers,
+    visible: bool,
+    round: bool,
+    size_attenuation: bool,
+    forward: bool,
+    // slot in uniform arena written by last prepare
+    uniform_offset: AtomicU32,
+}
+
+impl PointCloud {
+    pub fn new(renderer: &Renderer, points: &[CloudPoint]) -> Self {
+        let device = &*renderer.device;
+
+        let shader = Shader::with_device(
+            device,
+            include_str!("../shaders/points.wgsl"),
+            "vs_main",
+            "fs_main",
+            &[(
+                "PointCloud",
+                ShaderBinding::new(ShaderStage::VertexFragment, 0, ShaderBindingType::UniformBuffer),
+            )],
+            &[],
+        );
+
+        let mut bindings = shader.wgpu_bindings().collect::<Vec<_>>();
+        if let wgpu::BindingType::Buffer { has_dynamic_offset, .. } = &mut bindings[0].ty {
+            *has_dynamic_offset = true;
+        }
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &bindings,
+            label: None,
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            push_constant_ranges: &[],
+            bind_group_layouts: &[&bind_group_layout],
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: renderer.uniform_arena.binding_resource(),
+            }],
+            label: None,
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader.module,
+                entry_point: shader.vs_entry,
+                buffers: &[wgpu::VertexBufferLayout {
+                    array_stride: size_of::<CloudPoint>() as wgpu::BufferAddress,
+                    step_mode: wgpu::VertexStepMode::Instance,
+                    attributes: &wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32, 2 => Unorm8x4],
+                }],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: shader.fragment_module(),
+                entry_point: shader.fs_entry,
+                targets: &[INTERNAL_COLOR_ATTACHMENT_FORMAT.wgpu_type().into()],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: wgpu::TextureFormat::Depth32Float,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::LessEqual,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            label: None,
+            multisample: wgpu::MultisampleState::default(),
+        });
+
+        // too large for buffer pool in general
+        let data = points.as_bytes();
+        let point_size = data.len().max(size_of::<CloudPoint>());
+        let point_buf = device.create_buffer(&wgpu::BufferDescriptor {
+            size: point_size as u64,
+            usage: wgpu::BufferUsages::VERTEX,
+            label: None,
+            mapped_at_creation: true,
+        });
+        if !data.is_empty() {
+            point_buf.slice(..data.len() as u64).get_mapped_range_mut().copy_from_slice(data);
+        }
+        point_buf.unmap();
+
+        Self {
+            pipeline,
+            bind_group,
+            point_buf,
+            _allocation: renderer.memory.track("point clouds", point_size),
+            point_count: points.len() as u32,
+            arena: renderer.uniform_arena.clone(),
+            transform: Matrix4::identity(),
+            layers: RenderLayers::default(),
+            visible: true,
+            round: false,
+            size_attenuation: true,
+            forward: renderer.options.render_path == RenderPath::Deferred,
+            uniform_offset: AtomicU32::new(0),
+        }
+    }
+
+    pub fn set_transform(&mut self, transform: Matrix4<f32>) {
+        self.transform = transform;
+    }
+
+    pub fn transform(&self) -> &Matrix4<f32> {
+        &self.transform
+    }
+
+    pub fn set_layers(&mut self, layers: RenderLayers) {
+        self.layers = layers;
+    }
+
+    pub fn set_visible(&mut self, visible: bool) {
+        self.visible = visible;
+    }
+
+    // discards corners of quads
+    pub fn set_round(&mut self, round: bool) {
+        self.round = round;
+    }
+
+    // sizes are in world units if set, which is default. otherwise they're fractions of view height at any distance.
+    pub fn set_size_attenuation(&mut self, size_attenuation: bool) {
+        self.size_attenuation = size_attenuation;
+    }
+}
+
+impl Renderable for PointCloud {
+    fn render<'a>(&'a self, render_context: &mut RenderContext<'a>) {
+        if render_context.pass != MaterialPass::Main || self.point_count == 0 {
+            return;
+        }
+
+        render_context.set_pipeline(&self.pipeline);
+        render_context.set_bind_group(&self.bind_group, &[self.uniform_offset.load(Ordering::Relaxed)]);
+        render_context.set_vertex_buffer(0, self.point_buf.slice(..));
+        render_context.render_pass.draw(0..6, 0..self.point_count);
+    }
+
+    fn prepare(&self, view_projection: &Matrix4<f32>) {
+        let mvp = view_projection * self.transform;
+
+        let mut data = [0.0f32; 20];
+        data[..16].copy_from_slice(mvp.as_slice());
+        data[16] = if self.size_attenuation { 1.0 } else { 0.0 };
+        data[17] = if self.round { 1.0 } else { 0.0 };
+        self.uniform_offset.store(self.arena.push(data.as_bytes()), Ordering::Relaxed);
+    }
+
+    // drawn in forward pass on deferred render path, it can't write g-buffer
+    fn is_transparent(&self) -> bool {
+        self.forward
+    }
+
+    fn position(&self) -> Point3<f32> {
+        self.transform.transform_point(&Point3::origin())
+    }
+
+    fn layers(&self) -> RenderLayers {
+        self.layers
+    }
+
+    fn is_visible(&self) -> bool {
+        self.visible
+    }
+}
@@ -0,0 +1,242 @@
+use alloc::{string::String, sync::Arc, vec::Vec};
+
+use hashbrown::HashMap;
+use nalgebra::Matrix4;
+
+use crate::{
+    constants::INTERNAL_COLOR_ATTACHMENT_FORMAT, Buffer, Renderer, Shader, ShaderBinding, ShaderBindingType, ShaderStage, Texture, TextureFormat,
+};
+
+pub struct PostProcessContext<'a> {
+    pub(crate) device: &'a wgpu::Device,
+    pub(crate) command_encoder: &'a mut wgpu::CommandEncoder,
+    pub(crate) input: &'a Texture,
+    pub(crate) output: &'a wgpu::TextureView,
+    pub(crate) viewport_size: (u32, u32),
+    // scene depth and camera projection, given to post processes added to renderer
+    pub(crate) depth: Option<&'a Texture>,
+    pub(crate) projection: Matrix4<f32>,
+}
+
+// accessors let post processes record their own passes besides FullscreenPass
+impl<'a> PostProcessContext<'a> {
+    pub fn viewport_size(&self) -> (u32, u32) {
+        self.viewport_size
+    }
+
+    pub fn device(&self) -> &'a wgpu::Device {
+        self.device
+    }
+
+    pub fn command_encoder(&mut self) -> &mut wgpu::CommandEncoder {
+        self.command_encoder
+    }
+
+    // image written by previous post process, or the scene
+    pub fn input(&self) -> &'a Texture {
+        self.input
+    }
+
+    // view to write result to, read by next post process
+    pub fn output(&self) -> &'a wgpu::TextureView {
+        self.output
+    }
+
+    pub fn depth(&self) -> Option<&'a Texture> {
+        self.depth
+    }
+
+    pub fn projection(&self) -> &Matrix4<f32> {
+        &self.projection
+    }
+}
+
+pub trait PostProcess: Sync + Send {
+    fn apply(&self, context: &mut PostProcessContext);
+}
+
+// Runs a fragment shader over the whole viewport, reading the previous image.
+// The fragment source is appended to shaders/fullscreen.wgsl, which declares `texture`, `sampler` and `screen_uv`.
+pub struct FullscreenPass {
+    pipeline: wgpu::RenderPipeline,
+    bind_group_layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+    shader: Shader,
+    textures: HashMap<&'static str, Arc<Texture>>,
+    uniforms: HashMap<&'static str, Arc<Buffer>>,
+}
+
+impl FullscreenPass {
+    pub fn new(
+        renderer: &Renderer,
+        source: &str,
+        fs_entry: &'static str,
+        bindings: &[(&'static str, ShaderBinding)],
+        textures: &[(&'static str, Arc<Texture>)],
+        uniforms: &[(&'static str, Arc<Buffer>)],
+    ) -> Self {
+        let source = renderer.shader_preprocessor.process(source);
+
+        Self::with_device(&renderer.device, &source, fs_entry, bindings, textures, uniforms)
+    }
+
+    pub(crate) fn with_device(
+        device: &wgpu::Device,
+        source: &str,
+        fs_entry: &'static str,
+        bindings: &[(&'static str, ShaderBinding)],
+        textures: &[(&'static str, Arc<Texture>)],
+        uniforms: &[(&'static str, Arc<Buffer>)],
+    ) -> Self {
+        Self::with_format(device, source, fs_entry, bindings, textures, uniforms, INTERNAL_COLOR_ATTACHMENT_FORMAT)
+    }
+
+    // renders to output of given format instead of internal color format, e.g. for intermediate data
+    pub(crate) fn with_format(
+        device: &wgpu::Device,
+        source: &str,
+        fs_entry: &'static str,
+        bindings: &[(&'static str, ShaderBinding)],
+        textures: &[(&'static str, Arc<Texture>)],
+        uniforms: &[(&'static str, Arc<Buffer>)],
+        format: TextureFormat,
+    ) -> Self {
+        let mut full_source = String::from(include_str!("../shaders/fullscreen.wgsl"));
+        full_source.push_str(source);
+
+        let mut all_bindings = Vec::from(bindings);
+        all_bindings.push(("Texture", ShaderBinding::new(ShaderStage::Fragment, 1, ShaderBindingType::Texture2D)));
+        all_bindings.push(("Sampler", ShaderBinding::new(ShaderStage::Fragment, 2, ShaderBindingType::Sampler)));
+
+        let shader = Shader::with_device(device, &full_source, "fullscreen_vs_main", fs_entry, &all_bindings, &[]);
+
+        let entries = shader.wgpu_bindings().collect::<Vec<_>>();
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &entries,
+            label: None,
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            push_constant_ranges: &[],
+            bind_group_layouts: &[&bind_group_layout],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader.module,
+                entry_point: shader.vs_entry,
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: shader.fragment_module(),
+                entry_point: shader.fs_entry,
+                targets: &[format.wgpu_type().into()],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            label: None,
+            multisample: wgpu::MultisampleState::default(),
+        });
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        Self {
+            pipeline,
+            bind_group_layout,
+            sampler,
+            shader,
+            textures: textures.iter().cloned().collect(),
+            uniforms: uniforms.iter().cloned().collect(),
+        }
+    }
+
+    pub fn draw(&self, context: &mut PostProcessContext) {
+        let viewport = (0.0, 0.0, context.viewport_size.0 as f32, context.viewport_size.1 as f32);
+
+        self.draw_viewport(context, viewport)
+    }
+
+    pub(crate) fn draw_viewport(&self, context: &mut PostProcessContext, viewport: (f32, f32, f32, f32)) {
+        self.draw_with_textures(context, viewport, &[])
+    }
+
+    // textures given here are used instead of ones of same name given on creation, e.g. ones changing each frame
+    pub(crate) fn draw_with_textures(&self, context: &mut PostProcessContext, viewport: (f32, f32, f32, f32), textures: &[(&'static str, &Texture)]) {
+        let entries = self
+            .shader
+            .bindings
+            .iter()
+            .map(|(binding_name, binding)| {
+                let resource = match binding.binding_type {
+                    ShaderBindingType::UniformBuffer | ShaderBindingType::StorageBuffer | ShaderBindingType::ReadOnlyStorageBuffer => {
+                        match self.uniforms.get(binding_name) {
+                            Some(x) => x.binding_resource(),
+                            None => panic!("No such buffer named {}", binding_name),
+                        }
+                    }
+                    ShaderBindingType::Texture2D
+                    | ShaderBindingType::DepthTexture2D
+                    | ShaderBindingType::Texture2DArray
+                    | ShaderBindingType::Texture3D => {
+                        let texture = if *binding_name == "Texture" {
+                            Some(context.input)
+                        } else if let Some((_, x)) = textures.iter().find(|(name, _)| name == binding_name) {
+                            Some(*x)
+                        } else {
+                            self.textures.get(binding_name).map(|x| &**x)
+                        };
+                        match texture {
+                            Some(x) => wgpu::BindingResource::TextureView(&x.texture_view),
+                            None => panic!("No such texture named {}", binding_name),
+                        }
+                    }
+                    ShaderBindingType::Sampler => wgpu::BindingResource::Sampler(&self.sampler),
+                    ShaderBindingType::PushConstant(_) => panic!("Push constants are only supported for Mvp"),
+                };
+
+                wgpu::BindGroupEntry {
+                    binding: binding.binding,
+                    resource,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let bind_group = context.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &self.bind_group_layout,
+            entries: &entries,
+            label: None,
+        });
+
+        let mut render_pass = context.command_encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            color_attachments: &[wgpu::RenderPassColorAttachment {
+                view: context.output,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: true,
+                },
+            }],
+            depth_stencil_attachment: None,
+            label: Some("fullscreen pass"),
+        });
+        render_pass.set_viewport(viewport.0, viewport.1, viewport.2, viewport.3, 0.0, 1.0);
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
+
+impl PostProcess for FullscreenPass {
+    fn apply(&self, context: &mut PostProcessContext) {
+        self.draw(context)
+    }
+}
@@ -0,0 +1,203 @@
+use alloc::{boxed::Box, vec::Vec};
+
+use nalgebra::{Matrix4, Point3, Vector3};
+
+use crate::Aabb;
+
+// triangles per leaf, meshes with fewer triangles are tested without bvh.
+const BVH_LEAF_SIZE: usize = 8;
+
+#[derive(Clone, Copy, Debug)]
+pub struct Ray {
+    pub origin: Point3<f32>,
+    pub direction: Vector3<f32>,
+}
+
+impl Ray {
+    pub fn new(origin: Point3<f32>, direction: Vector3<f32>) -> Self {
+        Self { origin, direction }
+    }
+
+    pub fn point_at(&self, distance: f32) -> Point3<f32> {
+        self.origin + self.direction * distance
+    }
+
+    // direction isn't normalized, so distances stay same as before transform.
+    pub fn transform(&self, matrix: &Matrix4<f32>) -> Self {
+        Self {
+            origin: matrix.transform_point(&self.origin),
+            direction: matrix.transform_vector(&self.direction),
+        }
+    }
+
+    // moller-trumbore. returns distance and barycentrics of second and third vertex.
+    fn intersect_triangle(&self, a: &Point3<f32>, b: &Point3<f32>, c: &Point3<f32>) -> Option<(f32, f32, f32)> {
+        let edge1 = b - a;
+        let edge2 = c - a;
+        let p = self.direction.cross(&edge2);
+        let det = edge1.dot(&p);
+        // parallel to triangle. relative to edge lengths, so tiny and huge triangles are hit alike
+        if det.abs() <= f32::EPSILON * edge1.norm() * edge2.norm() * self.direction.norm() {
+            return None;
+        }
+
+        let inv_det = 1.0 / det;
+        let s = self.origin - a;
+        let u = s.dot(&p) * inv_det;
+        if !(0.0..=1.0).contains(&u) {
+            return None;
+        }
+
+        let q = s.cross(&edge1);
+        let v = self.direction.dot(&q) * inv_det;
+        if v < 0.0 || u + v > 1.0 {
+            return None;
+        }
+
+        let distance = edge2.dot(&q) * inv_det;
+        if distance < 0.0 {
+            return None;
+        }
+
+        Some((distance, u, v))
+    }
+
+    fn intersects_aabb(&self, aabb: &Aabb, max_distance: f32) -> bool {
+        let mut near = 0.0f32;
+        let mut far = max_distance;
+        for axis in 0..3 {
+            let inv = 1.0 / self.direction[axis];
+            let mut t0 = (aabb.min[axis] - self.origin[axis]) * inv;
+            let mut t1 = (aabb.max[axis] - self.origin[axis]) * inv;
+            if inv < 0.0 {
+                core::mem::swap(&mut t0, &mut t1);
+            }
+            near = near.max(t0);
+            far = far.min(t1);
+            if near > far {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct RayHit {
+    pub distance: f32,
+    pub triangle: usize,
+    // weights of triangle's three vertices
+    pub barycentric: Vector3<f32>,
+}
+
+enum BvhNode {
+    Leaf(Aabb, Vec<usize>),
+    Branch(Aabb, Box<BvhNode>, Box<BvhNode>),
+}
+
+// cpu copy of mesh positions for ray queries
+pub(crate) struct MeshShape {
+    positions: Vec<Point3<f32>>,
+    indices: Vec<u16>,
+    bvh: Option<BvhNode>,
+}
+
+impl MeshShape {
+    pub(crate) fn new(positions: Vec<Point3<f32>>, indices: &[u16]) -> Self {
+        let mut result = Self {
+            positions,
+            indices: Vec::from(indices),
+            bvh: None,
+        };
+
+        let triangle_count = result.indices.len() / 3;
+        if triangle_count > BVH_LEAF_SIZE {
+            result.bvh = Some(result.build_bvh((0..triangle_count).collect()));
+        }
+
+        result
+    }
+
+    pub(crate) fn positions(&self) -> &[Point3<f32>] {
+        &self.positions
+    }
+
+    pub(crate) fn intersect(&self, ray: &Ray) -> Option<RayHit> {
+        let mut closest = None;
+        match &self.bvh {
+            Some(node) => self.intersect_node(node, ray, &mut closest),
+            None => {
+                for triangle in 0..self.indices.len() / 3 {
+                    self.intersect_triangle(triangle, ray, &mut closest);
+                }
+            }
+        }
+
+        closest
+    }
+
+    fn intersect_node(&self, node: &BvhNode, ray: &Ray, closest: &mut Option<RayHit>) {
+        let max_distance = closest.map(|x| x.distance).unwrap_or(f32::MAX);
+        match node {
+            BvhNode::Leaf(aabb, triangles) => {
+                if ray.intersects_aabb(aabb, max_distance) {
+                    for &triangle in triangles {
+                        self.intersect_triangle(triangle, ray, closest);
+                    }
+                }
+            }
+            BvhNode::Branch(aabb, left, right) => {
+                if ray.intersects_aabb(aabb, max_distance) {
+                    self.intersect_node(left, ray, closest);
+                    self.intersect_node(right, ray, closest);
+                }
+            }
+        }
+    }
+
+    fn intersect_triangle(&self, triangle: usize, ray: &Ray, closest: &mut Option<RayHit>) {
+        let [a, b, c] = self.triangle(triangle);
+        if let Some((distance, u, v)) = ray.intersect_triangle(&a, &b, &c) {
+            if closest.map(|x| distance < x.distance).unwrap_or(true) {
+                *closest = Some(RayHit {
+                    distance,
+                    triangle,
+                    barycentric: Vector3::new(1.0 - u - v, u, v),
+                });
+            }
+        }
+    }
+
+    fn triangle(&self, triangle: usize) -> [Point3<f32>; 3] {
+        let index = |i| self.positions[self.indices[triangle * 3 + i] as usize];
+
+        [index(0), index(1), index(2)]
+    }
+
+    // splits at median of centroids along longest axis
+    fn build_bvh(&self, mut triangles: Vec<usize>) -> BvhNode {
+        let mut aabb = Aabb::from_points(&self.triangle(triangles[0])).unwrap();
+        for &triangle in &triangles[1..] {
+            for point in &self.triangle(triangle) {
+                aabb.grow(point);
+            }
+        }
+
+        if triangles.len() <= BVH_LEAF_SIZE {
+            return BvhNode::Leaf(aabb, triangles);
+        }
+
+        let extent = aabb.max - aabb.min;
+        let axis = extent.imax();
+        let centroid = |triangle: usize| {
+            let [a, b, c] = self.triangle(triangle);
+            a[axis] + b[axis] + c[axis]
+        };
+        triangles.sort_by(|&a, &b| centroid(a).partial_cmp(&centroid(b)).unwrap_or(core::cmp::Ordering::Equal));
+
+        let right = triangles.split_off(triangles.len() / 2);
+
+        BvhNode::Branch(aabb, Box::new(self.build_bvh(triangles)), Box::new(self.build_bvh(right)))
+    }
+}
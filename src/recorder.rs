@@ -0,0 +1,196 @@
+use alloc::{boxed::Box, collections::VecDeque, sync::Arc, vec::Vec};
+use core::{future::Future, pin::Pin};
+
+use futures::FutureExt;
+use spinning_top::Spinlock;
+
+use crate::{
+    memory::{Allocation, MemoryTracker},
+    Texture,
+};
+
+type MapFuture = Pin<Box<dyn Future<Output = Result<(), wgpu::BufferAsyncError>> + Send>>;
+
+// Scene image of a recorded frame before overlays, linear rgba8 rows from top to bottom without padding.
+pub struct RecordedFrame {
+    pub index: u64,
+    pub width: u32,
+    pub height: u32,
+    pub data: Vec<u8>,
+}
+
+// Receiving end of a recording, can be moved to an encoder thread.
+// Holds up to ring size frames, oldest are dropped if they aren't received in time. index shows the gap.
+#[derive(Clone)]
+pub struct FrameReceiver {
+    frames: Arc<Spinlock<VecDeque<RecordedFrame>>>,
+}
+
+impl FrameReceiver {
+    pub fn try_recv(&self) -> Option<RecordedFrame> {
+        self.frames.lock().pop_front()
+    }
+}
+
+struct Slot {
+    buffer: Arc<wgpu::Buffer>,
+    allocation: Allocation,
+    size: (u32, u32),
+    // frame index and pending map while gpu still owns the buffer
+    pending: Option<(u64, MapFuture)>,
+}
+
+// Copies every n-th frame into a ring of staging buffers, which are read once gpu is done with them.
+// Frames are dropped rather than waited for when every buffer is in flight, or receiver falls behind.
+pub(crate) struct FrameRecorder {
+    interval: u64,
+    frame_index: u64,
+    ring_size: usize,
+    slots: Vec<Slot>,
+    frames: Arc<Spinlock<VecDeque<RecordedFrame>>>,
+    memory: MemoryTracker,
+}
+
+impl FrameRecorder {
+    pub(crate) fn new(interval: u32, ring_size: usize, memory: &MemoryTracker) -> (Self, FrameReceiver) {
+        let frames = Arc::new(Spinlock::new(VecDeque::new()));
+
+        (
+            Self {
+                interval: interval.max(1) as u64,
+                frame_index: 0,
+                ring_size: ring_size.max(1),
+                slots: Vec::new(),
+                frames: frames.clone(),
+                memory: memory.clone(),
+            },
+            FrameReceiver { frames },
+        )
+    }
+
+    // records copy of top left size of texture, returns slot index to map after submission.
+    pub(crate) fn record(
+        &mut self,
+        device: &wgpu::Device,
+        command_encoder: &mut wgpu::CommandEncoder,
+        texture: &Texture,
+        size: (u32, u32),
+    ) -> Option<usize> {
+        let frame_index = self.frame_index;
+        self.frame_index += 1;
+        if !frame_index.is_multiple_of(self.interval) {
+            return None;
+        }
+
+        let index = match self.slots.iter().position(|x| x.pending.is_none()) {
+            Some(x) => x,
+            None if self.slots.len() < self.ring_size => {
+                let (buffer, allocation) = Self::create_buffer(device, &self.memory, size);
+                self.slots.push(Slot {
+                    buffer: Arc::new(buffer),
+                    allocation,
+                    size,
+                    pending: None,
+                });
+
+                self.slots.len() - 1
+            }
+            None => return None,
+        };
+
+        let slot = &mut self.slots[index];
+        if slot.size != size {
+            let (buffer, allocation) = Self::create_buffer(device, &self.memory, size);
+            slot.buffer = Arc::new(buffer);
+            slot.allocation = allocation;
+            slot.size = size;
+        }
+
+        command_encoder.copy_texture_to_buffer(
+            texture.texture.as_image_copy(),
+            wgpu::ImageCopyBuffer {
+                buffer: &slot.buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: core::num::NonZeroU32::new(Self::padded_bytes_per_row(size.0)),
+                    rows_per_image: None,
+                },
+            },
+            wgpu::Extent3d {
+                width: size.0,
+                height: size.1,
+                depth_or_array_layers: 1,
+            },
+        );
+
+        Some(index)
+    }
+
+    // must be called after command buffer containing the copy is submitted.
+    pub(crate) fn map(&mut self, index: usize) {
+        let slot = &mut self.slots[index];
+        let buffer = slot.buffer.clone();
+
+        let map = async move { buffer.slice(..).map_async(wgpu::MapMode::Read).await };
+        slot.pending = Some((self.frame_index - 1, Box::pin(map)));
+    }
+
+    // delivers frames whose buffers finished mapping, without waiting for the rest.
+    pub(crate) fn poll(&mut self, device: &wgpu::Device) {
+        device.poll(wgpu::Maintain::Poll);
+
+        for slot in &mut self.slots {
+            let result = match &mut slot.pending {
+                Some((_, map)) => match map.as_mut().now_or_never() {
+                    Some(x) => x,
+                    None => continue,
+                },
+                None => continue,
+            };
+            let (index, _) = slot.pending.take().unwrap();
+            if result.is_err() {
+                continue;
+            }
+
+            let padded_bytes_per_row = Self::padded_bytes_per_row(slot.size.0) as usize;
+            let bytes_per_row = slot.size.0 as usize * 4;
+            let mut data = Vec::with_capacity(bytes_per_row * slot.size.1 as usize);
+            {
+                let mapped = slot.buffer.slice(..).get_mapped_range();
+                for row in mapped.chunks(padded_bytes_per_row) {
+                    data.extend_from_slice(&row[..bytes_per_row]);
+                }
+            }
+            slot.buffer.unmap();
+
+            let mut frames = self.frames.lock();
+            if frames.len() >= self.ring_size {
+                frames.pop_front();
+            }
+            frames.push_back(RecordedFrame {
+                index,
+                width: slot.size.0,
+                height: slot.size.1,
+                data,
+            });
+        }
+    }
+
+    fn create_buffer(device: &wgpu::Device, memory: &MemoryTracker, size: (u32, u32)) -> (wgpu::Buffer, Allocation) {
+        let bytes = Self::padded_bytes_per_row(size.0) as u64 * size.1 as u64;
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: bytes,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        (buffer, memory.track("recorder", bytes as usize))
+    }
+
+    fn padded_bytes_per_row(width: u32) -> u32 {
+        let alignment = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+
+        (width * 4).div_ceil(alignment) * alignment
+    }
+}
@@ -0,0 +1,232 @@
+use alloc::sync::Arc;
+use core::convert::TryInto;
+
+use nalgebra::{Matrix4, Point3, Vector3};
+use zerocopy::AsBytes;
+
+use crate::{
+    buffer_pool::BufferPool, constants::INTERNAL_COLOR_ATTACHMENT_FORMAT, render_target::OffscreenRenderTarget, staging_belt::StagingBelt, Buffer,
+    Camera, FullscreenPass, PostProcessContext, RenderLayers, Renderer, ShaderBinding, ShaderBindingType, ShaderStage, Texture,
+};
+
+// wider than 90 degrees, so faces overlap and conversion can stay away from their edges
+const FACE_FOV: f32 = 100.0;
+// camera keeps y up, so up and down faces are tilted a little
+const FACE_DIRECTIONS: [[f32; 3]; 6] = [
+    [1.0, 0.0, 0.0],
+    [-1.0, 0.0, 0.0],
+    [0.0, 1.0, 0.01],
+    [0.0, -1.0, 0.01],
+    [0.0, 0.0, 1.0],
+    [0.0, 0.0, -1.0],
+];
+
+#[repr(C)]
+#[derive(AsBytes)]
+struct ProbeUniform {
+    faces: [[f32; 16]; 6],
+    position: [f32; 4],
+    output_size: [f32; 2],
+    face_size: f32,
+    _padding: f32,
+}
+
+// Captures the scene around a point into an equirectangular texture, when added and then every interval frames.
+// Renderer prefilters the probe nearest to the camera into image based lighting of pbr materials, instead of skybox.
+// With deferred render path, 3x2 faces must fit in the view.
+pub struct ReflectionProbe {
+    position: Point3<f32>,
+    face_size: u32,
+    // faces rendered in 3x2 grid
+    faces: OffscreenRenderTarget,
+    texture: Arc<Texture>,
+    interval: Option<u32>,
+    // captured when it reaches zero
+    frames_until_capture: u32,
+    captured: bool,
+}
+
+impl ReflectionProbe {
+    pub fn new(renderer: &Renderer, position: Point3<f32>, face_size: u32) -> Self {
+        let faces = OffscreenRenderTarget::with_device(&renderer.device, &renderer.memory, "reflections", face_size * 3, face_size * 2);
+        let texture = Texture::with_tracker(
+            &renderer.device,
+            &renderer.memory,
+            "reflections",
+            face_size * 4,
+            face_size * 2,
+            INTERNAL_COLOR_ATTACHMENT_FORMAT,
+        );
+
+        Self {
+            position,
+            face_size,
+            faces,
+            texture: Arc::new(texture),
+            interval: None,
+            frames_until_capture: 0,
+            captured: false,
+        }
+    }
+
+    pub fn position(&self) -> Point3<f32> {
+        self.position
+    }
+
+    // frames between captures, for probes around moving objects. none captures only once.
+    pub fn set_interval(&mut self, interval: Option<u32>) {
+        self.interval = interval;
+    }
+
+    // captures again on next frame
+    pub fn refresh(&mut self) {
+        self.captured = false;
+        self.frames_until_capture = 0;
+    }
+
+    // equirectangular, e.g. for LightingEnvironment::skybox or custom materials
+    pub fn texture(&self) -> Arc<Texture> {
+        self.texture.clone()
+    }
+
+    // advances a frame, returns true if probe should be captured now
+    pub(crate) fn due(&mut self) -> bool {
+        let due = match self.interval {
+            Some(_) => self.frames_until_capture == 0,
+            None => !self.captured,
+        };
+        if due {
+            self.frames_until_capture = self.interval.unwrap_or(0);
+            self.captured = true;
+        }
+        self.frames_until_capture = self.frames_until_capture.saturating_sub(1);
+
+        due
+    }
+
+    pub(crate) fn target(&self) -> &OffscreenRenderTarget {
+        &self.faces
+    }
+
+    // cameras of faces with settings of given one, and their viewports in target
+    pub(crate) fn faces(&self, camera: &Camera) -> impl Iterator<Item = (Camera, (f32, f32, f32, f32))> + '_ {
+        let camera = camera.clone();
+
+        FACE_DIRECTIONS.iter().enumerate().map(move |(i, direction)| {
+            let direction = Vector3::new(direction[0], direction[1], direction[2]);
+            let size = self.face_size as f32;
+            let viewport = ((i % 3) as f32 * size, (i / 3) as f32 * size, size, size);
+
+            (camera.looking(self.position, direction, FACE_FOV), viewport)
+        })
+    }
+}
+
+// Converts faces of reflection probes to equirectangular textures.
+pub(crate) struct ProbeCapture {
+    pass: FullscreenPass,
+    uniform_buf: Arc<Buffer>,
+}
+
+impl ProbeCapture {
+    pub(crate) fn new(device: &wgpu::Device, buffer_pool: &BufferPool) -> Self {
+        let uniform_buf = Arc::new(buffer_pool.alloc(core::mem::size_of::<ProbeUniform>()));
+        let pass = FullscreenPass::with_device(
+            device,
+            include_str!("../shaders/reflection.wgsl"),
+            "fs_equirect",
+            &[("Probe", ShaderBinding::new(ShaderStage::Fragment, 3, ShaderBindingType::UniformBuffer))],
+            &[],
+            &[("Probe", uniform_buf.clone())],
+        );
+
+        Self { pass, uniform_buf }
+    }
+
+    // submitted on its own, as the uniform is rewritten for the next probe
+    pub(crate) fn convert(&self, device: &wgpu::Device, staging_belt: &StagingBelt, probe: &ReflectionProbe, view_projections: &[Matrix4<f32>]) {
+        let mut faces = [[0.0; 16]; 6];
+        for (face, view_projection) in faces.iter_mut().zip(view_projections) {
+            *face = view_projection.as_slice().try_into().unwrap();
+        }
+        let size = (probe.face_size * 4, probe.face_size * 2);
+
+        let uniform = ProbeUniform {
+            faces,
+            position: [probe.position.x, probe.position.y, probe.position.z, 1.0],
+            output_size: [size.0 as f32, size.1 as f32],
+            face_size: probe.face_size as f32,
+            _padding: 0.0,
+        };
+        self.uniform_buf.write(uniform.as_bytes());
+
+        let mut command_encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        let mut context = PostProcessContext {
+            device,
+            command_encoder: &mut command_encoder,
+            input: &probe.faces.color_attachment,
+            output: &probe.texture.texture_view,
+            viewport_size: size,
+            depth: None,
+            projection: Matrix4::identity(),
+        };
+        self.pass.draw(&mut context);
+
+        staging_belt.submit(Some(command_encoder.finish()));
+    }
+}
+
+// Renders the scene mirrored by a plane every frame, for mirror-like floors and water drawn with Material::mirror.
+// Things behind the plane aren't clipped, so it suits surfaces nothing goes through.
+// With deferred render path, size must fit in the view.
+pub struct PlanarReflection {
+    point: Point3<f32>,
+    normal: Vector3<f32>,
+    layers: RenderLayers,
+    target: OffscreenRenderTarget,
+    // view projection of mirrored camera
+    view_buf: Arc<Buffer>,
+}
+
+impl PlanarReflection {
+    pub fn new(renderer: &Renderer, width: u32, height: u32, point: Point3<f32>, normal: Vector3<f32>) -> Self {
+        let view_buf = renderer.buffer_pool.alloc(core::mem::size_of::<[f32; 16]>());
+
+        Self {
+            point,
+            normal: normal.normalize(),
+            layers: RenderLayers::default(),
+            target: OffscreenRenderTarget::with_device(&renderer.device, &renderer.memory, "reflections", width, height),
+            view_buf: Arc::new(view_buf),
+        }
+    }
+
+    pub fn texture(&self) -> Arc<Texture> {
+        self.target.color_attachment.clone()
+    }
+
+    // layers drawn into reflection, layer 0 by default. models drawn with Material::mirror of this reflection
+    // can't be on them as their texture is being drawn, so put them on another layer the main camera draws.
+    pub fn set_layers(&mut self, layers: RenderLayers) {
+        self.layers = layers;
+    }
+
+    pub(crate) fn view_buffer(&self) -> Arc<Buffer> {
+        self.view_buf.clone()
+    }
+
+    pub(crate) fn target(&self) -> &OffscreenRenderTarget {
+        &self.target
+    }
+
+    pub(crate) fn camera(&self, camera: &Camera) -> Camera {
+        let mut mirrored = camera.mirrored(&self.point, &self.normal);
+        mirrored.set_layers(self.layers);
+
+        mirrored
+    }
+
+    pub(crate) fn set_view_projection(&self, view_projection: &Matrix4<f32>) {
+        self.view_buf.write(view_projection.as_slice().as_bytes());
+    }
+}
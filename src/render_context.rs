@@ -1,9 +1,98 @@
+use crate::{MaterialPass, Mesh};
+
 pub struct RenderContext<'a> {
     pub(crate) render_pass: wgpu::RenderPass<'a>,
+    pub(crate) pass: MaterialPass,
+
+    // addresses of last bound state, to skip redundant binds between sorted draws
+    pipeline: usize,
+    bind_group: usize,
+    mesh: usize,
+    // x, y, width, height, min depth, max depth set last, so draws changing it can restore it
+    viewport: Option<[f32; 6]>,
 }
 
 impl<'a> RenderContext<'a> {
     pub fn new(render_pass: wgpu::RenderPass<'a>) -> Self {
-        Self { render_pass }
+        Self::with_pass(render_pass, MaterialPass::Main)
+    }
+
+    pub(crate) fn with_pass(render_pass: wgpu::RenderPass<'a>, pass: MaterialPass) -> Self {
+        Self {
+            render_pass,
+            pass,
+            pipeline: 0,
+            bind_group: 0,
+            mesh: 0,
+            viewport: None,
+        }
+    }
+
+    // in pixels of the render target, depth range is within 0..1.
+    pub fn set_viewport(&mut self, x: f32, y: f32, width: f32, height: f32, min_depth: f32, max_depth: f32) {
+        self.render_pass.set_viewport(x, y, width, height, min_depth, max_depth);
+        self.viewport = Some([x, y, width, height, min_depth, max_depth]);
+    }
+
+    pub fn viewport(&self) -> Option<[f32; 6]> {
+        self.viewport
+    }
+
+    // draws are clipped to the rect, which must lie inside the render target.
+    pub fn set_scissor_rect(&mut self, x: u32, y: u32, width: u32, height: u32) {
+        self.render_pass.set_scissor_rect(x, y, width, height);
+    }
+
+    // draws recorded by f are grouped under label in graphics debuggers, see Renderable::label
+    pub(crate) fn debug_group(&mut self, label: Option<&str>, f: impl FnOnce(&mut Self)) {
+        match label {
+            Some(label) => {
+                self.render_pass.push_debug_group(label);
+                f(self);
+                self.render_pass.pop_debug_group();
+            }
+            None => f(self),
+        }
+    }
+
+    pub(crate) fn set_pipeline(&mut self, pipeline: &'a wgpu::RenderPipeline) {
+        let address = pipeline as *const _ as usize;
+        if self.pipeline != address {
+            self.render_pass.set_pipeline(pipeline);
+            self.pipeline = address;
+            // bind groups may be invalidated by incompatible layout
+            self.bind_group = 0;
+        }
+    }
+
+    // bind groups with dynamic offsets are always rebound, as offsets differ per draw.
+    pub(crate) fn set_bind_group(&mut self, bind_group: &'a wgpu::BindGroup, offsets: &[u32]) {
+        let address = bind_group as *const _ as usize;
+        if self.bind_group != address || !offsets.is_empty() {
+            self.render_pass.set_bind_group(0, bind_group, offsets);
+            self.bind_group = address;
+        }
+    }
+
+    // for renderables drawing without mesh, later set_mesh rebinds mesh buffers
+    pub(crate) fn set_vertex_buffer(&mut self, slot: u32, buffer_slice: wgpu::BufferSlice<'a>) {
+        self.render_pass.set_vertex_buffer(slot, buffer_slice);
+        self.mesh = 0;
+    }
+
+    pub(crate) fn set_index_buffer(&mut self, buffer_slice: wgpu::BufferSlice<'a>) {
+        self.render_pass.set_index_buffer(buffer_slice, wgpu::IndexFormat::Uint16);
+        self.mesh = 0;
+    }
+
+    pub(crate) fn set_mesh(&mut self, mesh: &'a Mesh) {
+        let address = mesh as *const _ as usize;
+        if self.mesh != address {
+            self.render_pass.set_index_buffer(mesh.index_buffer.as_slice(), wgpu::IndexFormat::Uint16);
+            for (i, vertex_buffer) in mesh.vertex_buffers.iter().enumerate() {
+                self.render_pass.set_vertex_buffer(i as u32, vertex_buffer.as_slice());
+            }
+            self.mesh = address;
+        }
     }
 }
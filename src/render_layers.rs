@@ -0,0 +1,37 @@
+// Bitmask of up to 32 layers. Cameras draw only renderables whose layers intersect theirs,
+// e.g. to keep editor gizmos or a first person weapon out of other cameras.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RenderLayers(u32);
+
+impl RenderLayers {
+    pub const fn layer(layer: u32) -> Self {
+        Self(1 << layer)
+    }
+
+    pub const fn all() -> Self {
+        Self(u32::MAX)
+    }
+
+    pub const fn none() -> Self {
+        Self(0)
+    }
+
+    pub const fn with(self, layer: u32) -> Self {
+        Self(self.0 | 1 << layer)
+    }
+
+    pub const fn without(self, layer: u32) -> Self {
+        Self(self.0 & !(1 << layer))
+    }
+
+    pub const fn intersects(self, other: Self) -> bool {
+        self.0 & other.0 != 0
+    }
+}
+
+// everything starts on layer 0
+impl Default for RenderLayers {
+    fn default() -> Self {
+        Self::layer(0)
+    }
+}
@@ -0,0 +1,114 @@
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CullMode {
+    None,
+    Front,
+    Back,
+}
+
+impl CullMode {
+    pub(crate) fn wgpu_type(&self) -> Option<wgpu::Face> {
+        match self {
+            CullMode::None => None,
+            CullMode::Front => Some(wgpu::Face::Front),
+            CullMode::Back => Some(wgpu::Face::Back),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DepthCompare {
+    Never,
+    Less,
+    Equal,
+    LessEqual,
+    Greater,
+    NotEqual,
+    GreaterEqual,
+    Always,
+}
+
+impl DepthCompare {
+    pub(crate) fn wgpu_type(&self) -> wgpu::CompareFunction {
+        match self {
+            DepthCompare::Never => wgpu::CompareFunction::Never,
+            DepthCompare::Less => wgpu::CompareFunction::Less,
+            DepthCompare::Equal => wgpu::CompareFunction::Equal,
+            DepthCompare::LessEqual => wgpu::CompareFunction::LessEqual,
+            DepthCompare::Greater => wgpu::CompareFunction::Greater,
+            DepthCompare::NotEqual => wgpu::CompareFunction::NotEqual,
+            DepthCompare::GreaterEqual => wgpu::CompareFunction::GreaterEqual,
+            DepthCompare::Always => wgpu::CompareFunction::Always,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PrimitiveTopology {
+    PointList,
+    LineList,
+    LineStrip,
+    TriangleList,
+    TriangleStrip,
+}
+
+impl PrimitiveTopology {
+    pub(crate) fn wgpu_type(&self) -> wgpu::PrimitiveTopology {
+        match self {
+            PrimitiveTopology::PointList => wgpu::PrimitiveTopology::PointList,
+            PrimitiveTopology::LineList => wgpu::PrimitiveTopology::LineList,
+            PrimitiveTopology::LineStrip => wgpu::PrimitiveTopology::LineStrip,
+            PrimitiveTopology::TriangleList => wgpu::PrimitiveTopology::TriangleList,
+            PrimitiveTopology::TriangleStrip => wgpu::PrimitiveTopology::TriangleStrip,
+        }
+    }
+
+    pub(crate) fn is_strip(&self) -> bool {
+        matches!(self, PrimitiveTopology::LineStrip | PrimitiveTopology::TriangleStrip)
+    }
+}
+
+// Fixed function state of pipelines drawing a material.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct RenderState {
+    pub cull_mode: CullMode,
+    pub depth_compare: DepthCompare,
+    // mesh's topology if none, otherwise mesh indices are read as given one
+    pub topology: Option<PrimitiveTopology>,
+    // polygon offset, constant in depth buffer units and slope scaled. pushes decals and outlines behind or in front.
+    pub depth_bias: i32,
+    pub depth_bias_slope_scale: f32,
+}
+
+impl Default for RenderState {
+    fn default() -> Self {
+        Self {
+            cull_mode: CullMode::Back,
+            depth_compare: DepthCompare::LessEqual,
+            topology: None,
+            depth_bias: 0,
+            depth_bias_slope_scale: 0.0,
+        }
+    }
+}
+
+impl RenderState {
+    pub(crate) fn primitive_state(&self, mesh_topology: PrimitiveTopology) -> wgpu::PrimitiveState {
+        let topology = self.topology.unwrap_or(mesh_topology);
+
+        wgpu::PrimitiveState {
+            topology: topology.wgpu_type(),
+            // mesh indices are u16
+            strip_index_format: if topology.is_strip() { Some(wgpu::IndexFormat::Uint16) } else { None },
+            cull_mode: self.cull_mode.wgpu_type(),
+            ..Default::default()
+        }
+    }
+
+    pub(crate) fn depth_bias_state(&self) -> wgpu::DepthBiasState {
+        wgpu::DepthBiasState {
+            constant: self.depth_bias,
+            slope_scale: self.depth_bias_slope_scale,
+            clamp: 0.0,
+        }
+    }
+}
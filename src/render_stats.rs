@@ -0,0 +1,151 @@
+use alloc::{boxed::Box, sync::Arc};
+use core::{convert::TryInto, future::Future, pin::Pin};
+
+use futures::FutureExt;
+use spinning_top::Spinlock;
+
+use crate::task_runner::MAX_BINS;
+
+type MapFuture = Pin<Box<dyn Future<Output = Result<(), wgpu::BufferAsyncError>> + Send>>;
+
+// depth pre-pass, transparent pass and each bin of opaque pass
+const QUERY_COUNT: usize = 2 + MAX_BINS;
+// vertex and fragment shader invocations as u64
+const QUERY_SIZE: u64 = 16;
+
+pub(crate) const PREPASS_QUERY: usize = 0;
+pub(crate) const TRANSPARENT_QUERY: usize = 1;
+// first of MAX_BINS
+pub(crate) const OPAQUE_QUERY: usize = 2;
+
+// Shader invocations of the main view, e.g. to compare a scene with and without Scene::depth_prepass.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub struct RenderStats {
+    // of depth pre-pass, zero if it didn't run
+    pub prepass_vertex_invocations: u64,
+    // of opaque and transparent passes
+    pub vertex_invocations: u64,
+    // of opaque and transparent passes, about one per covered pixel with depth pre-pass
+    pub fragment_invocations: u64,
+}
+
+#[derive(Default)]
+struct State {
+    // queries begun in frame being recorded
+    written: [bool; QUERY_COUNT],
+    submitted: Option<[bool; QUERY_COUNT]>,
+    pending: Option<([bool; QUERY_COUNT], MapFuture)>,
+    stats: Option<RenderStats>,
+}
+
+// Pipeline statistics queries of scene passes, read back without stalling like occlusion results.
+// Frames recorded while previous results are reading back aren't measured.
+pub(crate) struct StatsQuery {
+    query_set: wgpu::QuerySet,
+    readback: Arc<wgpu::Buffer>,
+    state: Spinlock<State>,
+}
+
+impl StatsQuery {
+    pub(crate) fn new(device: &wgpu::Device) -> Self {
+        let query_set = device.create_query_set(&wgpu::QuerySetDescriptor {
+            label: None,
+            ty: wgpu::QueryType::PipelineStatistics(
+                wgpu::PipelineStatisticsTypes::VERTEX_SHADER_INVOCATIONS | wgpu::PipelineStatisticsTypes::FRAGMENT_SHADER_INVOCATIONS,
+            ),
+            count: QUERY_COUNT as u32,
+        });
+        let readback = device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: QUERY_COUNT as u64 * QUERY_SIZE,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        Self {
+            query_set,
+            readback: Arc::new(readback),
+            state: Spinlock::new(State::default()),
+        }
+    }
+
+    // last results read back
+    pub(crate) fn stats(&self) -> Option<RenderStats> {
+        self.state.lock().stats
+    }
+
+    // picks up results which finished reading back, returns whether a new frame can be measured.
+    pub(crate) fn poll(&self, device: &wgpu::Device) -> bool {
+        let mut state = self.state.lock();
+
+        let result = match &mut state.pending {
+            Some((_, map)) => {
+                device.poll(wgpu::Maintain::Poll);
+                match map.as_mut().now_or_never() {
+                    Some(x) => x,
+                    None => return false,
+                }
+            }
+            None => return state.submitted.is_none(),
+        };
+        let (written, _) = state.pending.take().unwrap();
+        if result.is_err() {
+            return true;
+        }
+
+        let mut stats = RenderStats::default();
+        {
+            let mapped = self.readback.slice(..).get_mapped_range();
+            for (query, data) in mapped.chunks(QUERY_SIZE as usize).enumerate().filter(|(i, _)| written[*i]) {
+                let vertex = u64::from_le_bytes(data[..8].try_into().unwrap());
+                let fragment = u64::from_le_bytes(data[8..].try_into().unwrap());
+
+                if query == PREPASS_QUERY {
+                    stats.prepass_vertex_invocations = vertex;
+                } else {
+                    stats.vertex_invocations += vertex;
+                    stats.fragment_invocations += fragment;
+                }
+            }
+        }
+        self.readback.unmap();
+
+        state.stats = Some(stats);
+
+        true
+    }
+
+    pub(crate) fn begin(&self, render_pass: &mut wgpu::RenderPass, query: usize) {
+        self.state.lock().written[query] = true;
+
+        render_pass.begin_pipeline_statistics_query(&self.query_set, query as u32);
+    }
+
+    // records copy of queries begun so far, which are measured once submitted and mapped.
+    pub(crate) fn resolve(&self, command_encoder: &mut wgpu::CommandEncoder) {
+        let mut state = self.state.lock();
+        if !state.written.iter().any(|x| *x) {
+            return;
+        }
+
+        // unwritten queries have no results to resolve
+        for query in (0..QUERY_COUNT).filter(|x| state.written[*x]) {
+            command_encoder.resolve_query_set(&self.query_set, query as u32..query as u32 + 1, &self.readback, query as u64 * QUERY_SIZE);
+        }
+
+        state.submitted = Some(core::mem::take(&mut state.written));
+    }
+
+    // must be called after command buffer containing the resolve is submitted.
+    pub(crate) fn map(&self) {
+        let mut state = self.state.lock();
+        let written = match state.submitted.take() {
+            Some(x) => x,
+            None => return,
+        };
+
+        let readback = self.readback.clone();
+        let map = async move { readback.slice(..).map_async(wgpu::MapMode::Read).await };
+        state.pending = Some((written, Box::pin(map)));
+    }
+}
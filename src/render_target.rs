@@ -2,7 +2,9 @@ use alloc::sync::Arc;
 
 use crate::{
     constants::{INTERNAL_COLOR_ATTACHMENT_FORMAT, INTERNAL_DEPTH_ATTACHMENT_FORMAT},
-    Texture,
+    event::EventQueue,
+    memory::MemoryTracker,
+    RendererEvent, Texture,
 };
 
 pub trait RenderTarget: Sync + Send {
@@ -10,19 +12,39 @@ pub trait RenderTarget: Sync + Send {
     fn color_attachment(&self) -> &wgpu::TextureView;
     fn submit(&mut self);
     fn output_format(&self) -> wgpu::TextureFormat;
+
+    // called before rendering into color attachment, frame is skipped if it returns false
+    fn acquire(&mut self) -> bool {
+        true
+    }
+
+    // targets of fixed size ignore it
+    fn resize(&mut self, _width: u32, _height: u32) {}
+
+    // texture behind color attachment if it can be read back
+    fn texture(&self) -> Option<&Texture> {
+        None
+    }
 }
 
 pub struct WindowRenderTarget {
     texture_view: Option<wgpu::TextureView>,
     frame: Option<wgpu::SurfaceFrame>,
     surface: wgpu::Surface,
-    width: u32,
-    height: u32,
-    format: wgpu::TextureFormat,
+    device: Arc<wgpu::Device>,
+    config: wgpu::SurfaceConfiguration,
+    events: EventQueue,
 }
 
 impl WindowRenderTarget {
-    pub(crate) fn new(surface: wgpu::Surface, adapter: &wgpu::Adapter, device: &wgpu::Device, width: u32, height: u32) -> Self {
+    pub(crate) fn new(
+        surface: wgpu::Surface,
+        adapter: &wgpu::Adapter,
+        device: Arc<wgpu::Device>,
+        width: u32,
+        height: u32,
+        events: EventQueue,
+    ) -> Self {
         let format = surface.get_preferred_format(adapter).unwrap();
 
         let config = wgpu::SurfaceConfiguration {
@@ -33,41 +55,64 @@ impl WindowRenderTarget {
             present_mode: wgpu::PresentMode::Mailbox,
         };
 
-        surface.configure(device, &config);
-
-        let frame = surface.get_current_frame().unwrap();
-        let texture_view = frame.output.texture.create_view(&wgpu::TextureViewDescriptor::default());
+        surface.configure(&device, &config);
 
         Self {
             surface,
-            frame: Some(frame),
-            texture_view: Some(texture_view),
-            format,
-            width,
-            height,
+            frame: None,
+            texture_view: None,
+            device,
+            config,
+            events,
         }
     }
+
+    // outdated or lost surface is configured again, timed out frame is requested again.
+    // none if that fails too or gpu is out of memory.
+    fn acquire_frame(&mut self) -> Option<wgpu::SurfaceFrame> {
+        let result = match self.surface.get_current_frame() {
+            Ok(x) => return Some(x),
+            Err(wgpu::SurfaceError::Timeout) => {
+                self.events.push(RendererEvent::FrameTimeout);
+
+                self.surface.get_current_frame()
+            }
+            Err(wgpu::SurfaceError::Outdated) | Err(wgpu::SurfaceError::Lost) => {
+                self.surface.configure(&self.device, &self.config);
+                self.events.push(RendererEvent::SurfaceReconfigured {
+                    width: self.config.width,
+                    height: self.config.height,
+                });
+
+                self.surface.get_current_frame()
+            }
+            Err(e) => Err(e),
+        };
+
+        result.map_err(|_| self.events.push(RendererEvent::FrameSkipped)).ok()
+    }
 }
 
 impl RenderTarget for WindowRenderTarget {
     fn size(&self) -> (u32, u32) {
-        (self.width, self.height)
+        (self.config.width, self.config.height)
     }
 
     fn submit(&mut self) {
         // dropping frame makes it render
         self.texture_view = None;
         self.frame = None;
+    }
+
+    fn acquire(&mut self) -> bool {
+        if self.frame.is_none() {
+            if let Some(frame) = self.acquire_frame() {
+                self.texture_view = Some(frame.output.texture.create_view(&wgpu::TextureViewDescriptor::default()));
+                self.frame = Some(frame);
+            }
+        }
 
-        self.frame = Some(self.surface.get_current_frame().unwrap());
-        self.texture_view = Some(
-            self.frame
-                .as_ref()
-                .unwrap()
-                .output
-                .texture
-                .create_view(&wgpu::TextureViewDescriptor::default()),
-        );
+        self.frame.is_some()
     }
 
     fn color_attachment(&self) -> &wgpu::TextureView {
@@ -75,7 +120,16 @@ impl RenderTarget for WindowRenderTarget {
     }
 
     fn output_format(&self) -> wgpu::TextureFormat {
-        self.format
+        self.config.format
+    }
+
+    fn resize(&mut self, width: u32, height: u32) {
+        self.texture_view = None;
+        self.frame = None;
+
+        self.config.width = width;
+        self.config.height = height;
+        self.surface.configure(&self.device, &self.config);
     }
 }
 
@@ -88,9 +142,23 @@ pub struct OffscreenRenderTarget {
 }
 
 impl OffscreenRenderTarget {
-    pub(crate) fn with_device(device: &wgpu::Device, width: u32, height: u32) -> Self {
-        let color_attachment = Arc::new(Texture::with_device(device, width, height, INTERNAL_COLOR_ATTACHMENT_FORMAT));
-        let depth_attachment = Arc::new(Texture::with_device(device, width, height, INTERNAL_DEPTH_ATTACHMENT_FORMAT));
+    pub(crate) fn with_device(device: &wgpu::Device, memory: &MemoryTracker, label: &'static str, width: u32, height: u32) -> Self {
+        let color_attachment = Arc::new(Texture::with_tracker(
+            device,
+            memory,
+            label,
+            width,
+            height,
+            INTERNAL_COLOR_ATTACHMENT_FORMAT,
+        ));
+        let depth_attachment = Arc::new(Texture::with_tracker(
+            device,
+            memory,
+            label,
+            width,
+            height,
+            INTERNAL_DEPTH_ATTACHMENT_FORMAT,
+        ));
 
         Self {
             width,
@@ -115,4 +183,8 @@ impl RenderTarget for OffscreenRenderTarget {
     fn output_format(&self) -> wgpu::TextureFormat {
         INTERNAL_COLOR_ATTACHMENT_FORMAT.wgpu_type()
     }
+
+    fn texture(&self) -> Option<&Texture> {
+        Some(&self.color_attachment)
+    }
 }
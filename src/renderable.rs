@@ -1,5 +1,77 @@
-use crate::RenderContext;
+use core::any::Any;
 
-pub trait Renderable: Sync + Send {
+use nalgebra::{Matrix4, Point3};
+
+use crate::{Aabb, ComputeContext, RenderContext, RenderLayers};
+
+// Any lets scene hand out typed access to models it owns.
+pub trait Renderable: Any + Sync + Send {
     fn render<'a>(&'a self, render_context: &mut RenderContext<'a>);
+
+    // draws with id as output color, for picking. renderables which can't be picked draw nothing.
+    fn render_pick<'a>(&'a self, _render_context: &mut RenderContext<'a>, _id: u32) {}
+
+    // draws depth only before the scene when Scene::depth_prepass is set, so hidden fragments aren't shaded.
+    // renderables drawing nothing here are drawn and shaded as usual.
+    fn render_depth<'a>(&'a self, _render_context: &mut RenderContext<'a>) {}
+
+    // draws occluded parts after the scene, for materials with x-ray color.
+    fn render_x_ray<'a>(&'a self, _render_context: &mut RenderContext<'a>) {}
+
+    // draws selection outline after the scene, set with Model::set_outline.
+    fn render_outline<'a>(&'a self, _render_context: &mut RenderContext<'a>) {}
+
+    // called before rendering each view, to upload view dependent data.
+    fn prepare(&self, _view_projection: &Matrix4<f32>) {}
+
+    // called once a frame with Scene::time, before any view is prepared. updates animated material parameters.
+    fn animate(&self, _time: f32) {}
+
+    // called after prepare of each view, records compute work which drawing the view depends on.
+    fn dispatch(&self, _context: &mut ComputeContext) {}
+
+    // set by LodGroup before prepare while cross-fading levels, see shaders/lod_fade.wgsl.
+    fn set_lod_fade(&self, _fade: f32) {}
+
+    // opaque renderables are drawn in order of this, so ones sharing pipeline, material and mesh are adjacent.
+    fn sort_key(&self) -> (usize, usize, usize) {
+        (0, 0, 0)
+    }
+
+    // mvp and model transform of last prepare, for adjacent opaque renderables of same sort key which can be drawn
+    // together with render_instanced. none draws this one alone with render.
+    fn instance_transforms(&self) -> Option<[f32; 32]> {
+        None
+    }
+
+    // draws count instances with transforms of instance_transforms in instances, see shaders/instance.wgsl.
+    fn render_instanced<'a>(&'a self, _render_context: &mut RenderContext<'a>, _instances: wgpu::BufferSlice<'a>, _count: u32) {}
+
+    // transparent renderables are drawn after opaque ones, sorted back to front by position.
+    fn is_transparent(&self) -> bool {
+        false
+    }
+
+    fn position(&self) -> Point3<f32> {
+        Point3::origin()
+    }
+
+    // world space bounds, renderables without them are never occlusion culled.
+    fn aabb(&self) -> Option<Aabb> {
+        None
+    }
+
+    fn layers(&self) -> RenderLayers {
+        RenderLayers::default()
+    }
+
+    // groups its draws in graphics debuggers like RenderDoc
+    fn label(&self) -> Option<&str> {
+        None
+    }
+
+    // hidden renderables are skipped by every pass
+    fn is_visible(&self) -> bool {
+        true
+    }
 }
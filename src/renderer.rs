@@ -1,45 +1,178 @@
-use alloc::{boxed::Box, sync::Arc, vec};
+use alloc::{boxed::Box, sync::Arc, vec, vec::Vec};
 
 use nalgebra::Matrix4;
 use raw_window_handle::HasRawWindowHandle;
 use zerocopy::AsBytes;
 
 use crate::{
-    buffer::Buffer, buffer_pool::BufferPool, render_target::OffscreenRenderTarget, Camera, Material, Mesh, Model, RenderContext, RenderTarget,
-    Renderable, Scene, Shader, ShaderBinding, ShaderBindingType, ShaderStage, VertexFormat, VertexFormatItem, VertexItemType, WindowRenderTarget,
+    adapter::{self, AdapterInfo},
+    buffer::Buffer,
+    buffer_pool::BufferPool,
+    clustered_lighting::ClusteredLights,
+    compute::ComputeScheduler,
+    constants::{INTERNAL_COLOR_ATTACHMENT_FORMAT, MAX_PUSH_CONSTANT_SIZE},
+    conventions,
+    debug_draw::DebugRenderer,
+    deferred::DeferredPath,
+    deletion_queue::DeletionQueue,
+    environment::EnvironmentMaps,
+    event::EventQueue,
+    frame_pacer::FramePacer,
+    instancing::{Draw, InstanceBuffer},
+    lighting::LightingUniform,
+    memory::{MemoryReport, MemoryTracker},
+    occlusion::OcclusionCuller,
+    picking,
+    pipeline_cache::PipelineCache,
+    post_process::FullscreenPass,
+    recorder::FrameRecorder,
+    reflection::ProbeCapture,
+    render_stats::{StatsQuery, OPAQUE_QUERY, PREPASS_QUERY, TRANSPARENT_QUERY},
+    render_target::OffscreenRenderTarget,
+    staging_belt::StagingBelt,
+    stereo::{Eye, Stereo},
+    taa::TemporalAa,
+    target_pool::TargetPool,
+    task_runner::{self, Task},
+    uniform_arena::UniformArena,
+    AntiAliasing, Backend, CameraView, ClearConfig, Color, ComputeContext, ComputeJob, ComputeJobHandle, FrameReceiver, Material, MaterialPass, Mesh,
+    Model, ModelId, Overlay, PlanarReflection, PostProcess, PostProcessContext, ReflectionProbe, RenderContext, RenderPath, RenderStats,
+    RenderTarget, Renderable, RendererEvent, RendererOptions, Scene, Shader, ShaderBinding, ShaderBindingType, ShaderPreprocessor, ShaderStage,
+    StereoMode, TaskRunner, Texture, TextureFormat, VertexFormat, VertexFormatItem, VertexItemType, Viewpoint, WindowRenderTarget,
 };
 
+// Window surface driven by the renderer, see Renderer::create_surface.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct SurfaceId(usize);
+
+struct Surface {
+    render_target: Box<dyn RenderTarget>,
+    // blits offscreen target, then each post process target
+    present_models: Vec<Model>,
+}
+
 pub struct Renderer {
     pub(crate) device: Arc<wgpu::Device>,
-    pub(crate) mvp_buf: Buffer,
+    pub(crate) lighting_buf: Arc<Buffer>,
     pub buffer_pool: BufferPool,
 
     pub(crate) queue: Arc<wgpu::Queue>,
+    // every upload and submission goes through it, see StagingBelt
+    pub(crate) staging_belt: Arc<StagingBelt>,
 
-    render_target: Box<dyn RenderTarget>,
+    instance: wgpu::Instance,
+    adapter: wgpu::Adapter,
+    // first one is the window renderer was created with
+    surfaces: Vec<Surface>,
+    pub(crate) options: RendererOptions,
+
+    targets: TargetPool,
 
-    offscreen_target: OffscreenRenderTarget,
-    offscreen_to_render_target_model: Model,
+    post_processes: Vec<Box<dyn PostProcess>>,
+    stereo: Option<Stereo>,
+    view_copy: FullscreenPass,
+    custom_passes: Vec<(&'static str, Arc<Texture>)>,
+    deferred: Option<DeferredPath>,
+    debug_renderer: DebugRenderer,
+    compute_scheduler: ComputeScheduler,
+    recorder: Option<FrameRecorder>,
+    pub(crate) deletion_queue: Arc<DeletionQueue>,
+    frame_pacer: FramePacer,
+    occlusion: Option<OcclusionCuller>,
+    stats: Option<StatsQuery>,
+    pub(crate) environment: EnvironmentMaps,
+    // point lights of forward shaded materials, binned for each view
+    pub(crate) clustered_lights: ClusteredLights,
+    // anti-aliasing passes selected by options, run first and last of post processes
+    taa: Option<TemporalAa>,
+    // encodes opaque bins in parallel, see set_task_runner
+    task_runner: Option<Arc<dyn TaskRunner>>,
+    fxaa: Option<FullscreenPass>,
+
+    // composited after the scene in insertion order
+    pub overlays: Vec<Overlay>,
+    // captured before the scene, see ReflectionProbe and PlanarReflection
+    pub reflection_probes: Vec<ReflectionProbe>,
+    pub planar_reflections: Vec<PlanarReflection>,
+    probe_capture: ProbeCapture,
+    // includes and defines for shaders created with Shader::new and FullscreenPass::new
+    pub shader_preprocessor: ShaderPreprocessor,
+    scale_factor: f32,
+    // width / height of the 3d view, which is letterboxed inside surfaces
+    fixed_aspect: Option<f32>,
+    pub(crate) events: EventQueue,
+    pub(crate) memory: MemoryTracker,
+    pub(crate) downlevel: bool,
+
+    pub(crate) pipeline_cache: PipelineCache,
+    pub(crate) uniform_arena: Arc<UniformArena>,
+    instance_buffer: InstanceBuffer,
+    pub(crate) pick_shader: Arc<Shader>,
+    pub(crate) x_ray_shader: Arc<Shader>,
+    // extruded along normals, or from origin for meshes without them
+    pub(crate) outline_shaders: [Arc<Shader>; 2],
 }
 
 impl Renderer {
     pub async fn new<W: HasRawWindowHandle>(window: &W, width: u32, height: u32) -> Self {
-        let instance = wgpu::Instance::new(wgpu::Backends::PRIMARY);
+        Self::with_options(window, width, height, RendererOptions::default()).await
+    }
+
+    pub async fn with_options<W: HasRawWindowHandle>(window: &W, width: u32, height: u32, options: RendererOptions) -> Self {
+        let instance = wgpu::Instance::new(adapter::backends(options.backend));
         let surface = unsafe { instance.create_surface(window) };
 
-        let adapter = instance
-            .request_adapter(&wgpu::RequestAdapterOptions {
-                power_preference: wgpu::PowerPreference::default(),
-                compatible_surface: None,
-            })
-            .await
-            .unwrap();
+        Self::create(instance, width, height, options, |adapter, device, events, _| {
+            Box::new(WindowRenderTarget::new(surface, adapter, device, width, height, events))
+        })
+        .await
+    }
+
+    // renders into an offscreen texture instead of a window, e.g. for tests or servers.
+    pub async fn headless(width: u32, height: u32, options: RendererOptions) -> Self {
+        let instance = wgpu::Instance::new(adapter::backends(options.backend));
+
+        Self::create(instance, width, height, options, |_, device, _, memory| {
+            Box::new(OffscreenRenderTarget::with_device(&device, memory, "render targets", width, height))
+        })
+        .await
+    }
+
+    async fn create<F>(instance: wgpu::Instance, width: u32, height: u32, options: RendererOptions, create_target: F) -> Self
+    where
+        F: FnOnce(&wgpu::Adapter, Arc<wgpu::Device>, EventQueue, &MemoryTracker) -> Box<dyn RenderTarget>,
+    {
+        let adapter = adapter::request_adapter(&instance, &options).await;
+        let downlevel = options.downlevel || adapter::is_downlevel(&adapter);
+
+        // shaders declaring push constants fall back to uniform buffers without them
+        let (mut features, limits) = if downlevel {
+            (wgpu::Features::empty(), adapter::downlevel_limits(&adapter))
+        } else if adapter.features().contains(wgpu::Features::PUSH_CONSTANTS) && adapter.limits().max_push_constant_size >= MAX_PUSH_CONSTANT_SIZE {
+            (
+                wgpu::Features::PUSH_CONSTANTS,
+                wgpu::Limits {
+                    max_push_constant_size: MAX_PUSH_CONSTANT_SIZE,
+                    ..wgpu::Limits::default()
+                },
+            )
+        } else {
+            (wgpu::Features::empty(), wgpu::Limits::default())
+        };
+        // indirect batches draw one command at a time without it
+        if !downlevel {
+            features |= adapter.features() & wgpu::Features::MULTI_DRAW_INDIRECT;
+        }
+        // compressed textures are decoded on cpu without it
+        features |= adapter.features() & wgpu::Features::TEXTURE_COMPRESSION_BC;
+        // render stats aren't available without it
+        features |= adapter.features() & wgpu::Features::PIPELINE_STATISTICS_QUERY;
 
         let (device, queue) = adapter
             .request_device(
                 &wgpu::DeviceDescriptor {
-                    features: wgpu::Features::empty(),
-                    limits: wgpu::Limits::default(),
+                    features,
+                    limits,
                     label: None,
                 },
                 None,
@@ -50,53 +183,711 @@ impl Renderer {
         let device = Arc::new(device);
         let queue = Arc::new(queue);
 
-        let buffer_pool = BufferPool::new(device.clone(), queue.clone());
+        let events = EventQueue::default();
+        let memory = MemoryTracker::new(events.clone());
+        let deletion_queue = Arc::new(DeletionQueue::default());
+        let staging_belt = Arc::new(StagingBelt::new(device.clone(), queue.clone(), memory.clone()));
+        let buffer_pool = BufferPool::new(
+            device.clone(),
+            staging_belt.clone(),
+            events.clone(),
+            memory.clone(),
+            deletion_queue.clone(),
+        );
+
+        let render_target = create_target(&adapter, device.clone(), events.clone(), &memory);
+
+        let targets = TargetPool::new(&device, &memory, (width, height));
+        let texture_size = targets.size();
+        let surface = Self::create_surface_with_target(&device, &buffer_pool, render_target, None, &targets);
+
+        let lighting_buf = Arc::new(buffer_pool.alloc(core::mem::size_of::<LightingUniform>()));
+
+        let deferred = if options.render_path == RenderPath::Deferred {
+            Some(DeferredPath::new(&device, &buffer_pool, &memory, texture_size, lighting_buf.clone()))
+        } else {
+            None
+        };
+
+        let debug_renderer = DebugRenderer::new(&device, &buffer_pool);
+        // hi-z is built by compute
+        let occlusion = if options.occlusion_culling && !downlevel {
+            Some(OcclusionCuller::new(&device, &memory))
+        } else {
+            None
+        };
+        let stats = if device.features().contains(wgpu::Features::PIPELINE_STATISTICS_QUERY) {
+            Some(StatsQuery::new(&device))
+        } else {
+            None
+        };
+        let environment = EnvironmentMaps::new(&device, &staging_belt, &buffer_pool, &memory);
+        let probe_capture = ProbeCapture::new(&device, &buffer_pool);
+        let clustered_lights = ClusteredLights::new(&device, &staging_belt, &events, &buffer_pool, &memory, downlevel);
+        let taa = if options.anti_aliasing == AntiAliasing::Taa {
+            Some(TemporalAa::new(&device, &buffer_pool, &memory))
+        } else {
+            None
+        };
+        let fxaa = if options.anti_aliasing == AntiAliasing::Fxaa {
+            Some(FullscreenPass::with_device(
+                &device,
+                include_str!("../shaders/fxaa.wgsl"),
+                "fs_main",
+                &[],
+                &[],
+                &[],
+            ))
+        } else {
+            None
+        };
+        let view_copy = FullscreenPass::with_device(&device, include_str!("../shaders/copy.wgsl"), "fs_main", &[], &[], &[]);
+        let pipeline_cache = PipelineCache::new(&device);
+        let uniform_arena = Arc::new(UniformArena::new(&device, staging_belt.clone(), &memory));
+        let instance_buffer = InstanceBuffer::new(&device, staging_belt.clone(), &memory);
+
+        let pick_shader = Arc::new(Shader::with_device(
+            &device,
+            include_str!("../shaders/pick.wgsl"),
+            "vs_main",
+            "fs_main",
+            &[("Mvp", ShaderBinding::new(ShaderStage::Vertex, 0, ShaderBindingType::UniformBuffer))],
+            &[("Position", 0)],
+        ));
 
-        let render_target = Box::new(WindowRenderTarget::new(surface, &adapter, &device, width, height));
+        let x_ray_shader = Arc::new(Shader::with_device(
+            &device,
+            include_str!("../shaders/x_ray.wgsl"),
+            "vs_main",
+            "fs_main",
+            &[
+                ("Mvp", ShaderBinding::new(ShaderStage::Vertex, 0, ShaderBindingType::UniformBuffer)),
+                ("XRay", ShaderBinding::new(ShaderStage::Fragment, 1, ShaderBindingType::UniformBuffer)),
+            ],
+            &[("Position", 0)],
+        ));
 
-        let (offscreen_target, offscreen_to_render_target_model) =
-            Self::create_offscreen_target(&device, &buffer_pool, width, height, render_target.output_format());
+        let create_outline_shader = |vs_entry, inputs: &[_]| {
+            Arc::new(Shader::with_device(
+                &device,
+                include_str!("../shaders/outline.wgsl"),
+                vs_entry,
+                "fs_main",
+                &[
+                    ("Mvp", ShaderBinding::new(ShaderStage::Vertex, 0, ShaderBindingType::UniformBuffer)),
+                    (
+                        "Outline",
+                        ShaderBinding::new(ShaderStage::VertexFragment, 1, ShaderBindingType::UniformBuffer),
+                    ),
+                ],
+                inputs,
+            ))
+        };
+        let outline_shaders = [
+            create_outline_shader("vs_main", &[("Position", 0), ("Normal", 1)]),
+            create_outline_shader("vs_scale", &[("Position", 0)]),
+        ];
 
-        let mvp_buf = buffer_pool.alloc(64);
+        let frame_pacer = FramePacer::new(options.max_frames_in_flight);
+        let mut shader_preprocessor = ShaderPreprocessor::new();
+        if downlevel {
+            shader_preprocessor.define("DOWNLEVEL", "true");
+        }
 
         Self {
             device,
-            mvp_buf,
+            lighting_buf,
             buffer_pool,
             queue,
-            render_target,
-            offscreen_target,
-            offscreen_to_render_target_model,
+            staging_belt,
+            instance,
+            adapter,
+            surfaces: vec![surface],
+            options,
+            targets,
+            post_processes: Vec::new(),
+            stereo: None,
+            view_copy,
+            custom_passes: Vec::new(),
+            deferred,
+            debug_renderer,
+            compute_scheduler: ComputeScheduler::new(),
+            recorder: None,
+            deletion_queue,
+            frame_pacer,
+            occlusion,
+            stats,
+            environment,
+            clustered_lights,
+            taa,
+            task_runner: None,
+            fxaa,
+            overlays: Vec::new(),
+            reflection_probes: Vec::new(),
+            planar_reflections: Vec::new(),
+            probe_capture,
+            shader_preprocessor,
+            downlevel,
+            scale_factor: 1.0,
+            fixed_aspect: None,
+            events,
+            memory,
+            pipeline_cache,
+            uniform_arena,
+            instance_buffer,
+            pick_shader,
+            x_ray_shader,
+            outline_shaders,
         }
     }
 
+    // opaque draws of each view are encoded on runner's threads when there are enough of them.
+    pub fn set_task_runner(&mut self, runner: Option<Arc<dyn TaskRunner>>) {
+        self.task_runner = runner;
+    }
+
+    // shader invocations of main view measured a few frames ago. none until first results are read back,
+    // or if adapter doesn't support pipeline statistics queries.
+    pub fn stats(&self) -> Option<RenderStats> {
+        self.stats.as_ref().and_then(|x| x.stats())
+    }
+
+    // gpu memory of buffers and textures alive, by category. textures created by applications are under "textures"
+    // unless relabeled with Texture::set_label, buffers they allocate are part of the buffer pool.
+    pub fn memory_report(&self) -> MemoryReport {
+        self.memory.report(self.buffer_pool.used())
+    }
+
+    // raises RendererEvent::MemoryBudgetExceeded when tracked memory grows past budget bytes, e.g. to free caches on mobile.
+    pub fn set_memory_budget(&mut self, budget: Option<usize>) {
+        self.memory.set_budget(budget);
+    }
+
+    // frames submitted which gpu hasn't finished yet. render blocks while it's at RendererOptions::max_frames_in_flight,
+    // so applications may sample input right before rendering, or skip a frame instead of blocking.
+    pub fn frames_in_flight(&self) -> usize {
+        self.frame_pacer.in_flight(&self.device)
+    }
+
+    // true if render wouldn't block on frames in flight, e.g. to run other work and check again instead.
+    pub fn can_submit(&self) -> bool {
+        self.frame_pacer.can_submit(&self.device)
+    }
+
+    // adapters of backend, or of primary backends if none, e.g. to let users pick one with RendererOptions::adapter_name.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn enumerate_adapters(backend: Option<Backend>) -> Vec<AdapterInfo> {
+        adapter::enumerate_adapters(backend)
+    }
+
+    // true on adapters without compute or storage buffers, or if RendererOptions::downlevel is set.
+    // point lights aren't clustered, nearest ones light every fragment, and occlusion culling is off.
+    // indirect batches draw every instance without culling. compute kernels and jobs are skipped, morph targets are
+    // blended on cpu, and shaders get DOWNLEVEL defined, e.g. to skip storage buffers.
+    pub fn is_downlevel(&self) -> bool {
+        self.downlevel
+    }
+
+    // adapter renderer runs on
+    pub fn adapter_info(&self) -> AdapterInfo {
+        AdapterInfo::new(&self.adapter)
+    }
+
+    // options renderer was created with, e.g. to save them with RendererOptions::to_config.
+    pub fn options(&self) -> &RendererOptions {
+        &self.options
+    }
+
+    // returns events raised since last call, oldest first.
+    pub fn take_events(&self) -> Vec<RendererEvent> {
+        self.events.drain()
+    }
+
+    // ratio of physical to logical pixels of the window, used to place overlays.
+    pub fn set_scale_factor(&mut self, scale_factor: f32) {
+        self.scale_factor = scale_factor;
+    }
+
+    // models whose material participates in the pass are drawn into returned texture after main pass.
+    pub fn add_custom_pass(&mut self, name: &'static str) -> Arc<Texture> {
+        let size = self.targets.offscreen_target.size();
+        let texture = Arc::new(Texture::with_tracker(
+            &self.device,
+            &self.memory,
+            "render targets",
+            size.0,
+            size.1,
+            INTERNAL_COLOR_ATTACHMENT_FORMAT,
+        ));
+        self.custom_passes.push((name, texture.clone()));
+
+        texture
+    }
+
+    // eye_separation is distance between left and right eye cameras in world units.
+    pub fn set_stereo(&mut self, mode: Option<StereoMode>, eye_separation: f32) {
+        self.stereo = mode.map(|x| Stereo::new(&self.device, &self.memory, x, eye_separation, self.targets.offscreen_target.size()));
+    }
+
+    // job is stepped at the start of each frame until it reports finished.
+    // in downlevel mode it's dropped without running, and RendererEvent::ComputeSkipped is raised.
+    pub fn add_compute_job<J: ComputeJob + 'static>(&mut self, job: J) -> ComputeJobHandle {
+        if self.downlevel {
+            self.events.push(RendererEvent::ComputeSkipped);
+
+            return ComputeScheduler::skipped();
+        }
+
+        self.compute_scheduler.add(Box::new(job))
+    }
+
+    // another window sharing gpu resources with the main one, e.g. editor viewports.
+    // overlays are drawn on main window only.
+    pub fn create_surface<W: HasRawWindowHandle>(&mut self, window: &W, width: u32, height: u32) -> SurfaceId {
+        if self.grow_targets((width, height)) {
+            self.update_present_models();
+        }
+
+        let surface = unsafe { self.instance.create_surface(window) };
+        let render_target = Box::new(WindowRenderTarget::new(
+            surface,
+            &self.adapter,
+            self.device.clone(),
+            width,
+            height,
+            self.events.clone(),
+        ));
+
+        let surface = Self::create_surface_with_target(&self.device, &self.buffer_pool, render_target, self.fixed_aspect, &self.targets);
+        self.surfaces.push(surface);
+
+        SurfaceId(self.surfaces.len() - 1)
+    }
+
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.resize_surface(SurfaceId(0), width, height)
+    }
+
+    // intermediate targets grow to fit the largest surface and are kept when it shrinks.
+    pub fn resize_surface(&mut self, surface: SurfaceId, width: u32, height: u32) {
+        self.surfaces[surface.0].render_target.resize(width, height);
+
+        self.grow_targets((width, height));
+        self.update_present_models();
+    }
+
+    // current texture of the pass, which is replaced when targets are reallocated.
+    pub fn custom_pass(&self, name: &str) -> Option<Arc<Texture>> {
+        self.custom_passes.iter().find(|x| x.0 == name).map(|x| x.1.clone())
+    }
+
+    // 3d view keeps given width / height ratio and is centered in surfaces with bars around it.
+    // overlays still use the whole surface.
+    pub fn set_fixed_aspect(&mut self, aspect: Option<f32>) {
+        self.fixed_aspect = aspect;
+        self.update_present_models();
+    }
+
+    // returns whether targets were reallocated, in which case present models are stale.
+    fn grow_targets(&mut self, size: (u32, u32)) -> bool {
+        if self.targets.fits(size) {
+            return false;
+        }
+
+        let size = self
+            .surfaces
+            .iter()
+            .map(|x| x.render_target.size())
+            .fold(size, |a, b| (a.0.max(b.0), a.1.max(b.1)));
+        self.targets = TargetPool::new(&self.device, &self.memory, size);
+
+        // everything holding old targets is created again
+        let texture_size = self.targets.size();
+        self.stereo = self
+            .stereo
+            .as_ref()
+            .map(|x| Stereo::new(&self.device, &self.memory, x.mode, x.eye_separation, texture_size));
+        if self.deferred.is_some() {
+            self.deferred = Some(DeferredPath::new(
+                &self.device,
+                &self.buffer_pool,
+                &self.memory,
+                texture_size,
+                self.lighting_buf.clone(),
+            ));
+        }
+        for (_, texture) in &mut self.custom_passes {
+            *texture = Arc::new(Texture::with_tracker(
+                &self.device,
+                &self.memory,
+                "render targets",
+                texture_size.0,
+                texture_size.1,
+                INTERNAL_COLOR_ATTACHMENT_FORMAT,
+            ));
+        }
+
+        self.events.push(RendererEvent::TargetsReallocated {
+            width: texture_size.0,
+            height: texture_size.1,
+        });
+
+        true
+    }
+
+    fn update_present_models(&mut self) {
+        for surface in &mut self.surfaces {
+            let view_rect = Self::letterbox(surface.render_target.size(), self.fixed_aspect);
+            surface.present_models = Self::create_present_models(
+                &self.device,
+                &self.buffer_pool,
+                &*surface.render_target,
+                (view_rect.2, view_rect.3),
+                &self.targets,
+            );
+        }
+    }
+
+    // maps main window pixel to pixel inside the 3d view, none if it's on the bars.
+    pub fn window_to_view(&self, x: u32, y: u32) -> Option<(u32, u32)> {
+        let view_rect = Self::letterbox(self.main_target().size(), self.fixed_aspect);
+        let (x, y) = (x.checked_sub(view_rect.0)?, y.checked_sub(view_rect.1)?);
+
+        if x < view_rect.2 && y < view_rect.3 {
+            Some((x, y))
+        } else {
+            None
+        }
+    }
+
+    // main window frames are recorded every interval frames into ring_size staging buffers.
+    // frames are skipped instead of stalling when gpu hasn't released any buffer yet.
+    pub fn start_recording(&mut self, interval: u32, ring_size: usize) -> FrameReceiver {
+        let (recorder, receiver) = FrameRecorder::new(interval, ring_size, &self.memory);
+        self.recorder = Some(recorder);
+
+        receiver
+    }
+
+    pub fn stop_recording(&mut self) {
+        self.recorder = None;
+    }
+
+    // whether textures of format can be created and sampled on this device, to pick a fallback otherwise.
+    pub fn supports_format(&self, format: TextureFormat) -> bool {
+        let format = format.wgpu_type();
+
+        self.device.features().contains(format.describe().required_features)
+            && self
+                .adapter
+                .get_texture_format_features(format)
+                .allowed_usages
+                .contains(wgpu::TextureUsages::TEXTURE_BINDING)
+    }
+
+    // drops resource once frames already submitted are done with it, e.g. a texture replaced in a material.
+    pub fn release<T: Send + Sync + 'static>(&self, resource: T) {
+        self.deletion_queue.push(Box::new(resource));
+    }
+
+    // waits for gpu to destroy everything released or dropped, and frees buffer pool chunks left empty
+    // and cached pipelines and bind groups of dropped shaders and textures.
+    // e.g. after unloading a level, to bring memory back down without waiting for later frames.
+    pub fn trim(&mut self) {
+        self.deletion_queue.flush(&self.device);
+        self.buffer_pool.trim();
+        self.staging_belt.trim();
+        self.pipeline_cache.trim();
+    }
+
     pub fn render(&mut self, scene: &Scene) {
-        let size = self.render_target.size();
+        self.render_surface(scene, SurfaceId(0))
+    }
+
+    pub fn render_surface(&mut self, scene: &Scene, surface: SurfaceId) {
+        self.render_frame(scene, surface, &scene.camera)
+    }
+
+    // draws scene from viewpoint instead of scene camera, e.g. Camera2D or a rig of camera_motion.
+    // reflections are still captured from scene camera, and views draw with their own cameras.
+    pub fn render_viewpoint(&mut self, scene: &Scene, viewpoint: &dyn Viewpoint) {
+        self.render_frame(scene, SurfaceId(0), viewpoint)
+    }
 
-        let mvp = Self::get_mvp(&scene.camera, size.0 as f32 / size.1 as f32);
-        self.mvp_buf.write(mvp.as_slice().as_bytes());
+    fn render_frame(&mut self, scene: &Scene, surface: SurfaceId, viewpoint: &dyn Viewpoint) {
+        self.frame_pacer.wait(&self.device);
+        if !self.surfaces[surface.0].render_target.acquire() {
+            scene.debug_lines.clear();
+            return;
+        }
+
+        let view_rect = Self::letterbox(self.surfaces[surface.0].render_target.size(), self.fixed_aspect);
+        let size = (view_rect.2, view_rect.3);
+
+        for model in &scene.models {
+            model.animate(scene.time);
+        }
+        let captured = self.render_reflections(scene);
+
+        // nearest probe lights pbr materials instead of skybox
+        let eye = viewpoint.eye();
+        let nearest = (0..self.reflection_probes.len()).min_by(|&a, &b| {
+            let distance = |i: usize| (self.reflection_probes[i].position() - eye).norm_squared();
+            distance(a).partial_cmp(&distance(b)).unwrap_or(core::cmp::Ordering::Equal)
+        });
+        let probe = nearest.map(|x| self.reflection_probes[x].texture());
+        let source = probe.as_ref().or(scene.lighting.skybox.as_ref());
+        let changed = nearest.map(|x| captured[x]).unwrap_or(false);
+        self.environment.update(&self.device, &self.staging_belt, source, changed);
+
+        let mut command_encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("frame") });
+        {
+            let mut context = ComputeContext {
+                command_encoder: &mut command_encoder,
+            };
+            self.compute_scheduler.run(&mut context, self.options.compute_estimate_budget_ms);
+        }
+        let input_index = if let Some(stereo) = &self.stereo {
+            self.render_stereo(&mut command_encoder, scene, viewpoint, stereo, size)
+        } else {
+            let viewport = (0.0, 0.0, size.0 as f32, size.1 as f32);
+            let jittered;
+            let viewpoint = match &self.taa {
+                Some(x) => {
+                    jittered = x.jitter(viewpoint, size);
+                    &jittered as &dyn Viewpoint
+                }
+                None => viewpoint,
+            };
+            self.render_eye(
+                scene,
+                viewpoint,
+                &self.targets.offscreen_target,
+                viewport,
+                true,
+                self.occlusion.as_ref(),
+                self.stats.as_ref(),
+            );
+
+            0
+        };
+        if !scene.views.is_empty() {
+            self.targets.view_target(&self.device, &self.memory);
+
+            // each view is copied in its own submission, so work recorded so far goes first
+            let new_encoder = self
+                .device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("frame") });
+            self.staging_belt
+                .submit(Some(core::mem::replace(&mut command_encoder, new_encoder).finish()));
+
+            self.render_views(scene, size, input_index);
+        }
+        let output_index = self.post_process(&mut command_encoder, viewpoint, size, input_index);
+        self.present(&mut command_encoder, surface, view_rect, output_index);
+
+        let mut recorder = if surface == SurfaceId(0) { self.recorder.take() } else { None };
+        let record_slot = recorder
+            .as_mut()
+            .and_then(|x| x.record(&self.device, &mut command_encoder, self.targets.color_texture(output_index), size));
+
+        self.staging_belt.submit(Some(command_encoder.finish()));
+        self.surfaces[surface.0].render_target.submit();
+        self.frame_pacer.submitted(&self.queue);
+        self.compute_scheduler.submitted(&self.queue);
+        self.compute_scheduler.poll(&self.device);
+
+        self.deletion_queue.submitted(&self.queue);
+        self.deletion_queue.collect(&self.device);
+
+        if let Some(mut recorder) = recorder {
+            if let Some(slot) = record_slot {
+                recorder.map(slot);
+            }
+            recorder.poll(&self.device);
+            self.recorder = Some(recorder);
+        }
+
+        scene.debug_lines.clear();
+    }
 
-        let mut command_encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
-        self.render_scene(&mut command_encoder, scene, self.render_target.size());
-        self.present(&mut command_encoder, &*self.render_target);
+    // returns id of the scene model under given window pixel, if any. Scene::handle gives typed handle of it.
+    // models are prepared for picking, so scene should be rendered again before presenting.
+    pub async fn pick(&self, scene: &Scene, x: u32, y: u32) -> Option<ModelId> {
+        self.pick_viewpoint(scene, &scene.camera, x, y).await
+    }
+
+    // same as pick, seen from viewpoint instead of scene camera as in render_viewpoint.
+    pub async fn pick_viewpoint(&self, scene: &Scene, viewpoint: &dyn Viewpoint, x: u32, y: u32) -> Option<ModelId> {
+        let (x, y) = self.window_to_view(x, y)?;
+        let view_rect = Self::letterbox(self.main_target().size(), self.fixed_aspect);
+
+        self.pick_in_view(scene, viewpoint, x, y, (view_rect.2, view_rect.3)).await
+    }
+
+    // same as pick, with camera of scene.views[view] for pixels inside its viewport, e.g. to click on a gizmo.
+    pub async fn pick_view(&self, scene: &Scene, view: usize, x: u32, y: u32) -> Option<ModelId> {
+        let (x, y) = self.window_to_view(x, y)?;
+        let view_rect = Self::letterbox(self.main_target().size(), self.fixed_aspect);
+        let view = scene.views.get(view)?;
 
-        self.queue.submit(Some(command_encoder.finish()));
-        self.render_target.submit();
+        let viewport = Self::view_viewport(view, (view_rect.2, view_rect.3))?;
+        let (x, y) = (x as f32 - viewport.0, y as f32 - viewport.1);
+        if x < 0.0 || y < 0.0 || x >= viewport.2 || y >= viewport.3 {
+            return None;
+        }
+
+        self.pick_in_view(scene, &view.camera, x as u32, y as u32, (viewport.2 as u32, viewport.3 as u32))
+            .await
     }
 
-    fn create_offscreen_target(
+    // x and y are pixel inside view of size
+    async fn pick_in_view(&self, scene: &Scene, viewpoint: &dyn Viewpoint, x: u32, y: u32, size: (u32, u32)) -> Option<ModelId> {
+        let view_projection = picking::pick_matrix(x, y, size) * Self::get_view_projection(viewpoint, size);
+        for model in &scene.models {
+            model.prepare(&view_projection);
+        }
+        self.uniform_arena.flush();
+
+        let id_target = Texture::with_device(&self.device, 1, 1, TextureFormat::R32Uint);
+        let depth_target = Texture::with_device(&self.device, 1, 1, TextureFormat::Depth32);
+
+        let mut command_encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("pick") });
+        {
+            let render_pass = command_encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                color_attachments: &[wgpu::RenderPassColorAttachment {
+                    view: &id_target.texture_view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                        store: true,
+                    },
+                }],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &depth_target.texture_view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.0),
+                        store: true,
+                    }),
+                    stencil_ops: None,
+                }),
+                label: Some("pick"),
+            });
+            let mut render_context = RenderContext::new(render_pass);
+
+            // zero is cleared value, so ids start from one
+            for (i, model) in scene.models.iter().enumerate() {
+                if model.is_visible() && model.layers().intersects(viewpoint.layers()) {
+                    render_context.debug_group(model.label(), |x| model.render_pick(x, i as u32 + 1));
+                }
+            }
+        }
+
+        let readback = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("pick readback"),
+            size: wgpu::COPY_BYTES_PER_ROW_ALIGNMENT as u64,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        command_encoder.copy_texture_to_buffer(
+            id_target.texture.as_image_copy(),
+            wgpu::ImageCopyBuffer {
+                buffer: &readback,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: core::num::NonZeroU32::new(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT),
+                    rows_per_image: None,
+                },
+            },
+            wgpu::Extent3d {
+                width: 1,
+                height: 1,
+                depth_or_array_layers: 1,
+            },
+        );
+        self.staging_belt.submit(Some(command_encoder.finish()));
+
+        let slice = readback.slice(..);
+        let map = slice.map_async(wgpu::MapMode::Read);
+        self.device.poll(wgpu::Maintain::Wait);
+        map.await.ok()?;
+
+        let data = slice.get_mapped_range();
+        let id = u32::from_le_bytes([data[0], data[1], data[2], data[3]]);
+
+        scene.id((id as usize).checked_sub(1)?)
+    }
+
+    // post processes run in insertion order, each reading the output of the previous one.
+    pub fn add_post_process<P: PostProcess + 'static>(&mut self, post_process: P) {
+        self.post_processes.push(Box::new(post_process));
+    }
+
+    pub(crate) fn main_target(&self) -> &dyn RenderTarget {
+        &*self.surfaces[0].render_target
+    }
+
+    fn create_surface_with_target(
         device: &wgpu::Device,
         buffer_pool: &BufferPool,
-        width: u32,
-        height: u32,
-        surface_format: wgpu::TextureFormat,
-    ) -> (OffscreenRenderTarget, Model) {
-        let texture_width = Self::round_up_power_of_two(width);
-        let texture_height = Self::round_up_power_of_two(height);
-        let offscreen_target = OffscreenRenderTarget::with_device(device, texture_width, texture_height);
+        render_target: Box<dyn RenderTarget>,
+        fixed_aspect: Option<f32>,
+        targets: &TargetPool,
+    ) -> Surface {
+        let view_rect = Self::letterbox(render_target.size(), fixed_aspect);
+        let present_models = Self::create_present_models(device, buffer_pool, &*render_target, (view_rect.2, view_rect.3), targets);
+
+        Surface {
+            render_target,
+            present_models,
+        }
+    }
+
+    fn create_present_models(
+        device: &wgpu::Device,
+        buffer_pool: &BufferPool,
+        render_target: &dyn RenderTarget,
+        view_size: (u32, u32),
+        targets: &TargetPool,
+    ) -> Vec<Model> {
+        [
+            &targets.offscreen_target.color_attachment,
+            &targets.post_process_targets[0],
+            &targets.post_process_targets[1],
+        ]
+        .iter()
+        .map(|&x| Self::create_present_model(device, buffer_pool, x.clone(), view_size, targets.size(), render_target.output_format()))
+        .collect()
+    }
+
+    // returns (x, y, width, height) of the largest rect with given aspect centered in size.
+    fn letterbox(size: (u32, u32), aspect: Option<f32>) -> (u32, u32, u32, u32) {
+        let aspect = match aspect {
+            Some(x) => x,
+            None => return (0, 0, size.0, size.1),
+        };
+
+        let width = ((size.1 as f32 * aspect).round() as u32).clamp(1, size.0);
+        let height = ((size.0 as f32 / aspect).round() as u32).clamp(1, size.1);
+        let (width, height) = if width < size.0 { (width, size.1) } else { (size.0, height) };
+
+        ((size.0 - width) / 2, (size.1 - height) / 2, width, height)
+    }
 
-        let right = width as f32 / texture_width as f32;
-        let bottom = height as f32 / texture_height as f32;
+    fn create_present_model(
+        device: &wgpu::Device,
+        buffer_pool: &BufferPool,
+        texture: Arc<Texture>,
+        size: (u32, u32),
+        texture_size: (u32, u32),
+        surface_format: wgpu::TextureFormat,
+    ) -> Model {
+        let right = size.0 as f32 / texture_size.0 as f32;
+        let bottom = size.1 as f32 / texture_size.1 as f32;
 
         #[rustfmt::skip]
         let quad = [
@@ -119,11 +910,18 @@ impl Renderer {
             ])],
         );
 
+        // offscreen targets hold linear color, so encode it unless surface does.
+        let fs_entry = if conventions::is_srgb(surface_format) {
+            "fs_main"
+        } else {
+            "fs_main_encode_srgb"
+        };
+
         let shader = Shader::with_device(
             device,
             include_str!("../shaders/shader.wgsl"),
             "vs_main",
-            "fs_main",
+            fs_entry,
             &[
                 ("Texture", ShaderBinding::new(ShaderStage::Fragment, 1, ShaderBindingType::Texture2D)),
                 ("Sampler", ShaderBinding::new(ShaderStage::Fragment, 2, ShaderBindingType::Sampler)),
@@ -131,95 +929,660 @@ impl Renderer {
             &[("Position", 0), ("TexCoord", 1)],
         );
 
-        let material = Material::with_device(
-            device,
-            None,
-            &[("Texture", offscreen_target.color_attachment.clone())],
-            &[],
-            Arc::new(shader),
-        );
+        let material = Material::with_device(device, None, None, &[("Texture", texture)], &[], Arc::new(shader));
+
+        Model::with_surface_and_depth_format(device, mesh, material, surface_format, None)
+    }
+
+    // captures reflection probes which are due and planar reflections, returns whether each probe was captured.
+    fn render_reflections(&mut self, scene: &Scene) -> Vec<bool> {
+        // faces are cleared even if camera keeps what's drawn before
+        let mut camera = scene.camera.clone();
+        let clear = camera.clear();
+        camera.set_clear(ClearConfig {
+            color: Some(clear.color.unwrap_or(Color::BLACK)),
+            depth: Some(clear.depth.unwrap_or(1.0)),
+        });
+
+        let due = self.reflection_probes.iter_mut().map(|x| x.due()).collect::<Vec<_>>();
+        for (probe, _) in self.reflection_probes.iter().zip(due.iter()).filter(|x| *x.1) {
+            let mut view_projections = Vec::with_capacity(6);
+            for (i, (face, viewport)) in probe.faces(&camera).enumerate() {
+                self.render_eye(scene, &face, probe.target(), viewport, i == 0, None, None);
+                view_projections.push(Self::get_view_projection(&face, (1, 1)));
+            }
+
+            self.probe_capture.convert(&self.device, &self.staging_belt, probe, &view_projections);
+        }
+
+        for reflection in &self.planar_reflections {
+            let target = reflection.target();
+            let (width, height) = target.size();
+            let mirrored = reflection.camera(&camera);
+
+            reflection.set_view_projection(&Self::get_view_projection(&mirrored, (width, height)));
+            self.render_eye(scene, &mirrored, target, (0.0, 0.0, width as f32, height as f32), true, None, None);
+        }
+
+        due
+    }
 
-        (
-            offscreen_target,
-            Model::with_surface_and_depth_format(device, mesh, material, surface_format, None),
-        )
+    // model buffers are written for each view, so each eye is submitted separately.
+    // occlusion and stats are only given for main view, their results are of single camera.
+    #[allow(clippy::too_many_arguments)]
+    fn render_eye(
+        &self,
+        scene: &Scene,
+        viewpoint: &dyn Viewpoint,
+        target: &OffscreenRenderTarget,
+        viewport: (f32, f32, f32, f32),
+        clear: bool,
+        occlusion: Option<&OcclusionCuller>,
+        stats: Option<&StatsQuery>,
+    ) {
+        let view_projection = Self::get_view_projection(viewpoint, (viewport.2 as u32, viewport.3 as u32));
+        let clear = if clear { Some(viewpoint.clear()) } else { None };
+        self.lighting_buf.write(scene.lighting.uniform(&viewpoint.eye()).as_bytes());
+        self.clustered_lights.prepare(&scene.lighting, viewpoint, viewport);
+        for model in &scene.models {
+            model.prepare(&view_projection);
+        }
+        self.uniform_arena.flush();
+
+        if let Some(occlusion) = occlusion {
+            occlusion.poll(&self.device);
+        }
+        let (opaque, transparent) = Self::sort_models(scene, viewpoint, occlusion);
+        let draws = self.instance_buffer.merge(&opaque);
+        self.instance_buffer.flush();
+        let stats = stats.filter(|x| x.poll(&self.device));
+
+        let mut command_encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("scene") });
+        {
+            let mut context = ComputeContext {
+                command_encoder: &mut command_encoder,
+            };
+            self.clustered_lights.dispatch(&mut context);
+            for model in opaque.iter().chain(transparent.iter()) {
+                model.dispatch(&mut context);
+            }
+        }
+        // depth is cleared by pre-pass when it runs
+        let clear = if scene.depth_prepass && !opaque.is_empty() {
+            let depth_attachment = match &self.deferred {
+                Some(deferred) => &deferred.depth.texture_view,
+                None => &target.depth_attachment.texture_view,
+            };
+            let query = stats.map(|x| (x, PREPASS_QUERY));
+            self.render_depth_prepass(
+                &mut command_encoder,
+                &opaque,
+                depth_attachment,
+                viewport,
+                clear.and_then(|x| x.depth),
+                query,
+            );
+
+            clear.map(|x| ClearConfig { depth: None, ..x })
+        } else {
+            clear
+        };
+
+        // opaque bins encoded in parallel, submitted in order between work recorded before and after them
+        let mut command_buffers = Vec::new();
+        let depth_attachment = if let Some(deferred) = &self.deferred {
+            deferred.prepare(&view_projection, viewpoint, viewport, &scene.lighting);
+
+            self.render_opaque(
+                &mut command_encoder,
+                &mut command_buffers,
+                &draws,
+                &deferred.color_attachments(),
+                &deferred.depth.texture_view,
+                viewport,
+                clear,
+                stats,
+            );
+            deferred.resolve(&self.device, &mut command_encoder, target.color_attachment(), viewport);
+
+            &deferred.depth.texture_view
+        } else {
+            self.render_opaque(
+                &mut command_encoder,
+                &mut command_buffers,
+                &draws,
+                &[target.color_attachment()],
+                &target.depth_attachment.texture_view,
+                viewport,
+                clear,
+                stats,
+            );
+
+            &target.depth_attachment.texture_view
+        };
+
+        if !transparent.is_empty() {
+            Self::render_scene(
+                &mut command_encoder,
+                "transparent",
+                &Draw::singles(&transparent),
+                MaterialPass::Main,
+                &[target.color_attachment()],
+                depth_attachment,
+                viewport,
+                None,
+                stats.map(|x| (x, TRANSPARENT_QUERY)),
+            );
+        }
+
+        let all = opaque.iter().chain(transparent.iter()).copied().collect::<Vec<_>>();
+        self.render_x_ray(&mut command_encoder, &all, target.color_attachment(), depth_attachment, viewport);
+
+        let debug_vertices = scene.debug_lines.vertices();
+        let debug_vertex_buf = if !debug_vertices.is_empty() {
+            let data = debug_vertices.as_bytes();
+            let buffer = self.buffer_pool.alloc(data.len());
+            buffer.write(data);
+
+            self.debug_renderer.render(
+                &mut command_encoder,
+                &buffer,
+                debug_vertices.len(),
+                &view_projection,
+                target.color_attachment(),
+                depth_attachment,
+                viewport,
+            );
+
+            Some(buffer)
+        } else {
+            None
+        };
+
+        for (name, texture) in &self.custom_passes {
+            Self::render_scene(
+                &mut command_encoder,
+                name,
+                &Draw::singles(&all),
+                MaterialPass::Custom(name),
+                &[&texture.texture_view],
+                depth_attachment,
+                viewport,
+                clear,
+                None,
+            );
+        }
+
+        // occluded models are tested too, so they come back once revealed
+        if let Some(occlusion) = occlusion {
+            let models = scene
+                .models_with_ids()
+                .filter(|(_, x)| x.is_visible() && x.layers().intersects(viewpoint.layers()))
+                .collect::<Vec<_>>();
+            occlusion.test(
+                &self.device,
+                &self.staging_belt,
+                &mut command_encoder,
+                depth_attachment,
+                (viewport.2 as u32, viewport.3 as u32),
+                &view_projection,
+                &models,
+            );
+        }
+
+        if let Some(stats) = stats {
+            stats.resolve(&mut command_encoder);
+        }
+
+        command_buffers.push(command_encoder.finish());
+        self.staging_belt.submit(command_buffers);
+        if let Some(occlusion) = occlusion {
+            occlusion.map();
+        }
+        if let Some(stats) = stats {
+            stats.map();
+        }
+
+        // pool may reuse the range once freed, so keep it until submitted
+        drop(debug_vertex_buf);
     }
 
-    fn render_scene(&self, command_encoder: &mut wgpu::CommandEncoder, scene: &Scene, viewport_size: (u32, u32)) {
+    // depth tested against completed scene, so only occluded parts are drawn. selection outlines are drawn here too.
+    fn render_x_ray(
+        &self,
+        command_encoder: &mut wgpu::CommandEncoder,
+        models: &[&dyn Renderable],
+        color_attachment: &wgpu::TextureView,
+        depth_attachment: &wgpu::TextureView,
+        viewport: (f32, f32, f32, f32),
+    ) {
         let mut render_pass = command_encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
             color_attachments: &[wgpu::RenderPassColorAttachment {
-                view: self.offscreen_target.color_attachment(),
+                view: color_attachment,
                 resolve_target: None,
                 ops: wgpu::Operations {
-                    load: wgpu::LoadOp::Clear(wgpu::Color { r: 1., g: 1., b: 1., a: 1. }),
+                    load: wgpu::LoadOp::Load,
                     store: true,
                 },
             }],
             depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
-                view: &self.offscreen_target.depth_attachment.texture_view,
+                view: depth_attachment,
                 depth_ops: Some(wgpu::Operations {
-                    load: wgpu::LoadOp::Clear(1.0),
+                    load: wgpu::LoadOp::Load,
                     store: true,
                 }),
                 stencil_ops: None,
             }),
-            label: None,
+            label: Some("x-ray"),
         });
-        render_pass.set_viewport(0.0, 0.0, viewport_size.0 as f32, viewport_size.1 as f32, 0.0, 1.0);
+        render_pass.set_viewport(viewport.0, viewport.1, viewport.2, viewport.3, 0.0, 1.0);
         let mut render_context = RenderContext::new(render_pass);
 
-        for model in &scene.models {
-            model.render(&mut render_context);
+        for model in models {
+            render_context.debug_group(model.label(), |x| model.render_x_ray(x));
+        }
+        for model in models {
+            render_context.debug_group(model.label(), |x| model.render_outline(x));
         }
     }
 
-    fn present(&self, command_encoder: &mut wgpu::CommandEncoder, target: &dyn RenderTarget) {
-        let render_pass = command_encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-            color_attachments: &[wgpu::RenderPassColorAttachment {
-                view: target.color_attachment(),
+    // opaque models are grouped by state to minimize binds, transparent ones are sorted back to front.
+    fn sort_models<'a>(
+        scene: &'a Scene,
+        viewpoint: &dyn Viewpoint,
+        occlusion: Option<&OcclusionCuller>,
+    ) -> (Vec<&'a dyn Renderable>, Vec<&'a dyn Renderable>) {
+        let (mut transparent, mut opaque): (Vec<&dyn Renderable>, Vec<&dyn Renderable>) = scene
+            .models_with_ids()
+            .filter(|(_, x)| x.is_visible() && x.layers().intersects(viewpoint.layers()))
+            .filter(|(id, _)| !occlusion.map(|occlusion| occlusion.is_occluded(*id)).unwrap_or(false))
+            .map(|(_, x)| x)
+            .partition(|x| x.is_transparent());
+
+        opaque.sort_by_key(|x| x.sort_key());
+
+        let eye = viewpoint.eye();
+        transparent.sort_by(|a, b| {
+            let a = (a.position() - eye).norm_squared();
+            let b = (b.position() - eye).norm_squared();
+            b.partial_cmp(&a).unwrap_or(core::cmp::Ordering::Equal)
+        });
+
+        (opaque, transparent)
+    }
+
+    fn render_views(&self, scene: &Scene, size: (u32, u32), output_index: usize) {
+        let target = self.targets.view_target.as_ref().unwrap();
+
+        let mut views = scene.views.iter().collect::<Vec<_>>();
+        views.sort_by_key(|x| x.priority);
+
+        for view in views {
+            let viewport = match Self::view_viewport(view, size) {
+                Some(x) => x,
+                None => continue,
+            };
+
+            // views not clearing color draw over what's composed so far
+            if view.camera.clear().color.is_none() {
+                let mut command_encoder = self
+                    .device
+                    .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("view") });
+                let mut context = PostProcessContext {
+                    device: &self.device,
+                    command_encoder: &mut command_encoder,
+                    input: self.targets.color_texture(output_index),
+                    output: &target.color_attachment.texture_view,
+                    viewport_size: size,
+                    depth: None,
+                    projection: Matrix4::identity(),
+                };
+                self.view_copy.draw_viewport(&mut context, viewport);
+
+                self.staging_belt.submit(Some(command_encoder.finish()));
+            }
+
+            self.render_eye(scene, &view.camera, target, viewport, true, None, None);
+
+            let mut command_encoder = self
+                .device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("view") });
+            let mut context = PostProcessContext {
+                device: &self.device,
+                command_encoder: &mut command_encoder,
+                input: &target.color_attachment,
+                output: &self.targets.color_texture(output_index).texture_view,
+                viewport_size: size,
+                depth: None,
+                projection: Matrix4::identity(),
+            };
+            self.view_copy.draw_viewport(&mut context, viewport);
+
+            self.staging_belt.submit(Some(command_encoder.finish()));
+        }
+    }
+
+    // pixel rect of view inside 3d view of size, none if it's empty
+    fn view_viewport(view: &CameraView, size: (u32, u32)) -> Option<(f32, f32, f32, f32)> {
+        let (width, height) = (size.0 as f32, size.1 as f32);
+
+        let left = (view.viewport.0 * width).round();
+        let top = (view.viewport.1 * height).round();
+        let right = ((view.viewport.0 + view.viewport.2) * width).round().min(width);
+        let bottom = ((view.viewport.1 + view.viewport.3) * height).round().min(height);
+        if right <= left || bottom <= top {
+            return None;
+        }
+
+        Some((left, top, right - left, bottom - top))
+    }
+
+    // returns index of the color texture which has the composed image
+    fn render_stereo(
+        &self,
+        command_encoder: &mut wgpu::CommandEncoder,
+        scene: &Scene,
+        viewpoint: &dyn Viewpoint,
+        stereo: &Stereo,
+        size: (u32, u32),
+    ) -> usize {
+        let left = Eye {
+            viewpoint,
+            offset: -stereo.eye_separation / 2.0,
+        };
+        let right = Eye {
+            viewpoint,
+            offset: stereo.eye_separation / 2.0,
+        };
+        let (width, height) = (size.0 as f32, size.1 as f32);
+
+        match stereo.mode {
+            StereoMode::SideBySide => {
+                self.render_eye(
+                    scene,
+                    &left,
+                    &self.targets.offscreen_target,
+                    (0.0, 0.0, width / 2.0, height),
+                    true,
+                    None,
+                    None,
+                );
+                self.render_eye(
+                    scene,
+                    &right,
+                    &self.targets.offscreen_target,
+                    (width / 2.0, 0.0, width / 2.0, height),
+                    false,
+                    None,
+                    None,
+                );
+
+                0
+            }
+            StereoMode::Anaglyph => {
+                let viewport = (0.0, 0.0, width, height);
+                self.render_eye(scene, &left, &self.targets.offscreen_target, viewport, true, None, None);
+                self.render_eye(scene, &right, stereo.right_target.as_ref().unwrap(), viewport, true, None, None);
+
+                let mut context = PostProcessContext {
+                    device: &self.device,
+                    command_encoder,
+                    input: &self.targets.offscreen_target.color_attachment,
+                    output: &self.targets.post_process_targets[0].texture_view,
+                    viewport_size: size,
+                    depth: None,
+                    projection: Matrix4::identity(),
+                };
+                stereo.compose.as_ref().unwrap().draw(&mut context);
+
+                1
+            }
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn render_scene(
+        command_encoder: &mut wgpu::CommandEncoder,
+        label: &str,
+        draws: &[Draw],
+        pass: MaterialPass,
+        color_attachments: &[&wgpu::TextureView],
+        depth_attachment: &wgpu::TextureView,
+        viewport: (f32, f32, f32, f32),
+        clear: Option<ClearConfig>,
+        query: Option<(&StatsQuery, usize)>,
+    ) {
+        // custom passes start transparent and only test against main pass depth
+        let (color_load, depth_load) = match (clear, &pass) {
+            (Some(clear), MaterialPass::Main) => (
+                clear.color.map(|x| wgpu::LoadOp::Clear(x.wgpu_type())).unwrap_or(wgpu::LoadOp::Load),
+                clear.depth.map(wgpu::LoadOp::Clear).unwrap_or(wgpu::LoadOp::Load),
+            ),
+            (Some(_), MaterialPass::Custom(_)) => (wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT), wgpu::LoadOp::Load),
+            (None, _) => (wgpu::LoadOp::Load, wgpu::LoadOp::Load),
+        };
+
+        let color_attachments = color_attachments
+            .iter()
+            .map(|&view| wgpu::RenderPassColorAttachment {
+                view,
                 resolve_target: None,
                 ops: wgpu::Operations {
-                    load: wgpu::LoadOp::Clear(wgpu::Color { r: 1., g: 1., b: 1., a: 1. }),
+                    load: color_load,
                     store: true,
                 },
-            }],
-            depth_stencil_attachment: None,
-            label: None,
+            })
+            .collect::<Vec<_>>();
+
+        let mut render_pass = command_encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            color_attachments: &color_attachments,
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: depth_attachment,
+                depth_ops: Some(wgpu::Operations {
+                    load: depth_load,
+                    store: true,
+                }),
+                stencil_ops: None,
+            }),
+            label: Some(label),
         });
+        if let Some((stats, query)) = query {
+            stats.begin(&mut render_pass, query);
+        }
+        let mut render_context = RenderContext::with_pass(render_pass, pass);
+        render_context.set_viewport(viewport.0, viewport.1, viewport.2, viewport.3, 0.0, 1.0);
+
+        for draw in draws {
+            draw.render(&mut render_context);
+        }
+
+        if query.is_some() {
+            render_context.render_pass.end_pipeline_statistics_query();
+        }
+    }
 
+    // opaque models are split into bins encoded on task runner, whose command buffers are appended to command_buffers
+    // after work recorded so far. encoded in place without a runner or with too few models.
+    #[allow(clippy::too_many_arguments)]
+    fn render_opaque(
+        &self,
+        command_encoder: &mut wgpu::CommandEncoder,
+        command_buffers: &mut Vec<wgpu::CommandBuffer>,
+        draws: &[Draw],
+        color_attachments: &[&wgpu::TextureView],
+        depth_attachment: &wgpu::TextureView,
+        viewport: (f32, f32, f32, f32),
+        clear: Option<ClearConfig>,
+        stats: Option<&StatsQuery>,
+    ) {
+        let bin_count = task_runner::bin_count(self.task_runner.as_deref(), draws.len());
+        let runner = match &self.task_runner {
+            Some(x) if bin_count > 1 => x,
+            _ => {
+                return Self::render_scene(
+                    command_encoder,
+                    "opaque",
+                    draws,
+                    MaterialPass::Main,
+                    color_attachments,
+                    depth_attachment,
+                    viewport,
+                    clear,
+                    stats.map(|x| (x, OPAQUE_QUERY)),
+                )
+            }
+        };
+
+        let new_encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("scene") });
+        command_buffers.push(core::mem::replace(command_encoder, new_encoder).finish());
+
+        let device = &*self.device;
+        let mut results = (0..bin_count).map(|_| None).collect::<Vec<_>>();
+        let tasks = draws
+            .chunks(draws.len().div_ceil(bin_count))
+            .zip(results.iter_mut())
+            .enumerate()
+            .map(|(i, (bin, result))| {
+                Box::new(move || {
+                    let mut command_encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("opaque bin") });
+                    // first bin clears, later ones draw over it
+                    let clear = if i == 0 { clear } else { None };
+                    Self::render_scene(
+                        &mut command_encoder,
+                        "opaque",
+                        bin,
+                        MaterialPass::Main,
+                        color_attachments,
+                        depth_attachment,
+                        viewport,
+                        clear,
+                        stats.map(|x| (x, OPAQUE_QUERY + i)),
+                    );
+
+                    *result = Some(command_encoder.finish());
+                }) as Task
+            })
+            .collect();
+        runner.run(tasks);
+
+        command_buffers.extend(results.into_iter().flatten());
+    }
+
+    // opaque models write depth without color, so main pass only shades visible fragments
+    fn render_depth_prepass(
+        &self,
+        command_encoder: &mut wgpu::CommandEncoder,
+        models: &[&dyn Renderable],
+        depth_attachment: &wgpu::TextureView,
+        viewport: (f32, f32, f32, f32),
+        clear_depth: Option<f32>,
+        query: Option<(&StatsQuery, usize)>,
+    ) {
+        let mut render_pass = command_encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            color_attachments: &[],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: depth_attachment,
+                depth_ops: Some(wgpu::Operations {
+                    load: clear_depth.map(wgpu::LoadOp::Clear).unwrap_or(wgpu::LoadOp::Load),
+                    store: true,
+                }),
+                stencil_ops: None,
+            }),
+            label: Some("depth prepass"),
+        });
+        if let Some((stats, query)) = query {
+            stats.begin(&mut render_pass, query);
+        }
         let mut render_context = RenderContext::new(render_pass);
+        render_context.set_viewport(viewport.0, viewport.1, viewport.2, viewport.3, 0.0, 1.0);
+
+        for model in models {
+            render_context.debug_group(model.label(), |x| model.render_depth(x));
+        }
 
-        self.offscreen_to_render_target_model.render(&mut render_context);
+        if query.is_some() {
+            render_context.render_pass.end_pipeline_statistics_query();
+        }
     }
 
-    fn get_mvp(camera: &Camera, aspect_ratio: f32) -> Matrix4<f32> {
-        use core::f32::consts::PI;
+    // returns index of the present model which has the final image
+    fn post_process(
+        &self,
+        command_encoder: &mut wgpu::CommandEncoder,
+        viewpoint: &dyn Viewpoint,
+        viewport_size: (u32, u32),
+        input_index: usize,
+    ) -> usize {
+        let mut input_index = input_index;
+        let depth = match &self.deferred {
+            Some(x) => &x.depth,
+            None => &self.targets.offscreen_target.depth_attachment,
+        };
+        let projection = conventions::correct_projection(viewpoint.projection_matrix(viewport_size));
 
-        // nalgebra's perspective uses [-1, 1] NDC z range, so convert it to [0, 1].
-        #[rustfmt::skip]
-        let correction = nalgebra::Matrix4::<f32>::new(
-            1.0, 0.0, 0.0, 0.0,
-            0.0, 1.0, 0.0, 0.0,
-            0.0, 0.0, 0.5, 0.5,
-            0.0, 0.0, 0.0, 1.0,
-        );
+        let post_processes = self
+            .taa
+            .iter()
+            .map(|x| x as &dyn PostProcess)
+            .chain(self.post_processes.iter().map(|x| &**x))
+            .chain(self.fxaa.iter().map(|x| x as &dyn PostProcess));
+        for post_process in post_processes {
+            let output_index = if input_index == 1 { 2 } else { 1 };
+
+            let mut context = PostProcessContext {
+                device: &self.device,
+                command_encoder,
+                input: self.targets.color_texture(input_index),
+                output: &self.targets.color_texture(output_index).texture_view,
+                viewport_size,
+                depth: Some(depth),
+                projection,
+            };
+            post_process.apply(&mut context);
 
-        let projection = nalgebra::Matrix4::new_perspective(aspect_ratio, 45.0 * PI / 180.0, 1.0, 10.0);
-        correction * projection * camera.view()
+            input_index = output_index;
+        }
+
+        input_index
     }
 
-    //returns zero if v is zero.
-    fn round_up_power_of_two(mut v: u32) -> u32 {
-        //from http://graphics.stanford.edu/~seander/bithacks.html#RoundUpPowerOf2 (public domain)
+    fn present(&self, command_encoder: &mut wgpu::CommandEncoder, surface: SurfaceId, view_rect: (u32, u32, u32, u32), output_index: usize) {
+        let target = &*self.surfaces[surface.0].render_target;
+        let size = target.size();
+        let render_pass = command_encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            color_attachments: &[wgpu::RenderPassColorAttachment {
+                view: target.color_attachment(),
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    // visible as letterbox bars
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: true,
+                },
+            }],
+            depth_stencil_attachment: None,
+            label: Some("present"),
+        });
+
+        let mut render_context = RenderContext::new(render_pass);
+        render_context.set_viewport(view_rect.0 as f32, view_rect.1 as f32, view_rect.2 as f32, view_rect.3 as f32, 0.0, 1.0);
+
+        self.surfaces[surface.0].present_models[output_index].render(&mut render_context);
 
-        v -= 1;
-        v |= v >> 1;
-        v |= v >> 2;
-        v |= v >> 4;
-        v |= v >> 8;
-        v |= v >> 16;
-        v += 1;
+        if surface != SurfaceId(0) {
+            return;
+        }
+        render_context.set_viewport(0.0, 0.0, size.0 as f32, size.1 as f32, 0.0, 1.0);
+        for overlay in self.overlays.iter().filter(|x| x.visible) {
+            overlay.prepare(target.size(), self.scale_factor);
+            overlay.render(&mut render_context);
+        }
+    }
 
-        v
+    fn get_view_projection(viewpoint: &dyn Viewpoint, viewport_size: (u32, u32)) -> Matrix4<f32> {
+        conventions::correct_projection(viewpoint.projection_matrix(viewport_size)) * viewpoint.view_matrix()
     }
 }
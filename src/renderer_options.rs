@@ -0,0 +1,300 @@
+use alloc::{format, string::String, vec::Vec};
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum RenderPath {
+    Forward,
+    // models write albedo, normal and material params to three targets, lighting is resolved in a separate pass.
+    // only pbr materials and ones of shaders with g-buffer entry point do, others are drawn forward after it.
+    Deferred,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum AntiAliasing {
+    None,
+    // post process blurring along detected edges, cheap but softens textures slightly
+    Fxaa,
+    // jitters projection each frame and accumulates frames reprojected by camera motion.
+    // also smooths shading aliasing, but fast moving objects may leave trails.
+    Taa,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Backend {
+    Vulkan,
+    Metal,
+    Dx12,
+    Dx11,
+    Gl,
+    BrowserWebGpu,
+}
+
+impl Backend {
+    pub(crate) fn wgpu_type(&self) -> wgpu::Backends {
+        match self {
+            Backend::Vulkan => wgpu::Backends::VULKAN,
+            Backend::Metal => wgpu::Backends::METAL,
+            Backend::Dx12 => wgpu::Backends::DX12,
+            Backend::Dx11 => wgpu::Backends::DX11,
+            Backend::Gl => wgpu::Backends::GL,
+            Backend::BrowserWebGpu => wgpu::Backends::BROWSER_WEBGPU,
+        }
+    }
+
+    pub(crate) fn from_wgpu(backend: wgpu::Backend) -> Option<Self> {
+        Some(match backend {
+            wgpu::Backend::Vulkan => Backend::Vulkan,
+            wgpu::Backend::Metal => Backend::Metal,
+            wgpu::Backend::Dx12 => Backend::Dx12,
+            wgpu::Backend::Dx11 => Backend::Dx11,
+            wgpu::Backend::Gl => Backend::Gl,
+            wgpu::Backend::BrowserWebGpu => Backend::BrowserWebGpu,
+            wgpu::Backend::Empty => return None,
+        })
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Backend::Vulkan => "vulkan",
+            Backend::Metal => "metal",
+            Backend::Dx12 => "dx12",
+            Backend::Dx11 => "dx11",
+            Backend::Gl => "gl",
+            Backend::BrowserWebGpu => "webgpu",
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum PowerPreference {
+    // usually integrated gpu on laptops
+    LowPower,
+    // usually discrete gpu on laptops
+    HighPerformance,
+}
+
+#[derive(Clone)]
+pub struct RendererOptions {
+    pub render_path: RenderPath,
+    // limit of ComputeJob::estimated_step_ms summed over steps run each frame, in milliseconds.
+    // estimates are reported by jobs and not measured, so actual gpu time may differ.
+    pub compute_estimate_budget_ms: f32,
+    // skips models occluded in earlier frames, tested against hierarchical depth on gpu.
+    // pays off in dense scenes, hidden models may show up a frame or two late when revealed.
+    pub occlusion_culling: bool,
+    pub anti_aliasing: AntiAliasing,
+    // frames cpu may submit before gpu finishes the oldest, render blocks beyond it.
+    // 1 gives lowest input latency, more keep gpu busy when frame times vary.
+    pub max_frames_in_flight: u32,
+    // none picks among vulkan, metal, dx12 and webgpu, whichever platform has.
+    pub backend: Option<Backend>,
+    pub power_preference: PowerPreference,
+    // picks first adapter whose name contains this, ignoring case, like one from Renderer::enumerate_adapters.
+    // falls back to power preference when none matches, or on web where adapters can't be enumerated.
+    pub adapter_name: Option<String>,
+    // runs in downlevel mode even on adapters capable of more, e.g. to preview how app looks on webgl.
+    pub downlevel: bool,
+}
+
+impl Default for RendererOptions {
+    fn default() -> Self {
+        Self {
+            render_path: RenderPath::Forward,
+            compute_estimate_budget_ms: 2.0,
+            occlusion_culling: false,
+            anti_aliasing: AntiAliasing::None,
+            max_frames_in_flight: 2,
+            backend: None,
+            power_preference: PowerPreference::LowPower,
+            adapter_name: None,
+            downlevel: false,
+        }
+    }
+}
+
+impl RendererOptions {
+    // key=value lines for a settings file, readable back with from_config.
+    pub fn to_config(&self) -> String {
+        let render_path = match self.render_path {
+            RenderPath::Forward => "forward",
+            RenderPath::Deferred => "deferred",
+        };
+
+        let anti_aliasing = match self.anti_aliasing {
+            AntiAliasing::None => "none",
+            AntiAliasing::Fxaa => "fxaa",
+            AntiAliasing::Taa => "taa",
+        };
+
+        let backend = self.backend.map(|x| x.name()).unwrap_or("primary");
+
+        let power_preference = match self.power_preference {
+            PowerPreference::LowPower => "low_power",
+            PowerPreference::HighPerformance => "high_performance",
+        };
+
+        format!(
+            "render_path={}\ncompute_estimate_budget_ms={}\nocclusion_culling={}\nanti_aliasing={}\nmax_frames_in_flight={}\nbackend={}\npower_preference={}\nadapter_name={}\ndownlevel={}\n",
+            render_path,
+            self.compute_estimate_budget_ms,
+            self.occlusion_culling,
+            anti_aliasing,
+            self.max_frames_in_flight,
+            backend,
+            power_preference,
+            escape(self.adapter_name.as_deref().unwrap_or("")),
+            self.downlevel
+        )
+    }
+
+    // missing, unknown or malformed entries keep their defaults, so older files still load.
+    pub fn from_config(config: &str) -> Self {
+        let mut result = Self::default();
+
+        for (key, value) in config_entries(config) {
+            result.apply_config_entry(key, &value);
+        }
+
+        result
+    }
+
+    // false for keys which aren't of renderer options
+    pub(crate) fn apply_config_entry(&mut self, key: &str, value: &str) -> bool {
+        match key {
+            "render_path" => match value {
+                "forward" => self.render_path = RenderPath::Forward,
+                "deferred" => self.render_path = RenderPath::Deferred,
+                _ => {}
+            },
+            // older files name it compute_budget_ms
+            "compute_estimate_budget_ms" | "compute_budget_ms" => {
+                if let Ok(x) = value.parse() {
+                    self.compute_estimate_budget_ms = x;
+                }
+            }
+            "occlusion_culling" => {
+                if let Ok(x) = value.parse() {
+                    self.occlusion_culling = x;
+                }
+            }
+            "anti_aliasing" => match value {
+                "none" => self.anti_aliasing = AntiAliasing::None,
+                "fxaa" => self.anti_aliasing = AntiAliasing::Fxaa,
+                "taa" => self.anti_aliasing = AntiAliasing::Taa,
+                _ => {}
+            },
+            "max_frames_in_flight" => {
+                if let Ok(x) = value.parse() {
+                    self.max_frames_in_flight = x;
+                }
+            }
+            "backend" => match value {
+                "primary" => self.backend = None,
+                "vulkan" => self.backend = Some(Backend::Vulkan),
+                "metal" => self.backend = Some(Backend::Metal),
+                "dx12" => self.backend = Some(Backend::Dx12),
+                "dx11" => self.backend = Some(Backend::Dx11),
+                "gl" => self.backend = Some(Backend::Gl),
+                "webgpu" => self.backend = Some(Backend::BrowserWebGpu),
+                _ => {}
+            },
+            "power_preference" => match value {
+                "low_power" => self.power_preference = PowerPreference::LowPower,
+                "high_performance" => self.power_preference = PowerPreference::HighPerformance,
+                _ => {}
+            },
+            "downlevel" => {
+                if let Ok(x) = value.parse() {
+                    self.downlevel = x;
+                }
+            }
+            "adapter_name" => self.adapter_name = Some(value.into()).filter(|x: &String| !x.is_empty()),
+            _ => return false,
+        }
+
+        true
+    }
+}
+
+// newlines in values would end their line early, so they're written as \n
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('\n', "\\n").replace('\r', "\\r")
+}
+
+fn unescape(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    let mut chars = value.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('n') => result.push('\n'),
+            Some('r') => result.push('\r'),
+            Some('\\') => result.push('\\'),
+            Some(x) => {
+                result.push('\\');
+                result.push(x);
+            }
+            None => result.push('\\'),
+        }
+    }
+
+    result
+}
+
+// key and unescaped value of each key=value line. values are kept as written, spaces included.
+pub(crate) fn config_entries(config: &str) -> Vec<(&str, String)> {
+    config
+        .lines()
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, value)| (key.trim(), unescape(value)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_round_trip() {
+        let options = RendererOptions {
+            render_path: RenderPath::Deferred,
+            compute_estimate_budget_ms: 0.5,
+            occlusion_culling: true,
+            anti_aliasing: AntiAliasing::Taa,
+            max_frames_in_flight: 3,
+            backend: Some(Backend::Vulkan),
+            power_preference: PowerPreference::HighPerformance,
+            adapter_name: Some(" GPU\nrender_path=forward \\n".into()),
+            downlevel: true,
+        };
+
+        let config = options.to_config();
+        let result = RendererOptions::from_config(&config);
+
+        assert_eq!(result.to_config(), config);
+        assert_eq!(result.adapter_name, options.adapter_name);
+        assert!(result.render_path == RenderPath::Deferred);
+    }
+
+    #[test]
+    fn test_config_defaults() {
+        let result = RendererOptions::from_config(" max_frames_in_flight =1\ncompute_budget_ms=3\nbackend=unknown\nunknown=1\nmalformed\n");
+
+        assert_eq!(result.max_frames_in_flight, 1);
+        assert_eq!(result.compute_estimate_budget_ms, 3.0);
+        assert_eq!(result.backend, None);
+        assert_eq!(
+            result.to_config(),
+            RendererOptions {
+                max_frames_in_flight: 1,
+                compute_estimate_budget_ms: 3.0,
+                ..Default::default()
+            }
+            .to_config()
+        );
+    }
+}
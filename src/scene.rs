@@ -1,18 +1,211 @@
 use alloc::{boxed::Box, vec::Vec};
+use core::{
+    any::Any,
+    marker::PhantomData,
+    sync::atomic::{AtomicU64, Ordering},
+};
 
-use crate::{Camera, Renderable};
+use nalgebra::{Matrix4, Point3};
+
+use crate::{debug_draw::DebugLines, Aabb, Camera, LightingEnvironment, Renderable};
+
+// Additional camera drawn over part of the main camera's view, e.g. for split screen or picture in picture.
+pub struct CameraView {
+    pub camera: Camera,
+    // x, y, width, height in fractions of the view size
+    pub viewport: (f32, f32, f32, f32),
+    // views are drawn from lowest to highest priority, after the main camera
+    pub priority: i32,
+}
+
+// ids are unique across scenes, so handles of other scenes refer to nothing
+static NEXT_MODEL_ID: AtomicU64 = AtomicU64::new(0);
+
+// Refers to a model added to a scene, stays valid while other models are added or removed.
+pub struct ModelHandle<T> {
+    id: u64,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> ModelHandle<T> {
+    // same as id of handles of other types to the model, e.g. to compare with pick results
+    pub fn id(&self) -> ModelId {
+        ModelId(self.id)
+    }
+}
+
+// Identifies a model without its type, for as long as its handles do. see Renderer::pick.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ModelId(u64);
+
+impl<T> Clone for ModelHandle<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for ModelHandle<T> {}
+
+impl<T> PartialEq for ModelHandle<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl<T> Eq for ModelHandle<T> {}
 
 pub struct Scene {
     pub camera: Camera,
-    pub models: Vec<Box<dyn Renderable>>,
+    pub views: Vec<CameraView>,
+    pub(crate) models: Vec<Box<dyn Renderable>>,
+    // handle id of each model
+    ids: Vec<u64>,
+    pub lighting: LightingEnvironment,
+    // seconds material animations are sampled at, see Material::animate
+    pub time: f32,
+    // draws depth of opaque models before shading them, see Renderable::render_depth.
+    // pays off when fragment shading is heavy and models overlap, compare with Renderer::stats.
+    pub depth_prepass: bool,
+    pub(crate) debug_lines: DebugLines,
 }
 
 impl Scene {
     pub fn new(camera: Camera) -> Self {
-        Self { camera, models: Vec::new() }
+        Self {
+            camera,
+            views: Vec::new(),
+            models: Vec::new(),
+            ids: Vec::new(),
+            lighting: LightingEnvironment::default(),
+            time: 0.0,
+            depth_prepass: false,
+            debug_lines: DebugLines::default(),
+        }
     }
 
-    pub fn add<F: Renderable + 'static>(&mut self, model: F) {
+    pub fn add<F: Renderable + 'static>(&mut self, model: F) -> ModelHandle<F> {
+        let id = NEXT_MODEL_ID.fetch_add(1, Ordering::Relaxed);
+
         self.models.push(Box::new(model));
+        self.ids.push(id);
+
+        ModelHandle { id, _marker: PhantomData }
+    }
+
+    pub fn get<F: Renderable>(&self, handle: ModelHandle<F>) -> Option<&F> {
+        let index = self.index(handle)?;
+
+        (&*self.models[index] as &dyn Any).downcast_ref()
+    }
+
+    pub fn get_mut<F: Renderable>(&mut self, handle: ModelHandle<F>) -> Option<&mut F> {
+        let index = self.index(handle)?;
+
+        (&mut *self.models[index] as &mut dyn Any).downcast_mut()
+    }
+
+    // later models move down one index, handles stay valid.
+    pub fn remove<F: Renderable>(&mut self, handle: ModelHandle<F>) -> Option<F> {
+        let index = self.index(handle)?;
+        if !(&*self.models[index] as &dyn Any).is::<F>() {
+            return None;
+        }
+        self.ids.remove(index);
+
+        let model: Box<dyn Any> = self.models.remove(index);
+        model.downcast().ok().map(|x| *x)
+    }
+
+    // models in draw submission order
+    pub fn models(&self) -> &[Box<dyn Renderable>] {
+        &self.models
+    }
+
+    // typed handle of model with id, if it's in this scene and of type F. e.g. for pick results.
+    pub fn handle<F: Renderable>(&self, id: ModelId) -> Option<ModelHandle<F>> {
+        let index = self.ids.iter().position(|&x| x == id.0)?;
+        let model: &dyn Any = &*self.models[index];
+
+        model.is::<F>().then_some(ModelHandle {
+            id: id.0,
+            _marker: PhantomData,
+        })
+    }
+
+    pub(crate) fn id(&self, index: usize) -> Option<ModelId> {
+        self.ids.get(index).map(|&x| ModelId(x))
+    }
+
+    // models with their handle ids, which unlike addresses aren't reused by later models
+    pub(crate) fn models_with_ids(&self) -> impl Iterator<Item = (u64, &dyn Renderable)> + '_ {
+        self.ids.iter().copied().zip(self.models.iter().map(|x| &**x))
+    }
+
+    fn index<F>(&self, handle: ModelHandle<F>) -> Option<usize> {
+        self.ids.iter().position(|&x| x == handle.id)
+    }
+
+    pub fn add_view(&mut self, camera: Camera, viewport: (f32, f32, f32, f32), priority: i32) {
+        self.views.push(CameraView { camera, viewport, priority });
+    }
+
+    pub fn set_lighting(&mut self, lighting: LightingEnvironment) {
+        self.lighting = lighting;
+    }
+
+    // debug shapes are drawn over the scene on next render only, so queue them every frame.
+    pub fn debug_line(&mut self, start: Point3<f32>, end: Point3<f32>, color: [f32; 3]) {
+        self.debug_lines.line(start, end, color);
+    }
+
+    pub fn debug_aabb(&mut self, aabb: &Aabb, color: [f32; 3]) {
+        self.debug_lines.aabb(aabb, color);
+    }
+
+    pub fn debug_sphere(&mut self, center: Point3<f32>, radius: f32, color: [f32; 3]) {
+        self.debug_lines.sphere(center, radius, color);
+    }
+
+    pub fn debug_axes(&mut self, transform: &Matrix4<f32>, size: f32) {
+        self.debug_lines.axes(transform, size);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RenderContext;
+
+    struct Dummy;
+    struct Other;
+
+    impl Renderable for Dummy {
+        fn render<'a>(&'a self, _render_context: &mut RenderContext<'a>) {}
+    }
+
+    impl Renderable for Other {
+        fn render<'a>(&'a self, _render_context: &mut RenderContext<'a>) {}
+    }
+
+    #[test]
+    fn test_remove_keeps_mismatched_models() {
+        let mut scene = Scene::new(Camera::new(Point3::new(0.0, 0.0, 5.0), Point3::origin()));
+        let mut other_scene = Scene::new(Camera::new(Point3::new(0.0, 0.0, 5.0), Point3::origin()));
+
+        let handle = scene.add(Dummy);
+        let foreign = other_scene.add(Dummy);
+        let wrong_type = ModelHandle::<Other> {
+            id: handle.id,
+            _marker: PhantomData,
+        };
+
+        assert!(scene.remove(foreign).is_none());
+        assert!(scene.remove(wrong_type).is_none());
+        assert_eq!(scene.models().len(), 1);
+
+        assert!(scene.handle::<Other>(handle.id()).is_none());
+        assert!(scene.handle::<Dummy>(handle.id()) == Some(handle));
+        assert!(scene.remove(handle).is_some());
+        assert!(scene.models().is_empty());
     }
 }
@@ -1,12 +1,25 @@
+use alloc::{borrow::Cow, format};
+
 use hashbrown::HashMap;
 
-use crate::Renderer;
+use crate::{clustered_lighting::ClusteredLights, Renderer, VertexFormat};
 
 #[derive(Clone)]
 pub enum ShaderBindingType {
     UniformBuffer,
+    StorageBuffer,
+    // storage buffer readable from vertex stage, like morph targets
+    ReadOnlyStorageBuffer,
     Texture2D,
+    DepthTexture2D,
+    // `texture_2d_array<f32>`, see Texture::with_layers
+    Texture2DArray,
+    // `texture_3d<f32>`, see Texture::with_texels_3d
+    Texture3D,
     Sampler,
+    // `var<push_constant>` of given size in bytes, only for Mvp. declared as a uniform at the binding number
+    // instead if device doesn't support push constants.
+    PushConstant(u32),
 }
 
 impl ShaderBindingType {
@@ -17,15 +30,41 @@ impl ShaderBindingType {
                 has_dynamic_offset: false,
                 min_binding_size: None,
             },
+            ShaderBindingType::StorageBuffer => wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only: false },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            ShaderBindingType::ReadOnlyStorageBuffer => wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only: true },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
             ShaderBindingType::Texture2D => wgpu::BindingType::Texture {
                 sample_type: wgpu::TextureSampleType::Float { filterable: true },
                 multisampled: false,
                 view_dimension: wgpu::TextureViewDimension::D2,
             },
+            ShaderBindingType::DepthTexture2D => wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Depth,
+                multisampled: false,
+                view_dimension: wgpu::TextureViewDimension::D2,
+            },
+            ShaderBindingType::Texture2DArray => wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                multisampled: false,
+                view_dimension: wgpu::TextureViewDimension::D2Array,
+            },
+            ShaderBindingType::Texture3D => wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                multisampled: false,
+                view_dimension: wgpu::TextureViewDimension::D3,
+            },
             ShaderBindingType::Sampler => wgpu::BindingType::Sampler {
                 comparison: false,
                 filtering: true,
             },
+            ShaderBindingType::PushConstant(_) => panic!("Push constants are not part of bind group"),
         }
     }
 }
@@ -34,6 +73,8 @@ impl ShaderBindingType {
 pub enum ShaderStage {
     Vertex,
     Fragment,
+    VertexFragment,
+    Compute,
 }
 
 impl ShaderStage {
@@ -41,6 +82,18 @@ impl ShaderStage {
         match self {
             ShaderStage::Vertex => wgpu::ShaderStages::VERTEX,
             ShaderStage::Fragment => wgpu::ShaderStages::FRAGMENT,
+            ShaderStage::VertexFragment => wgpu::ShaderStages::VERTEX_FRAGMENT,
+            ShaderStage::Compute => wgpu::ShaderStages::COMPUTE,
+        }
+    }
+
+    #[cfg(feature = "glsl")]
+    fn naga_type(&self) -> Option<naga::ShaderStage> {
+        match self {
+            ShaderStage::Vertex => Some(naga::ShaderStage::Vertex),
+            ShaderStage::Fragment => Some(naga::ShaderStage::Fragment),
+            ShaderStage::VertexFragment => None,
+            ShaderStage::Compute => Some(naga::ShaderStage::Compute),
         }
     }
 }
@@ -73,8 +126,16 @@ impl ShaderBinding {
 
 pub struct Shader {
     pub(crate) module: wgpu::ShaderModule,
+    // separate module of fragment stage, for sources compiled per stage
+    pub(crate) fragment_module: Option<wgpu::ShaderModule>,
     pub(crate) vs_entry: &'static str,
     pub(crate) fs_entry: &'static str,
+    // fragment entry point returning GBufferOutput of gbuffer.wgsl, for opaque models on deferred render path
+    pub(crate) gbuffer_entry: Option<&'static str>,
+    // vertex entry point taking Occlusion input, for meshes having that item
+    pub(crate) occlusion_entry: Option<&'static str>,
+    // vertex entry point taking Instance of instance.wgsl, for merged draws of same mesh and material
+    pub(crate) instanced_entry: Option<&'static str>,
     pub(crate) bindings: HashMap<&'static str, ShaderBinding>,
     pub(crate) inputs: HashMap<&'static str, u32>,
 }
@@ -88,9 +149,12 @@ impl Shader {
         bindings: &[(&'static str, ShaderBinding)],
         inputs: &[(&'static str, u32)],
     ) -> Self {
-        Self::with_device(&*renderer.device, source, vs_entry, fs_entry, bindings, inputs)
+        let source = renderer.shader_preprocessor.process(source);
+
+        Self::with_device(&renderer.device, &source, vs_entry, fs_entry, bindings, inputs).lowered(renderer)
     }
 
+    // source isn't preprocessed
     pub(crate) fn with_device(
         device: &wgpu::Device,
         source: &str,
@@ -99,21 +163,165 @@ impl Shader {
         bindings: &[(&'static str, ShaderBinding)],
         inputs: &[(&'static str, u32)],
     ) -> Self {
+        let mut source = Cow::Borrowed(source);
+        let mut bindings = bindings.iter().cloned().collect::<HashMap<_, _>>();
+
+        if !device.features().contains(wgpu::Features::PUSH_CONSTANTS) {
+            for binding in bindings.values_mut() {
+                if let ShaderBindingType::PushConstant(_) = binding.binding_type {
+                    let declaration = format!("[[group(0), binding({})]] var<uniform>", binding.binding);
+                    source = Cow::Owned(source.replace("var<push_constant>", &declaration));
+                    binding.binding_type = ShaderBindingType::UniformBuffer;
+                }
+            }
+        }
+
         let module = device.create_shader_module(&wgpu::ShaderModuleDescriptor {
             label: None,
-            source: wgpu::ShaderSource::Wgsl(source.into()),
+            source: wgpu::ShaderSource::Wgsl(source),
         });
 
         Self {
             module,
+            fragment_module: None,
             vs_entry,
             fs_entry,
+            gbuffer_entry: None,
+            occlusion_entry: None,
+            instanced_entry: None,
+            bindings,
+            inputs: inputs.iter().cloned().collect(),
+        }
+    }
+
+    // binary module which may contain both stages. push constant bindings aren't lowered to uniforms.
+    #[cfg(feature = "spirv")]
+    pub fn from_spirv(
+        renderer: &Renderer,
+        data: &[u8],
+        vs_entry: &'static str,
+        fs_entry: &'static str,
+        bindings: &[(&'static str, ShaderBinding)],
+        inputs: &[(&'static str, u32)],
+    ) -> Self {
+        let module = renderer.device.create_shader_module(&wgpu::ShaderModuleDescriptor {
+            label: None,
+            source: wgpu::util::make_spirv(data),
+        });
+
+        Self {
+            module,
+            fragment_module: None,
+            vs_entry,
+            fs_entry,
+            gbuffer_entry: None,
+            occlusion_entry: None,
+            instanced_entry: None,
             bindings: bindings.iter().cloned().collect(),
             inputs: inputs.iter().cloned().collect(),
         }
     }
 
+    // one source per stage, both vertex and fragment are required. entry points are named main.
+    // sources are translated to wgsl, so push constant bindings are lowered as in Shader::new.
+    #[cfg(feature = "glsl")]
+    pub fn from_glsl(
+        renderer: &Renderer,
+        sources: &[(ShaderStage, &str)],
+        bindings: &[(&'static str, ShaderBinding)],
+        inputs: &[(&'static str, u32)],
+    ) -> Self {
+        let translate = |stage: naga::ShaderStage| {
+            let source = sources
+                .iter()
+                .find(|(x, _)| x.naga_type() == Some(stage))
+                .unwrap_or_else(|| panic!("No glsl source for {:?} stage", stage))
+                .1;
+
+            let module = naga::front::glsl::Parser::default()
+                .parse(&naga::front::glsl::Options::from(stage), source)
+                .unwrap_or_else(|e| panic!("Failed to parse glsl: {:?}", e));
+            let info = naga::valid::Validator::new(naga::valid::ValidationFlags::all(), naga::valid::Capabilities::all())
+                .validate(&module)
+                .unwrap_or_else(|e| panic!("Invalid glsl: {:?}", e));
+
+            naga::back::wgsl::write_string(&module, &info).unwrap_or_else(|e| panic!("Failed to translate glsl: {:?}", e))
+        };
+
+        let vertex = Self::with_device(&renderer.device, &translate(naga::ShaderStage::Vertex), "main", "main", bindings, inputs);
+        let fragment = Self::with_device(
+            &renderer.device,
+            &translate(naga::ShaderStage::Fragment),
+            "main",
+            "main",
+            bindings,
+            inputs,
+        );
+
+        Self {
+            fragment_module: Some(fragment.module),
+            ..vertex
+        }
+    }
+
+    // models of materials without one are drawn forward after lighting is resolved on deferred render path.
+    pub fn with_gbuffer_entry(mut self, fs_entry: &'static str) -> Self {
+        self.gbuffer_entry = Some(fs_entry);
+
+        self
+    }
+
+    // meshes with Occlusion item, e.g. from bake_vertex_ao, are drawn with vs_entry instead of default one.
+    pub fn with_occlusion_entry(mut self, vs_entry: &'static str) -> Self {
+        self.occlusion_entry = Some(vs_entry);
+
+        self
+    }
+
+    // consecutive opaque models of same mesh and material are merged into one instanced draw with vs_entry,
+    // which reads transforms from Instance of instance.wgsl instead of Mvp.
+    pub fn with_instanced_entry(mut self, vs_entry: &'static str) -> Self {
+        self.instanced_entry = Some(vs_entry);
+
+        self
+    }
+
+    pub(crate) fn mesh_vs_entry(&self, vertex_formats: &[VertexFormat]) -> &'static str {
+        match self.occlusion_entry {
+            Some(x) if vertex_formats.iter().any(|format| format.has_item("Occlusion")) => x,
+            _ => self.vs_entry,
+        }
+    }
+
+    // bindings of clusters.wgsl differ in downlevel mode, and morph.wgsl has none as mesh is morphed on cpu
+    pub(crate) fn lowered(mut self, renderer: &Renderer) -> Self {
+        if renderer.downlevel {
+            ClusteredLights::lower_bindings(&mut self.bindings);
+            self.bindings.remove("MorphTargets");
+        }
+
+        self
+    }
+
+    pub(crate) fn fragment_module(&self) -> &wgpu::ShaderModule {
+        self.fragment_module.as_ref().unwrap_or(&self.module)
+    }
+
     pub(crate) fn wgpu_bindings(&self) -> impl Iterator<Item = wgpu::BindGroupLayoutEntry> + '_ {
-        self.bindings.iter().map(|(_, x)| x.wgpu_entry())
+        self.bindings
+            .iter()
+            .filter(|(_, x)| !matches!(x.binding_type, ShaderBindingType::PushConstant(_)))
+            .map(|(_, x)| x.wgpu_entry())
+    }
+
+    // stages and size of push constant binding, none if it was lowered to uniform buffer
+    pub(crate) fn push_constant_range(&self) -> Option<wgpu::PushConstantRange> {
+        self.bindings.values().find_map(|x| match x.binding_type {
+            ShaderBindingType::PushConstant(size) => Some(wgpu::PushConstantRange {
+                stages: x.stage.wgpu_type(),
+                range: 0..size,
+            }),
+            _ => None,
+        })
     }
 }
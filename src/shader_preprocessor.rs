@@ -0,0 +1,179 @@
+use alloc::{borrow::ToOwned, string::String, vec::Vec};
+
+use hashbrown::{HashMap, HashSet};
+
+// Expands `#include "name"` from registered sources and replaces `#define`d identifiers before WGSL is parsed.
+// Each file is included once per shader, so shared structs can be included from several files.
+// `#ifdef`, `#ifndef`, `#else` and `#endif` select lines by whether a name is defined.
+#[derive(Clone)]
+pub struct ShaderPreprocessor {
+    files: HashMap<String, String>,
+    defines: HashMap<String, String>,
+}
+
+impl Default for ShaderPreprocessor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ShaderPreprocessor {
+    // built-in files are registered under their names in shaders directory, like "lighting.wgsl".
+    pub fn new() -> Self {
+        let mut result = Self {
+            files: HashMap::new(),
+            defines: HashMap::new(),
+        };
+        result.add_file("alpha_cutoff.wgsl", include_str!("../shaders/alpha_cutoff.wgsl"));
+        result.add_file("clusters.wgsl", include_str!("../shaders/clusters.wgsl"));
+        result.add_file("gbuffer.wgsl", include_str!("../shaders/gbuffer.wgsl"));
+        result.add_file("instance.wgsl", include_str!("../shaders/instance.wgsl"));
+        result.add_file("lighting.wgsl", include_str!("../shaders/lighting.wgsl"));
+        result.add_file("lod_fade.wgsl", include_str!("../shaders/lod_fade.wgsl"));
+        result.add_file("morph.wgsl", include_str!("../shaders/morph.wgsl"));
+
+        result
+    }
+
+    // replaces built-in file of same name too.
+    pub fn add_file(&mut self, name: &str, source: &str) {
+        self.files.insert(name.to_owned(), source.to_owned());
+    }
+
+    // applies to every shader processed afterwards, `#define` in source overrides it.
+    pub fn define(&mut self, name: &str, value: &str) {
+        self.defines.insert(name.to_owned(), value.to_owned());
+    }
+
+    pub fn process(&self, source: &str) -> String {
+        let mut state = State {
+            defines: self.defines.clone(),
+            included: HashSet::new(),
+            conditions: Vec::new(),
+        };
+        let mut result = String::with_capacity(source.len());
+
+        self.process_source(source, &mut state, &mut result);
+        if !state.conditions.is_empty() {
+            panic!("#ifdef without #endif");
+        }
+
+        result
+    }
+
+    fn process_source(&self, source: &str, state: &mut State, result: &mut String) {
+        for line in source.lines() {
+            let trimmed = line.trim_start();
+            let active = state.conditions.iter().all(|x| *x);
+
+            if let Some(rest) = trimmed.strip_prefix("#ifdef") {
+                state.conditions.push(state.defines.contains_key(rest.trim()));
+            } else if let Some(rest) = trimmed.strip_prefix("#ifndef") {
+                state.conditions.push(!state.defines.contains_key(rest.trim()));
+            } else if trimmed.starts_with("#else") {
+                let condition = state.conditions.last_mut().expect("#else without #ifdef");
+                *condition = !*condition;
+            } else if trimmed.starts_with("#endif") {
+                state.conditions.pop().expect("#endif without #ifdef");
+            } else if !active {
+                continue;
+            } else if let Some(rest) = trimmed.strip_prefix("#include") {
+                let name = rest.trim().trim_matches('"');
+                if state.included.insert(name.to_owned()) {
+                    let file = self.files.get(name).unwrap_or_else(|| panic!("No such shader file named {}", name));
+                    self.process_source(file, state, result);
+                }
+            } else if let Some(rest) = trimmed.strip_prefix("#define") {
+                let mut parts = rest.trim().splitn(2, char::is_whitespace);
+                let name = parts.next().filter(|x| !x.is_empty()).expect("#define without name");
+                let value = parts.next().unwrap_or("").trim();
+                state.defines.insert(name.to_owned(), value.to_owned());
+            } else {
+                Self::replace_identifiers(line, &state.defines, result);
+                result.push('\n');
+            }
+        }
+    }
+
+    // comments are copied as is
+    fn replace_identifiers(line: &str, defines: &HashMap<String, String>, result: &mut String) {
+        let (code, comment) = match line.find("//") {
+            Some(x) => line.split_at(x),
+            None => (line, ""),
+        };
+
+        let mut rest = code;
+        while let Some(start) = rest.find(|c: char| c == '_' || c.is_alphanumeric()) {
+            result.push_str(&rest[..start]);
+            rest = &rest[start..];
+
+            let end = rest.find(|c: char| c != '_' && !c.is_alphanumeric()).unwrap_or(rest.len());
+            let word = &rest[..end];
+            // words starting with digit are numbers like 1u
+            match defines.get(word) {
+                Some(x) if !word.starts_with(|c: char| c.is_ascii_digit()) => result.push_str(x),
+                _ => result.push_str(word),
+            }
+            rest = &rest[end..];
+        }
+        result.push_str(rest);
+        result.push_str(comment);
+    }
+}
+
+struct State {
+    defines: HashMap<String, String>,
+    included: HashSet<String>,
+    // whether lines are taken in each nested #ifdef
+    conditions: Vec<bool>,
+}
+
+// used by shaders built into the renderer, which have no user files or defines
+pub(crate) fn process_builtin(source: &str) -> String {
+    ShaderPreprocessor::new().process(source)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nested_ifdef() {
+        let mut preprocessor = ShaderPreprocessor::new();
+        preprocessor.define("OUTER", "");
+
+        let source = "#ifdef OUTER\na\n#ifdef INNER\nb\n#else\nc\n#endif\nd\n#else\ne\n#ifdef INNER\nf\n#else\ng\n#endif\n#endif\nh\n";
+
+        assert_eq!(preprocessor.process(source), "a\nc\nd\nh\n");
+    }
+
+    #[test]
+    fn test_ifndef_in_excluded_branch() {
+        let preprocessor = ShaderPreprocessor::new();
+
+        let source = "#ifdef OUTER\n#ifndef INNER\na\n#endif\n#else\n#ifndef INNER\nb\n#endif\n#endif\n";
+
+        assert_eq!(preprocessor.process(source), "b\n");
+    }
+
+    #[test]
+    fn test_replace_identifiers() {
+        let mut preprocessor = ShaderPreprocessor::new();
+        preprocessor.define("COUNT", "4u");
+
+        let source = "let x = COUNT + COUNT_MAX + 1u;// COUNT\nCOUNT//COUNT\n";
+
+        assert_eq!(preprocessor.process(source), "let x = 4u + COUNT_MAX + 1u;// COUNT\n4u//COUNT\n");
+    }
+
+    #[test]
+    fn test_define_in_source() {
+        let mut preprocessor = ShaderPreprocessor::new();
+        preprocessor.define("BINDING", "1");
+        preprocessor.add_file("a.wgsl", "binding(BINDING)\n");
+
+        let source = "#define BINDING 2\n#include \"a.wgsl\"\n#include \"a.wgsl\"\n";
+
+        assert_eq!(preprocessor.process(source), "binding(2)\n");
+    }
+}
@@ -0,0 +1,88 @@
+use alloc::{string::String, sync::Arc, vec::Vec};
+
+use hashbrown::HashMap;
+use spinning_top::Spinlock;
+
+use crate::{Renderer, Shader, ShaderBinding};
+
+struct Feature {
+    name: &'static str,
+    bindings: Vec<(&'static str, ShaderBinding)>,
+    inputs: Vec<(&'static str, u32)>,
+}
+
+// One logical shader compiled once per set of enabled features, like SKINNED or NORMAL_MAP.
+// Each feature is defined as `true` for the preprocessor, so source can test it with #ifdef.
+pub struct ShaderVariants {
+    source: String,
+    vs_entry: &'static str,
+    fs_entry: &'static str,
+    bindings: Vec<(&'static str, ShaderBinding)>,
+    inputs: Vec<(&'static str, u32)>,
+    features: Vec<Feature>,
+    // sorted feature names to compiled shader
+    shaders: Spinlock<HashMap<Vec<&'static str>, Arc<Shader>>>,
+}
+
+impl ShaderVariants {
+    // bindings and inputs are used by every variant.
+    pub fn new(
+        source: &str,
+        vs_entry: &'static str,
+        fs_entry: &'static str,
+        bindings: &[(&'static str, ShaderBinding)],
+        inputs: &[(&'static str, u32)],
+    ) -> Self {
+        Self {
+            source: source.into(),
+            vs_entry,
+            fs_entry,
+            bindings: bindings.to_vec(),
+            inputs: inputs.to_vec(),
+            features: Vec::new(),
+            shaders: Spinlock::new(HashMap::new()),
+        }
+    }
+
+    // bindings and inputs only present in variants with the feature enabled.
+    // features without any don't need to be added.
+    pub fn add_feature(&mut self, name: &'static str, bindings: &[(&'static str, ShaderBinding)], inputs: &[(&'static str, u32)]) {
+        self.features.push(Feature {
+            name,
+            bindings: bindings.to_vec(),
+            inputs: inputs.to_vec(),
+        });
+    }
+
+    // compiles variant on first use, later calls with same features in any order share it.
+    pub fn shader(&self, renderer: &Renderer, features: &[&'static str]) -> Arc<Shader> {
+        let mut key = features.to_vec();
+        key.sort_unstable();
+        key.dedup();
+
+        self.shaders
+            .lock()
+            .entry(key)
+            .or_insert_with_key(|key| Arc::new(self.compile(renderer, key)))
+            .clone()
+    }
+
+    fn compile(&self, renderer: &Renderer, features: &[&'static str]) -> Shader {
+        let mut preprocessor = renderer.shader_preprocessor.clone();
+        let mut bindings = self.bindings.clone();
+        let mut inputs = self.inputs.clone();
+
+        for &name in features {
+            preprocessor.define(name, "true");
+
+            if let Some(feature) = self.features.iter().find(|x| x.name == name) {
+                bindings.extend(feature.bindings.iter().cloned());
+                inputs.extend(feature.inputs.iter().cloned());
+            }
+        }
+
+        let source = preprocessor.process(&self.source);
+
+        Shader::with_device(&renderer.device, &source, self.vs_entry, self.fs_entry, &bindings, &inputs).lowered(renderer)
+    }
+}
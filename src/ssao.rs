@@ -0,0 +1,159 @@
+use alloc::sync::Arc;
+use core::convert::TryInto;
+
+use nalgebra::Matrix4;
+use spinning_top::Spinlock;
+use zerocopy::AsBytes;
+
+use crate::{
+    bake::hemisphere_directions, constants::INTERNAL_COLOR_ATTACHMENT_FORMAT, memory::MemoryTracker, Buffer, FullscreenPass, PostProcess,
+    PostProcessContext, Renderer, ShaderBinding, ShaderBindingType, ShaderStage, Texture,
+};
+
+const MAX_KERNEL_SIZE: usize = 32;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SsaoQuality {
+    Low,
+    Medium,
+    High,
+}
+
+impl SsaoQuality {
+    fn sample_count(&self) -> usize {
+        match self {
+            SsaoQuality::Low => 8,
+            SsaoQuality::Medium => 16,
+            SsaoQuality::High => MAX_KERNEL_SIZE,
+        }
+    }
+
+    // half size of blurred square, 2 covers whole kernel rotation pattern
+    fn blur_radius(&self) -> i32 {
+        match self {
+            SsaoQuality::Low => 1,
+            SsaoQuality::Medium | SsaoQuality::High => 2,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(AsBytes)]
+struct SsaoUniform {
+    projection: [f32; 16],
+    inverse_projection: [f32; 16],
+    kernel: [[f32; 4]; MAX_KERNEL_SIZE],
+    viewport: [f32; 2],
+    radius: f32,
+    intensity: f32,
+    sample_count: u32,
+    blur_radius: i32,
+    _padding: [u32; 2],
+}
+
+// Screen space ambient occlusion of main camera, darkening creases and contact areas.
+// Positions and normals are reconstructed from depth, so it works with either render path.
+pub struct Ssao {
+    occlusion: FullscreenPass,
+    blur: FullscreenPass,
+    uniform_buf: Arc<Buffer>,
+    quality: SsaoQuality,
+    radius: f32,
+    intensity: f32,
+    kernel: [[f32; 4]; MAX_KERNEL_SIZE],
+    memory: MemoryTracker,
+    // unblurred occlusion, resized with viewport
+    target: Spinlock<Option<((u32, u32), Texture)>>,
+}
+
+impl Ssao {
+    // radius is sampled distance in world units, intensity of 1 darkens fully occluded pixels to black
+    pub fn new(renderer: &Renderer, quality: SsaoQuality, radius: f32, intensity: f32) -> Self {
+        let uniform_buf = Arc::new(renderer.buffer_pool.alloc(core::mem::size_of::<SsaoUniform>()));
+        let ssao_binding = ("Ssao", ShaderBinding::new(ShaderStage::Fragment, 3, ShaderBindingType::UniformBuffer));
+        let depth_binding = ("Depth", ShaderBinding::new(ShaderStage::Fragment, 4, ShaderBindingType::DepthTexture2D));
+        let occlusion_binding = ("Occlusion", ShaderBinding::new(ShaderStage::Fragment, 5, ShaderBindingType::Texture2D));
+        let create_pass = |fs_entry, bindings: &[(&'static str, ShaderBinding)]| {
+            FullscreenPass::new(
+                renderer,
+                include_str!("../shaders/ssao.wgsl"),
+                fs_entry,
+                bindings,
+                &[],
+                &[("Ssao", uniform_buf.clone())],
+            )
+        };
+
+        // samples get denser close to the center, where occluders matter more
+        let mut kernel = [[0.0; 4]; MAX_KERNEL_SIZE];
+        let count = quality.sample_count();
+        for (i, (sample, direction)) in kernel.iter_mut().zip(hemisphere_directions(count)).enumerate() {
+            let t = i as f32 / count as f32;
+            let scale = 0.1 + 0.9 * t * t;
+
+            *sample = [direction.x * scale, direction.y * scale, direction.z * scale, 0.0];
+        }
+
+        Self {
+            occlusion: create_pass("fs_occlusion", &[ssao_binding.clone(), depth_binding]),
+            blur: create_pass("fs_blur", &[ssao_binding, occlusion_binding]),
+            uniform_buf,
+            quality,
+            radius,
+            intensity,
+            kernel,
+            memory: renderer.memory.clone(),
+            target: Spinlock::new(None),
+        }
+    }
+}
+
+impl PostProcess for Ssao {
+    fn apply(&self, context: &mut PostProcessContext) {
+        let depth = context.depth.expect("Ssao needs scene depth, add it with Renderer::add_post_process");
+
+        let inverse_projection = context.projection.try_inverse().unwrap_or_else(Matrix4::identity);
+        let uniform = SsaoUniform {
+            projection: context.projection.as_slice().try_into().unwrap(),
+            inverse_projection: inverse_projection.as_slice().try_into().unwrap(),
+            kernel: self.kernel,
+            viewport: [context.viewport_size.0 as f32, context.viewport_size.1 as f32],
+            radius: self.radius,
+            intensity: self.intensity,
+            sample_count: self.quality.sample_count() as u32,
+            blur_radius: self.quality.blur_radius(),
+            _padding: [0; 2],
+        };
+        self.uniform_buf.write(uniform.as_bytes());
+
+        let mut target = self.target.lock();
+        if target.as_ref().map(|x| x.0 != context.viewport_size).unwrap_or(true) {
+            let (width, height) = context.viewport_size;
+            *target = Some((
+                context.viewport_size,
+                Texture::with_tracker(
+                    context.device,
+                    &self.memory,
+                    "post process",
+                    width,
+                    height,
+                    INTERNAL_COLOR_ATTACHMENT_FORMAT,
+                ),
+            ));
+        }
+        let occlusion = &target.as_ref().unwrap().1;
+
+        let viewport = (0.0, 0.0, context.viewport_size.0 as f32, context.viewport_size.1 as f32);
+        let mut occlusion_context = PostProcessContext {
+            device: context.device,
+            command_encoder: context.command_encoder,
+            input: context.input,
+            output: &occlusion.texture_view,
+            viewport_size: context.viewport_size,
+            depth: context.depth,
+            projection: context.projection,
+        };
+        self.occlusion.draw_with_textures(&mut occlusion_context, viewport, &[("Depth", depth)]);
+        self.blur.draw_with_textures(context, viewport, &[("Occlusion", occlusion)]);
+    }
+}
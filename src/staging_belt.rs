@@ -0,0 +1,274 @@
+use alloc::{boxed::Box, sync::Arc, vec::Vec};
+use core::{future::Future, pin::Pin};
+
+use futures::FutureExt;
+use spinning_top::Spinlock;
+
+use crate::{
+    memory::{Allocation, MemoryTracker},
+    TextureFormat,
+};
+
+type MapFuture = Pin<Box<dyn Future<Output = Result<(), wgpu::BufferAsyncError>> + Send>>;
+
+// larger uploads get a chunk of their own size
+const CHUNK_SIZE: u64 = 1048576;
+// offsets of copies into textures must be multiples of texel block size
+const ALIGNMENT: u64 = 16;
+
+enum ChunkState {
+    // next free offset
+    Mapped(u64),
+    // in flight, mapped again once gpu is done copying from it
+    Recalling(MapFuture),
+}
+
+struct Chunk {
+    buffer: Arc<wgpu::Buffer>,
+    size: u64,
+    state: ChunkState,
+    _allocation: Allocation,
+}
+
+enum Copy {
+    Buffer {
+        source: Arc<wgpu::Buffer>,
+        source_offset: u64,
+        target: Arc<wgpu::Buffer>,
+        offset: u64,
+        size: u64,
+    },
+    Texture {
+        source: Arc<wgpu::Buffer>,
+        source_offset: u64,
+        bytes_per_row: u32,
+        rows_per_image: u32,
+        target: Arc<wgpu::Texture>,
+        origin: wgpu::Origin3d,
+        extent: wgpu::Extent3d,
+    },
+}
+
+#[derive(Default)]
+struct State {
+    chunks: Vec<Chunk>,
+    // recorded into a command buffer submitted ahead of the next submission
+    copies: Vec<Copy>,
+}
+
+// Uploads written into a ring of mapped staging chunks, copied to their targets before the next submission.
+// Chunks are recalled without stalling once gpu is done with them, so steady uploads don't allocate.
+// Every submission must go through submit, so uploads are ordered as with queue.write_buffer.
+pub(crate) struct StagingBelt {
+    device: Arc<wgpu::Device>,
+    queue: Arc<wgpu::Queue>,
+    memory: MemoryTracker,
+    state: Spinlock<State>,
+}
+
+impl StagingBelt {
+    pub(crate) fn new(device: Arc<wgpu::Device>, queue: Arc<wgpu::Queue>, memory: MemoryTracker) -> Self {
+        Self {
+            device,
+            queue,
+            memory,
+            state: Spinlock::new(State::default()),
+        }
+    }
+
+    // size of data must be a multiple of 4
+    pub(crate) fn write_buffer(&self, target: &Arc<wgpu::Buffer>, offset: u64, data: &[u8]) {
+        if data.is_empty() {
+            return;
+        }
+
+        let mut state = self.state.lock();
+        let (source, source_offset) = self.allocate(&mut state, data.len() as u64);
+        source
+            .slice(source_offset..source_offset + data.len() as u64)
+            .get_mapped_range_mut()
+            .copy_from_slice(data);
+
+        state.copies.push(Copy::Buffer {
+            source,
+            source_offset,
+            target: target.clone(),
+            offset,
+            size: data.len() as u64,
+        });
+    }
+
+    // data is tightly packed rows of texels, or of blocks for compressed formats, slice by slice.
+    pub(crate) fn write_texture(
+        &self,
+        target: &Arc<wgpu::Texture>,
+        format: TextureFormat,
+        origin: wgpu::Origin3d,
+        extent: wgpu::Extent3d,
+        data: &[u8],
+    ) {
+        let block_height = format.wgpu_type().describe().block_dimensions.1 as u32;
+        let row_pitch = format.row_pitch(extent.width) as usize;
+        let rows = extent.height.div_ceil(block_height);
+        // rows are padded to copy alignment in staging
+        let bytes_per_row = (row_pitch as u32).div_ceil(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT) * wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+        let size = bytes_per_row as u64 * rows as u64 * extent.depth_or_array_layers as u64;
+        if size == 0 {
+            return;
+        }
+
+        let mut state = self.state.lock();
+        let (source, source_offset) = self.allocate(&mut state, size);
+        {
+            let mut mapped = source.slice(source_offset..source_offset + size).get_mapped_range_mut();
+            for (row, texels) in mapped.chunks_mut(bytes_per_row as usize).zip(data.chunks(row_pitch)) {
+                row[..texels.len()].copy_from_slice(texels);
+            }
+        }
+
+        state.copies.push(Copy::Texture {
+            source,
+            source_offset,
+            bytes_per_row,
+            rows_per_image: rows * block_height,
+            target: target.clone(),
+            origin,
+            extent,
+        });
+    }
+
+    // submits pending uploads followed by command buffers, like queue.submit.
+    pub(crate) fn submit<I: IntoIterator<Item = wgpu::CommandBuffer>>(&self, command_buffers: I) {
+        let mut state = self.state.lock();
+        if state.copies.is_empty() {
+            self.queue.submit(command_buffers);
+            return;
+        }
+
+        let mut command_encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("staging belt") });
+        for copy in state.copies.drain(..) {
+            match copy {
+                Copy::Buffer {
+                    source,
+                    source_offset,
+                    target,
+                    offset,
+                    size,
+                } => command_encoder.copy_buffer_to_buffer(&source, source_offset, &target, offset, size),
+                Copy::Texture {
+                    source,
+                    source_offset,
+                    bytes_per_row,
+                    rows_per_image,
+                    target,
+                    origin,
+                    extent,
+                } => command_encoder.copy_buffer_to_texture(
+                    wgpu::ImageCopyBuffer {
+                        buffer: &source,
+                        layout: wgpu::ImageDataLayout {
+                            offset: source_offset,
+                            bytes_per_row: core::num::NonZeroU32::new(bytes_per_row),
+                            rows_per_image: core::num::NonZeroU32::new(rows_per_image),
+                        },
+                    },
+                    wgpu::ImageCopyTexture {
+                        texture: &target,
+                        mip_level: 0,
+                        origin,
+                        aspect: wgpu::TextureAspect::All,
+                    },
+                    extent,
+                ),
+            }
+        }
+
+        // written chunks can't stay mapped while gpu copies from them
+        let written = state
+            .chunks
+            .iter()
+            .enumerate()
+            .filter(|(_, x)| matches!(x.state, ChunkState::Mapped(offset) if offset > 0))
+            .map(|(i, _)| i)
+            .collect::<Vec<_>>();
+        for &i in &written {
+            state.chunks[i].buffer.unmap();
+        }
+
+        self.queue.submit(Some(command_encoder.finish()).into_iter().chain(command_buffers));
+
+        for i in written {
+            let buffer = state.chunks[i].buffer.clone();
+            let map = async move { buffer.slice(..).map_async(wgpu::MapMode::Write).await };
+            state.chunks[i].state = ChunkState::Recalling(Box::pin(map));
+        }
+        // starts mapping right away
+        Self::recall(&mut state);
+    }
+
+    // drops chunks not in flight, which are allocated again on demand
+    pub(crate) fn trim(&self) {
+        let mut state = self.state.lock();
+
+        self.device.poll(wgpu::Maintain::Poll);
+        Self::recall(&mut state);
+        state.chunks.retain(|x| !matches!(x.state, ChunkState::Mapped(0)));
+    }
+
+    fn allocate(&self, state: &mut State, size: u64) -> (Arc<wgpu::Buffer>, u64) {
+        let fits = |chunk: &Chunk| matches!(chunk.state, ChunkState::Mapped(offset) if offset + size <= chunk.size);
+
+        if !state.chunks.iter().any(fits) && state.chunks.iter().any(|x| matches!(x.state, ChunkState::Recalling(_))) {
+            self.device.poll(wgpu::Maintain::Poll);
+            Self::recall(state);
+        }
+
+        let index = match state.chunks.iter().position(fits) {
+            Some(x) => x,
+            None => {
+                let chunk_size = size.max(CHUNK_SIZE).div_ceil(ALIGNMENT) * ALIGNMENT;
+                let buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+                    label: Some("staging belt"),
+                    size: chunk_size,
+                    usage: wgpu::BufferUsages::MAP_WRITE | wgpu::BufferUsages::COPY_SRC,
+                    mapped_at_creation: true,
+                });
+                state.chunks.push(Chunk {
+                    buffer: Arc::new(buffer),
+                    size: chunk_size,
+                    state: ChunkState::Mapped(0),
+                    _allocation: self.memory.track("staging belt", chunk_size as usize),
+                });
+
+                state.chunks.len() - 1
+            }
+        };
+
+        let chunk = &mut state.chunks[index];
+        let offset = match chunk.state {
+            ChunkState::Mapped(offset) => offset,
+            ChunkState::Recalling(_) => unreachable!(),
+        };
+        chunk.state = ChunkState::Mapped((offset + size).div_ceil(ALIGNMENT) * ALIGNMENT);
+
+        (chunk.buffer.clone(), offset)
+    }
+
+    // chunks which finished mapping are reused from start, ones which failed to map are dropped.
+    fn recall(state: &mut State) {
+        state.chunks.retain_mut(|chunk| {
+            let result = match &mut chunk.state {
+                ChunkState::Recalling(map) => match map.as_mut().now_or_never() {
+                    Some(x) => x,
+                    None => return true,
+                },
+                ChunkState::Mapped(_) => return true,
+            };
+
+            chunk.state = ChunkState::Mapped(0);
+            result.is_ok()
+        });
+    }
+}
@@ -0,0 +1,72 @@
+use nalgebra::{Matrix4, Vector3};
+
+use crate::{
+    memory::MemoryTracker, post_process::FullscreenPass, render_target::OffscreenRenderTarget, ClearConfig, RenderLayers, ShaderBinding,
+    ShaderBindingType, ShaderStage, Viewpoint,
+};
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum StereoMode {
+    SideBySide,
+    Anaglyph,
+}
+
+pub(crate) struct Stereo {
+    pub(crate) mode: StereoMode,
+    pub(crate) eye_separation: f32,
+
+    // anaglyph renders right eye separately and composes both into one image
+    pub(crate) right_target: Option<OffscreenRenderTarget>,
+    pub(crate) compose: Option<FullscreenPass>,
+}
+
+impl Stereo {
+    pub(crate) fn new(device: &wgpu::Device, memory: &MemoryTracker, mode: StereoMode, eye_separation: f32, texture_size: (u32, u32)) -> Self {
+        let (right_target, compose) = if mode == StereoMode::Anaglyph {
+            let right_target = OffscreenRenderTarget::with_device(device, memory, "render targets", texture_size.0, texture_size.1);
+            let compose = FullscreenPass::with_device(
+                device,
+                include_str!("../shaders/anaglyph.wgsl"),
+                "fs_main",
+                &[("Right", ShaderBinding::new(ShaderStage::Fragment, 3, ShaderBindingType::Texture2D))],
+                &[("Right", right_target.color_attachment.clone())],
+                &[],
+            );
+
+            (Some(right_target), Some(compose))
+        } else {
+            (None, None)
+        };
+
+        Self {
+            mode,
+            eye_separation,
+            right_target,
+            compose,
+        }
+    }
+}
+
+// viewpoint moved along x axis of its view by offset, as Camera::offset does
+pub(crate) struct Eye<'a> {
+    pub(crate) viewpoint: &'a dyn Viewpoint,
+    pub(crate) offset: f32,
+}
+
+impl Viewpoint for Eye<'_> {
+    fn view_matrix(&self) -> Matrix4<f32> {
+        Matrix4::new_translation(&Vector3::new(-self.offset, 0.0, 0.0)) * self.viewpoint.view_matrix()
+    }
+
+    fn projection_matrix(&self, viewport_size: (u32, u32)) -> Matrix4<f32> {
+        self.viewpoint.projection_matrix(viewport_size)
+    }
+
+    fn layers(&self) -> RenderLayers {
+        self.viewpoint.layers()
+    }
+
+    fn clear(&self) -> ClearConfig {
+        self.viewpoint.clear()
+    }
+}
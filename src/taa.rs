@@ -0,0 +1,208 @@
+use alloc::sync::Arc;
+use core::convert::TryInto;
+
+use nalgebra::{Matrix4, Point3, Vector3};
+use spinning_top::Spinlock;
+use zerocopy::AsBytes;
+
+use crate::{
+    buffer_pool::BufferPool, constants::INTERNAL_COLOR_ATTACHMENT_FORMAT, conventions, memory::MemoryTracker, shader_preprocessor, Buffer,
+    ClearConfig, FullscreenPass, PostProcess, PostProcessContext, RenderLayers, ShaderBinding, ShaderBindingType, ShaderStage, Texture,
+    TextureFormat, Viewpoint,
+};
+
+// jitter sequence repeats after this many frames
+const JITTER_FRAMES: u32 = 8;
+// weight of current frame once history is there
+const CURRENT_WEIGHT: f32 = 0.1;
+
+#[repr(C)]
+#[derive(AsBytes)]
+struct TaaUniform {
+    inverse_view_projection: [f32; 16],
+    previous_view_projection: [f32; 16],
+    viewport: [f32; 2],
+    blend: f32,
+    _padding: f32,
+}
+
+#[derive(Default)]
+struct State {
+    frame: u32,
+    // unjittered, of frame being rendered and last resolved one
+    view_projection: Option<Matrix4<f32>>,
+    previous_view_projection: Option<Matrix4<f32>>,
+    size: (u32, u32),
+    velocity: Option<Texture>,
+    // resolved frames, written alternately so last one can be read
+    history: Option<[Texture; 2]>,
+    current: usize,
+    // history is valid and of same size
+    accumulated: bool,
+}
+
+// Temporal anti-aliasing of main view, run before other post processes.
+pub(crate) struct TemporalAa {
+    velocity: FullscreenPass,
+    resolve: FullscreenPass,
+    copy: FullscreenPass,
+    uniform_buf: Arc<Buffer>,
+    memory: MemoryTracker,
+    state: Spinlock<State>,
+}
+
+impl TemporalAa {
+    pub(crate) fn new(device: &wgpu::Device, buffer_pool: &BufferPool, memory: &MemoryTracker) -> Self {
+        let uniform_buf = Arc::new(buffer_pool.alloc(core::mem::size_of::<TaaUniform>()));
+        let source = shader_preprocessor::process_builtin(include_str!("../shaders/taa.wgsl"));
+        let taa_binding = ("Taa", ShaderBinding::new(ShaderStage::Fragment, 3, ShaderBindingType::UniformBuffer));
+
+        let velocity = FullscreenPass::with_format(
+            device,
+            &source,
+            "fs_velocity",
+            &[
+                taa_binding.clone(),
+                ("Depth", ShaderBinding::new(ShaderStage::Fragment, 4, ShaderBindingType::DepthTexture2D)),
+            ],
+            &[],
+            &[("Taa", uniform_buf.clone())],
+            TextureFormat::Rgba16Float,
+        );
+        let resolve = FullscreenPass::with_device(
+            device,
+            &source,
+            "fs_resolve",
+            &[
+                taa_binding,
+                ("Velocity", ShaderBinding::new(ShaderStage::Fragment, 5, ShaderBindingType::Texture2D)),
+                ("History", ShaderBinding::new(ShaderStage::Fragment, 6, ShaderBindingType::Texture2D)),
+            ],
+            &[],
+            &[("Taa", uniform_buf.clone())],
+        );
+        let copy = FullscreenPass::with_device(device, include_str!("../shaders/copy.wgsl"), "fs_main", &[], &[], &[]);
+
+        Self {
+            velocity,
+            resolve,
+            copy,
+            uniform_buf,
+            memory: memory.clone(),
+            state: Spinlock::new(State::default()),
+        }
+    }
+
+    // viewpoint offset by subpixel amount of this frame, following halton sequence
+    pub(crate) fn jitter<'a>(&self, viewpoint: &'a dyn Viewpoint, viewport_size: (u32, u32)) -> Jittered<'a> {
+        let mut state = self.state.lock();
+
+        state.view_projection = Some(conventions::correct_projection(viewpoint.projection_matrix(viewport_size)) * viewpoint.view_matrix());
+
+        let index = state.frame % JITTER_FRAMES + 1;
+        let x = (Self::halton(index, 2) - 0.5) * 2.0 / viewport_size.0 as f32;
+        let y = (Self::halton(index, 3) - 0.5) * 2.0 / viewport_size.1 as f32;
+
+        Jittered { viewpoint, jitter: (x, y) }
+    }
+
+    fn halton(index: u32, base: u32) -> f32 {
+        let mut result = 0.0;
+        let mut fraction = 1.0;
+        let mut index = index;
+        while index > 0 {
+            fraction /= base as f32;
+            result += fraction * (index % base) as f32;
+            index /= base;
+        }
+
+        result
+    }
+}
+
+impl PostProcess for TemporalAa {
+    fn apply(&self, context: &mut PostProcessContext) {
+        let mut state = self.state.lock();
+        let (depth, view_projection) = match (context.depth, state.view_projection.take()) {
+            (Some(depth), Some(view_projection)) => (depth, view_projection),
+            // not jittered this frame, e.g. stereo rendering
+            _ => return self.copy.draw(context),
+        };
+
+        let size = context.viewport_size;
+        if state.size != size || state.history.is_none() {
+            let create = |format| Texture::with_tracker(context.device, &self.memory, "post process", size.0, size.1, format);
+            state.history = Some([create(INTERNAL_COLOR_ATTACHMENT_FORMAT), create(INTERNAL_COLOR_ATTACHMENT_FORMAT)]);
+            state.velocity = Some(create(TextureFormat::Rgba16Float));
+            state.size = size;
+            state.accumulated = false;
+        }
+
+        let inverse_view_projection = view_projection.try_inverse().unwrap_or_else(Matrix4::identity);
+        let uniform = TaaUniform {
+            inverse_view_projection: inverse_view_projection.as_slice().try_into().unwrap(),
+            previous_view_projection: state.previous_view_projection.unwrap_or(view_projection).as_slice().try_into().unwrap(),
+            viewport: [size.0 as f32, size.1 as f32],
+            blend: if state.accumulated { CURRENT_WEIGHT } else { 1.0 },
+            _padding: 0.0,
+        };
+        self.uniform_buf.write(uniform.as_bytes());
+
+        let history = state.history.as_ref().unwrap();
+        let (previous, resolved) = (&history[state.current], &history[1 - state.current]);
+        let velocity = state.velocity.as_ref().unwrap();
+        let viewport = (0.0, 0.0, size.0 as f32, size.1 as f32);
+
+        let mut pass_context = PostProcessContext {
+            device: context.device,
+            command_encoder: context.command_encoder,
+            input: context.input,
+            output: &velocity.texture_view,
+            viewport_size: size,
+            depth: context.depth,
+            projection: context.projection,
+        };
+        self.velocity.draw_with_textures(&mut pass_context, viewport, &[("Depth", depth)]);
+
+        pass_context.output = &resolved.texture_view;
+        self.resolve
+            .draw_with_textures(&mut pass_context, viewport, &[("Velocity", velocity), ("History", previous)]);
+
+        pass_context.input = resolved;
+        pass_context.output = context.output;
+        self.copy.draw(&mut pass_context);
+
+        state.current = 1 - state.current;
+        state.previous_view_projection = Some(view_projection);
+        state.accumulated = true;
+        state.frame += 1;
+    }
+}
+
+// projection offset in normalized device coordinates
+pub(crate) struct Jittered<'a> {
+    viewpoint: &'a dyn Viewpoint,
+    jitter: (f32, f32),
+}
+
+impl Viewpoint for Jittered<'_> {
+    fn view_matrix(&self) -> Matrix4<f32> {
+        self.viewpoint.view_matrix()
+    }
+
+    fn projection_matrix(&self, viewport_size: (u32, u32)) -> Matrix4<f32> {
+        Matrix4::new_translation(&Vector3::new(self.jitter.0, self.jitter.1, 0.0)) * self.viewpoint.projection_matrix(viewport_size)
+    }
+
+    fn eye(&self) -> Point3<f32> {
+        self.viewpoint.eye()
+    }
+
+    fn layers(&self) -> RenderLayers {
+        self.viewpoint.layers()
+    }
+
+    fn clear(&self) -> ClearConfig {
+        self.viewpoint.clear()
+    }
+}
@@ -0,0 +1,86 @@
+use alloc::sync::Arc;
+
+use crate::{constants::INTERNAL_COLOR_ATTACHMENT_FORMAT, memory::MemoryTracker, render_target::OffscreenRenderTarget, RenderTarget, Texture};
+
+// Owns screen sized intermediate targets, allocated at power of two sizes covering every surface.
+// Renderer replaces the pool when a surface outgrows it, and rebuilds everything bound to the old targets.
+pub(crate) struct TargetPool {
+    pub(crate) offscreen_target: OffscreenRenderTarget,
+    pub(crate) post_process_targets: [Arc<Texture>; 2],
+    // camera views are rendered here and copied over the main view, allocated on first use
+    pub(crate) view_target: Option<OffscreenRenderTarget>,
+}
+
+impl TargetPool {
+    pub(crate) fn new(device: &wgpu::Device, memory: &MemoryTracker, size: (u32, u32)) -> Self {
+        let texture_width = Self::round_up_power_of_two(size.0);
+        let texture_height = Self::round_up_power_of_two(size.1);
+
+        let offscreen_target = OffscreenRenderTarget::with_device(device, memory, "render targets", texture_width, texture_height);
+        let post_process_targets = [
+            Arc::new(Texture::with_tracker(
+                device,
+                memory,
+                "render targets",
+                texture_width,
+                texture_height,
+                INTERNAL_COLOR_ATTACHMENT_FORMAT,
+            )),
+            Arc::new(Texture::with_tracker(
+                device,
+                memory,
+                "render targets",
+                texture_width,
+                texture_height,
+                INTERNAL_COLOR_ATTACHMENT_FORMAT,
+            )),
+        ];
+
+        Self {
+            offscreen_target,
+            post_process_targets,
+            view_target: None,
+        }
+    }
+
+    pub(crate) fn size(&self) -> (u32, u32) {
+        self.offscreen_target.size()
+    }
+
+    pub(crate) fn fits(&self, size: (u32, u32)) -> bool {
+        let texture_size = self.size();
+
+        size.0 <= texture_size.0 && size.1 <= texture_size.1
+    }
+
+    pub(crate) fn view_target(&mut self, device: &wgpu::Device, memory: &MemoryTracker) -> &OffscreenRenderTarget {
+        let size = self.size();
+
+        self.view_target
+            .get_or_insert_with(|| OffscreenRenderTarget::with_device(device, memory, "render targets", size.0, size.1))
+    }
+
+    // 0 is offscreen target, others are post process targets
+    pub(crate) fn color_texture(&self, index: usize) -> &Texture {
+        if index == 0 {
+            &self.offscreen_target.color_attachment
+        } else {
+            &self.post_process_targets[index - 1]
+        }
+    }
+
+    //returns zero if v is zero.
+    fn round_up_power_of_two(mut v: u32) -> u32 {
+        //from http://graphics.stanford.edu/~seander/bithacks.html#RoundUpPowerOf2 (public domain)
+
+        v -= 1;
+        v |= v >> 1;
+        v |= v >> 2;
+        v |= v >> 4;
+        v |= v >> 8;
+        v |= v >> 16;
+        v += 1;
+
+        v
+    }
+}
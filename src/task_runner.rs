@@ -0,0 +1,25 @@
+use alloc::{boxed::Box, vec::Vec};
+
+// draws below this aren't worth a command buffer of their own
+pub(crate) const MIN_BIN_SIZE: usize = 256;
+pub(crate) const MAX_BINS: usize = 8;
+
+pub type Task<'a> = Box<dyn FnOnce() + Send + 'a>;
+
+// Runs tasks on worker threads of the application, which renderer can't spawn itself.
+// e.g. with std::thread::scope, spawning each task and returning once the scope ends, or with rayon::scope.
+pub trait TaskRunner: Send + Sync {
+    // must return only after every task finished
+    fn run(&self, tasks: Vec<Task<'_>>);
+
+    // tasks worth running at once, usually number of worker threads
+    fn concurrency(&self) -> usize;
+}
+
+// number of bins draws are split into, one if they're too few to split
+pub(crate) fn bin_count(runner: Option<&dyn TaskRunner>, draw_count: usize) -> usize {
+    runner
+        .map(|x| x.concurrency().min(MAX_BINS).min(draw_count / MIN_BIN_SIZE))
+        .unwrap_or(1)
+        .max(1)
+}
@@ -0,0 +1,406 @@
+use alloc::{sync::Arc, vec::Vec};
+use core::{
+    mem::size_of,
+    sync::atomic::{AtomicU32, AtomicUsize, Ordering},
+};
+
+use nalgebra::{Matrix4, Point3, Vector3};
+use zerocopy::AsBytes;
+
+use crate::{
+    constants::INTERNAL_COLOR_ATTACHMENT_FORMAT, uniform_arena::UniformArena, Aabb, Buffer, MaterialPass, RenderContext, RenderLayers, RenderPath,
+    Renderable, Renderer, Shader, ShaderBinding, ShaderBindingType, ShaderStage, Texture,
+};
+
+// quads on each side of a chunk, keeps chunk vertices within u16 indices
+const CHUNK_SIZE: usize = 64;
+// coarsest level still has 4 quads on each side
+const LEVEL_COUNT: usize = 5;
+// chunk isn't drawn in current view
+const CULLED: usize = usize::MAX;
+
+#[repr(C)]
+#[derive(AsBytes, Clone, Copy)]
+struct TerrainVertex {
+    position: [f32; 3],
+    normal: [f32; 3],
+    tex_coord: [f32; 2],
+}
+
+// Textures blended by weights in splat map channels, each repeated layer_scale times across terrain.
+pub struct TerrainSplat {
+    pub splat_map: Arc<Texture>,
+    pub layers: [Arc<Texture>; 4],
+    pub layer_scale: [f32; 4],
+}
+
+struct TerrainChunk {
+    vertex_buf: Buffer,
+    aabb: Aabb,
+    // level of detail selected by last prepare, or CULLED
+    level: AtomicUsize,
+}
+
+// Heightmap split into chunks, each drawn at a level of detail by its distance and skipped outside the view.
+// Levels skip every other vertex of finer one (geomipmapping). chunks have skirts hanging down their borders,
+// which hide cracks between neighbours of different levels, so terrain is drawn without culling faces.
+pub struct Terrain {
+    pipeline: wgpu::RenderPipeline,
+    bind_group: wgpu::BindGroup,
+    chunks: Vec<TerrainChunk>,
+    // shared by every chunk, one for each level
+    index_bufs: Vec<(Buffer, u32)>,
+    arena: Arc<UniformArena>,
+    transform: Matrix4<f32>,
+    layers: RenderLayers,
+    visible: bool,
+    lod_distance: f32,
+    forward: bool,
+    // slot in uniform arena written by last prepare
+    uniform_offset: AtomicU32,
+
+    _splat: TerrainSplat,
+    _terrain_buf: Buffer,
+}
+
+impl Terrain {
+    // heights are in world units, row major from min z with width columns along x. cells are cell_size apart.
+    pub fn new(renderer: &Renderer, heights: &[f32], width: usize, depth: usize, cell_size: f32, splat: TerrainSplat) -> Self {
+        let device = &*renderer.device;
+
+        let shader = Shader::new(
+            renderer,
+            include_str!("../shaders/terrain.wgsl"),
+            "vs_main",
+            "fs_main",
+            &[
+                ("Mvp", ShaderBinding::new(ShaderStage::Vertex, 0, ShaderBindingType::UniformBuffer)),
+                ("SplatMap", ShaderBinding::new(ShaderStage::Fragment, 1, ShaderBindingType::Texture2D)),
+                ("Sampler", ShaderBinding::new(ShaderStage::Fragment, 2, ShaderBindingType::Sampler)),
+                ("Layer0", ShaderBinding::new(ShaderStage::Fragment, 3, ShaderBindingType::Texture2D)),
+                ("Layer1", ShaderBinding::new(ShaderStage::Fragment, 4, ShaderBindingType::Texture2D)),
+                ("Layer2", ShaderBinding::new(ShaderStage::Fragment, 5, ShaderBindingType::Texture2D)),
+                ("Layer3", ShaderBinding::new(ShaderStage::Fragment, 6, ShaderBindingType::Texture2D)),
+                ("Terrain", ShaderBinding::new(ShaderStage::Fragment, 7, ShaderBindingType::UniformBuffer)),
+                ("Lighting", ShaderBinding::new(ShaderStage::Fragment, 8, ShaderBindingType::UniformBuffer)),
+            ],
+            &[],
+        );
+
+        let mut bindings = shader.wgpu_bindings().collect::<Vec<_>>();
+        for entry in bindings.iter_mut().filter(|x| x.binding == 0) {
+            if let wgpu::BindingType::Buffer { has_dynamic_offset, .. } = &mut entry.ty {
+                *has_dynamic_offset = true;
+            }
+        }
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &bindings,
+            label: None,
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            push_constant_ranges: &[],
+            bind_group_layouts: &[&bind_group_layout],
+        });
+
+        let terrain_buf = renderer.buffer_pool.alloc(size_of::<[f32; 4]>());
+        terrain_buf.write(splat.layer_scale.as_bytes());
+
+        let sampler = renderer.pipeline_cache.sampler();
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: renderer.uniform_arena.binding_resource(),
+                },
+                Self::texture_entry(1, &splat.splat_map),
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+                Self::texture_entry(3, &splat.layers[0]),
+                Self::texture_entry(4, &splat.layers[1]),
+                Self::texture_entry(5, &splat.layers[2]),
+                Self::texture_entry(6, &splat.layers[3]),
+                wgpu::BindGroupEntry {
+                    binding: 7,
+                    resource: terrain_buf.binding_resource(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 8,
+                    resource: renderer.lighting_buf.binding_resource(),
+                },
+            ],
+            label: None,
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader.module,
+                entry_point: shader.vs_entry,
+                buffers: &[wgpu::VertexBufferLayout {
+                    array_stride: size_of::<TerrainVertex>() as wgpu::BufferAddress,
+                    step_mode: wgpu::VertexStepMode::Vertex,
+                    attributes: &wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x3, 2 => Float32x2],
+                }],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: shader.fragment_module(),
+                entry_point: shader.fs_entry,
+                targets: &[INTERNAL_COLOR_ATTACHMENT_FORMAT.wgpu_type().into()],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: wgpu::TextureFormat::Depth32Float,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::LessEqual,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            label: None,
+            multisample: wgpu::MultisampleState::default(),
+        });
+
+        let heightmap = Heightmap {
+            heights,
+            width,
+            depth,
+            cell_size,
+        };
+        let chunks_x = (width.max(2) - 1).div_ceil(CHUNK_SIZE);
+        let chunks_z = (depth.max(2) - 1).div_ceil(CHUNK_SIZE);
+        let chunks = (0..chunks_z)
+            .flat_map(|z| (0..chunks_x).map(move |x| (x, z)))
+            .map(|(x, z)| heightmap.build_chunk(renderer, x * CHUNK_SIZE, z * CHUNK_SIZE))
+            .collect();
+
+        let index_bufs = (0..LEVEL_COUNT)
+            .map(|level| {
+                let indices = Self::level_indices(1 << level);
+                let buffer = renderer.buffer_pool.alloc_index(indices.as_bytes().len());
+                buffer.write(indices.as_bytes());
+
+                (buffer, indices.len() as u32)
+            })
+            .collect();
+
+        Self {
+            pipeline,
+            bind_group,
+            chunks,
+            index_bufs,
+            arena: renderer.uniform_arena.clone(),
+            transform: Matrix4::identity(),
+            layers: RenderLayers::default(),
+            visible: true,
+            lod_distance: cell_size * CHUNK_SIZE as f32,
+            forward: renderer.options.render_path == RenderPath::Deferred,
+            uniform_offset: AtomicU32::new(0),
+            _splat: splat,
+            _terrain_buf: terrain_buf,
+        }
+    }
+
+    // heights from red channel of rgba8 texels, 0 is min_height and 255 is max_height.
+    pub fn heights_from_texels(texels: &[u8], min_height: f32, max_height: f32) -> Vec<f32> {
+        texels
+            .chunks(4)
+            .map(|x| min_height + x[0] as f32 / 255.0 * (max_height - min_height))
+            .collect()
+    }
+
+    pub fn set_transform(&mut self, transform: Matrix4<f32>) {
+        self.transform = transform;
+    }
+
+    pub fn transform(&self) -> &Matrix4<f32> {
+        &self.transform
+    }
+
+    pub fn set_layers(&mut self, layers: RenderLayers) {
+        self.layers = layers;
+    }
+
+    pub fn set_visible(&mut self, visible: bool) {
+        self.visible = visible;
+    }
+
+    // chunks closer than this are drawn at full detail, each doubling of distance drops one level.
+    // defaults to width of a chunk.
+    pub fn set_lod_distance(&mut self, lod_distance: f32) {
+        self.lod_distance = lod_distance;
+    }
+
+    fn texture_entry(binding: u32, texture: &Texture) -> wgpu::BindGroupEntry<'_> {
+        wgpu::BindGroupEntry {
+            binding,
+            resource: wgpu::BindingResource::TextureView(&texture.texture_view),
+        }
+    }
+
+    // grid of chunk with vertices step apart, followed by skirts on each border
+    fn level_indices(step: usize) -> Vec<u16> {
+        let row = CHUNK_SIZE + 1;
+        let grid = |x: usize, z: usize| (z * row + x) as u16;
+        let skirt = |edge: usize, k: usize| (row * row + edge * row + k) as u16;
+
+        let mut indices = Vec::new();
+        for z in (0..CHUNK_SIZE).step_by(step) {
+            for x in (0..CHUNK_SIZE).step_by(step) {
+                let (x1, z1) = (x + step, z + step);
+                indices.extend_from_slice(&[grid(x, z), grid(x, z1), grid(x1, z1), grid(x, z), grid(x1, z1), grid(x1, z)]);
+            }
+        }
+
+        // edges are min z, max z, min x, max x
+        for edge in 0..4 {
+            let border = |k: usize| match edge {
+                0 => grid(k, 0),
+                1 => grid(k, CHUNK_SIZE),
+                2 => grid(0, k),
+                _ => grid(CHUNK_SIZE, k),
+            };
+            for k in (0..CHUNK_SIZE).step_by(step) {
+                let k1 = k + step;
+                indices.extend_from_slice(&[border(k), skirt(edge, k), skirt(edge, k1), border(k), skirt(edge, k1), border(k1)]);
+            }
+        }
+
+        indices
+    }
+}
+
+struct Heightmap<'a> {
+    heights: &'a [f32],
+    width: usize,
+    depth: usize,
+    cell_size: f32,
+}
+
+impl Heightmap<'_> {
+    // clamped to edges, so chunks past the last row or column repeat it
+    fn height(&self, x: isize, z: isize) -> f32 {
+        let x = x.clamp(0, self.width as isize - 1) as usize;
+        let z = z.clamp(0, self.depth as isize - 1) as usize;
+
+        self.heights[z * self.width + x]
+    }
+
+    fn vertex(&self, x: usize, z: usize, y_offset: f32) -> TerrainVertex {
+        let (cx, cz) = (x.min(self.width - 1) as isize, z.min(self.depth - 1) as isize);
+
+        let normal = Vector3::new(
+            self.height(cx - 1, cz) - self.height(cx + 1, cz),
+            2.0 * self.cell_size,
+            self.height(cx, cz - 1) - self.height(cx, cz + 1),
+        )
+        .normalize();
+
+        TerrainVertex {
+            position: [cx as f32 * self.cell_size, self.height(cx, cz) + y_offset, cz as f32 * self.cell_size],
+            normal: normal.into(),
+            tex_coord: [cx as f32 / (self.width - 1).max(1) as f32, cz as f32 / (self.depth - 1).max(1) as f32],
+        }
+    }
+
+    fn build_chunk(&self, renderer: &Renderer, origin_x: usize, origin_z: usize) -> TerrainChunk {
+        let row = CHUNK_SIZE + 1;
+
+        let mut vertices = Vec::with_capacity(row * row + 4 * row);
+        for z in 0..row {
+            for x in 0..row {
+                vertices.push(self.vertex(origin_x + x, origin_z + z, 0.0));
+            }
+        }
+
+        let positions = vertices.iter().map(|x| Point3::from(x.position)).collect::<Vec<_>>();
+        let aabb = Aabb::from_points(&positions).unwrap();
+
+        // deep enough to cover any neighbour within chunk's height range
+        let skirt_depth = -(aabb.max.y - aabb.min.y + self.cell_size);
+        for edge in 0..4 {
+            for k in 0..row {
+                let (x, z) = match edge {
+                    0 => (k, 0),
+                    1 => (k, CHUNK_SIZE),
+                    2 => (0, k),
+                    _ => (CHUNK_SIZE, k),
+                };
+                vertices.push(self.vertex(origin_x + x, origin_z + z, skirt_depth));
+            }
+        }
+
+        let vertex_buf = renderer.buffer_pool.alloc(vertices.as_bytes().len());
+        vertex_buf.write(vertices.as_bytes());
+
+        TerrainChunk {
+            vertex_buf,
+            aabb,
+            level: AtomicUsize::new(0),
+        }
+    }
+}
+
+impl Renderable for Terrain {
+    fn render<'a>(&'a self, render_context: &mut RenderContext<'a>) {
+        if render_context.pass != MaterialPass::Main {
+            return;
+        }
+
+        render_context.set_pipeline(&self.pipeline);
+        render_context.set_bind_group(&self.bind_group, &[self.uniform_offset.load(Ordering::Relaxed)]);
+
+        for chunk in &self.chunks {
+            let level = chunk.level.load(Ordering::Relaxed);
+            if level == CULLED {
+                continue;
+            }
+
+            let (index_buf, index_count) = &self.index_bufs[level];
+            render_context.set_vertex_buffer(0, chunk.vertex_buf.as_slice());
+            render_context.set_index_buffer(index_buf.as_slice());
+            render_context.render_pass.draw_indexed(0..*index_count, 0, 0..1);
+        }
+    }
+
+    fn prepare(&self, view_projection: &Matrix4<f32>) {
+        let mvp = view_projection * self.transform;
+
+        let mut data = [0.0f32; 32];
+        data[..16].copy_from_slice(mvp.as_slice());
+        data[16..].copy_from_slice(self.transform.as_slice());
+        self.uniform_offset.store(self.arena.push(data.as_bytes()), Ordering::Relaxed);
+
+        for chunk in &self.chunks {
+            let level = if chunk.aabb.intersects_frustum(&mvp) {
+                // clip space w is view depth with perspective projection
+                let distance = (mvp * chunk.aabb.center().to_homogeneous()).w;
+                let doublings = (distance / self.lod_distance).max(1.0).log2() as usize;
+
+                doublings.min(LEVEL_COUNT - 1)
+            } else {
+                CULLED
+            };
+            chunk.level.store(level, Ordering::Relaxed);
+        }
+    }
+
+    // drawn in forward pass on deferred render path, it can't write g-buffer
+    fn is_transparent(&self) -> bool {
+        self.forward
+    }
+
+    fn position(&self) -> Point3<f32> {
+        self.transform.transform_point(&Point3::origin())
+    }
+
+    fn layers(&self) -> RenderLayers {
+        self.layers
+    }
+
+    fn is_visible(&self) -> bool {
+        self.visible
+    }
+}
@@ -0,0 +1,99 @@
+use alloc::vec::Vec;
+
+use crate::{Renderer, Scene};
+
+// Per pixel result of comparing rendered image against a reference.
+pub struct ImageDiff {
+    // perceptual color difference of each pixel, 0 is identical and 1 is black against white
+    pub errors: Vec<f32>,
+    pub max_error: f32,
+    // pixels whose error is over the threshold
+    pub mismatched_pixels: usize,
+}
+
+impl ImageDiff {
+    pub fn passed(&self) -> bool {
+        self.mismatched_pixels == 0
+    }
+}
+
+// Renders scene with a headless renderer and reads back the final image as srgb rgba8 rows from top to bottom.
+pub async fn render_image(renderer: &mut Renderer, scene: &Scene) -> Vec<u8> {
+    renderer.render(scene);
+
+    let target = renderer.main_target();
+    let texture = target.texture().expect("Renderer is not headless");
+    let (width, height) = target.size();
+
+    let padded_bytes_per_row = (width * 4).div_ceil(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT) * wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+    let readback = renderer.device.create_buffer(&wgpu::BufferDescriptor {
+        label: None,
+        size: padded_bytes_per_row as u64 * height as u64,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+
+    let mut command_encoder = renderer.device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    command_encoder.copy_texture_to_buffer(
+        texture.texture.as_image_copy(),
+        wgpu::ImageCopyBuffer {
+            buffer: &readback,
+            layout: wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: core::num::NonZeroU32::new(padded_bytes_per_row),
+                rows_per_image: None,
+            },
+        },
+        wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+    );
+    renderer.staging_belt.submit(Some(command_encoder.finish()));
+
+    let slice = readback.slice(..);
+    let map = slice.map_async(wgpu::MapMode::Read);
+    renderer.device.poll(wgpu::Maintain::Wait);
+    map.await.unwrap();
+
+    let data = slice.get_mapped_range();
+    data.chunks(padded_bytes_per_row as usize)
+        .flat_map(|row| &row[..width as usize * 4])
+        .copied()
+        .collect()
+}
+
+// Compares rgba8 images of same size by perceived color difference, alpha is ignored.
+// threshold around 0.1 tolerates driver rasterization differences.
+pub fn compare_images(actual: &[u8], reference: &[u8], threshold: f32) -> ImageDiff {
+    assert_eq!(actual.len(), reference.len(), "Image sizes differ");
+
+    let errors = actual
+        .chunks(4)
+        .zip(reference.chunks(4))
+        .map(|(a, b)| color_delta(a, b))
+        .collect::<Vec<_>>();
+    let max_error = errors.iter().cloned().fold(0.0, f32::max);
+    let mismatched_pixels = errors.iter().filter(|&&x| x > threshold).count();
+
+    ImageDiff {
+        errors,
+        max_error,
+        mismatched_pixels,
+    }
+}
+
+// distance in yiq space weighted by perceived importance, from "Measuring perceived color difference using YIQ NTSC transmission color space" (Kotsarenko, Ramos)
+fn color_delta(a: &[u8], b: &[u8]) -> f32 {
+    let r = a[0] as f32 - b[0] as f32;
+    let g = a[1] as f32 - b[1] as f32;
+    let b = a[2] as f32 - b[2] as f32;
+
+    let y = r * 0.298_895_3 + g * 0.586_622_5 + b * 0.114_482_23;
+    let i = r * 0.595_977_99 - g * 0.274_176_1 - b * 0.321_801_9;
+    let q = r * 0.211_470_17 - g * 0.522_617_1 + b * 0.311_146_94;
+
+    // 35215 is the delta between black and white
+    ((0.5053 * y * y + 0.299 * i * i + 0.1957 * q * q) / 35215.0).sqrt()
+}
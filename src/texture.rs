@@ -1,12 +1,36 @@
-use alloc::{vec, vec::Vec};
+use alloc::{sync::Arc, vec, vec::Vec};
 
-use crate::Renderer;
+use crate::{
+    deletion_queue::DeletionQueue,
+    memory::{Allocation, MemoryTracker},
+    staging_belt::StagingBelt,
+    Renderer,
+};
 
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum TextureFormat {
     Rgba8Unorm,
     Bgra8Unorm,
+    // texels are srgb encoded, decoded to linear when sampled. use for color images like albedo textures.
+    Rgba8UnormSrgb,
+    Bgra8UnormSrgb,
+    R8Unorm,
+    Rg8Unorm,
+    R16Float,
+    Rg16Float,
     Rgba16Float,
+    R32Float,
+    Rgba32Float,
+    R32Uint,
     Depth32,
+    Depth24Plus,
+    Depth24PlusStencil8,
+    // block compressed, check Renderer::supports_format before using
+    Bc1RgbaUnormSrgb,
+    Bc2RgbaUnormSrgb,
+    Bc3RgbaUnormSrgb,
+    Bc4RUnorm,
+    Bc5RgUnorm,
 }
 
 impl TextureFormat {
@@ -14,20 +38,72 @@ impl TextureFormat {
         match self {
             TextureFormat::Rgba8Unorm => wgpu::TextureFormat::Rgba8Unorm,
             TextureFormat::Bgra8Unorm => wgpu::TextureFormat::Bgra8Unorm,
+            TextureFormat::Rgba8UnormSrgb => wgpu::TextureFormat::Rgba8UnormSrgb,
+            TextureFormat::Bgra8UnormSrgb => wgpu::TextureFormat::Bgra8UnormSrgb,
+            TextureFormat::R8Unorm => wgpu::TextureFormat::R8Unorm,
+            TextureFormat::Rg8Unorm => wgpu::TextureFormat::Rg8Unorm,
+            TextureFormat::R16Float => wgpu::TextureFormat::R16Float,
+            TextureFormat::Rg16Float => wgpu::TextureFormat::Rg16Float,
             TextureFormat::Rgba16Float => wgpu::TextureFormat::Rgba16Float,
+            TextureFormat::R32Float => wgpu::TextureFormat::R32Float,
+            TextureFormat::Rgba32Float => wgpu::TextureFormat::Rgba32Float,
+            TextureFormat::R32Uint => wgpu::TextureFormat::R32Uint,
             TextureFormat::Depth32 => wgpu::TextureFormat::Depth32Float,
+            TextureFormat::Depth24Plus => wgpu::TextureFormat::Depth24Plus,
+            TextureFormat::Depth24PlusStencil8 => wgpu::TextureFormat::Depth24PlusStencil8,
+            TextureFormat::Bc1RgbaUnormSrgb => wgpu::TextureFormat::Bc1RgbaUnormSrgb,
+            TextureFormat::Bc2RgbaUnormSrgb => wgpu::TextureFormat::Bc2RgbaUnormSrgb,
+            TextureFormat::Bc3RgbaUnormSrgb => wgpu::TextureFormat::Bc3RgbaUnormSrgb,
+            TextureFormat::Bc4RUnorm => wgpu::TextureFormat::Bc4RUnorm,
+            TextureFormat::Bc5RgUnorm => wgpu::TextureFormat::Bc5RgUnorm,
         }
     }
-    pub(crate) fn bytes_per_row(&self) -> usize {
+
+    pub fn is_srgb(&self) -> bool {
+        self.wgpu_type().describe().srgb
+    }
+
+    pub fn is_depth(&self) -> bool {
+        matches!(
+            self,
+            TextureFormat::Depth32 | TextureFormat::Depth24Plus | TextureFormat::Depth24PlusStencil8
+        )
+    }
+
+    pub fn is_compressed(&self) -> bool {
+        self.wgpu_type().describe().block_dimensions != (1, 1)
+    }
+
+    // bytes of a texel, or of a 4x4 block for compressed formats
+    pub(crate) fn bytes_per_block(&self) -> usize {
+        self.wgpu_type().describe().block_size as usize
+    }
+
+    // bytes of a row of texels, or of blocks for compressed formats
+    pub(crate) fn row_pitch(&self, width: u32) -> u32 {
+        let block_width = self.wgpu_type().describe().block_dimensions.0 as u32;
+
+        width.div_ceil(block_width) * self.bytes_per_block() as u32
+    }
+
+    // depth24 formats can't be copied, compressed ones can't be rendered to
+    pub(crate) fn usage(&self) -> wgpu::TextureUsages {
         match self {
-            TextureFormat::Rgba8Unorm => 4,
-            TextureFormat::Bgra8Unorm => 4,
-            TextureFormat::Rgba16Float => 8,
-            TextureFormat::Depth32 => 4,
+            TextureFormat::Depth24Plus | TextureFormat::Depth24PlusStencil8 => {
+                wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::RENDER_ATTACHMENT
+            }
+            x if x.is_compressed() => wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            _ => {
+                wgpu::TextureUsages::TEXTURE_BINDING
+                    | wgpu::TextureUsages::COPY_SRC
+                    | wgpu::TextureUsages::COPY_DST
+                    | wgpu::TextureUsages::RENDER_ATTACHMENT
+            }
         }
     }
 }
 
+// block compressed color images are srgb encoded, decoded ones are stored in srgb format.
 #[allow(clippy::upper_case_acronyms)]
 pub enum CompressedTextureFormat {
     BC1,
@@ -38,23 +114,75 @@ pub enum CompressedTextureFormat {
 impl CompressedTextureFormat {
     pub(crate) fn decoded_format(&self) -> TextureFormat {
         match self {
-            CompressedTextureFormat::BC1 => TextureFormat::Rgba8Unorm,
-            CompressedTextureFormat::BC2 => TextureFormat::Rgba8Unorm,
-            CompressedTextureFormat::BC3 => TextureFormat::Rgba8Unorm,
+            CompressedTextureFormat::BC1 => TextureFormat::Rgba8UnormSrgb,
+            CompressedTextureFormat::BC2 => TextureFormat::Rgba8UnormSrgb,
+            CompressedTextureFormat::BC3 => TextureFormat::Rgba8UnormSrgb,
+        }
+    }
+
+    pub(crate) fn format(&self) -> TextureFormat {
+        match self {
+            CompressedTextureFormat::BC1 => TextureFormat::Bc1RgbaUnormSrgb,
+            CompressedTextureFormat::BC2 => TextureFormat::Bc2RgbaUnormSrgb,
+            CompressedTextureFormat::BC3 => TextureFormat::Bc3RgbaUnormSrgb,
         }
     }
 }
 
 pub struct Texture {
+    pub(crate) texture: Arc<wgpu::Texture>,
     pub(crate) texture_view: wgpu::TextureView,
+    pub(crate) format: TextureFormat,
+    pub(crate) extent: wgpu::Extent3d,
+    // none for textures renderer doesn't report, like 1x1 picking targets
+    pub(crate) allocation: Option<Allocation>,
+    // destroys texture once frames using it are done, for textures applications create and drop mid-run.
+    // renderer owned ones are left to wgpu
+    pub(crate) deletion_queue: Option<Arc<DeletionQueue>>,
 }
 
 impl Texture {
     pub fn new(renderer: &Renderer, width: u32, height: u32, format: TextureFormat) -> Self {
-        Self::with_device(&*renderer.device, width, height, format)
+        let mut texture = Self::with_tracker(&renderer.device, &renderer.memory, "textures", width, height, format);
+        texture.deletion_queue = Some(renderer.deletion_queue.clone());
+
+        texture
+    }
+
+    // reported under label in Renderer::memory_report instead of "textures", e.g. to tell assets apart.
+    pub fn set_label(&mut self, label: &'static str) {
+        if let Some(allocation) = &mut self.allocation {
+            allocation.relabel(label);
+        }
+    }
+
+    // reported under label until dropped
+    pub(crate) fn with_tracker(
+        device: &wgpu::Device,
+        memory: &MemoryTracker,
+        label: &'static str,
+        width: u32,
+        height: u32,
+        format: TextureFormat,
+    ) -> Self {
+        let extent = wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        };
+
+        // named by memory category in graphics debuggers
+        let mut texture = Self::create(device, Some(label), width, height, format);
+        texture.allocation = Some(memory.track_texture(label, format.wgpu_type(), extent, 1));
+
+        texture
     }
 
     pub(crate) fn with_device(device: &wgpu::Device, width: u32, height: u32, format: TextureFormat) -> Self {
+        Self::create(device, None, width, height, format)
+    }
+
+    fn create(device: &wgpu::Device, label: Option<&str>, width: u32, height: u32, format: TextureFormat) -> Self {
         let extent = wgpu::Extent3d {
             width,
             height,
@@ -66,13 +194,20 @@ impl Texture {
             sample_count: 1,
             dimension: wgpu::TextureDimension::D2,
             format: format.wgpu_type(),
-            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST | wgpu::TextureUsages::RENDER_ATTACHMENT,
-            label: None,
+            usage: format.usage(),
+            label,
         });
 
         let texture_view = texture.create_view(&wgpu::TextureViewDescriptor::default());
 
-        Self { texture_view }
+        Self {
+            texture: Arc::new(texture),
+            texture_view,
+            format,
+            extent,
+            allocation: None,
+            deletion_queue: None,
+        }
     }
 
     pub fn with_texels(renderer: &Renderer, width: u32, height: u32, texels: &[u8], format: TextureFormat) -> Self {
@@ -81,37 +216,134 @@ impl Texture {
             height,
             depth_or_array_layers: 1,
         };
+
+        Self::with_extent(
+            renderer,
+            extent,
+            wgpu::TextureDimension::D2,
+            wgpu::TextureViewDimension::D2,
+            texels,
+            format,
+        )
+    }
+
+    // texels are stored slice by slice, for volumes sampled with texture_3d like fog densities.
+    pub fn with_texels_3d(renderer: &Renderer, width: u32, height: u32, depth: u32, texels: &[u8], format: TextureFormat) -> Self {
+        let extent = wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: depth,
+        };
+
+        Self::with_extent(
+            renderer,
+            extent,
+            wgpu::TextureDimension::D3,
+            wgpu::TextureViewDimension::D3,
+            texels,
+            format,
+        )
+    }
+
+    // layers of same size sampled with texture_2d_array, bound as ShaderBindingType::Texture2DArray.
+    pub fn with_layers(renderer: &Renderer, width: u32, height: u32, layers: &[&[u8]], format: TextureFormat) -> Self {
+        let extent = wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: layers.len() as u32,
+        };
+        let texels = layers.concat();
+
+        Self::with_extent(
+            renderer,
+            extent,
+            wgpu::TextureDimension::D2,
+            wgpu::TextureViewDimension::D2Array,
+            &texels,
+            format,
+        )
+    }
+
+    fn with_extent(
+        renderer: &Renderer,
+        extent: wgpu::Extent3d,
+        dimension: wgpu::TextureDimension,
+        view_dimension: wgpu::TextureViewDimension,
+        texels: &[u8],
+        format: TextureFormat,
+    ) -> Self {
         let texture = renderer.device.create_texture(&wgpu::TextureDescriptor {
             size: extent,
             mip_level_count: 1,
             sample_count: 1,
-            dimension: wgpu::TextureDimension::D2,
+            dimension,
             format: format.wgpu_type(),
-            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST | wgpu::TextureUsages::RENDER_ATTACHMENT,
+            usage: format.usage(),
             label: None,
         });
 
-        let texture_view = texture.create_view(&wgpu::TextureViewDescriptor::default());
-        renderer.queue.write_texture(
-            wgpu::ImageCopyTexture {
-                texture: &texture,
-                mip_level: 0,
-                origin: wgpu::Origin3d::ZERO,
-                aspect: wgpu::TextureAspect::All,
-            },
-            texels,
-            wgpu::ImageDataLayout {
-                offset: 0,
-                bytes_per_row: core::num::NonZeroU32::new(format.bytes_per_row() as u32 * extent.width as u32),
-                rows_per_image: None,
-            },
+        let texture_view = texture.create_view(&wgpu::TextureViewDescriptor {
+            dimension: Some(view_dimension),
+            ..Default::default()
+        });
+        let texture = Arc::new(texture);
+        renderer
+            .staging_belt
+            .write_texture(&texture, format, wgpu::Origin3d::ZERO, extent, texels);
+
+        Self {
+            texture,
+            texture_view,
+            format,
             extent,
-        );
+            allocation: Some(renderer.memory.track_texture("textures", format.wgpu_type(), extent, 1)),
+            deletion_queue: Some(renderer.deletion_queue.clone()),
+        }
+    }
+
+    pub fn format(&self) -> TextureFormat {
+        self.format
+    }
+
+    // for passes recorded outside renderer, like in PostProcess::apply
+    pub fn view(&self) -> &wgpu::TextureView {
+        &self.texture_view
+    }
 
-        Self { texture_view }
+    // replaces texels of a rect of first layer, data is rows of width texels. e.g. for dynamic atlases.
+    pub fn write_region(&self, renderer: &Renderer, x: u32, y: u32, width: u32, height: u32, data: &[u8]) {
+        self.write_region_with_belt(&renderer.staging_belt, x, y, width, height, data)
     }
 
+    pub(crate) fn write_region_with_belt(&self, staging_belt: &StagingBelt, x: u32, y: u32, width: u32, height: u32, data: &[u8]) {
+        if x as u64 + width as u64 > self.extent.width as u64 || y as u64 + height as u64 > self.extent.height as u64 {
+            panic!(
+                "Texture region {}x{} at ({}, {}) is outside texture of {}x{}",
+                width, height, x, y, self.extent.width, self.extent.height
+            );
+        }
+        let block_height = self.format.wgpu_type().describe().block_dimensions.1 as u32;
+        let size = self.format.row_pitch(width) as usize * height.div_ceil(block_height) as usize;
+        if data.len() != size {
+            panic!("Texture region {}x{} needs {} bytes, got {}", width, height, size, data.len());
+        }
+
+        let extent = wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        };
+
+        staging_belt.write_texture(&self.texture, self.format, wgpu::Origin3d { x, y, z: 0 }, extent, data);
+    }
+
+    // uploaded as is if device supports the format, decoded on cpu otherwise.
     pub fn with_compressed_texels(renderer: &Renderer, width: u32, height: u32, data: &[u8], format: CompressedTextureFormat) -> Self {
+        // compressed textures must consist of whole blocks
+        if renderer.supports_format(format.format()) && width.is_multiple_of(4) && height.is_multiple_of(4) {
+            return Self::with_texels(renderer, width, height, data, format.format());
+        }
+
         let uncompressed = Self::decode_texture(data, width, height, &format);
 
         Self::with_texels(renderer, width, height, &uncompressed, format.decoded_format())
@@ -131,3 +363,18 @@ impl Texture {
         result
     }
 }
+
+impl Drop for Texture {
+    fn drop(&mut self) {
+        if let Some(deletion_queue) = self.deletion_queue.take() {
+            let texture = self.texture.clone();
+            // memory is reported until texture is actually destroyed
+            let allocation = self.allocation.take();
+
+            deletion_queue.defer(move || {
+                texture.destroy();
+                drop(allocation);
+            });
+        }
+    }
+}
@@ -0,0 +1,169 @@
+use alloc::{string::String, sync::Arc, vec::Vec};
+
+use hashbrown::HashMap;
+
+use crate::{staging_belt::StagingBelt, Renderer, Texture, TextureFormat};
+
+// gap between images so linear filtering doesn't bleed neighbours in
+const PADDING: u32 = 1;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AtlasRegion {
+    // pixel rect inside atlas texture
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    // left, top, right, bottom texture coordinates
+    pub uv: [f32; 4],
+}
+
+struct Shelf {
+    y: u32,
+    // without padding below
+    height: u32,
+    // next free x
+    x: u32,
+}
+
+// Places rects on horizontal shelves, each as tall as the first rect put on it.
+struct ShelfAllocator {
+    size: (u32, u32),
+    shelves: Vec<Shelf>,
+}
+
+impl ShelfAllocator {
+    fn new(width: u32, height: u32) -> Self {
+        Self {
+            size: (width, height),
+            shelves: Vec::new(),
+        }
+    }
+
+    // picks the shortest shelf that fits to limit wasted height, opens a new one below otherwise.
+    fn allocate(&mut self, width: u32, height: u32) -> Option<(u32, u32)> {
+        let size = self.size;
+
+        let shelf = self
+            .shelves
+            .iter_mut()
+            .filter(|x| x.height >= height && x.x + width <= size.0)
+            .min_by_key(|x| x.height);
+        if let Some(shelf) = shelf {
+            let x = shelf.x;
+            shelf.x += width + PADDING;
+
+            return Some((x, shelf.y));
+        }
+
+        let y = self.shelves.last().map(|x| x.y + x.height + PADDING).unwrap_or(0);
+        if y + height > size.1 || width > size.0 {
+            return None;
+        }
+        self.shelves.push(Shelf {
+            y,
+            height,
+            x: width + PADDING,
+        });
+
+        Some((0, y))
+    }
+}
+
+// Packs many small rgba8 images into one texture at runtime, so draws using them share a bind group.
+// Images are placed on horizontal shelves, each as tall as the first image put on it.
+// Padding is kept right of and below images, except at atlas edges, so an image may be as large as the atlas.
+pub struct TextureAtlas {
+    texture: Arc<Texture>,
+    staging_belt: Arc<StagingBelt>,
+    size: (u32, u32),
+    allocator: ShelfAllocator,
+    regions: HashMap<String, AtlasRegion>,
+}
+
+impl TextureAtlas {
+    pub fn new(renderer: &Renderer, width: u32, height: u32) -> Self {
+        Self {
+            texture: Arc::new(Texture::with_tracker(
+                &renderer.device,
+                &renderer.memory,
+                "texture atlases",
+                width,
+                height,
+                TextureFormat::Rgba8Unorm,
+            )),
+            staging_belt: renderer.staging_belt.clone(),
+            size: (width, height),
+            allocator: ShelfAllocator::new(width, height),
+            regions: HashMap::new(),
+        }
+    }
+
+    pub fn texture(&self) -> Arc<Texture> {
+        self.texture.clone()
+    }
+
+    // uploads image and returns its region, none if atlas is full. inserting existing name replaces its region.
+    pub fn insert(&mut self, name: &str, width: u32, height: u32, texels: &[u8]) -> Option<AtlasRegion> {
+        if texels.len() != width as usize * height as usize * 4 {
+            panic!("Atlas image of {}x{} needs {} bytes of rgba8 texels", width, height, width * height * 4);
+        }
+        let (x, y) = self.allocator.allocate(width, height)?;
+
+        self.texture.write_region_with_belt(&self.staging_belt, x, y, width, height, texels);
+
+        let (atlas_width, atlas_height) = (self.size.0 as f32, self.size.1 as f32);
+        let region = AtlasRegion {
+            x,
+            y,
+            width,
+            height,
+            uv: [
+                x as f32 / atlas_width,
+                y as f32 / atlas_height,
+                (x + width) as f32 / atlas_width,
+                (y + height) as f32 / atlas_height,
+            ],
+        };
+        self.regions.insert(name.into(), region);
+
+        Some(region)
+    }
+
+    pub fn region(&self, name: &str) -> Option<AtlasRegion> {
+        self.regions.get(name).copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shelf_packing() {
+        let mut allocator = ShelfAllocator::new(16, 16);
+
+        assert_eq!(allocator.allocate(4, 4), Some((0, 0)));
+        // same shelf, right of padding
+        assert_eq!(allocator.allocate(4, 2), Some((5, 0)));
+        // taller one opens a shelf below
+        assert_eq!(allocator.allocate(4, 6), Some((0, 5)));
+        // shortest fitting shelf is picked
+        assert_eq!(allocator.allocate(3, 3), Some((10, 0)));
+        // no padding needed at right edge
+        assert_eq!(allocator.allocate(2, 4), Some((14, 0)));
+        assert_eq!(allocator.allocate(11, 6), Some((5, 5)));
+        // no padding needed at bottom edge either
+        assert_eq!(allocator.allocate(16, 4), Some((0, 12)));
+        assert_eq!(allocator.allocate(1, 1), None);
+    }
+
+    #[test]
+    fn test_shelf_whole_atlas() {
+        let mut allocator = ShelfAllocator::new(8, 8);
+
+        assert_eq!(allocator.allocate(8, 8), Some((0, 0)));
+        assert_eq!(allocator.allocate(1, 1), None);
+        assert_eq!(ShelfAllocator::new(8, 8).allocate(9, 1), None);
+    }
+}
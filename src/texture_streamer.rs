@@ -0,0 +1,84 @@
+use alloc::{collections::VecDeque, sync::Arc, vec::Vec};
+
+use crate::{Renderer, Texture};
+
+struct Tile {
+    texture: Arc<Texture>,
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+    data: Vec<u8>,
+}
+
+// Spreads large texture updates over frames, uploading queued tiles up to a byte budget each frame.
+// Useful for video frames or atlas pages where a full upload would cause a hitch. uncompressed formats only.
+pub struct TextureStreamer {
+    tile_size: u32,
+    tiles: VecDeque<Tile>,
+}
+
+impl TextureStreamer {
+    // regions are split into tiles of at most tile_size texels square
+    pub fn new(tile_size: u32) -> Self {
+        Self {
+            tile_size: tile_size.max(1),
+            tiles: VecDeque::new(),
+        }
+    }
+
+    // queues rect of texture to be replaced by data, rows of width texels.
+    // tiles of a texture already queued for the same rect are dropped, so only latest content is uploaded.
+    pub fn push(&mut self, texture: Arc<Texture>, x: u32, y: u32, width: u32, height: u32, data: &[u8]) {
+        let texel_size = texture.format.bytes_per_block();
+        self.tiles.retain(|tile| {
+            let covered = tile.x >= x && tile.y >= y && tile.x + tile.width <= x + width && tile.y + tile.height <= y + height;
+
+            !(Arc::ptr_eq(&tile.texture, &texture) && covered)
+        });
+
+        for tile_y in (0..height).step_by(self.tile_size as usize) {
+            for tile_x in (0..width).step_by(self.tile_size as usize) {
+                let tile_width = self.tile_size.min(width - tile_x);
+                let tile_height = self.tile_size.min(height - tile_y);
+
+                let mut tile_data = Vec::with_capacity((tile_width * tile_height) as usize * texel_size);
+                for row in tile_y..tile_y + tile_height {
+                    let start = (row * width + tile_x) as usize * texel_size;
+                    tile_data.extend_from_slice(&data[start..start + tile_width as usize * texel_size]);
+                }
+
+                self.tiles.push_back(Tile {
+                    texture: texture.clone(),
+                    x: x + tile_x,
+                    y: y + tile_y,
+                    width: tile_width,
+                    height: tile_height,
+                    data: tile_data,
+                });
+            }
+        }
+    }
+
+    // call once a frame. at least one tile is uploaded even if it's over budget, so streaming always progresses.
+    // returns true when nothing is left queued.
+    pub fn update(&mut self, renderer: &Renderer, max_bytes: usize) -> bool {
+        let mut uploaded = 0;
+        while let Some(tile) = self.tiles.front() {
+            if uploaded > 0 && uploaded + tile.data.len() > max_bytes {
+                break;
+            }
+
+            let tile = self.tiles.pop_front().unwrap();
+            tile.texture.write_region(renderer, tile.x, tile.y, tile.width, tile.height, &tile.data);
+            uploaded += tile.data.len();
+        }
+
+        self.tiles.is_empty()
+    }
+
+    // bytes queued for upload
+    pub fn pending_bytes(&self) -> usize {
+        self.tiles.iter().map(|x| x.data.len()).sum()
+    }
+}
@@ -0,0 +1,143 @@
+// Accumulates frame time and reports how many fixed updates to run, so simulation doesn't depend on frame rate.
+pub struct FixedTimestep {
+    step: f32,
+    accumulator: f32,
+    // caps updates per frame so a long stall doesn't snowball into more work
+    max_steps: u32,
+}
+
+impl FixedTimestep {
+    pub fn new(step: f32) -> Self {
+        Self {
+            step,
+            accumulator: 0.0,
+            max_steps: 8,
+        }
+    }
+
+    pub fn set_max_steps(&mut self, max_steps: u32) {
+        self.max_steps = max_steps;
+    }
+
+    pub fn step(&self) -> f32 {
+        self.step
+    }
+
+    // adds frame delta in seconds and returns number of fixed updates to run now.
+    pub fn advance(&mut self, dt: f32) -> u32 {
+        self.accumulator += dt;
+
+        let mut steps = 0;
+        while self.accumulator >= self.step && steps < self.max_steps {
+            self.accumulator -= self.step;
+            steps += 1;
+        }
+
+        // time beyond the cap is dropped
+        if steps == self.max_steps {
+            self.accumulator = self.accumulator.min(self.step);
+        }
+
+        steps
+    }
+
+    // fraction of a step since the last update, for interpolating render state between last two updates.
+    pub fn alpha(&self) -> f32 {
+        (self.accumulator / self.step).min(1.0)
+    }
+}
+
+// Frame timing derived from a monotonic clock reading the application passes in each frame.
+#[derive(Default)]
+pub struct Time {
+    start: Option<f64>,
+    last: f64,
+    delta: f32,
+    elapsed: f64,
+    frame: u64,
+    fps: f32,
+}
+
+impl Time {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // now is in seconds from any fixed origin, e.g. Instant elapsed or performance.now() / 1000.
+    pub fn update(&mut self, now: f64) {
+        let start = *self.start.get_or_insert(now);
+        if self.frame > 0 {
+            self.delta = (now - self.last) as f32;
+        }
+        self.last = now;
+        self.elapsed = now - start;
+        self.frame += 1;
+
+        // exponential moving average so fps readout doesn't flicker
+        if self.delta > 0.0 {
+            let fps = 1.0 / self.delta;
+            self.fps = if self.fps == 0.0 { fps } else { self.fps + (fps - self.fps) * 0.1 };
+        }
+    }
+
+    // seconds between last two updates, zero on first frame
+    pub fn delta(&self) -> f32 {
+        self.delta
+    }
+
+    // seconds since first update
+    pub fn elapsed(&self) -> f64 {
+        self.elapsed
+    }
+
+    // number of updates so far
+    pub fn frame(&self) -> u64 {
+        self.frame
+    }
+
+    pub fn fps(&self) -> f32 {
+        self.fps
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fixed_timestep() {
+        let mut timestep = FixedTimestep::new(0.25);
+
+        assert_eq!(timestep.advance(0.125), 0);
+        assert_eq!(timestep.alpha(), 0.5);
+        assert_eq!(timestep.advance(0.5), 2);
+        assert_eq!(timestep.alpha(), 0.5);
+    }
+
+    #[test]
+    fn test_fixed_timestep_clamp() {
+        let mut timestep = FixedTimestep::new(0.25);
+        timestep.set_max_steps(2);
+
+        // stall worth 40 steps runs only 2, leaving at most a step behind
+        assert_eq!(timestep.advance(10.0), 2);
+        assert_eq!(timestep.alpha(), 1.0);
+        assert_eq!(timestep.advance(0.0), 1);
+        assert_eq!(timestep.advance(0.0), 0);
+    }
+
+    #[test]
+    fn test_time() {
+        let mut time = Time::new();
+
+        time.update(10.0);
+        assert_eq!(time.delta(), 0.0);
+        assert_eq!(time.elapsed(), 0.0);
+
+        time.update(10.5);
+        assert_eq!(time.delta(), 0.5);
+        assert_eq!(time.elapsed(), 0.5);
+        assert_eq!(time.frame(), 2);
+        assert_eq!(time.fps(), 2.0);
+    }
+}
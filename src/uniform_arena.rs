@@ -0,0 +1,73 @@
+use alloc::{sync::Arc, vec::Vec};
+
+use spinning_top::Spinlock;
+
+use crate::{
+    memory::{Allocation, MemoryTracker},
+    staging_belt::StagingBelt,
+};
+
+// 16384 draws per view
+const ARENA_SIZE: usize = 4194304;
+
+// Per-view uniforms of all models packed into one buffer, bound with dynamic offsets.
+// Data is staged while preparing a view and uploaded with a single write.
+pub(crate) struct UniformArena {
+    staging_belt: Arc<StagingBelt>,
+    buffer: Arc<wgpu::Buffer>,
+    staging: Spinlock<Vec<u8>>,
+    _allocation: Allocation,
+}
+
+impl UniformArena {
+    // space for each draw, fits mvp and model matrices
+    pub(crate) const SLOT_SIZE: usize = wgpu::BIND_BUFFER_ALIGNMENT as usize;
+
+    pub(crate) fn new(device: &wgpu::Device, staging_belt: Arc<StagingBelt>, memory: &MemoryTracker) -> Self {
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            size: ARENA_SIZE as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            label: Some("uniform arena"),
+            mapped_at_creation: false,
+        });
+
+        Self {
+            staging_belt,
+            buffer: Arc::new(buffer),
+            staging: Spinlock::new(Vec::new()),
+            _allocation: memory.track("uniform arena", ARENA_SIZE),
+        }
+    }
+
+    // binding of a single slot, moved by dynamic offset
+    pub(crate) fn binding_resource(&self) -> wgpu::BindingResource<'_> {
+        wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+            buffer: &self.buffer,
+            offset: 0,
+            size: wgpu::BufferSize::new(Self::SLOT_SIZE as u64),
+        })
+    }
+
+    // returns dynamic offset of pushed data
+    pub(crate) fn push(&self, data: &[u8]) -> u32 {
+        let mut staging = self.staging.lock();
+        let offset = staging.len();
+        if offset + Self::SLOT_SIZE > ARENA_SIZE {
+            panic!("Uniform arena is full");
+        }
+
+        staging.extend_from_slice(data);
+        staging.resize(offset + Self::SLOT_SIZE, 0);
+
+        offset as u32
+    }
+
+    // uploads staged data, it's visible to commands submitted after this.
+    pub(crate) fn flush(&self) {
+        let mut staging = self.staging.lock();
+        if !staging.is_empty() {
+            self.staging_belt.write_buffer(&self.buffer, 0, &staging);
+        }
+        staging.clear();
+    }
+}
@@ -1,9 +1,10 @@
-use alloc::vec::Vec;
+use alloc::{vec, vec::Vec};
 
 use hashbrown::HashMap;
 
 pub enum VertexItemType {
     UByte4,
+    Float1,
     Float2,
     Float3,
     Float4,
@@ -15,6 +16,7 @@ impl VertexItemType {
     pub(crate) fn wgpu_type(&self) -> wgpu::VertexFormat {
         match self {
             VertexItemType::UByte4 => wgpu::VertexFormat::Uint8x4,
+            VertexItemType::Float1 => wgpu::VertexFormat::Float32,
             VertexItemType::Float2 => wgpu::VertexFormat::Float32x2,
             VertexItemType::Float3 => wgpu::VertexFormat::Float32x3,
             VertexItemType::Float4 => wgpu::VertexFormat::Float32x4,
@@ -49,13 +51,38 @@ impl VertexFormat {
         Self { items }
     }
 
+    pub(crate) fn has_item(&self, shader_name: &str) -> bool {
+        self.items.iter().any(|x| x.shader_name == shader_name)
+    }
+
+    // offset and component count of position item, if it has one
+    pub(crate) fn position(&self) -> Option<(usize, usize)> {
+        self.items.iter().find(|x| x.shader_name == "Position").and_then(|x| match x.item_type {
+            VertexItemType::Float2 => Some((x.offset, 2)),
+            VertexItemType::Float3 => Some((x.offset, 3)),
+            VertexItemType::Float4 => Some((x.offset, 4)),
+            _ => None,
+        })
+    }
+
+    // moves position item to a format of its own, where it's at start of each vertex
+    pub(crate) fn split_position(&mut self) -> Option<VertexFormat> {
+        let index = self.items.iter().position(|x| x.shader_name == "Position")?;
+        let item = self.items.remove(index);
+
+        Some(VertexFormat::new(vec![VertexFormatItem { offset: 0, ..item }]))
+    }
+
+    // items which shader doesn't take are skipped
     pub(crate) fn wgpu_attributes(&self, shader_inputs: &HashMap<&'static str, u32>) -> Vec<wgpu::VertexAttribute> {
         self.items
             .iter()
-            .map(|x| wgpu::VertexAttribute {
-                format: x.item_type.wgpu_type(),
-                offset: x.offset as u64,
-                shader_location: *shader_inputs.get(x.shader_name).unwrap(),
+            .filter_map(|x| {
+                Some(wgpu::VertexAttribute {
+                    format: x.item_type.wgpu_type(),
+                    offset: x.offset as u64,
+                    shader_location: *shader_inputs.get(x.shader_name)?,
+                })
             })
             .collect::<Vec<_>>()
     }